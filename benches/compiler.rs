@@ -0,0 +1,69 @@
+//! Benchmarks for the phases of the compiler, on made up inputs of 1k, 10k
+//! and 100k definitions. Run them with `cargo bench`.
+
+#[macro_use]
+extern crate criterion;
+extern crate simcom;
+
+use std::fmt::Write;
+
+use criterion::{BenchmarkId, Criterion, Throughput};
+use simcom::lexer::Lexer;
+use simcom::parser::{Ast, Parser};
+use simcom::semantic::Semantic;
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// `count` documented definitions. Each one uses the one at half its
+/// position, so the dependencies are as deep as in a tree, not a list.
+fn input(count: usize) -> String {
+    let mut input = String::new();
+    for i in 0..count {
+        let parent = if i == 0 { String::from("Entero") } else { format!("Tipo{}", i / 2) };
+        writeln!(input, "/// El tipo número {}.", i).unwrap();
+        writeln!(input, "@obsoleto tipo Tipo{}(\n    primero: Entero,\n    segundo: {},\n    nombre_largo: Texto,\n);\n",
+                 i, parent).unwrap();
+    }
+    input
+}
+
+fn lexer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lexer");
+    for &size in &SIZES {
+        let input = input(size);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+            b.iter(|| Lexer::new(input).count())
+        });
+    }
+    group.finish();
+}
+
+fn parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parser");
+    for &size in &SIZES {
+        let input = input(size);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+            b.iter(|| Parser::new(Lexer::new(input)).count())
+        });
+    }
+    group.finish();
+}
+
+fn semantic(c: &mut Criterion) {
+    let mut group = c.benchmark_group("semantic");
+    group.sample_size(10);
+    for &size in &SIZES {
+        let input = input(size);
+        let asts: Vec<Ast> = Parser::new(Lexer::new(&input)).collect();
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &asts, |b, asts| {
+            b.iter(|| Semantic::analyze(asts.iter().cloned()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lexer, parser, semantic);
+criterion_main!(benches);
@@ -0,0 +1,12 @@
+//! Feeds the lexer, the parser and the semantic analyzer with made up
+//! programs and raw bytes. See `simcom::fuzz`.
+
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate simcom;
+
+fuzz_target!(|data: &[u8]| {
+    simcom::fuzz::run(data);
+});
@@ -0,0 +1,55 @@
+/*
+ * The C API of simcom, available when the library is built with the `cabi`
+ * feature:
+ *
+ *     cargo rustc --lib --release --features cabi --crate-type cdylib
+ *
+ * A compilation returns an opaque result, which is queried with the
+ * `simcom_result_*` functions and freed with `simcom_free`. Strings returned
+ * by the queries belong to the result: don't free them, and don't use them
+ * after freeing the result.
+ */
+
+#ifndef SIMCOM_H
+#define SIMCOM_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct SimcomResult SimcomResult;
+
+/* Compiles `source`, a NUL-terminated UTF-8 string, with the default options.
+ * Returns NULL if `source` is NULL or isn't valid UTF-8. */
+SimcomResult *simcom_compile(const char *source);
+
+/* 1 if there were no errors, 0 otherwise. */
+int simcom_result_is_ok(const SimcomResult *result);
+
+/* How many types there are in the order. 0 when there were syntax errors. */
+size_t simcom_result_get_order_len(const SimcomResult *result);
+
+/* The name of the type at `index` in the order the definitions must be
+ * written in, or NULL if `index` is out of bounds. */
+const char *simcom_result_get_order(const SimcomResult *result, size_t index);
+
+/* The generated C code, or NULL if there were errors. */
+const char *simcom_result_get_code(const SimcomResult *result);
+
+/* How many diagnostics (errors and warnings) there are. */
+size_t simcom_result_get_diagnostics_len(const SimcomResult *result);
+
+/* The diagnostic at `index`, like "error: 2:1: unexpected `;`", or NULL
+ * if `index` is out of bounds. */
+const char *simcom_result_get_diagnostic(const SimcomResult *result, size_t index);
+
+/* Frees a result and every string it returned. NULL is ignored. */
+void simcom_free(SimcomResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
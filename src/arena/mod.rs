@@ -0,0 +1,198 @@
+//! Arena module.
+//!
+//! Parsing a big file makes a lot of tiny allocations: a `String` for every
+//! name and a `Vec` for every list of parameters, and the same amount of
+//! frees when the AST is dropped. An [`Arena`][0] hands out memory from a few
+//! big chunks instead, one after the other, and frees all of them at once
+//! when it is dropped. The [arena-backed AST][1] lives in one.
+//!
+//! Only `Copy` values go in an arena, so dropping it never has to run their
+//! destructors.
+//!
+//! [0]: struct.Arena.html
+//! [1]: ../parser/arena/index.html
+
+use alloc::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::mem;
+use core::ptr::{self, NonNull};
+use core::slice;
+use core::str;
+
+/// Size of the first chunk. Every new chunk doubles the last one, up to
+/// `MAX_CHUNK_SIZE`.
+const CHUNK_SIZE: usize = 4 * 1024;
+const MAX_CHUNK_SIZE: usize = 1024 * 1024;
+/// Enough for anything the AST stores.
+const CHUNK_ALIGN: usize = 16;
+
+/// A bump allocator: memory is handed out in order from the current chunk,
+/// and only given back when the whole arena is dropped.
+///
+/// # Examples
+///
+///     use simcom::arena::Arena;
+///
+///     let arena = Arena::new();
+///     let name = arena.alloc_str("Punto");
+///     let fields = arena.alloc_slice(&[name, "x", "y"]);
+///     assert_eq!(fields, ["Punto", "x", "y"]);
+pub struct Arena {
+    /// Every chunk allocated so far. The last one is the current one.
+    chunks: RefCell<Vec<(NonNull<u8>, Layout)>>,
+    /// Bytes used in the current chunk.
+    used: Cell<usize>,
+}
+
+impl Arena {
+    /// Create an empty arena. It doesn't allocate until something is put in
+    /// it.
+    pub fn new() -> Arena {
+        Arena { chunks: RefCell::new(Vec::new()), used: Cell::new(0) }
+    }
+
+    /// Moves `value` into the arena.
+    pub fn alloc<T: Copy>(&self, value: T) -> &T {
+        let pointer = self.alloc_layout::<T>(Layout::new::<T>());
+        // SAFETY: the memory is fresh, big enough and aligned for a `T`.
+        unsafe {
+            ptr::write(pointer.as_ptr(), value);
+            &*pointer.as_ptr()
+        }
+    }
+
+    /// Copies `items` into the arena.
+    pub fn alloc_slice<T: Copy>(&self, items: &[T]) -> &[T] {
+        let layout = match Layout::array::<T>(items.len()) {
+            Ok(layout) => layout,
+            Err(_) => panic!("slice too big for the arena"),
+        };
+        let pointer = self.alloc_layout::<T>(layout);
+        // SAFETY: the memory is fresh, big enough and aligned for
+        // `items.len()` values of type `T`, and `T` is `Copy`.
+        unsafe {
+            ptr::copy_nonoverlapping(items.as_ptr(), pointer.as_ptr(), items.len());
+            slice::from_raw_parts(pointer.as_ptr(), items.len())
+        }
+    }
+
+    /// Copies `text` into the arena.
+    pub fn alloc_str(&self, text: &str) -> &str {
+        let bytes = self.alloc_slice(text.as_bytes());
+        // SAFETY: the bytes were copied from a `str`.
+        unsafe { str::from_utf8_unchecked(bytes) }
+    }
+
+    /// How many bytes the arena has taken from the allocator, used or not.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::arena::Arena;
+    ///
+    ///     let arena = Arena::new();
+    ///     assert_eq!(arena.allocated_bytes(), 0);
+    ///     arena.alloc_str("tipo");
+    ///     assert!(arena.allocated_bytes() >= 4);
+    pub fn allocated_bytes(&self) -> usize {
+        self.chunks.borrow().iter().map(|&(_, layout)| layout.size()).sum()
+    }
+
+    /// Finds room for `layout` in the current chunk, or in a new one if it
+    /// doesn't fit.
+    fn alloc_layout<T>(&self, layout: Layout) -> NonNull<T> {
+        if layout.size() == 0 {
+            return NonNull::dangling();
+        }
+
+        let mut chunks = self.chunks.borrow_mut();
+        if let Some(&(base, chunk)) = chunks.last() {
+            let address = base.as_ptr() as usize + self.used.get();
+            let aligned = (address + layout.align() - 1) & !(layout.align() - 1);
+            let start = aligned - base.as_ptr() as usize;
+
+            if start + layout.size() <= chunk.size() {
+                self.used.set(start + layout.size());
+                // SAFETY: `start` is inside the chunk.
+                return unsafe { NonNull::new_unchecked(base.as_ptr().add(start)).cast() };
+            }
+        }
+
+        let size = chunks.last()
+            .map(|&(_, chunk)| (chunk.size() * 2).min(MAX_CHUNK_SIZE))
+            .unwrap_or(CHUNK_SIZE)
+            .max(layout.size());
+        let chunk = match Layout::from_size_align(size, layout.align().max(CHUNK_ALIGN)) {
+            Ok(chunk) => chunk,
+            Err(_) => panic!("allocation too big for the arena"),
+        };
+        // SAFETY: `chunk` has a non-zero size.
+        let base = match NonNull::new(unsafe { alloc(chunk) }) {
+            Some(base) => base,
+            None => handle_alloc_error(chunk),
+        };
+
+        chunks.push((base, chunk));
+        self.used.set(layout.size());
+        base.cast()
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Arena::new()
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        for (base, chunk) in mem::take(self.chunks.get_mut()) {
+            // SAFETY: every chunk was allocated with its layout, and nothing
+            // can point into it anymore.
+            unsafe { dealloc(base.as_ptr(), chunk) };
+        }
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Arena")
+            .field("chunks", &self.chunks.borrow().len())
+            .field("allocated_bytes", &self.allocated_bytes())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Arena, CHUNK_SIZE};
+
+    #[test]
+    fn values_keep_their_alignment() {
+        let arena = Arena::new();
+        arena.alloc_str("a");
+        let number = arena.alloc(7u64);
+        assert_eq!(number as *const u64 as usize % 8, 0);
+        assert_eq!(*number, 7);
+
+        let pairs = arena.alloc_slice(&[(1u8, 2u32), (3, 4)]);
+        assert_eq!(pairs.as_ptr() as usize % 4, 0);
+        assert_eq!(pairs, [(1, 2), (3, 4)]);
+    }
+
+    #[test]
+    fn grows_in_new_chunks() {
+        let arena = Arena::new();
+        let names: Vec<&str> = (0..1000).map(|i| arena.alloc_str(&format!("Tipo{}", i))).collect();
+
+        assert!(arena.allocated_bytes() > CHUNK_SIZE);
+        assert_eq!(names[0], "Tipo0");
+        assert_eq!(names[999], "Tipo999");
+
+        let big = arena.alloc_slice(&[1u8; 3 * CHUNK_SIZE]);
+        assert_eq!(big.len(), 3 * CHUNK_SIZE);
+        assert_eq!(arena.alloc_str(""), "");
+        assert!(arena.alloc_slice::<u32>(&[]).is_empty());
+    }
+}
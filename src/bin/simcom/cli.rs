@@ -0,0 +1,437 @@
+//! The command line: subcommands, options and the usage message.
+//!
+//! Parsed by hand, the language is small enough for that. The first argument
+//! may be a subcommand; without one, the inputs are built. Options that only
+//! make sense for some subcommands are rejected by the rest, so a typo doesn't
+//! go unnoticed.
+
+use std::path::PathBuf;
+
+use simcom::codegen::Backend;
+use simcom::config::Level;
+use simcom::graph::Format as GraphFormat;
+use simcom::lexer::Language;
+use simcom::locale::Locale;
+
+pub const USAGE: &str = "\
+Usage: simcom [COMMAND] [OPTIONS] [INPUTS...]
+
+Works on the type definitions in INPUTS, or stdin when there are none. Inputs
+can be files, directories or glob patterns like `schemas/**/*.sc`. Without a
+command, the inputs are built.
+
+Commands:
+    build        Compile the inputs to C
+    check        Look for errors without generating anything
+    fmt          Format the inputs and write them back (or to stdout, when
+                 reading from stdin)
+    lint         Look for suspicious code. The lints are: naming,
+                 unused_types (allowed by default), duplicate_fields,
+                 deprecated, misspelled_types, field_number_gaps and
+                 conflicting_units
+    tokens       Print the tokens of the inputs, with their positions
+    doc          Write an HTML (or Markdown) page for every type, with its
+                 doc comments, fields and dependencies, plus an index of all
+                 of them
+    graph        Print the dependencies between types as a Graphviz (dot),
+                 Mermaid or JSON graph
+    diff         Compare two versions of a schema, given as the inputs OLD
+                 and NEW, and print what changed. The exit code is 1 if a
+                 change breaks the programs written for OLD
+    lsp          Start a language server on stdin and stdout, that reports
+                 errors and lint findings, lists the types of a document,
+                 finds where a type is defined and where it is used
+    completions  Print a completion script for bash, zsh or fish. For example,
+                 in bash: `source <(simcom completions bash)`
+    help         Print this message
+
+Options:
+    -c, --config FILE       Use FILE instead of looking for simcom.toml
+        --no-config         Don't look for simcom.toml
+        --lang es|en        Language of the keywords
+        --locale es|en      Language of the messages (default: from LANG)
+        --ext EXT           Extension of the files searched in directories,
+                            can be repeated (default: sc)
+        --max-errors N      Report at most N syntax errors, 0 for all of them
+                            (default: 20)
+        --stdin-filename PATH
+                            Name of the file being read from stdin, to use
+                            in messages
+    -j, --jobs N            Parse the files in N threads, 0 for one per CPU
+                            (default: 0)
+        --cache DIR         Keep what was parsed and analyzed in DIR, to skip
+                            the files that didn't change in the next run
+        --no-cache          Don't use the cache, even if simcom.toml has one
+    -v, --verbose           Print what is being done, -vv for more detail
+        --explain CODE      Explain the diagnostics with CODE, like E0001, and
+                            exit
+    -h, --help              Print this message
+
+Build options:
+        --backend NAME      Code generator to use (c)
+        --builtin NAME=TYPE Add or replace a built-in type
+    -o, --output FILE       Write the generated code to FILE
+        --stamp             Start the code with a comment saying which inputs
+                            and options it was generated from
+
+Fmt options:
+        --stdout            Print the formatted code instead of writing it
+                            back
+        --check             Only check that the inputs are formatted: the
+                            differences are printed and the exit code is 1
+                            if there are any
+
+Check and lint options:
+        --fix               Apply the suggestions that are certainly right
+                            and write the inputs back
+
+Lint options:
+    -D, --deny LINT         Report LINT as an error
+    -W, --warn LINT         Report LINT as a warning
+    -A, --allow LINT        Don't run LINT
+
+Doc options:
+        --out-dir DIR       Where to write the pages (default: doc)
+        --markdown          Write Markdown instead of HTML, with a Mermaid
+                            diagram of the neighbors of each type
+        --single-page       With `--markdown`, write everything to index.md
+
+Graph options:
+        --format FORMAT     dot, mermaid or json (default: dot)
+        --root TYPE         Only the types TYPE depends on, directly or not
+
+Diff options:
+        --format FORMAT     text or json (default: text)
+";
+
+/// What to do with the inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Command {
+    #[default]
+    Build,
+    Check,
+    Fmt,
+    Lint,
+    Tokens,
+    Doc,
+    Graph,
+    Diff,
+    Lsp,
+    Completions,
+    Help,
+}
+
+/// Every subcommand, with its name and a short description.
+pub const COMMANDS: &[(&str, Command, &str)] = &[
+    ("build", Command::Build, "Compile the inputs to C"),
+    ("check", Command::Check, "Look for errors without generating anything"),
+    ("fmt", Command::Fmt, "Format the inputs"),
+    ("lint", Command::Lint, "Look for suspicious code"),
+    ("tokens", Command::Tokens, "Print the tokens of the inputs"),
+    ("doc", Command::Doc, "Write HTML documentation"),
+    ("graph", Command::Graph, "Print the dependency graph"),
+    ("diff", Command::Diff, "Compare two versions of a schema"),
+    ("lsp", Command::Lsp, "Start the language server"),
+    ("completions", Command::Completions, "Print a shell completion script"),
+    ("help", Command::Help, "Print the help"),
+];
+
+impl Command {
+    pub fn from_name(name: &str) -> Option<Command> {
+        COMMANDS.iter()
+            .find(|&&(n, _, _)| n == name)
+            .map(|&(_, command, _)| command)
+    }
+
+    pub fn name(self) -> &'static str {
+        COMMANDS.iter()
+            .find(|&&(_, c, _)| c == self)
+            .map(|&(name, _, _)| name)
+            .unwrap_or("build")
+    }
+}
+
+/// How `simcom diff` prints the changes.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DiffFormat {
+    /// One line per change.
+    #[default]
+    Text,
+    Json,
+}
+
+/// What was given in the command line. Everything is optional so we can tell
+/// apart what has to override the configuration file.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub command: Command,
+
+    pub config: Option<PathBuf>,
+    pub no_config: bool,
+    pub language: Option<Language>,
+    pub locale: Option<Locale>,
+    pub extensions: Vec<String>,
+    pub max_errors: Option<usize>,
+    pub stdin_filename: Option<PathBuf>,
+    pub jobs: Option<usize>,
+    pub cache: Option<PathBuf>,
+    pub no_cache: bool,
+    pub verbose: usize,
+    pub explain: Option<String>,
+    pub inputs: Vec<PathBuf>,
+
+    pub backend: Option<Backend>,
+    pub builtins: Vec<(String, String)>,
+    pub output: Option<PathBuf>,
+    pub stamp: bool,
+
+    pub stdout: bool,
+    pub check: bool,
+
+    pub fix: bool,
+
+    pub lints: Vec<(String, Level)>,
+
+    pub out_dir: Option<PathBuf>,
+    pub markdown: bool,
+    pub single_page: bool,
+
+    pub format: GraphFormat,
+    pub root: Option<String>,
+
+    pub diff_format: DiffFormat,
+}
+
+/// Parses the arguments, without the name of the program.
+pub fn parse<I: Iterator<Item = String>>(iter: I) -> Result<Args, String> {
+    let mut iter = iter.peekable();
+    let mut args = Args::default();
+
+    if let Some(command) = iter.peek().and_then(|arg| Command::from_name(arg)) {
+        args.command = command;
+        iter.next();
+    }
+
+    while let Some(arg) = iter.next() {
+        // Every option but the flags takes a value right after it.
+        let mut value = |name: &str| {
+            iter.next().ok_or_else(|| format!("`{}` needs a value", name))
+        };
+
+        // Options only some commands understand.
+        let command = args.command;
+        let only = |commands: &[Command]| {
+            if commands.contains(&command) || command == Command::Help {
+                Ok(())
+            } else {
+                Err(format!("`{}` can't be used with `simcom {}`", arg, command.name()))
+            }
+        };
+
+        match &arg[..] {
+            "-h" | "--help" => args.command = Command::Help,
+            "-v" | "--verbose" => args.verbose += 1,
+            "-vv" => args.verbose += 2,
+            "--explain" => args.explain = Some(value(&arg)?),
+            "-c" | "--config" => args.config = Some(PathBuf::from(value(&arg)?)),
+            "--no-config" => args.no_config = true,
+            "--lang" => {
+                let code = value(&arg)?;
+                args.language = Some(Language::from_code(&code)
+                    .ok_or_else(|| format!("unknown language `{}`", code))?);
+            },
+            "--locale" => {
+                let code = value(&arg)?;
+                args.locale = Some(Locale::from_code(&code)
+                    .ok_or_else(|| format!("unknown locale `{}`", code))?);
+            },
+            "--ext" => args.extensions.push(value(&arg)?),
+            "--max-errors" => {
+                let max = value(&arg)?;
+                args.max_errors = Some(max.parse()
+                    .map_err(|_| format!("`--max-errors` expects a number, found `{}`", max))?);
+            },
+            "--stdin-filename" => args.stdin_filename = Some(PathBuf::from(value(&arg)?)),
+            "--cache" => args.cache = Some(PathBuf::from(value(&arg)?)),
+            "--no-cache" => args.no_cache = true,
+            "-j" | "--jobs" => {
+                let jobs = value(&arg)?;
+                args.jobs = Some(jobs.parse()
+                    .map_err(|_| format!("`{}` expects a number, found `{}`", arg, jobs))?);
+            },
+
+            "--backend" => {
+                only(&[Command::Build])?;
+                let name = value(&arg)?;
+                args.backend = Some(Backend::from_name(&name)
+                    .ok_or_else(|| format!("unknown backend `{}`", name))?);
+            },
+            "--builtin" => {
+                only(&[Command::Build])?;
+                let builtin = value(&arg)?;
+                match builtin.find('=') {
+                    Some(i) => args.builtins.push((
+                        String::from(&builtin[..i]),
+                        String::from(&builtin[i + 1..]),
+                    )),
+                    None => return Err(format!("`--builtin` expects NAME=TYPE, found `{}`", builtin)),
+                }
+            },
+            "-o" | "--output" => {
+                only(&[Command::Build])?;
+                args.output = Some(PathBuf::from(value(&arg)?));
+            },
+            "--stamp" => {
+                only(&[Command::Build])?;
+                args.stamp = true;
+            },
+
+            "--stdout" => {
+                only(&[Command::Fmt])?;
+                args.stdout = true;
+            },
+            "--check" => {
+                only(&[Command::Fmt])?;
+                args.check = true;
+            },
+
+            "--fix" => {
+                only(&[Command::Check, Command::Lint])?;
+                args.fix = true;
+            },
+
+            "-D" | "--deny" | "-W" | "--warn" | "-A" | "--allow" => {
+                only(&[Command::Lint])?;
+                let level = match &arg[..] {
+                    "-D" | "--deny" => Level::Deny,
+                    "-W" | "--warn" => Level::Warn,
+                    _ => Level::Allow,
+                };
+                args.lints.push((value(&arg)?, level));
+            },
+
+            "--out-dir" => {
+                only(&[Command::Doc])?;
+                args.out_dir = Some(PathBuf::from(value(&arg)?));
+            },
+            "--markdown" => {
+                only(&[Command::Doc])?;
+                args.markdown = true;
+            },
+            "--single-page" => {
+                only(&[Command::Doc])?;
+                args.single_page = true;
+            },
+
+            "--format" if command == Command::Diff => {
+                args.diff_format = match &value(&arg)?[..] {
+                    "text" => DiffFormat::Text,
+                    "json" => DiffFormat::Json,
+                    name => return Err(format!("unknown diff format `{}`", name)),
+                };
+            },
+            "--format" => {
+                only(&[Command::Graph])?;
+                let name = value(&arg)?;
+                args.format = GraphFormat::from_name(&name)
+                    .ok_or_else(|| format!("unknown graph format `{}`", name))?;
+            },
+            "--root" => {
+                only(&[Command::Graph])?;
+                args.root = Some(value(&arg)?);
+            },
+
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option `{}`\n\n{}", arg, USAGE));
+            },
+            _ => args.inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_args(args: &[&str]) -> Result<Args, String> {
+        parse(args.iter().map(|arg| String::from(*arg)))
+    }
+
+    #[test]
+    fn default_command() {
+        let args = get_args(&["a.sc", "-o", "a.h"]).unwrap();
+
+        assert_eq!(args.command, Command::Build);
+        assert_eq!(args.inputs, vec![PathBuf::from("a.sc")]);
+        assert_eq!(args.output, Some(PathBuf::from("a.h")));
+    }
+
+    #[test]
+    fn subcommands() {
+        assert_eq!(get_args(&["check"]).unwrap().command, Command::Check);
+        assert_eq!(get_args(&["tokens", "-vv"]).unwrap().verbose, 2);
+        assert_eq!(get_args(&["lint", "--help"]).unwrap().command, Command::Help);
+
+        // Only the first argument can be a command.
+        let args = get_args(&["a.sc", "fmt"]).unwrap();
+        assert_eq!(args.command, Command::Build);
+        assert_eq!(args.inputs.len(), 2);
+    }
+
+    #[test]
+    fn options_of_other_commands() {
+        let error = get_args(&["lint", "--check"]).unwrap_err();
+        assert_eq!(error, "`--check` can't be used with `simcom lint`");
+
+        let error = get_args(&["-D", "naming"]).unwrap_err();
+        assert_eq!(error, "`-D` can't be used with `simcom build`");
+
+        assert!(get_args(&["check", "--fix"]).unwrap().fix);
+        let error = get_args(&["fmt", "--fix"]).unwrap_err();
+        assert_eq!(error, "`--fix` can't be used with `simcom fmt`");
+
+        let args = get_args(&["doc", "--markdown", "--single-page"]).unwrap();
+        assert!(args.markdown && args.single_page);
+        let error = get_args(&["graph", "--markdown"]).unwrap_err();
+        assert_eq!(error, "`--markdown` can't be used with `simcom graph`");
+
+        let args = get_args(&["lint", "-D", "naming", "-A", "deprecated"]).unwrap();
+        assert_eq!(args.lints, vec![
+            (String::from("naming"), Level::Deny),
+            (String::from("deprecated"), Level::Allow),
+        ]);
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(get_args(&["diff", "a.sc", "b.sc"]).unwrap().diff_format, DiffFormat::Text);
+        assert_eq!(get_args(&["diff", "--format", "json"]).unwrap().diff_format, DiffFormat::Json);
+        assert_eq!(get_args(&["graph", "--format", "json"]).unwrap().format, GraphFormat::Json);
+        assert_eq!(get_args(&["diff", "--format", "dot"]).unwrap_err(), "unknown diff format `dot`");
+        assert_eq!(get_args(&["check", "--format", "json"]).unwrap_err(), "`--format` can't be used with `simcom check`");
+    }
+
+    #[test]
+    fn bad_values() {
+        assert_eq!(get_args(&["--lang"]).unwrap_err(), "`--lang` needs a value");
+        assert_eq!(get_args(&["--lang", "fr"]).unwrap_err(), "unknown language `fr`");
+        assert_eq!(get_args(&["--locale", "fr"]).unwrap_err(), "unknown locale `fr`");
+        assert!(get_args(&["--nope"]).unwrap_err().starts_with("unknown option `--nope`"));
+        assert_eq!(get_args(&["-j", "x"]).unwrap_err(), "`-j` expects a number, found `x`");
+    }
+
+    #[test]
+    fn explain() {
+        assert_eq!(get_args(&["--explain", "E0001"]).unwrap().explain, Some(String::from("E0001")));
+        assert_eq!(get_args(&["check", "--explain"]).unwrap_err(), "`--explain` needs a value");
+    }
+
+    #[test]
+    fn usage_lists_every_command() {
+        for &(name, _, _) in COMMANDS {
+            assert!(USAGE.contains(&format!("\n    {} ", name)), "`{}` isn't in the usage", name);
+        }
+    }
+}
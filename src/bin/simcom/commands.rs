@@ -0,0 +1,268 @@
+//! One function for each subcommand that works on the inputs.
+//!
+//! They all start from a [`Session`][0], so they only have to care about what
+//! makes them different.
+//!
+//! [0]: ../session/struct.Session.html
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use simcom::codegen;
+use simcom::diagnostics::{Diagnostic, Severity};
+use simcom::diff::diff_schemas;
+use simcom::doc;
+use simcom::doc::markdown::{self, Layout};
+use simcom::fmt::{format_source, FormatOptions};
+use simcom::graph::Graph;
+use simcom::json::{Json, ToJson};
+use simcom::lint::{self, Lint};
+
+use cli::{Args, DiffFormat};
+use diff;
+use session::{load_config, Session};
+
+/// Compiles the inputs, writing the code to the configured output or stdout.
+pub fn build(args: &Args) -> Result<(), String> {
+    let session = Session::new(load_config(args)?, args)?;
+    let config = &session.config;
+    let semantic = session.analyze("compile")?;
+
+    let started = Instant::now();
+    let mut output = codegen::emit(&semantic, config.backend, &config.builtins);
+    if config.stamp {
+        output.insert_str(0, &session.compilation.metadata().header());
+    }
+    info!("generated {} bytes of code in {:.2?}", output.len(), started.elapsed());
+
+    match config.output {
+        Some(ref path) => fs::write(path, output)
+            .map_err(|e| format!("{}: {}", path.display(), e)),
+        None => io::stdout().write_all(output.as_bytes())
+            .map_err(|e| format!("<stdout>: {}", e)),
+    }
+}
+
+/// Like `build`, but nothing is generated: only errors are reported. With
+/// `--fix`, the syntax errors that can be fixed are fixed first.
+pub fn check(args: &Args) -> Result<(), String> {
+    let mut session = Session::new(load_config(args)?, args)?;
+    if args.fix {
+        session.fix_syntax()?;
+    }
+    session.analyze("check the inputs").map(|_| ())
+}
+
+/// Prints every token with its position, one per line.
+pub fn tokens(args: &Args) -> Result<(), String> {
+    let session = Session::new(load_config(args)?, args)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    for file in session.compilation.sources().iter() {
+        for (token, span) in session.compilation.lexer(file).spanned() {
+            writeln!(out, "{}\t{}..{}\t{:?}", session.locate(span),
+                span.start, span.end, token)
+                .map_err(|e| format!("<stdout>: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the lints and prints what they find, with a summary at the end.
+/// Denied lints and syntax errors make the command fail.
+pub fn lint(args: &Args) -> Result<(), String> {
+    let config = load_config(args)?;
+    for name in config.lints.keys() {
+        if Lint::from_name(name).is_none() {
+            return Err(format!("unknown lint `{}`", name));
+        }
+    }
+
+    let mut session = Session::new(config, args)?;
+    if args.fix {
+        session.fix_syntax()?;
+    }
+    let semantic = session.analyze("lint")?;
+
+    let mut warnings = 0;
+    let mut errors = 0;
+
+    let started = Instant::now();
+    let mut findings = lint::run(&semantic, &session.config.lints);
+    info!("ran the lints in {:.2?}", started.elapsed());
+
+    // What's left after fixing is what gets reported.
+    if args.fix {
+        let diagnostics: Vec<Diagnostic> = findings.iter().cloned().map(Diagnostic::from).collect();
+        if session.fix(&diagnostics)? > 0 {
+            let semantic = session.analyze("lint")?;
+            findings = lint::run(&semantic, &session.config.lints);
+        }
+    }
+
+    for finding in findings {
+        let diagnostic = Diagnostic::from(finding);
+        match diagnostic.severity {
+            Severity::Error => errors += 1,
+            Severity::Warning => warnings += 1,
+        }
+        eprint!("{}", session.compilation.render(&diagnostic));
+    }
+
+    eprintln!("{} {}, {} {}",
+        warnings, if warnings == 1 { "warning" } else { "warnings" },
+        errors, if errors == 1 { "error" } else { "errors" });
+
+    match errors {
+        0 => Ok(()),
+        _ => Err(String::from("denied lints were found")),
+    }
+}
+
+/// Writes the documentation of every type to the output directory.
+pub fn doc(args: &Args) -> Result<(), String> {
+    if args.single_page && !args.markdown {
+        return Err(String::from("`--single-page` only works with `--markdown`"));
+    }
+
+    let session = Session::new(load_config(args)?, args)?;
+    let semantic = session.analyze("document")?;
+
+    let out_dir = args.out_dir.clone().unwrap_or_else(|| PathBuf::from("doc"));
+    fs::create_dir_all(&out_dir)
+        .map_err(|e| format!("{}: {}", out_dir.display(), e))?;
+
+    let pages = match (args.markdown, args.single_page) {
+        (false, _) => doc::render(&semantic, "Documentation"),
+        (true, false) => markdown::render(&semantic, "Documentation", Layout::PagePerType),
+        (true, true) => markdown::render(&semantic, "Documentation", Layout::SinglePage),
+    };
+    for page in pages {
+        let path = out_dir.join(&page.path);
+        fs::write(&path, page.content)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+
+    Ok(())
+}
+
+/// Prints the dependency graph, or the part of it reachable from the root.
+pub fn graph(args: &Args) -> Result<(), String> {
+    let session = Session::new(load_config(args)?, args)?;
+    let semantic = session.analyze("build the graph")?;
+
+    let mut graph = Graph::new(&semantic);
+    if let Some(ref root) = args.root {
+        graph = graph.closure(root)
+            .ok_or_else(|| format!("unknown type `{}`", root))?;
+    }
+
+    print!("{}", graph.render(args.format));
+    Ok(())
+}
+
+/// Compares the old version of a schema with the new one, each analyzed on
+/// its own. Breaking changes make the command fail, after printing them all.
+pub fn diff(args: &Args) -> Result<(), String> {
+    let (old, new) = match args.inputs[..] {
+        [ref old, ref new] => (old, new),
+        _ => return Err(String::from("expected two inputs, the old schema and the new one")),
+    };
+
+    let config = load_config(args)?;
+    let analyze = |input: &PathBuf| {
+        let mut config = config.clone();
+        config.inputs = vec![input.clone()];
+        Session::new(config, args)?.analyze(&format!("compare `{}`", input.display()))
+    };
+    let changes = diff_schemas(&analyze(old)?, &analyze(new)?);
+    let breaking = changes.iter().filter(|change| change.is_breaking()).count();
+
+    match args.diff_format {
+        DiffFormat::Text => {
+            for change in &changes {
+                if change.is_breaking() {
+                    println!("{} (breaking)", change);
+                } else {
+                    println!("{}", change);
+                }
+            }
+        },
+        DiffFormat::Json => {
+            let changes = changes.iter()
+                .map(|change| Json::object(vec![
+                    ("change", change.to_json()),
+                    ("message", Json::from(change.to_string())),
+                    ("breaking", Json::from(change.is_breaking())),
+                ]))
+                .collect();
+            println!("{}", Json::object(vec![("changes", Json::Array(changes)), ("breaking", Json::from(breaking > 0))]));
+        },
+    }
+
+    match breaking {
+        0 => Ok(()),
+        1 => Err(String::from("1 breaking change was found")),
+        n => Err(format!("{} breaking changes were found", n)),
+    }
+}
+
+/// Formats every input. With `--check` nothing is written, differences are
+/// printed as a diff and count as failures.
+pub fn format(args: &Args) -> Result<(), String> {
+    let session = Session::new(load_config(args)?, args)?;
+    let options = FormatOptions::from(&session.config);
+    let mut failed = 0;
+    let mut unformatted = 0;
+
+    // Files are in the compilation in the same order as in the sources.
+    for (source, file) in session.sources.iter().zip(session.compilation.sources().iter()) {
+        let name = source.name();
+        let content = &source.content;
+
+        let formatted = match format_source(content, options) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for mut error in errors {
+                    error.span = error.span.in_file(file.id);
+                    eprint!("{}", session.compilation.render(&error));
+                }
+                failed += 1;
+                continue;
+            },
+        };
+
+        debug!("{}: {}", name, if formatted == *content { "already formatted" } else { "needs formatting" });
+
+        if args.check {
+            if formatted != *content {
+                print!("{}", diff::unified(&name, &format!("{} (formatted)", name),
+                    content, &formatted));
+                unformatted += 1;
+            }
+            continue;
+        }
+
+        match source.path {
+            Some(ref path) if !args.stdout => {
+                if formatted != *content {
+                    fs::write(path, formatted)
+                        .map_err(|e| format!("{}: {}", name, e))?;
+                }
+            },
+            _ => print!("{}", formatted),
+        }
+    }
+
+    match (failed, unformatted) {
+        (0, 0) => Ok(()),
+        (0, 1) => Err(String::from("1 file isn't formatted")),
+        (0, n) => Err(format!("{} files aren't formatted", n)),
+        (1, _) => Err(String::from("1 file couldn't be formatted")),
+        (n, _) => Err(format!("{} files couldn't be formatted", n)),
+    }
+}
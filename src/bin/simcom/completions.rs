@@ -0,0 +1,280 @@
+//! The `simcom completions` subcommand.
+//!
+//! Prints a completion script for bash, zsh or fish. All of them are written
+//! from the table of subcommands and the table of options below, which has to
+//! be kept in sync with the usage message (a test makes sure of it).
+
+use simcom::lint::Lint;
+
+use cli::COMMANDS;
+
+
+/// What an option takes after it.
+#[derive(Clone, Copy)]
+enum Value {
+    /// Nothing, it is a flag.
+    Nothing,
+    File,
+    Directory,
+    /// One of these words.
+    OneOf(&'static [&'static str]),
+    /// The name of a lint.
+    Lint,
+    /// Anything, there is nothing to complete.
+    Any,
+}
+
+struct Opt {
+    short: Option<char>,
+    long: &'static str,
+    value: Value,
+    /// Whether it can be given more than once.
+    repeated: bool,
+    description: &'static str,
+}
+
+const fn opt(short: Option<char>, long: &'static str, value: Value, description: &'static str) -> Opt {
+    Opt { short, long, value, repeated: false, description }
+}
+
+const fn repeated(short: Option<char>, long: &'static str, value: Value, description: &'static str) -> Opt {
+    Opt { short, long, value, repeated: true, description }
+}
+
+const OPTIONS: &[Opt] = &[
+    opt(Some('c'), "config", Value::File, "Use FILE instead of looking for simcom.toml"),
+    opt(None, "no-config", Value::Nothing, "Don't look for simcom.toml"),
+    opt(None, "lang", Value::OneOf(&["es", "en"]), "Language of the keywords"),
+    opt(None, "locale", Value::OneOf(&["es", "en"]), "Language of the messages"),
+    opt(None, "backend", Value::OneOf(&["c"]), "Code generator to use"),
+    repeated(None, "builtin", Value::Any, "Add or replace a built-in type"),
+    repeated(None, "ext", Value::Any, "Extension of the files searched in directories"),
+    opt(Some('o'), "output", Value::File, "Write the generated code to FILE"),
+    opt(None, "stamp", Value::Nothing, "Say what the code was generated from"),
+    opt(None, "max-errors", Value::Any, "Report at most N syntax errors"),
+    opt(None, "stdin-filename", Value::File, "Name of the file being read from stdin"),
+    opt(Some('j'), "jobs", Value::Any, "Parse the files in N threads"),
+    opt(None, "cache", Value::Directory, "Keep what was parsed and analyzed in DIR"),
+    opt(None, "no-cache", Value::Nothing, "Don't use the cache"),
+    opt(None, "explain", Value::Any, "Explain the diagnostics with CODE"),
+    opt(None, "stdout", Value::Nothing, "Print the formatted code"),
+    opt(None, "check", Value::Nothing, "Only check that the inputs are formatted"),
+    opt(None, "fix", Value::Nothing, "Apply the suggestions that are certainly right"),
+    opt(None, "out-dir", Value::Directory, "Where to write the documentation"),
+    opt(None, "markdown", Value::Nothing, "Write Markdown documentation"),
+    opt(None, "single-page", Value::Nothing, "Write the Markdown documentation to a single page"),
+    opt(None, "format", Value::OneOf(&["dot", "mermaid", "json", "text"]), "Format of the graph or the diff"),
+    opt(None, "root", Value::Any, "Only the types TYPE depends on"),
+    repeated(Some('D'), "deny", Value::Lint, "Report LINT as an error"),
+    repeated(Some('W'), "warn", Value::Lint, "Report LINT as a warning"),
+    repeated(Some('A'), "allow", Value::Lint, "Don't run LINT"),
+    repeated(Some('v'), "verbose", Value::Nothing, "Print what is being done"),
+    opt(Some('h'), "help", Value::Nothing, "Print the help"),
+];
+
+/// The shells we can write scripts for.
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+/// The completion script for `shell`, if we know it.
+pub fn script(shell: &str) -> Option<String> {
+    match shell {
+        "bash" => Some(bash()),
+        "zsh" => Some(zsh()),
+        "fish" => Some(fish()),
+        _ => None,
+    }
+}
+
+fn lint_names() -> Vec<&'static str> {
+    Lint::ALL.iter().map(|lint| lint.name()).collect()
+}
+
+fn words(value: Value) -> Option<Vec<&'static str>> {
+    match value {
+        Value::OneOf(words) => Some(words.to_vec()),
+        Value::Lint => Some(lint_names()),
+        _ => None,
+    }
+}
+
+/// Every spelling of an option, like `-c|--config`.
+fn spellings(option: &Opt) -> Vec<String> {
+    let mut spellings = Vec::new();
+    if let Some(short) = option.short {
+        spellings.push(format!("-{}", short));
+    }
+    spellings.push(format!("--{}", option.long));
+    spellings
+}
+
+fn bash() -> String {
+    let subcommands: Vec<&str> = COMMANDS.iter().map(|&(name, _, _)| name).collect();
+    let options: Vec<String> = OPTIONS.iter().flat_map(spellings).collect();
+
+    let mut cases = String::new();
+    for option in OPTIONS {
+        let action = match (option.value, words(option.value)) {
+            (Value::Nothing, _) => continue,
+            (_, Some(words)) => format!("COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", words.join(" ")),
+            (Value::File, _) => String::from("COMPREPLY=($(compgen -f -- \"$cur\"))"),
+            (Value::Directory, _) => String::from("COMPREPLY=($(compgen -d -- \"$cur\"))"),
+            _ => String::from("COMPREPLY=()"),
+        };
+        cases.push_str(&format!("        {})\n            {}\n            return;;\n",
+            spellings(option).join("|"), action));
+    }
+
+    format!("\
+_simcom() {{
+    local cur prev
+    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"
+    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"
+
+    case \"$prev\" in
+{cases}    esac
+
+    if [[ \"${{COMP_WORDS[1]}}\" == completions ]]; then
+        COMPREPLY=($(compgen -W \"{shells}\" -- \"$cur\"))
+    elif [[ \"$cur\" == -* ]]; then
+        COMPREPLY=($(compgen -W \"{options}\" -- \"$cur\"))
+    elif [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W \"{subcommands}\" -- \"$cur\") $(compgen -f -- \"$cur\"))
+    else
+        COMPREPLY=($(compgen -f -- \"$cur\"))
+    fi
+}}
+
+complete -o filenames -F _simcom simcom
+",
+        cases = cases,
+        shells = SHELLS.join(" "),
+        options = options.join(" "),
+        subcommands = subcommands.join(" "))
+}
+
+/// Escapes the characters `_arguments` gives a meaning to.
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh() -> String {
+    let mut arguments = Vec::new();
+
+    for option in OPTIONS {
+        let spellings = spellings(option);
+        let exclusion = match (option.repeated, spellings.len()) {
+            (true, _) => String::from("*"),
+            (false, 1) => String::new(),
+            (false, _) => format!("({})", spellings.join(" ")),
+        };
+        // Brace expansion only works outside the quotes.
+        let names = match spellings.len() {
+            1 => spellings[0].clone(),
+            _ => format!("'{{{}}}'", spellings.join(",")),
+        };
+        let action = match (option.value, words(option.value)) {
+            (Value::Nothing, _) => String::new(),
+            (_, Some(words)) => format!(":{}:({})", option.long, words.join(" ")),
+            (Value::File, _) => String::from(":file:_files"),
+            (Value::Directory, _) => String::from(":directory:_files -/"),
+            _ => format!(":{}: ", option.long),
+        };
+
+        arguments.push(format!("'{}{}[{}]{}'",
+            exclusion, names, zsh_escape(option.description), action));
+    }
+
+    let subcommands: Vec<String> = COMMANDS.iter()
+        .map(|&(name, _, description)| format!("{}\\:\"{}\"", name, description))
+        .collect();
+    arguments.push(format!("'1: :{{_alternative \"commands:command:(({}))\" \"files:input:_files\"}}'",
+        subcommands.join(" ")));
+    arguments.push(String::from("'*:input:_files'"));
+
+    format!("#compdef simcom\n\n_simcom() {{\n    _arguments -s \\\n        {}\n}}\n\n_simcom \"$@\"\n",
+        arguments.join(" \\\n        "))
+}
+
+fn fish() -> String {
+    let mut out = String::new();
+
+    for &(name, _, description) in COMMANDS {
+        out.push_str(&format!("complete -c simcom -n __fish_use_subcommand -a {} -d '{}'\n",
+            name, description));
+    }
+    out.push_str(&format!("complete -c simcom -n '__fish_seen_subcommand_from completions' -f -a '{}'\n",
+        SHELLS.join(" ")));
+
+    for option in OPTIONS {
+        let mut line = String::from("complete -c simcom");
+        if let Some(short) = option.short {
+            line.push_str(&format!(" -s {}", short));
+        }
+        line.push_str(&format!(" -l {}", option.long));
+
+        match (option.value, words(option.value)) {
+            (Value::Nothing, _) => (),
+            (_, Some(words)) => line.push_str(&format!(" -x -a '{}'", words.join(" "))),
+            (Value::File, _) => line.push_str(" -r -F"),
+            (Value::Directory, _) => line.push_str(" -x -a '(__fish_complete_directories)'"),
+            _ => line.push_str(" -x"),
+        }
+
+        line.push_str(&format!(" -d '{}'\n", option.description.replace('\'', "\\'")));
+        out.push_str(&line);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::cli::USAGE;
+    use super::*;
+
+    #[test]
+    fn in_sync_with_usage() {
+        for option in OPTIONS {
+            assert!(USAGE.contains(&format!("--{}", option.long)), "--{} isn't in the usage", option.long);
+            if let Some(short) = option.short {
+                assert!(USAGE.contains(&format!("-{}, --{}", short, option.long)));
+            }
+        }
+    }
+
+    #[test]
+    fn bash() {
+        let script = script("bash").unwrap();
+
+        assert!(script.contains("        --lang)\n            COMPREPLY=($(compgen -W \"es en\" -- \"$cur\"))\n"));
+        assert!(script.contains("-D|--deny)\n            COMPREPLY=($(compgen -W \"naming unused_types"));
+        assert!(script.ends_with("complete -o filenames -F _simcom simcom\n"));
+    }
+
+    #[test]
+    fn zsh() {
+        let script = script("zsh").unwrap();
+
+        assert!(script.starts_with("#compdef simcom\n"));
+        assert!(script.contains("'(-c --config)'{-c,--config}'[Use FILE instead of looking for simcom.toml]:file:_files'"));
+        assert!(script.contains("'*'{-A,--allow}'[Don'\\''t run LINT]:allow:(naming"));
+        assert!(script.contains("'--no-config[Don'\\''t look for simcom.toml]'"));
+    }
+
+    #[test]
+    fn fish() {
+        let script = script("fish").unwrap();
+
+        assert!(script.contains("complete -c simcom -n __fish_use_subcommand -a fmt -d 'Format the inputs'\n"));
+        assert!(script.contains("complete -c simcom -l format -x -a 'dot mermaid json text' -d 'Format of the graph or the diff'\n"));
+        assert!(script.contains("complete -c simcom -s o -l output -r -F -d 'Write the generated code to FILE'\n"));
+    }
+
+    #[test]
+    fn unknown_shell() {
+        assert_eq!(script("cmd"), None);
+    }
+}
@@ -0,0 +1,160 @@
+//! Line based unified diffs.
+//!
+//! Good enough to show what `simcom fmt --check` would change. The longest
+//! common subsequence is computed with the classic quadratic table, which is
+//! fine for files of a few thousand lines.
+
+/// Lines of context around each change.
+const CONTEXT: usize = 3;
+
+/// An edit in the script that turns the old text into the new one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Keep,
+    Remove,
+    Insert,
+}
+
+/// Renders the differences between `old` and `new` as a unified diff, or an
+/// empty string when they are equal.
+pub fn unified(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let edits = edits(&old, &new);
+
+    if edits.iter().all(|&edit| edit == Edit::Keep) {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+
+    // Position of every edit in both texts, so hunks know where they start.
+    let mut positions = Vec::with_capacity(edits.len());
+    let (mut o, mut n) = (0, 0);
+    for &edit in &edits {
+        positions.push((o, n));
+        match edit {
+            Edit::Keep => { o += 1; n += 1; },
+            Edit::Remove => o += 1,
+            Edit::Insert => n += 1,
+        }
+    }
+
+    let mut i = 0;
+    while i < edits.len() {
+        if edits[i] == Edit::Keep {
+            i += 1;
+            continue;
+        }
+
+        // A hunk goes from a change to the last one that is close enough,
+        // with some context on both sides.
+        let start = i.saturating_sub(CONTEXT);
+        let mut end = i;
+        let mut j = i;
+        while j < edits.len() {
+            if edits[j] != Edit::Keep {
+                end = j;
+            } else if j - end > 2 * CONTEXT {
+                break;
+            }
+            j += 1;
+        }
+        let end = (end + CONTEXT + 1).min(edits.len());
+
+        let (old_start, new_start) = positions[start];
+        let old_len = edits[start..end].iter().filter(|&&e| e != Edit::Insert).count();
+        let new_len = edits[start..end].iter().filter(|&&e| e != Edit::Remove).count();
+        out.push_str(&format!("@@ -{} +{} @@\n",
+            range(old_start, old_len), range(new_start, new_len)));
+
+        for k in start..end {
+            let (o, n) = positions[k];
+            match edits[k] {
+                Edit::Keep => out.push_str(&format!(" {}\n", old[o])),
+                Edit::Remove => out.push_str(&format!("-{}\n", old[o])),
+                Edit::Insert => out.push_str(&format!("+{}\n", new[n])),
+            }
+        }
+
+        i = end;
+    }
+
+    out
+}
+
+/// The `start,length` part of a hunk header. Lines are numbered from 1, and an
+/// empty range points to the line before it.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// The shortest edit script, from the longest common subsequence.
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // lcs[i][j] is the length of the LCS of old[i..] and new[j..].
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            edits.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            edits.push(Edit::Remove);
+            i += 1;
+        } else {
+            edits.push(Edit::Insert);
+            j += 1;
+        }
+    }
+
+    edits
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn equal() {
+        assert_eq!(unified("a", "b", "x\ny\n", "x\ny\n"), "");
+    }
+
+    #[test]
+    fn single_change() {
+        let diff = unified("old", "new", "a\nb\nc\n", "a\nB\nc\n");
+        assert_eq!(diff, "--- old\n+++ new\n@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n");
+    }
+
+    #[test]
+    fn separate_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "one\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\ntwelve\n";
+        let diff = unified("old", "new", old, new);
+
+        assert_eq!(diff, "--- old\n+++ new\n\
+                          @@ -1,4 +1,4 @@\n-1\n+one\n 2\n 3\n 4\n\
+                          @@ -9,4 +9,4 @@\n 9\n 10\n 11\n-12\n+twelve\n");
+    }
+
+    #[test]
+    fn insertion_at_end() {
+        let diff = unified("old", "new", "a\n", "a\nb\n");
+        assert_eq!(diff, "--- old\n+++ new\n@@ -1 +1,2 @@\n a\n+b\n");
+    }
+}
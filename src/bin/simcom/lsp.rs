@@ -0,0 +1,983 @@
+//! The `simcom lsp` subcommand.
+//!
+//! A minimal language server, so editors can show errors as you type. It
+//! talks JSON-RPC over stdin and stdout and only knows about the documents the
+//! editor opens: it never reads the files on disk.
+//!
+//! What it does:
+//!
+//! - Publishes the syntax errors and lint findings of a document every time
+//!   it is opened or changed.
+//! - Lists the types (and their fields) of a document.
+//! - Jumps from a field's type to the definition of that type, looking in
+//!   every open document.
+//! - Finds the fields of every open document that use a type.
+//! - Completes the types of fields.
+//! - Renames a type in every open document.
+//! - Offers the suggestions of the diagnostics as quick fixes.
+//! - Formats a document, like `simcom fmt`.
+//!
+//! The editor sends only what changed, and only the definitions the change
+//! touches are [parsed again][0].
+//!
+//! [0]: ../../simcom/parser/incremental/index.html
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+use simcom::config::Config;
+use simcom::diagnostics::{Diagnostic, Severity};
+use simcom::fmt::{format_source, FormatOptions};
+use simcom::ide::{self, CompletionKind, CompletionOptions, FoldingKind, Index, Position, Symbol, SymbolKind};
+use simcom::json::Json;
+use simcom::lexer::Span;
+use simcom::lint;
+use simcom::locale::{translate, Locale};
+use simcom::parser::incremental::{Edit, ParsedFile};
+use simcom::parser::{Ast, TypeDefinition};
+use simcom::semantic::Semantic;
+use simcom::source_map::SourceMap;
+
+/// Error codes from the JSON-RPC and LSP specifications.
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const REQUEST_FAILED: i32 = -32803;
+
+/// Kinds of document symbols, from the LSP specification.
+const SYMBOL_FIELD: usize = 8;
+const SYMBOL_ENUM: usize = 10;
+const SYMBOL_INTERFACE: usize = 11;
+const SYMBOL_ENUM_MEMBER: usize = 22;
+const SYMBOL_STRUCT: usize = 23;
+
+/// Kinds of completion items, from the LSP specification.
+const COMPLETION_KEYWORD: usize = 14;
+const COMPLETION_STRUCT: usize = 22;
+
+/// Serves requests until the editor says goodbye.
+pub fn run(config: Config) -> Result<(), String> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut input = stdin.lock();
+    let mut output = stdout.lock();
+    let mut server = Server::new(config);
+
+    loop {
+        let message = match read_message(&mut input).map_err(|e| format!("<stdin>: {}", e))? {
+            Some(message) => message,
+            // The editor went away without asking us to exit.
+            None => return Err(String::from("the client closed the connection")),
+        };
+
+        let responses = match Json::parse(&message) {
+            Ok(message) => server.handle(&message),
+            Err(e) => {
+                eprintln!("error: invalid message: {}", e);
+                continue;
+            },
+        };
+
+        for response in responses {
+            write_message(&mut output, &response).map_err(|e| format!("<stdout>: {}", e))?;
+        }
+
+        match server.exit {
+            Some(true) => return Ok(()),
+            Some(false) => return Err(String::from("exit before shutdown")),
+            None => (),
+        }
+    }
+}
+
+/// Reads the body of the next message, or `None` at the end of the input.
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length")
+    })?;
+
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message<W: Write>(output: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// The state of the server: the open documents by URI, already parsed.
+struct Server {
+    config: Config,
+    documents: BTreeMap<String, ParsedFile>,
+    shutdown: bool,
+    /// Set when the editor asks us to exit, to whether it was a clean one.
+    exit: Option<bool>,
+}
+
+impl Server {
+    fn new(config: Config) -> Server {
+        Server {
+            config,
+            documents: BTreeMap::new(),
+            shutdown: false,
+            exit: None,
+        }
+    }
+
+    /// Handles a single message, returning what has to be sent back.
+    fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").unwrap_or(&Json::Null);
+
+        // Requests have an id and always get a response, notifications don't.
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => return self.notification(method, params),
+        };
+
+        if self.shutdown {
+            return vec![error(id, INVALID_REQUEST, "the server is shutting down")];
+        }
+
+        let result = match method {
+            "initialize" => capabilities(),
+            "shutdown" => {
+                self.shutdown = true;
+                Json::Null
+            },
+            "textDocument/documentSymbol" => self.symbols(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/codeAction" => self.code_actions(params),
+            "textDocument/formatting" => self.format(params),
+            "textDocument/foldingRange" => self.folding(params),
+            "textDocument/rename" => match self.rename(params) {
+                Ok(edit) => edit,
+                Err(message) => return vec![error(id, REQUEST_FAILED, &message)],
+            },
+            _ => return vec![error(id, METHOD_NOT_FOUND, &format!("unknown method `{}`", method))],
+        };
+
+        vec![Json::object(vec![
+            ("jsonrpc", Json::from("2.0")),
+            ("id", id),
+            ("result", result),
+        ])]
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params.pointer(&["textDocument", "uri"])
+            .and_then(Json::as_str)
+            .map(String::from);
+
+        match (method, uri) {
+            ("exit", _) => {
+                self.exit = Some(self.shutdown);
+                Vec::new()
+            },
+            ("textDocument/didOpen", Some(uri)) => {
+                let text = params.pointer(&["textDocument", "text"])
+                    .and_then(Json::as_str)
+                    .unwrap_or("");
+                self.documents.insert(uri.clone(), ParsedFile::new(String::from(text), self.config.language));
+                vec![self.diagnostics(&uri)]
+            },
+            ("textDocument/didChange", Some(uri)) => {
+                let changes = params.get("contentChanges").and_then(Json::as_array).unwrap_or(&[]);
+                for change in changes {
+                    self.change(&uri, change);
+                }
+                vec![self.diagnostics(&uri)]
+            },
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(&uri);
+                vec![publish(&uri, Vec::new())]
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Applies a change to a document. Changes with a range only parse
+    /// again the definitions they touch; the rest replace the whole text.
+    fn change(&mut self, uri: &str, change: &Json) {
+        let text = match change.get("text").and_then(Json::as_str) {
+            Some(text) => String::from(text),
+            None => return,
+        };
+        let document = match self.documents.get_mut(uri) {
+            Some(document) => document,
+            None => return,
+        };
+
+        let point = |name: &str| {
+            let line = change.pointer(&["range", name, "line"]).and_then(Json::as_f64)?;
+            let character = change.pointer(&["range", name, "character"]).and_then(Json::as_f64)?;
+            Some(offset(document.source(), line as usize, character as usize))
+        };
+        match (point("start"), point("end")) {
+            (Some(start), Some(end)) if start <= end => {
+                document.edit(&Edit { range: start..end, new_text: text });
+            },
+            _ => *document = ParsedFile::new(text, self.config.language),
+        }
+    }
+
+    /// The definitions of a document and its syntax errors.
+    fn parse<'a>(&self, document: &'a ParsedFile) -> (Vec<&'a TypeDefinition<'static>>, Vec<Diagnostic>) {
+        let mut definitions = Vec::new();
+        let mut errors = Vec::new();
+
+        for ast in document.asts() {
+            match *ast {
+                Ast::TypeDefinition(ref definition) => definitions.push(definition),
+                Ast::Unexpected(ref token, span) => {
+                    errors.push(Diagnostic::unexpected_in(document.source(), token, span));
+                },
+            }
+        }
+
+        (definitions, errors)
+    }
+
+    /// The syntax errors of a document or, if there are none, the errors of
+    /// the analysis or what the lints find in it.
+    fn check(&self, document: &ParsedFile) -> Vec<Diagnostic> {
+        let (definitions, mut diagnostics) = self.parse(document);
+
+        if diagnostics.is_empty() {
+            let asts = definitions.into_iter().cloned().map(Ast::TypeDefinition);
+            match Semantic::check(asts) {
+                Ok(semantic) => {
+                    let findings = lint::run(&semantic, &self.config.lints);
+                    diagnostics.extend(findings.into_iter().map(Diagnostic::from));
+                },
+                Err(errors) => diagnostics.extend(errors),
+            }
+        }
+
+        diagnostics
+    }
+
+    fn diagnostics(&self, uri: &str) -> Json {
+        let document = match self.documents.get(uri) {
+            Some(document) => document,
+            None => return publish(uri, Vec::new()),
+        };
+        let text = document.source();
+
+        let locale = self.config.locale.unwrap_or_default();
+        publish(uri, self.check(document).iter().map(|d| diagnostic(uri, text, d, locale)).collect())
+    }
+
+    /// Every definition as a symbol, with its fields as children.
+    fn symbols(&self, params: &Json) -> Json {
+        let document = match self.document(params) {
+            Some((_, document)) => document,
+            None => return Json::Null,
+        };
+        let text = document.source();
+
+        let symbols = self.parse(document).0.into_iter()
+            .map(|definition| symbol(text, Symbol::from(definition)))
+            .collect();
+
+        Json::Array(symbols)
+    }
+
+    /// Where the type under the cursor is defined. The document itself is
+    /// searched first, then the rest of the open ones.
+    fn definition(&self, params: &Json) -> Json {
+        let (sources, position) = match self.cursor(params) {
+            Some(found) => found,
+            None => return Json::Null,
+        };
+
+        match ide::definition_at_with_language(&sources, position, self.config.language) {
+            Some(found) => location(&sources, found.name_span),
+            None => Json::Null,
+        }
+    }
+
+    /// Every field, in any open document, whose type is the one under the
+    /// cursor, and its definition too if the editor asks for it.
+    fn references(&self, params: &Json) -> Json {
+        let (sources, position) = match self.cursor(params) {
+            Some(found) => found,
+            None => return Json::Null,
+        };
+
+        let index = Index::with_language(&sources, self.config.language);
+        let name = match index.name_at(position) {
+            Some(name) => name,
+            None => return Json::Null,
+        };
+
+        let mut spans = Vec::new();
+        let declaration = params.pointer(&["context", "includeDeclaration"]) == Some(&Json::from(true));
+        if declaration {
+            spans.extend(index.definition(name, position.file).map(|location| location.name_span));
+        }
+        spans.extend_from_slice(index.references(name));
+
+        Json::Array(spans.into_iter().map(|span| location(&sources, span)).collect())
+    }
+
+    /// The types that can go at the cursor, when it's where the type of a
+    /// field goes.
+    fn completion(&self, params: &Json) -> Json {
+        let (sources, position) = match self.cursor(params) {
+            Some(found) => found,
+            None => return Json::Null,
+        };
+
+        let options = CompletionOptions {
+            language: self.config.language,
+            builtins: self.config.builtins.clone(),
+            exclude_enclosing: false,
+        };
+        let items = ide::completions_at(&sources, position, &options).into_iter()
+            .map(|completion| {
+                let kind = match completion.kind {
+                    CompletionKind::Type => COMPLETION_STRUCT,
+                    CompletionKind::Builtin => COMPLETION_KEYWORD,
+                };
+                let mut fields = vec![
+                    ("label", Json::from(completion.label)),
+                    ("kind", Json::from(kind)),
+                ];
+                if let Some(documentation) = completion.documentation {
+                    fields.push(("documentation", Json::from(documentation)));
+                }
+                Json::object(fields)
+            })
+            .collect();
+
+        Json::Array(items)
+    }
+
+    /// The edits, in every open document, that rename the type under the
+    /// cursor.
+    fn rename(&self, params: &Json) -> Result<Json, String> {
+        let (sources, position) = self.cursor(params).ok_or("no document at the position")?;
+        let new_name = params.get("newName").and_then(Json::as_str).ok_or("`newName` is missing")?;
+
+        let index = Index::with_language(&sources, self.config.language);
+        let name = index.name_at(position).ok_or("there is no type to rename here")?;
+        let edits = index.rename(name, new_name).map_err(|e| e.to_string())?;
+
+        let mut changes: Vec<(String, Json)> = Vec::new();
+        for edit in edits {
+            let file = match sources.get(edit.span.file) {
+                Some(file) => file,
+                None => continue,
+            };
+            let text_edit = Json::object(vec![
+                ("range", range(&file.content, edit.span)),
+                ("newText", Json::from(edit.replacement)),
+            ]);
+            match changes.iter_mut().find(|&&mut (ref uri, _)| *uri == file.name) {
+                Some(&mut (_, Json::Array(ref mut edits))) => edits.push(text_edit),
+                _ => changes.push((file.name.clone(), Json::Array(vec![text_edit]))),
+            }
+        }
+
+        Ok(Json::object(vec![("changes", Json::Object(changes))]))
+    }
+
+    /// A quick fix for every suggestion of the diagnostics in the range of
+    /// the request.
+    fn code_actions(&self, params: &Json) -> Json {
+        let (uri, document) = match self.document(params) {
+            Some(found) => found,
+            None => return Json::Null,
+        };
+        let text = document.source();
+
+        let point = |name: &str| {
+            let line = params.pointer(&["range", name, "line"]).and_then(Json::as_f64)?;
+            let character = params.pointer(&["range", name, "character"]).and_then(Json::as_f64)?;
+            Some(offset(text, line as usize, character as usize))
+        };
+        let (start, end) = match (point("start"), point("end")) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Json::Null,
+        };
+
+        let locale = self.config.locale.unwrap_or_default();
+        let mut actions = Vec::new();
+        for found in self.check(document) {
+            if found.span.end < start || end < found.span.start {
+                continue;
+            }
+
+            for suggestion in &found.suggestions {
+                let edits = suggestion.edits.iter()
+                    .map(|edit| Json::object(vec![
+                        ("range", range(text, edit.span)),
+                        ("newText", Json::from(&edit.replacement[..])),
+                    ]))
+                    .collect();
+
+                actions.push(Json::object(vec![
+                    ("title", Json::from(translate(&suggestion.message, locale))),
+                    ("kind", Json::from("quickfix")),
+                    ("diagnostics", Json::Array(vec![diagnostic(uri, text, &found, locale)])),
+                    ("edit", Json::object(vec![
+                        ("changes", Json::object(vec![(&uri[..], Json::Array(edits))])),
+                    ])),
+                ]));
+            }
+        }
+
+        Json::Array(actions)
+    }
+
+    /// A single edit that replaces the whole document with its formatted
+    /// text. Documents with syntax errors aren't formatted.
+    fn format(&self, params: &Json) -> Json {
+        let document = match self.document(params) {
+            Some((_, document)) => document,
+            None => return Json::Null,
+        };
+        let text = document.source();
+
+        match format_source(text, FormatOptions::from(&self.config)) {
+            Ok(ref formatted) if formatted == text => Json::Array(Vec::new()),
+            Ok(formatted) => Json::Array(vec![Json::object(vec![
+                ("range", range(text, Span::new(0, text.len()))),
+                ("newText", Json::from(formatted)),
+            ])]),
+            Err(_) => Json::Null,
+        }
+    }
+
+    /// The definitions and comment runs of a document, by lines. Editors
+    /// fold everything but the first line.
+    fn folding(&self, params: &Json) -> Json {
+        let document = match self.document(params) {
+            Some((_, document)) => document,
+            None => return Json::Null,
+        };
+        let text = document.source();
+        let line = |offset: usize| text[..offset].matches('\n').count();
+
+        let ranges = ide::folding_ranges(text, self.config.language).into_iter()
+            .map(|range| {
+                let mut fields = vec![
+                    ("startLine", Json::from(line(range.span.start))),
+                    ("endLine", Json::from(line(range.span.end))),
+                ];
+                if range.kind == FoldingKind::Comment {
+                    fields.push(("kind", Json::from("comment")));
+                }
+                Json::object(fields)
+            })
+            .collect();
+
+        Json::Array(ranges)
+    }
+
+    /// Every open document, the one of the request first, and the position
+    /// of the cursor in it.
+    fn cursor(&self, params: &Json) -> Option<(SourceMap, Position)> {
+        let (uri, document) = self.document(params)?;
+        let line = params.pointer(&["position", "line"]).and_then(Json::as_f64)?;
+        let character = params.pointer(&["position", "character"]).and_then(Json::as_f64)?;
+
+        let mut sources = SourceMap::new();
+        let file = sources.add(uri.clone(), document.source());
+        for (other, document) in self.documents.iter().filter(|&(other, _)| other != uri) {
+            sources.add(other.clone(), document.source());
+        }
+
+        let position = Position::new(file, offset(document.source(), line as usize, character as usize));
+        Some((sources, position))
+    }
+
+    /// The open document a request is about, with its URI.
+    fn document(&self, params: &Json) -> Option<(&String, &ParsedFile)> {
+        let uri = params.pointer(&["textDocument", "uri"]).and_then(Json::as_str)?;
+        self.documents.get_key_value(uri)
+    }
+}
+
+/// A diagnostic as the LSP specification wants it. Labels are related
+/// information, in the same document.
+fn diagnostic(uri: &str, text: &str, diagnostic: &Diagnostic, locale: Locale) -> Json {
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+    };
+
+    let mut fields = vec![
+        ("range", range(text, diagnostic.span)),
+        ("severity", Json::from(severity)),
+    ];
+    if let Some(ref code) = diagnostic.code {
+        fields.push(("code", Json::from(&code[..])));
+    }
+    fields.push(("source", Json::from("simcom")));
+
+    let mut message = diagnostic.message_in(locale);
+    for note in &diagnostic.notes {
+        message.push_str(&format!("\n{}: {}", translate("note", locale), translate(note, locale)));
+    }
+    fields.push(("message", Json::from(message)));
+
+    if !diagnostic.labels.is_empty() {
+        let related = diagnostic.labels.iter()
+            .map(|label| Json::object(vec![
+                ("location", Json::object(vec![
+                    ("uri", Json::from(uri)),
+                    ("range", range(text, label.span)),
+                ])),
+                ("message", Json::from(translate(&label.message, locale))),
+            ]))
+            .collect();
+        fields.push(("relatedInformation", Json::Array(related)));
+    }
+
+    Json::object(fields)
+}
+
+/// A symbol of the outline as the LSP specification wants it. Only
+/// definitions have children.
+fn symbol(text: &str, symbol: Symbol) -> Json {
+    let kind = match symbol.kind {
+        SymbolKind::Type => SYMBOL_STRUCT,
+        SymbolKind::Interface => SYMBOL_INTERFACE,
+        SymbolKind::Enum => SYMBOL_ENUM,
+        SymbolKind::Field => SYMBOL_FIELD,
+        SymbolKind::Variant => SYMBOL_ENUM_MEMBER,
+    };
+
+    let mut fields = vec![("name", Json::from(symbol.name))];
+    if let Some(detail) = symbol.detail {
+        fields.push(("detail", Json::from(detail)));
+    }
+    fields.push(("kind", Json::from(kind)));
+    fields.push(("range", range(text, symbol.span)));
+    fields.push(("selectionRange", range(text, symbol.name_span)));
+    if !matches!(symbol.kind, SymbolKind::Field | SymbolKind::Variant) {
+        let children = symbol.children.into_iter().map(|child| self::symbol(text, child)).collect();
+        fields.push(("children", Json::Array(children)));
+    }
+
+    Json::object(fields)
+}
+
+/// What the server can do.
+fn capabilities() -> Json {
+    Json::object(vec![
+        ("capabilities", Json::object(vec![
+            // Incremental synchronization: changes only send what changed.
+            ("textDocumentSync", Json::from(2)),
+            ("documentSymbolProvider", Json::from(true)),
+            ("definitionProvider", Json::from(true)),
+            ("referencesProvider", Json::from(true)),
+            ("renameProvider", Json::from(true)),
+            ("codeActionProvider", Json::from(true)),
+            ("documentFormattingProvider", Json::from(true)),
+            ("foldingRangeProvider", Json::from(true)),
+            ("completionProvider", Json::object(vec![
+                ("triggerCharacters", Json::Array(vec![Json::from(":")])),
+            ])),
+        ])),
+        ("serverInfo", Json::object(vec![
+            ("name", Json::from("simcom")),
+            ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+        ])),
+    ])
+}
+
+fn error(id: Json, code: i32, message: &str) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::from("2.0")),
+        ("id", id),
+        ("error", Json::object(vec![
+            ("code", Json::from(code)),
+            ("message", Json::from(message)),
+        ])),
+    ])
+}
+
+fn publish(uri: &str, diagnostics: Vec<Json>) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::from("2.0")),
+        ("method", Json::from("textDocument/publishDiagnostics")),
+        ("params", Json::object(vec![
+            ("uri", Json::from(uri)),
+            ("diagnostics", Json::Array(diagnostics)),
+        ])),
+    ])
+}
+
+/// An LSP range. Lines start at 0 and characters are counted in UTF-16 code
+/// units, as the specification wants.
+fn range(text: &str, span: Span) -> Json {
+    Json::object(vec![
+        ("start", position(text, span.start)),
+        ("end", position(text, span.end)),
+    ])
+}
+
+/// An LSP location: the URI of a document and a range in it.
+fn location(sources: &SourceMap, span: Span) -> Json {
+    match sources.get(span.file) {
+        Some(file) => Json::object(vec![
+            ("uri", Json::from(&file.name[..])),
+            ("range", range(&file.content, span)),
+        ]),
+        None => Json::Null,
+    }
+}
+
+fn position(text: &str, offset: usize) -> Json {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+
+    Json::object(vec![
+        ("line", Json::from(line)),
+        ("character", Json::from(character)),
+    ])
+}
+
+/// The byte offset of an LSP position. Positions past the end of a line are
+/// moved to its end.
+fn offset(text: &str, line: usize, character: usize) -> usize {
+    let mut line_start = 0;
+    for _ in 0..line {
+        match text[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return text.len(),
+        }
+    }
+
+    let mut units = 0;
+    for (i, ch) in text[line_start..].char_indices() {
+        if units >= character || ch == '\n' {
+            return line_start + i;
+        }
+        units += ch.len_utf16();
+    }
+
+    text.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn open(server: &mut Server, uri: &str, text: &str) -> Json {
+        let message = Json::object(vec![
+            ("jsonrpc", Json::from("2.0")),
+            ("method", Json::from("textDocument/didOpen")),
+            ("params", Json::object(vec![
+                ("textDocument", Json::object(vec![
+                    ("uri", Json::from(uri)),
+                    ("text", Json::from(text)),
+                ])),
+            ])),
+        ]);
+        server.handle(&message).remove(0)
+    }
+
+    fn request(server: &mut Server, method: &str, params: Json) -> Json {
+        let message = Json::object(vec![
+            ("jsonrpc", Json::from("2.0")),
+            ("id", Json::from(1)),
+            ("method", Json::from(method)),
+            ("params", params),
+        ]);
+        server.handle(&message).remove(0)
+    }
+
+    fn at(uri: &str, line: usize, character: usize) -> Json {
+        Json::object(vec![
+            ("textDocument", Json::object(vec![("uri", Json::from(uri))])),
+            ("position", Json::object(vec![
+                ("line", Json::from(line)),
+                ("character", Json::from(character)),
+            ])),
+        ])
+    }
+
+    #[test]
+    fn framing() {
+        let mut input = &b"Content-Length: 2\r\n\r\n{}Content-Length: 4\r\n\r\nnull"[..];
+        assert_eq!(read_message(&mut input).unwrap(), Some(String::from("{}")));
+        assert_eq!(read_message(&mut input).unwrap(), Some(String::from("null")));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+
+        let mut output = Vec::new();
+        write_message(&mut output, &Json::Null).unwrap();
+        assert_eq!(output, b"Content-Length: 4\r\n\r\nnull");
+    }
+
+    fn change(server: &mut Server, uri: &str, changes: Vec<Json>) -> Json {
+        let message = Json::object(vec![
+            ("jsonrpc", Json::from("2.0")),
+            ("method", Json::from("textDocument/didChange")),
+            ("params", Json::object(vec![
+                ("textDocument", Json::object(vec![("uri", Json::from(uri))])),
+                ("contentChanges", Json::Array(changes)),
+            ])),
+        ]);
+        server.handle(&message).remove(0)
+    }
+
+    #[test]
+    fn incremental_changes() {
+        let mut server = Server::new(Config::default());
+        open(&mut server, "file:///a.sc", "tipo A(b: B);\ntipo B(x: Entero);");
+
+        // Breaks the first line and then fixes it, in two changes.
+        let edit = |line: usize, start: usize, end: usize, text: &str| Json::object(vec![
+            ("range", Json::object(vec![
+                ("start", Json::object(vec![("line", Json::from(line)), ("character", Json::from(start))])),
+                ("end", Json::object(vec![("line", Json::from(line)), ("character", Json::from(end))])),
+            ])),
+            ("text", Json::from(text)),
+        ]);
+        let published = change(&mut server, "file:///a.sc", vec![edit(0, 8, 9, "")]);
+        let diagnostics = published.pointer(&["params", "diagnostics"]).and_then(Json::as_array).unwrap();
+        assert_eq!(diagnostics[0].get("code"), Some(&Json::from("E0001")));
+
+        let published = change(&mut server, "file:///a.sc", vec![edit(0, 8, 8, ":"), edit(1, 5, 6, "Bote")]);
+        assert_eq!(published.pointer(&["params", "diagnostics"]), Some(&Json::Array(vec![])));
+        assert_eq!(server.documents["file:///a.sc"].source(), "tipo A(b: B);\ntipo Bote(x: Entero);");
+
+        // Without a range, the text replaces the whole document.
+        change(&mut server, "file:///a.sc", vec![Json::object(vec![("text", Json::from("tipo C(x: C);"))])]);
+        assert_eq!(server.documents["file:///a.sc"].asts().len(), 1);
+    }
+
+    #[test]
+    fn diagnostics() {
+        let mut server = Server::new(Config::default());
+
+        let published = open(&mut server, "file:///a.sc", "tipo A(b B);");
+        let diagnostics = published.pointer(&["params", "diagnostics"]).unwrap();
+        assert_eq!(diagnostics.to_string(), "[{\"range\":{\
+            \"start\":{\"line\":0,\"character\":9},\"end\":{\"line\":0,\"character\":10}},\
+            \"severity\":1,\"code\":\"E0001\",\"source\":\"simcom\",\"message\":\"unexpected `B`\"}]");
+
+        let published = open(&mut server, "file:///a.sc", "tipo a(x: Entero);");
+        let diagnostics = published.pointer(&["params", "diagnostics"]).unwrap();
+        assert_eq!(diagnostics.as_array().unwrap()[0].get("code"), Some(&Json::from("W0202")));
+    }
+
+    #[test]
+    fn related_information() {
+        let mut server = Server::new(Config::default());
+
+        let published = open(&mut server, "file:///a.sc", "tipo A(x: Entero);\ntipo A(y: Real);");
+        let diagnostic = &published.pointer(&["params", "diagnostics"]).and_then(Json::as_array).unwrap()[0];
+        assert_eq!(diagnostic.get("code"), Some(&Json::from("E0101")));
+        assert_eq!(diagnostic.get("relatedInformation").unwrap().to_string(), "[{\"location\":{\"uri\":\"file:///a.sc\",\
+            \"range\":{\"start\":{\"line\":0,\"character\":5},\"end\":{\"line\":0,\"character\":6}}},\
+            \"message\":\"previous definition of `A` is here\"}]");
+    }
+
+    #[test]
+    fn symbols() {
+        let mut server = Server::new(Config::default());
+        open(&mut server, "file:///a.sc", "tipo Punto(x: Entero);");
+
+        let response = request(&mut server, "textDocument/documentSymbol", at("file:///a.sc", 0, 0));
+        let symbols = response.get("result").and_then(Json::as_array).unwrap();
+        assert_eq!(symbols[0].get("name"), Some(&Json::from("Punto")));
+
+        let children = symbols[0].get("children").and_then(Json::as_array).unwrap();
+        assert_eq!(children[0].get("name"), Some(&Json::from("x")));
+        assert_eq!(children[0].get("detail"), Some(&Json::from("Entero")));
+    }
+
+    #[test]
+    fn definition() {
+        let mut server = Server::new(Config::default());
+        open(&mut server, "file:///a.sc", "tipo A(b: B, c: C);\ntipo B(x: Entero);");
+        open(&mut server, "file:///c.sc", "tipo C(a: A);");
+
+        let response = request(&mut server, "textDocument/definition", at("file:///a.sc", 0, 10));
+        assert_eq!(response.get("result").unwrap().to_string(), "{\"uri\":\"file:///a.sc\",\
+            \"range\":{\"start\":{\"line\":1,\"character\":5},\"end\":{\"line\":1,\"character\":6}}}");
+
+        let response = request(&mut server, "textDocument/definition", at("file:///a.sc", 0, 17));
+        assert_eq!(response.pointer(&["result", "uri"]), Some(&Json::from("file:///c.sc")));
+
+        let response = request(&mut server, "textDocument/definition", at("file:///a.sc", 1, 10));
+        assert_eq!(response.get("result"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn references() {
+        let mut server = Server::new(Config::default());
+        open(&mut server, "file:///a.sc", "tipo A(b: B);\ntipo B(x: Entero);");
+        open(&mut server, "file:///c.sc", "tipo C(b: B, c: C);");
+
+        let response = request(&mut server, "textDocument/references", at("file:///a.sc", 1, 5));
+        let uris: Vec<&Json> = response.get("result").and_then(Json::as_array).unwrap().iter()
+            .map(|location| location.get("uri").unwrap())
+            .collect();
+        assert_eq!(uris, [&Json::from("file:///a.sc"), &Json::from("file:///c.sc")]);
+
+        let mut params = at("file:///c.sc", 0, 17);
+        if let Json::Object(ref mut fields) = params {
+            fields.push((String::from("context"), Json::object(vec![("includeDeclaration", Json::from(true))])));
+        }
+        let response = request(&mut server, "textDocument/references", params);
+        assert_eq!(response.get("result").and_then(Json::as_array).map(|a| a.len()), Some(2));
+
+        let response = request(&mut server, "textDocument/references", at("file:///a.sc", 0, 7));
+        assert_eq!(response.get("result"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn completion() {
+        let mut server = Server::new(Config::default());
+        open(&mut server, "file:///a.sc", "/// Un punto.\ntipo Punto(x: Entero);\ntipo Linea(a: ");
+
+        let response = request(&mut server, "textDocument/completion", at("file:///a.sc", 2, 14));
+        let items = response.get("result").and_then(Json::as_array).unwrap();
+        let labels: Vec<&str> = items.iter().filter_map(|item| item.get("label")?.as_str()).collect();
+        assert_eq!(labels, ["Caracter", "Entero", "Linea", "Logico", "Punto", "Real"]);
+        assert_eq!(items[4].get("documentation"), Some(&Json::from("Un punto.")));
+        assert_eq!(items[4].get("kind"), Some(&Json::from(COMPLETION_STRUCT)));
+
+        let response = request(&mut server, "textDocument/completion", at("file:///a.sc", 2, 11));
+        assert_eq!(response.get("result"), Some(&Json::Array(vec![])));
+    }
+
+    #[test]
+    fn code_actions() {
+        let mut server = Server::new(Config::default());
+        open(&mut server, "file:///a.sc", "tipo Punto(x: Entero)\ntipo Linea(a: Punto);");
+
+        let mut params = at("file:///a.sc", 1, 0);
+        if let Json::Object(ref mut fields) = params {
+            fields.push((String::from("range"), Json::object(vec![
+                ("start", Json::object(vec![("line", Json::from(1)), ("character", Json::from(0))])),
+                ("end", Json::object(vec![("line", Json::from(1)), ("character", Json::from(4))])),
+            ])));
+        }
+        let response = request(&mut server, "textDocument/codeAction", params.clone());
+        let action = &response.get("result").and_then(Json::as_array).unwrap()[0];
+        assert_eq!(action.get("title"), Some(&Json::from("insert `;`")));
+        assert_eq!(action.get("kind"), Some(&Json::from("quickfix")));
+        assert_eq!(action.pointer(&["edit", "changes", "file:///a.sc"]).unwrap().to_string(), "[{\"range\":{\
+            \"start\":{\"line\":0,\"character\":21},\"end\":{\"line\":0,\"character\":21}},\"newText\":\";\"}]");
+
+        // Nothing to fix in the range.
+        open(&mut server, "file:///a.sc", "tipo Punto(x: Entero);\ntipo Linea(a: Punto);");
+        let response = request(&mut server, "textDocument/codeAction", params);
+        assert_eq!(response.get("result"), Some(&Json::Array(vec![])));
+    }
+
+    #[test]
+    fn formatting() {
+        let mut server = Server::new(Config::default());
+        open(&mut server, "file:///a.sc", "tipo A( b:B );\n\n\ntipo B(x: Entero);");
+
+        let response = request(&mut server, "textDocument/formatting", at("file:///a.sc", 0, 0));
+        assert_eq!(response.get("result").unwrap().to_string(), "[{\"range\":{\
+            \"start\":{\"line\":0,\"character\":0},\"end\":{\"line\":3,\"character\":18}},\
+            \"newText\":\"tipo A(b: B);\\n\\ntipo B(x: Entero);\\n\"}]");
+
+        open(&mut server, "file:///a.sc", "tipo A(b: B);\n");
+        let response = request(&mut server, "textDocument/formatting", at("file:///a.sc", 0, 0));
+        assert_eq!(response.get("result"), Some(&Json::Array(vec![])));
+
+        open(&mut server, "file:///a.sc", "tipo A(b B);");
+        let response = request(&mut server, "textDocument/formatting", at("file:///a.sc", 0, 0));
+        assert_eq!(response.get("result"), Some(&Json::Null));
+    }
+
+    #[test]
+    fn folding_ranges() {
+        let mut server = Server::new(Config::default());
+        open(&mut server, "file:///a.sc", "/// Un punto.\n/// En el plano.\ntipo Punto(\n    x: Entero,\n);\ntipo A(b: Punto);");
+
+        let response = request(&mut server, "textDocument/foldingRange", at("file:///a.sc", 0, 0));
+        assert_eq!(response.get("result").unwrap().to_string(), "[\
+            {\"startLine\":0,\"endLine\":1,\"kind\":\"comment\"},\
+            {\"startLine\":2,\"endLine\":4}]");
+    }
+
+    #[test]
+    fn rename() {
+        let mut server = Server::new(Config::default());
+        open(&mut server, "file:///a.sc", "tipo A(b: B);\ntipo B(x: Entero);");
+        open(&mut server, "file:///c.sc", "tipo C(b: B);");
+
+        let mut params = at("file:///c.sc", 0, 10);
+        if let Json::Object(ref mut fields) = params {
+            fields.push((String::from("newName"), Json::from("Bote")));
+        }
+        let response = request(&mut server, "textDocument/rename", params);
+        let changes = response.pointer(&["result", "changes"]).unwrap();
+        assert_eq!(changes.get("file:///a.sc").and_then(Json::as_array).map(|a| a.len()), Some(2));
+        assert_eq!(changes.get("file:///c.sc").unwrap().to_string(), "[{\"range\":{\
+            \"start\":{\"line\":0,\"character\":10},\"end\":{\"line\":0,\"character\":11}},\
+            \"newText\":\"Bote\"}]");
+
+        let mut params = at("file:///c.sc", 0, 10);
+        if let Json::Object(ref mut fields) = params {
+            fields.push((String::from("newName"), Json::from("A")));
+        }
+        let response = request(&mut server, "textDocument/rename", params);
+        assert_eq!(response.pointer(&["error", "message"]), Some(&Json::from("`A` is already defined")));
+    }
+
+    #[test]
+    fn lifecycle() {
+        let mut server = Server::new(Config::default());
+
+        let response = request(&mut server, "initialize", Json::object(vec![]));
+        assert_eq!(response.pointer(&["result", "capabilities", "definitionProvider"]),
+                   Some(&Json::from(true)));
+
+        let response = request(&mut server, "nope", Json::Null);
+        assert_eq!(response.pointer(&["error", "code"]), Some(&Json::from(METHOD_NOT_FOUND)));
+
+        request(&mut server, "shutdown", Json::Null);
+        server.handle(&Json::object(vec![("method", Json::from("exit"))]));
+        assert_eq!(server.exit, Some(true));
+    }
+
+    #[test]
+    fn utf16_positions() {
+        let text = "tipo 😀(x: B);\ntipo ñ(y: B);";
+
+        assert_eq!(position(text, 10).to_string(), "{\"line\":0,\"character\":8}");
+        assert_eq!(offset(text, 0, 8), 10);
+        assert_eq!(offset(text, 1, 5), 22);
+        assert_eq!(offset(text, 0, 100), 16);
+        assert_eq!(offset(text, 5, 0), text.len());
+    }
+}
@@ -0,0 +1,90 @@
+//! The `simcom` command.
+//!
+//! Reads the input files (or stdin), analyzes them and does something with
+//! them: by default, writing the generated code. Settings come from the
+//! project's `simcom.toml`, if there is one, and from the command line, which
+//! overrides the file.
+//!
+//! Each subcommand lives in [`commands`][0], on top of a shared
+//! [`Session`][1]. The command line itself is parsed in [`cli`][2].
+//!
+//! [0]: commands/index.html
+//! [1]: session/struct.Session.html
+//! [2]: cli/index.html
+
+extern crate simcom;
+
+#[macro_use]
+mod verbose;
+
+mod cli;
+mod commands;
+mod completions;
+mod diff;
+mod lsp;
+mod session;
+mod sources;
+
+use std::env;
+use std::path::PathBuf;
+use std::process;
+
+use simcom::diagnostics;
+
+use cli::{Command, USAGE};
+
+fn main() {
+    let args = match cli::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => fail(&message),
+    };
+
+    verbose::set_level(args.verbose);
+
+    if let Some(ref code) = args.explain {
+        match diagnostics::explain(code) {
+            Some(explanation) => print!("{}", explanation),
+            None => fail(&format!("no diagnostic has the code `{}`", code)),
+        }
+        return;
+    }
+
+    let result = match args.command {
+        Command::Build => commands::build(&args),
+        Command::Check => commands::check(&args),
+        Command::Fmt => commands::format(&args),
+        Command::Lint => commands::lint(&args),
+        Command::Tokens => commands::tokens(&args),
+        Command::Doc => commands::doc(&args),
+        Command::Graph => commands::graph(&args),
+        Command::Diff => commands::diff(&args),
+        Command::Lsp => session::load_config(&args).and_then(lsp::run),
+        Command::Completions => print_completions(&args.inputs),
+        Command::Help => {
+            print!("{}", USAGE);
+            Ok(())
+        },
+    };
+
+    if let Err(message) = result {
+        fail(&message);
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
+
+/// Prints the completion script of the shell in `args`.
+fn print_completions(args: &[PathBuf]) -> Result<(), String> {
+    let shell = match args {
+        [shell] => shell.to_string_lossy(),
+        _ => return Err(format!("expected one shell: {}", completions::SHELLS.join(", "))),
+    };
+
+    let script = completions::script(&shell)
+        .ok_or_else(|| format!("unknown shell `{}`", shell))?;
+    print!("{}", script);
+    Ok(())
+}
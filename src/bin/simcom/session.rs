@@ -0,0 +1,224 @@
+//! What every subcommand that works on the inputs does first.
+//!
+//! Loading the configuration, reading the inputs, parsing them and analyzing
+//! them is the same whatever is done with the result, so it lives here. Errors
+//! are reported on the way and only a summary is returned.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use simcom::config::Config;
+use simcom::diagnostics::Diagnostic;
+use simcom::driver::{Compilation, Options};
+use simcom::lexer::Span;
+use simcom::loader::DiskLoader;
+use simcom::locale::Locale;
+use simcom::parser::Ast;
+use simcom::rewrite;
+use simcom::semantic::Semantic;
+
+use cli::Args;
+use sources::Sources;
+use verbose;
+
+/// Loads `simcom.toml` and applies the command line on top of it.
+pub fn load_config(args: &Args) -> Result<Config, String> {
+    let mut config = match (&args.config, args.no_config) {
+        (Some(path), _) => Config::load(path).map_err(|e| e.to_string())?,
+        (None, true) => Config::default(),
+        (None, false) => {
+            let cwd = env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+            Config::discover(&cwd).map_err(|e| e.to_string())?.unwrap_or_default()
+        },
+    };
+
+    if !args.inputs.is_empty() {
+        config.inputs = args.inputs.clone();
+    }
+    if !args.extensions.is_empty() {
+        config.extensions = args.extensions.clone();
+    }
+    if let Some(language) = args.language {
+        config.language = language;
+    }
+    if args.locale.is_some() {
+        config.locale = args.locale;
+    }
+    if config.locale.is_none() {
+        config.locale = Locale::from_env();
+    }
+    if let Some(backend) = args.backend {
+        config.backend = backend;
+    }
+    for (name, target) in &args.builtins {
+        config.builtins.insert(name.clone(), target.clone());
+    }
+    if let Some(ref output) = args.output {
+        config.output = Some(output.clone());
+    }
+    if args.stamp {
+        config.stamp = true;
+    }
+    if let Some(max_errors) = args.max_errors {
+        config.max_errors = max_errors;
+    }
+    if let Some(jobs) = args.jobs {
+        config.jobs = jobs;
+    }
+    if let Some(ref cache) = args.cache {
+        config.cache = Some(cache.clone());
+    }
+    if args.no_cache {
+        config.cache = None;
+    }
+    for (name, level) in &args.lints {
+        config.lints.insert(name.clone(), *level);
+    }
+
+    Ok(config)
+}
+
+/// The configuration and the inputs of a run.
+pub struct Session {
+    pub config: Config,
+    pub sources: Sources,
+    /// The same inputs, ready to be compiled. Files are in the same order as
+    /// in `sources`.
+    pub compilation: Compilation,
+}
+
+impl Session {
+    /// Reads the inputs given by the configuration.
+    pub fn new(config: Config, args: &Args) -> Result<Session, String> {
+        if args.stdin_filename.is_some() && !config.inputs.is_empty() {
+            return Err(String::from("`--stdin-filename` can only be used when reading from stdin"));
+        }
+
+        let sources = Sources::read(&config, args.stdin_filename.as_deref(), &DiskLoader)?;
+        let mut compilation = Compilation::new(Options::from(&config));
+        for source in sources.iter() {
+            compilation.add_file(source.name(), source.content.clone());
+        }
+
+        Ok(Session { config, sources, compilation })
+    }
+
+    /// Applies the machine applicable suggestions of `diagnostics` to the
+    /// inputs and writes them back. The session goes on with the fixed
+    /// inputs. Returns how many edits were made.
+    pub fn fix(&mut self, diagnostics: &[Diagnostic]) -> Result<usize, String> {
+        if self.sources.iter().any(|source| source.path.is_none()) {
+            return Err(String::from("`--fix` can't write the fixes back to stdin"));
+        }
+
+        let edits = rewrite::fixes(diagnostics);
+        let rewritten = rewrite::apply_all(self.compilation.sources(), &edits)
+            .map_err(|e| format!("can't apply the fixes: {}", e))?;
+
+        // Files are in the compilation in the same order as in the sources.
+        for (source, file) in self.sources.iter_mut().zip(self.compilation.sources().iter()) {
+            let (fixed, path) = match (rewritten.iter().find(|&&(id, _)| id == file.id), &source.path) {
+                (Some((_, fixed)), Some(path)) => (fixed, path),
+                _ => continue,
+            };
+
+            let made = edits.iter().filter(|edit| edit.span.file == file.id).count();
+            eprintln!("{}: made {} {}", source.name(), made, if made == 1 { "edit" } else { "edits" });
+            fs::write(path, fixed).map_err(|e| format!("{}: {}", path.display(), e))?;
+            source.content = fixed.clone();
+        }
+
+        self.compilation = Compilation::new(Options::from(&self.config));
+        for source in self.sources.iter() {
+            self.compilation.add_file(source.name(), source.content.clone());
+        }
+        Ok(edits.len())
+    }
+
+    /// Fixes the syntax errors, again and again while there are fixes: the
+    /// parser skips everything up to the next `;` after an error, so fixing
+    /// one can show the next.
+    pub fn fix_syntax(&mut self) -> Result<(), String> {
+        loop {
+            let errors = self.compilation.parse().1;
+            if self.fix(&errors)? == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Where `span` is, as `file:line:column`.
+    pub fn locate(&self, span: Span) -> String {
+        self.compilation.locate(span)
+    }
+
+    /// Parses every source, reporting the unexpected tokens. Returns only the
+    /// definitions, or the number of errors if there was any.
+    pub fn parse(&self) -> Result<Vec<Ast<'_>>, usize> {
+        let config = &self.config;
+        let started = Instant::now();
+        let (definitions, errors) = self.compilation.parse();
+
+        for (i, error) in errors.iter().enumerate() {
+            if config.max_errors == 0 || i < config.max_errors {
+                eprint!("{}", self.compilation.render(error));
+            }
+            debug!("{}: skipped to the next `;`", self.locate(error.span));
+        }
+
+        // Going over every definition of every file is only worth it if
+        // it's going to be printed.
+        if verbose::level() >= 2 {
+            for file in self.compilation.sources().iter() {
+                let found = definitions.iter()
+                    .filter(|d| d.name_span.file == file.id)
+                    .inspect(|d| debug!("{}: parsed `{}`", self.locate(d.name_span), d.name))
+                    .count();
+                debug!("{}: {} definitions", file.name, found);
+            }
+        }
+
+        let errors = errors.len();
+        if config.max_errors > 0 && errors > config.max_errors {
+            let suppressed = errors - config.max_errors;
+            eprintln!("error: {} additional {} suppressed", suppressed,
+                if suppressed == 1 { "error" } else { "errors" });
+        }
+
+        info!("parsed {} definitions in {:.2?}, {} errors", definitions.len(), started.elapsed(), errors);
+
+        match errors {
+            0 => Ok(definitions.into_iter().map(Ast::TypeDefinition).collect()),
+            _ => Err(errors),
+        }
+    }
+
+    /// Parses and analyzes the inputs. `action` is what couldn't be done if
+    /// there are errors, like "compile".
+    pub fn analyze(&self, action: &str) -> Result<Semantic, String> {
+        let definitions = self.parse()
+            .map_err(|_| format!("couldn't {} due to previous errors", action))?;
+
+        let started = Instant::now();
+        let semantic = self.compilation.analyze(definitions).map_err(|errors| {
+            for error in &errors {
+                eprint!("{}", self.compilation.render(error));
+            }
+            format!("couldn't {} due to previous errors", action)
+        })?;
+
+        info!("analyzed {} types in {:.2?}, {} in cycles", semantic.definitions.len(),
+            started.elapsed(), semantic.cycles.len());
+        debug!("order: {}", semantic.order.join(", "));
+
+        let mut cycles: Vec<&String> = semantic.cycles.iter().collect();
+        cycles.sort();
+        for name in cycles {
+            debug!("`{}` is part of a cycle", name);
+        }
+
+        Ok(semantic)
+    }
+}
@@ -0,0 +1,89 @@
+//! The inputs of a run, read into memory.
+//!
+//! Reading them is all this module does; they are compiled together in a
+//! [`Compilation`][0].
+//!
+//! [0]: ../../simcom/driver/struct.Compilation.html
+
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use simcom::config::Config;
+use simcom::inputs;
+use simcom::loader::{self, FileLoader};
+
+/// A single input.
+pub struct Source {
+    /// `None` for stdin.
+    pub path: Option<PathBuf>,
+    /// What stdin is called, if someone told us.
+    stdin_name: Option<PathBuf>,
+    pub content: String,
+}
+
+impl Source {
+    /// How the file is called in messages.
+    pub fn name(&self) -> String {
+        match (&self.path, &self.stdin_name) {
+            (Some(path), _) | (None, Some(path)) => path.display().to_string(),
+            (None, None) => String::from("<stdin>"),
+        }
+    }
+}
+
+/// All the inputs of a run.
+pub struct Sources {
+    files: Vec<Source>,
+}
+
+impl Sources {
+    /// Reads every input in the configuration with `loader`, or stdin if
+    /// there are none.
+    ///
+    /// Editors that pipe a file through stdin can say which one it is with
+    /// `stdin_name`, so messages point to the real file. Nothing is ever
+    /// written to it.
+    pub fn read(config: &Config, stdin_name: Option<&Path>, loader: &dyn FileLoader) -> Result<Sources, String> {
+        let started = Instant::now();
+        let mut contents = Vec::new();
+
+        if config.inputs.is_empty() {
+            let mut bytes = Vec::new();
+            let content = io::stdin().read_to_end(&mut bytes)
+                .and_then(|_| loader::decode(bytes))
+                .map_err(|e| format!("{}: {}", stdin_name.unwrap_or_else(|| Path::new("<stdin>")).display(), e))?;
+            debug!("read {} bytes from stdin", content.len());
+            contents.push((None, content));
+        } else {
+            let files = inputs::expand(&config.inputs, &config.extensions)
+                .map_err(|e| e.to_string())?;
+            for file in files {
+                let content = loader.load(&file)
+                    .map_err(|e| format!("{}: {}", file.display(), e))?;
+                debug!("read {} ({} bytes)", file.display(), content.len());
+                contents.push((Some(file), content));
+            }
+        }
+
+        let files: Vec<Source> = contents.into_iter()
+            .map(|(path, content)| {
+                let stdin_name = stdin_name.map(Path::to_path_buf);
+                Source { path, stdin_name, content }
+            })
+            .collect();
+
+        info!("read {} {} in {:.2?}", files.len(),
+            if files.len() == 1 { "file" } else { "files" }, started.elapsed());
+
+        Ok(Sources { files })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Source> {
+        self.files.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Source> {
+        self.files.iter_mut()
+    }
+}
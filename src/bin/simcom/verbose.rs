@@ -0,0 +1,36 @@
+//! What `-v` and `-vv` print.
+//!
+//! With `-v`, every phase says what it did and how long it took. With `-vv`,
+//! also every file, every definition and every place where the parser had to
+//! skip code to recover from an error. Everything goes to stderr, so it never
+//! gets mixed with the generated code.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static LEVEL: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_level(level: usize) {
+    LEVEL.store(level, Ordering::Relaxed);
+}
+
+pub fn level() -> usize {
+    LEVEL.load(Ordering::Relaxed)
+}
+
+/// Printed with `-v`.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::verbose::level() >= 1 {
+            eprintln!("info: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+/// Printed with `-vv`.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::verbose::level() >= 2 {
+            eprintln!("debug: {}", format_args!($($arg)*));
+        }
+    };
+}
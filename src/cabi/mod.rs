@@ -0,0 +1,212 @@
+//! C API module.
+//!
+//! Lets build systems that aren't written in Rust embed the compiler as a
+//! shared library instead of running the `simcom` binary. Only available with
+//! the `cabi` feature; the declarations are in `include/simcom.h`.
+//!
+//! A compilation is a single call that returns an opaque result, which is
+//! queried with the `simcom_result_*` functions and freed with `simcom_free`.
+//! Strings returned by the queries belong to the result and live as long as
+//! it does.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use super::driver::{compile, Options};
+
+/// What `simcom_compile` returns.
+pub struct SimcomResult {
+    ok: bool,
+    order: Vec<CString>,
+    code: Option<CString>,
+    diagnostics: Vec<CString>,
+}
+
+/// Strings from the compiler never have NUL bytes in them, but if one did, an
+/// empty string is better than a panic crossing into C.
+fn c_string(s: &str) -> CString {
+    CString::new(s).unwrap_or_default()
+}
+
+/// Compiles `source`, a NUL-terminated UTF-8 string, with the default
+/// options. Returns `NULL` if `source` is `NULL` or isn't valid UTF-8.
+///
+/// # Safety
+///
+/// `source` must be `NULL` or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn simcom_compile(source: *const c_char) -> *mut SimcomResult {
+    if source.is_null() {
+        return ptr::null_mut();
+    }
+    let source = match CStr::from_ptr(source).to_str() {
+        Ok(source) => source,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let result = compile(source, &Options::default());
+        let order = match result.semantic {
+            Some(ref semantic) => semantic.order.iter().map(|name| c_string(name)).collect(),
+            None => Vec::new(),
+        };
+        // There is a single file, so the location is its line and column.
+        let diagnostics = result.diagnostics.iter()
+            .map(|d| {
+                let (line, column) = d.span.position(source);
+                c_string(d.render(&format!("{}:{}", line, column)).trim_end())
+            })
+            .collect();
+
+        SimcomResult {
+            ok: result.is_ok(),
+            order,
+            code: result.code.as_ref().map(|code| c_string(code)),
+            diagnostics,
+        }
+    }));
+
+    match result {
+        Ok(result) => Box::into_raw(Box::new(result)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// 1 if there were no errors, 0 otherwise.
+///
+/// # Safety
+///
+/// `result` must come from `simcom_compile` and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn simcom_result_is_ok(result: *const SimcomResult) -> c_int {
+    match result.as_ref() {
+        Some(result) if result.ok => 1,
+        _ => 0,
+    }
+}
+
+/// How many types there are in the order. 0 when there were syntax errors.
+///
+/// # Safety
+///
+/// `result` must come from `simcom_compile` and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn simcom_result_get_order_len(result: *const SimcomResult) -> usize {
+    result.as_ref().map(|result| result.order.len()).unwrap_or(0)
+}
+
+/// The name of the type at `index` in the order the definitions must be
+/// written in, or `NULL` if `index` is out of bounds.
+///
+/// # Safety
+///
+/// `result` must come from `simcom_compile` and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn simcom_result_get_order(result: *const SimcomResult, index: usize) -> *const c_char {
+    result.as_ref()
+        .and_then(|result| result.order.get(index))
+        .map(|name| name.as_ptr())
+        .unwrap_or(ptr::null())
+}
+
+/// The generated C code, or `NULL` if there were errors.
+///
+/// # Safety
+///
+/// `result` must come from `simcom_compile` and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn simcom_result_get_code(result: *const SimcomResult) -> *const c_char {
+    result.as_ref()
+        .and_then(|result| result.code.as_ref())
+        .map(|code| code.as_ptr())
+        .unwrap_or(ptr::null())
+}
+
+/// How many diagnostics (errors and warnings) there are.
+///
+/// # Safety
+///
+/// `result` must come from `simcom_compile` and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn simcom_result_get_diagnostics_len(result: *const SimcomResult) -> usize {
+    result.as_ref().map(|result| result.diagnostics.len()).unwrap_or(0)
+}
+
+/// The diagnostic at `index`, as `simcom` prints it but with `line:column`
+/// as the location, or `NULL` if `index` is out of bounds.
+///
+/// # Safety
+///
+/// `result` must come from `simcom_compile` and not be freed.
+#[no_mangle]
+pub unsafe extern "C" fn simcom_result_get_diagnostic(result: *const SimcomResult, index: usize) -> *const c_char {
+    result.as_ref()
+        .and_then(|result| result.diagnostics.get(index))
+        .map(|diagnostic| diagnostic.as_ptr())
+        .unwrap_or(ptr::null())
+}
+
+/// Frees a result and every string it returned. `NULL` is ignored.
+///
+/// # Safety
+///
+/// `result` must be `NULL` or come from `simcom_compile`, and not be freed
+/// twice.
+#[no_mangle]
+pub unsafe extern "C" fn simcom_free(result: *mut SimcomResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    use super::*;
+
+    fn string(s: *const c_char) -> Option<String> {
+        if s.is_null() {
+            return None;
+        }
+        Some(unsafe { CStr::from_ptr(s) }.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn compile_and_query() {
+        let source = CString::new("tipo Linea(a: Punto);\ntipo Punto(x: Entero);").unwrap();
+
+        unsafe {
+            let result = simcom_compile(source.as_ptr());
+            assert_eq!(simcom_result_is_ok(result), 1);
+            assert_eq!(simcom_result_get_order_len(result), 3);
+            assert_eq!(string(simcom_result_get_order(result, 2)), Some(String::from("Linea")));
+            assert_eq!(string(simcom_result_get_order(result, 3)), None);
+            assert!(string(simcom_result_get_code(result)).unwrap().contains("typedef struct Linea {"));
+            simcom_free(result);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let source = CString::new("tipo A(x: Entero);\n;").unwrap();
+
+        unsafe {
+            let result = simcom_compile(source.as_ptr());
+            assert_eq!(simcom_result_is_ok(result), 0);
+            assert_eq!(simcom_result_get_order_len(result), 0);
+            assert_eq!(simcom_result_get_code(result), ptr::null());
+            assert_eq!(simcom_result_get_diagnostics_len(result), 1);
+            assert_eq!(string(simcom_result_get_diagnostic(result, 0)),
+                Some(String::from("error[E0001]: 2:1: unexpected `;`")));
+            simcom_free(result);
+
+            assert!(simcom_compile(ptr::null()).is_null());
+            assert_eq!(simcom_result_is_ok(ptr::null()), 0);
+            simcom_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,596 @@
+//! Cache module.
+//!
+//! Parsing is fast, but in a big project most files don't change between two
+//! runs. This module writes the AST of a file in a compact binary format,
+//! named after a hash of the file's content, so the next run can read it
+//! back instead of lexing and parsing the file again. The result of the
+//! semantic analysis of a whole set of files is kept too, as JSON, named after
+//! the hashes of all of them.
+//!
+//! The format is ours and may change: files written by other versions of the
+//! compiler are simply ignored.
+
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use super::json::{FromJson, Json, ToJson};
+use super::lexer::{Language, Span, Token};
+use super::parser::{Annotation, Argument, Ast, DefinitionKind, Implementation, Parameter, TypeDefinition, Variant};
+use super::semantic::Semantic;
+use super::source_map::FileId;
+
+/// Every cache file starts with this.
+const MAGIC: &[u8] = b"simcom-ast";
+
+/// Changes every time the format does.
+const FORMAT_VERSION: u8 = 6;
+
+/// The version of the compiler, part of every key: another version may parse
+/// the same file in another way.
+const COMPILER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// FNV-1a of the parts, one after the other, which gives the same result in
+/// every platform and every version of Rust, unlike the hasher of the
+/// standard library.
+pub fn hash(parts: &[&[u8]]) -> u64 {
+    parts.iter()
+        .flat_map(|bytes| bytes.iter())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// The key a file is cached under: a hash of its content and of everything
+/// else that changes how it is parsed.
+///
+/// # Examples
+///
+///     use simcom::cache::key;
+///     use simcom::lexer::Language;
+///
+///     let content = "tipo A(x: Entero);";
+///     assert_eq!(key(content, Language::Spanish), key(content, Language::Spanish));
+///     assert_ne!(key(content, Language::Spanish), key(content, Language::English));
+pub fn key(content: &str, language: Language) -> u64 {
+    let language: &[u8] = match language {
+        Language::Spanish => b"es",
+        Language::English => b"en",
+    };
+
+    hash(&[&[FORMAT_VERSION], COMPILER_VERSION.as_bytes(), language, content.as_bytes()])
+}
+
+/// The key the semantic result of some files is cached under: a hash of the
+/// keys of all of them, in order.
+pub fn semantic_key(keys: &[u64]) -> u64 {
+    let bytes: Vec<u8> = keys.iter().flat_map(|key| key.to_le_bytes()).collect();
+    hash(&[b"semantic", &bytes])
+}
+
+/// Where the AST of a file with that key is stored.
+pub fn path(dir: &Path, key: u64) -> PathBuf {
+    dir.join(format!("{:016x}.ast", key))
+}
+
+/// Stores the AST of `content`, creating the directory if needed.
+///
+/// # Examples
+///
+///     use std::env;
+///     use simcom::cache::{load_cached, store_cached};
+///     use simcom::lexer::{Language, Lexer};
+///     use simcom::parser::{Ast, Parser};
+///     use simcom::source_map::FileId;
+///
+///     let dir = env::temp_dir().join("simcom-cache-example");
+///     let content = "tipo A(x: Entero);";
+///     let asts: Vec<Ast> = Parser::new(Lexer::new(content)).collect();
+///
+///     store_cached(&dir, content, Language::Spanish, &asts).unwrap();
+///     let cached = load_cached(&dir, content, Language::Spanish, FileId::default());
+///     assert_eq!(cached, Some(asts));
+pub fn store_cached(dir: &Path, content: &str, language: Language, asts: &[Ast]) -> io::Result<()> {
+    let key = key(content, language);
+    let path = path(dir, key);
+
+    write(dir, &path, &encode(key, asts))
+}
+
+/// Writes the file aside and then moves it, so a run that is interrupted (or
+/// two of them at once) never leaves half a file behind.
+fn write(dir: &Path, path: &Path, bytes: &[u8]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let temporary = path.with_extension(format!("tmp{}", process::id()));
+    fs::write(&temporary, bytes)?;
+    fs::rename(&temporary, path)
+}
+
+/// Loads the AST of `content`, if it was cached. Spans are placed in `file`.
+///
+/// Any problem (there is no cache, it was written by another version, it is
+/// corrupt...) is a miss: the file just has to be parsed again.
+pub fn load_cached(dir: &Path, content: &str, language: Language, file: FileId) -> Option<Vec<Ast<'static>>> {
+    let key = key(content, language);
+    let bytes = fs::read(path(dir, key)).ok()?;
+    decode(&bytes, key, file)
+}
+
+/// Stores the semantic result of the files with those keys (see
+/// [`semantic_key`][0]).
+///
+/// # Examples
+///
+///     use std::env;
+///     use simcom::cache::{key, load_semantic, semantic_key, store_semantic};
+///     use simcom::lexer::{Language, Lexer};
+///     use simcom::parser::Parser;
+///     use simcom::semantic::Semantic;
+///
+///     let dir = env::temp_dir().join("simcom-cache-semantic-example");
+///     let content = "tipo A(x: Entero);";
+///     let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+///
+///     let key = semantic_key(&[key(content, Language::Spanish)]);
+///     store_semantic(&dir, key, &semantic).unwrap();
+///     assert_eq!(load_semantic(&dir, key).unwrap().order, semantic.order);
+///
+/// [0]: fn.semantic_key.html
+pub fn store_semantic(dir: &Path, key: u64, semantic: &Semantic) -> io::Result<()> {
+    let json = Json::object(vec![
+        ("key", Json::from(format!("{:016x}", key))),
+        ("semantic", semantic.to_json()),
+    ]);
+    write(dir, &semantic_path(dir, key), json.to_string().as_bytes())
+}
+
+/// Loads the semantic result stored under `key`, if there is one. Any problem
+/// is a miss, like with the ASTs.
+pub fn load_semantic(dir: &Path, key: u64) -> Option<Semantic> {
+    let text = fs::read_to_string(semantic_path(dir, key)).ok()?;
+    let json = Json::parse(&text).ok()?;
+    if json.get("key")?.as_str()? != format!("{:016x}", key) {
+        return None;
+    }
+    Semantic::from_json(json.get("semantic")?).ok()
+}
+
+/// Where the semantic result with that key is stored.
+fn semantic_path(dir: &Path, key: u64) -> PathBuf {
+    dir.join(format!("{:016x}.json", key))
+}
+
+/// Writes the header and then the ASTs.
+fn encode(key: u64, asts: &[Ast]) -> Vec<u8> {
+    let mut out = Writer { bytes: Vec::new() };
+    out.bytes.extend_from_slice(MAGIC);
+    out.bytes.push(FORMAT_VERSION);
+    out.bytes.extend_from_slice(&key.to_le_bytes());
+
+    out.number(asts.len());
+    for ast in asts {
+        out.ast(ast);
+    }
+    out.bytes
+}
+
+/// Reads what `encode` wrote, if the header matches.
+fn decode(bytes: &[u8], key: u64, file: FileId) -> Option<Vec<Ast<'static>>> {
+    let header = MAGIC.len() + 1 + 8;
+    if bytes.len() < header
+        || &bytes[..MAGIC.len()] != MAGIC
+        || bytes[MAGIC.len()] != FORMAT_VERSION
+        || bytes[MAGIC.len() + 1..header] != key.to_le_bytes() {
+        return None;
+    }
+
+    let mut input = Reader { bytes: &bytes[header..], file };
+    let asts = input.list(Reader::ast)?;
+
+    match input.bytes.len() {
+        0 => Some(asts),
+        _ => None,
+    }
+}
+
+/// Numbers are written in LEB128, so small ones (most of them) take a byte.
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn number(&mut self, mut n: usize) {
+        while n >= 0x80 {
+            self.bytes.push((n as u8 & 0x7f) | 0x80);
+            n >>= 7;
+        }
+        self.bytes.push(n as u8);
+    }
+
+    fn string(&mut self, s: &str) {
+        self.number(s.len());
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    fn strings(&mut self, strings: &[Cow<str>]) {
+        self.number(strings.len());
+        for s in strings {
+            self.string(s);
+        }
+    }
+
+    /// A string that may not be there, as a list of zero or one.
+    fn optional(&mut self, s: Option<&str>) {
+        match s {
+            Some(s) => {
+                self.number(1);
+                self.string(s);
+            },
+            None => self.number(0),
+        }
+    }
+
+    /// Numbers that can be negative or big, in eight bytes.
+    fn optional_integer(&mut self, value: Option<i64>) {
+        match value {
+            Some(value) => {
+                self.number(1);
+                self.bytes.extend_from_slice(&value.to_le_bytes());
+            },
+            None => self.number(0),
+        }
+    }
+
+    /// Only the offsets: the file is the one the AST is loaded into.
+    fn span(&mut self, span: Span) {
+        self.number(span.start);
+        self.number(span.end);
+    }
+
+    fn token(&mut self, token: &Token) {
+        let (tag, value) = match *token {
+            Token::Illegal => (0, None),
+            Token::EOF => (1, None),
+            Token::Ident(ref value) => (2, Some(value)),
+            Token::ParL => (3, None),
+            Token::ParR => (4, None),
+            Token::Colon => (5, None),
+            Token::Semicolon => (6, None),
+            Token::Comma => (7, None),
+            Token::At => (8, None),
+            Token::Type => (9, None),
+            Token::DocComment(ref value) => (10, Some(value)),
+            Token::Whitespace(ref value) => (11, Some(value)),
+            Token::Module => (12, None),
+            Token::Interface => (13, None),
+            Token::Implements => (14, None),
+            Token::Number(ref value) => (15, Some(value)),
+            Token::Equals => (16, None),
+            Token::Enum => (17, None),
+            Token::Hash => (18, None),
+            Token::Text(ref value) => (19, Some(value)),
+        };
+
+        self.bytes.push(tag);
+        if let Some(value) = value {
+            self.string(value);
+        }
+    }
+
+    fn annotations(&mut self, annotations: &[Annotation]) {
+        self.number(annotations.len());
+        for annotation in annotations {
+            self.string(&annotation.name);
+            self.number(annotation.arguments.len());
+            for argument in &annotation.arguments {
+                match *argument {
+                    Argument::Number(number) => {
+                        self.bytes.push(0);
+                        self.bytes.extend_from_slice(&number.to_le_bytes());
+                    },
+                    Argument::Text(ref text) => {
+                        self.bytes.push(1);
+                        self.string(text);
+                    },
+                }
+            }
+            self.span(annotation.span);
+        }
+    }
+
+    fn ast(&mut self, ast: &Ast) {
+        match *ast {
+            Ast::TypeDefinition(ref definition) => {
+                self.bytes.push(0);
+                self.string(&definition.name);
+                self.optional(definition.module.as_deref());
+                self.bytes.push(match definition.kind {
+                    DefinitionKind::Type => 0,
+                    DefinitionKind::Interface => 1,
+                    DefinitionKind::Enum => 2,
+                });
+                self.number(definition.implements.len());
+                for implementation in &definition.implements {
+                    self.string(&implementation.name);
+                    self.span(implementation.span);
+                }
+                self.strings(&definition.docs);
+                self.annotations(&definition.annotations);
+                self.span(definition.name_span);
+                self.span(definition.span);
+
+                self.number(definition.parameters.len());
+                for parameter in &definition.parameters {
+                    self.string(&parameter.name);
+                    self.string(&parameter.typename);
+                    self.optional_integer(parameter.number);
+                    self.strings(&parameter.docs);
+                    self.annotations(&parameter.annotations);
+                    self.span(parameter.name_span);
+                    self.span(parameter.type_span);
+                    self.span(parameter.span);
+                }
+
+                self.number(definition.variants.len());
+                for variant in &definition.variants {
+                    self.string(&variant.name);
+                    self.optional_integer(variant.value);
+                    self.strings(&variant.docs);
+                    self.annotations(&variant.annotations);
+                    self.span(variant.name_span);
+                    self.span(variant.span);
+                }
+            },
+            Ast::Unexpected(ref token, span) => {
+                self.bytes.push(1);
+                self.token(token);
+                self.span(span);
+            },
+        }
+    }
+}
+
+/// Every method returns `None` as soon as something doesn't make sense.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    file: FileId,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let (&byte, rest) = self.bytes.split_first()?;
+        self.bytes = rest;
+        Some(byte)
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let mut n = 0usize;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= usize::BITS {
+                return None;
+            }
+            n |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(n);
+            }
+            shift += 7;
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.number()?;
+        if len > self.bytes.len() {
+            return None;
+        }
+        let (s, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        String::from_utf8(s.to_vec()).ok()
+    }
+
+    /// A string for the AST, which owns it.
+    fn text(&mut self) -> Option<Cow<'static, str>> {
+        self.string().map(Cow::Owned)
+    }
+
+    /// What `Writer::optional` wrote.
+    fn optional(&mut self) -> Option<Option<Cow<'static, str>>> {
+        match self.number()? {
+            0 => Some(None),
+            1 => self.text().map(Some),
+            _ => None,
+        }
+    }
+
+    /// A length and then that many items.
+    fn list<T, F: Fn(&mut Self) -> Option<T>>(&mut self, item: F) -> Option<Vec<T>> {
+        let len = self.number()?;
+        // Every item takes at least a byte, a bigger length is a lie.
+        if len > self.bytes.len() {
+            return None;
+        }
+        (0..len).map(|_| item(self)).collect()
+    }
+
+    fn span(&mut self) -> Option<Span> {
+        let start = self.number()?;
+        let end = self.number()?;
+        Some(Span::new(start, end).in_file(self.file))
+    }
+
+    fn token(&mut self) -> Option<Token> {
+        let token = match self.byte()? {
+            0 => Token::Illegal,
+            1 => Token::EOF,
+            2 => Token::Ident(self.string()?),
+            3 => Token::ParL,
+            4 => Token::ParR,
+            5 => Token::Colon,
+            6 => Token::Semicolon,
+            7 => Token::Comma,
+            8 => Token::At,
+            9 => Token::Type,
+            10 => Token::DocComment(self.string()?),
+            11 => Token::Whitespace(self.string()?),
+            12 => Token::Module,
+            13 => Token::Interface,
+            14 => Token::Implements,
+            15 => Token::Number(self.string()?),
+            16 => Token::Equals,
+            17 => Token::Enum,
+            18 => Token::Hash,
+            19 => Token::Text(self.string()?),
+            _ => return None,
+        };
+        Some(token)
+    }
+
+    fn annotation(&mut self) -> Option<Annotation<'static>> {
+        Some(Annotation { name: self.text()?, arguments: self.list(Reader::argument)?, span: self.span()? })
+    }
+
+    fn argument(&mut self) -> Option<Argument<'static>> {
+        match self.byte()? {
+            0 => self.integer().map(Argument::Number),
+            1 => self.text().map(Argument::Text),
+            _ => None,
+        }
+    }
+
+    fn kind(&mut self) -> Option<DefinitionKind> {
+        match self.byte()? {
+            0 => Some(DefinitionKind::Type),
+            1 => Some(DefinitionKind::Interface),
+            2 => Some(DefinitionKind::Enum),
+            _ => None,
+        }
+    }
+
+    fn implementation(&mut self) -> Option<Implementation<'static>> {
+        Some(Implementation { name: self.text()?, span: self.span()? })
+    }
+
+    fn parameter(&mut self) -> Option<Parameter<'static>> {
+        Some(Parameter {
+            name: self.text()?,
+            typename: self.text()?,
+            number: self.optional_integer()?,
+            docs: self.list(Reader::text)?,
+            annotations: self.list(Reader::annotation)?,
+            name_span: self.span()?,
+            type_span: self.span()?,
+            span: self.span()?,
+        })
+    }
+
+    /// A number that doesn't fit in `number`, in eight bytes.
+    fn integer(&mut self) -> Option<i64> {
+        if self.bytes.len() < 8 {
+            return None;
+        }
+        let (bytes, rest) = self.bytes.split_at(8);
+        self.bytes = rest;
+        let mut array = [0; 8];
+        array.copy_from_slice(bytes);
+        Some(i64::from_le_bytes(array))
+    }
+
+    fn optional_integer(&mut self) -> Option<Option<i64>> {
+        match self.number()? {
+            0 => Some(None),
+            1 => self.integer().map(Some),
+            _ => None,
+        }
+    }
+
+    fn variant(&mut self) -> Option<Variant<'static>> {
+        Some(Variant {
+            name: self.text()?,
+            value: self.optional_integer()?,
+            docs: self.list(Reader::text)?,
+            annotations: self.list(Reader::annotation)?,
+            name_span: self.span()?,
+            span: self.span()?,
+        })
+    }
+
+    fn ast(&mut self) -> Option<Ast<'static>> {
+        match self.byte()? {
+            0 => {
+                let name = self.text()?;
+                let module = self.optional()?;
+                let kind = self.kind()?;
+                let implements = self.list(Reader::implementation)?;
+                let docs = self.list(Reader::text)?;
+                let annotations = self.list(Reader::annotation)?;
+                let name_span = self.span()?;
+                let span = self.span()?;
+                let parameters = self.list(Reader::parameter)?;
+                let variants = self.list(Reader::variant)?;
+                Some(Ast::TypeDefinition(TypeDefinition {
+                    name, module, kind, parameters, implements, variants, docs, annotations, name_span, span,
+                }))
+            },
+            1 => Some(Ast::Unexpected(self.token()?, self.span()?)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::{Language, Lexer, Span, Token};
+    use super::super::parser::{Ast, Parser};
+    use super::super::source_map::SourceMap;
+    use super::{decode, encode, key};
+
+    fn parse(content: &str) -> Vec<Ast<'_>> {
+        Parser::new(Lexer::new(content)).collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let content = "/// Un punto.\n@obsoleto @desde(2) tipo Punto(@unidad(\"m\") x: Entero #1, @otra y: Entero #2) implementa Par;\ninterfaz Par(x: Entero);\n\
+                       enum E(A = -3, B, C = 9000000000);\ntipo ;";
+        let asts = parse(content);
+        let bytes = encode(7, &asts);
+
+        assert_eq!(decode(&bytes, 7, Default::default()), Some(asts));
+    }
+
+    #[test]
+    fn spans_go_to_the_new_file() {
+        let mut sources = SourceMap::new();
+        sources.add("a.sc", "");
+        let file = sources.add("b.sc", "tipo ;");
+
+        let bytes = encode(0, &parse("tipo ;"));
+        let asts = decode(&bytes, 0, file).unwrap();
+        assert_eq!(asts, vec![Ast::Unexpected(Token::Semicolon, Span::new(5, 6).in_file(file))]);
+    }
+
+    #[test]
+    fn bad_files_are_misses() {
+        let bytes = encode(1, &parse("tipo A(x: Entero); tipo B(y: A);"));
+
+        assert_eq!(decode(&bytes, 2, Default::default()), None);
+        assert_eq!(decode(&bytes[..bytes.len() - 1], 1, Default::default()), None);
+        assert_eq!(decode(b"simcom-ast", 1, Default::default()), None);
+
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_eq!(decode(&longer, 1, Default::default()), None);
+
+        let mut other_version = bytes.clone();
+        other_version[10] += 1;
+        assert_eq!(decode(&other_version, 1, Default::default()), None);
+    }
+
+    #[test]
+    fn keys() {
+        assert_ne!(key("tipo A(x: B);", Language::Spanish), key("tipo A(x: C);", Language::Spanish));
+    }
+}
@@ -0,0 +1,165 @@
+//! What the generated code was generated from.
+//!
+//! Generated files end up far from the schemas they come from, so it helps
+//! to know which inputs and options produced them: [`Metadata`][0] has the
+//! version of the compiler, a hash of every input and the options that change
+//! the output, and can be written at the top of the output as a comment
+//! (with `stamp = true` in `simcom.toml`, or `--stamp`). Nothing in it
+//! depends on the time or the machine, so the same inputs always give the
+//! same header.
+//!
+//! [0]: struct.Metadata.html
+
+use super::super::cache;
+use super::super::json::{Json, ToJson};
+use super::super::lexer::Language;
+use super::{Backend, Builtins};
+
+/// An input of the compilation and a hash of its content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Input {
+    pub name: String,
+    /// FNV-1a of the content, which is the same everywhere.
+    pub hash: u64,
+}
+
+impl Input {
+    pub fn new<N: Into<String>>(name: N, content: &str) -> Input {
+        Input { name: name.into(), hash: cache::hash(&[content.as_bytes()]) }
+    }
+}
+
+/// Everything the output of a compilation depends on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metadata {
+    /// The version of the compiler.
+    pub version: &'static str,
+    /// The inputs, in the order they were compiled.
+    pub inputs: Vec<Input>,
+    pub language: Language,
+    pub backend: Backend,
+    pub builtins: Builtins,
+}
+
+impl Metadata {
+    /// The metadata of the current version of the compiler, with no inputs
+    /// yet.
+    pub fn new(language: Language, backend: Backend, builtins: Builtins) -> Metadata {
+        Metadata { version: env!("CARGO_PKG_VERSION"), inputs: Vec::new(), language, backend, builtins }
+    }
+
+    /// A comment, in the syntax of the backend, to put at the top of the
+    /// output. It ends with an empty line.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::codegen::metadata::{Input, Metadata};
+    ///     use simcom::codegen::{Backend, Builtins};
+    ///     use simcom::lexer::Language;
+    ///
+    ///     let mut metadata = Metadata::new(Language::Spanish, Backend::C, Builtins::new());
+    ///     metadata.inputs.push(Input::new("punto.sc", "tipo Punto(x: Entero);"));
+    ///
+    ///     let header = metadata.header();
+    ///     assert!(header.starts_with("/*\n * Generated by simcom "));
+    ///     assert!(header.contains(" *     punto.sc "));
+    ///     assert!(header.ends_with(" */\n\n"));
+    pub fn header(&self) -> String {
+        let mut lines = vec![
+            format!("Generated by simcom {}. Don't edit it by hand.", self.version),
+            String::new(),
+            String::from("Inputs:"),
+        ];
+        lines.extend(self.inputs.iter().map(|input| format!("    {} {:016x}", input.name, input.hash)));
+        lines.push(String::from("Options:"));
+        lines.push(format!("    language = {}", self.language.code()));
+        lines.push(format!("    backend = {}", self.backend.name()));
+        for (name, target) in &self.builtins {
+            lines.push(format!("    builtin {} = {}", name, target));
+        }
+
+        match self.backend {
+            Backend::C => {
+                let mut header = String::from("/*\n");
+                for line in lines {
+                    header.push_str(if line.is_empty() { " *" } else { " * " });
+                    header.push_str(&line);
+                    header.push('\n');
+                }
+                header.push_str(" */\n\n");
+                header
+            },
+        }
+    }
+}
+
+/// Hashes are strings of 16 hex digits, since JSON numbers can't hold every
+/// `u64`.
+impl ToJson for Metadata {
+    fn to_json(&self) -> Json {
+        let inputs = self.inputs.iter()
+            .map(|input| Json::object(vec![
+                ("name", Json::from(&input.name[..])),
+                ("hash", Json::from(format!("{:016x}", input.hash))),
+            ]))
+            .collect();
+        let builtins = self.builtins.iter()
+            .map(|(name, target)| (name.clone(), Json::from(&target[..])))
+            .collect();
+
+        Json::object(vec![
+            ("version", Json::from(self.version)),
+            ("inputs", Json::Array(inputs)),
+            ("language", Json::from(self.language.code())),
+            ("backend", Json::from(self.backend.name())),
+            ("builtins", Json::Object(builtins)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::json::{Json, ToJson};
+    use super::super::super::lexer::Language;
+    use super::super::{default_builtins, Backend, Builtins};
+    use super::{Input, Metadata};
+
+    #[test]
+    fn header() {
+        let mut builtins = Builtins::new();
+        builtins.insert(String::from("Entero"), String::from("long"));
+        let mut metadata = Metadata::new(Language::English, Backend::C, builtins);
+        metadata.version = "1.2.3";
+        metadata.inputs.push(Input { name: String::from("a.sc"), hash: 0xabc });
+
+        assert_eq!(metadata.header(), "/*\n\
+                                        \x20* Generated by simcom 1.2.3. Don't edit it by hand.\n\
+                                        \x20*\n\
+                                        \x20* Inputs:\n\
+                                        \x20*     a.sc 0000000000000abc\n\
+                                        \x20* Options:\n\
+                                        \x20*     language = en\n\
+                                        \x20*     backend = c\n\
+                                        \x20*     builtin Entero = long\n\
+                                        \x20*/\n\n");
+    }
+
+    #[test]
+    fn hashes_only_depend_on_the_content() {
+        let a = Input::new("a.sc", "tipo A(x: Entero);");
+        assert_eq!(a.hash, Input::new("b.sc", "tipo A(x: Entero);").hash);
+        assert_ne!(a.hash, Input::new("a.sc", "tipo A(x: Real);").hash);
+        assert_eq!(Input::new("vacio.sc", "").hash, 0xcbf2_9ce4_8422_2325);
+    }
+
+    #[test]
+    fn json() {
+        let mut metadata = Metadata::new(Language::Spanish, Backend::C, default_builtins());
+        metadata.inputs.push(Input { name: String::from("a.sc"), hash: 1 });
+
+        let json = metadata.to_json();
+        assert_eq!(json.get("version"), Some(&Json::from(env!("CARGO_PKG_VERSION"))));
+        assert_eq!(json.to_string().matches("\"hash\":\"0000000000000001\"").count(), 1);
+        assert_eq!(json.get("builtins").and_then(|b| b.get("Real")), Some(&Json::from("double")));
+    }
+}
@@ -0,0 +1,316 @@
+//! Code generation module.
+//!
+//! Once the [semantic analyzer][0] has decided in which order the definitions
+//! must be written, a backend walks them and produces the output text. For now
+//! the only target is C, as promised in the README.
+//!
+//! [0]: ../semantic/struct.Semantic.html
+
+use std::collections::BTreeMap;
+
+use super::parser::DefinitionKind;
+use super::semantic::Semantic;
+use super::units::unit;
+use super::versions::{self, Range};
+
+pub mod metadata;
+
+/// The languages we know how to write.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Backend {
+    /// C `typedef struct`s.
+    #[default]
+    C,
+}
+
+impl Backend {
+    /// Looks up a backend by the name used in the command line and in
+    /// `simcom.toml`.
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name {
+            "c" => Some(Backend::C),
+            _ => None,
+        }
+    }
+
+    /// The name `from_name` takes.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::C => "c",
+        }
+    }
+}
+
+/// Built-in types, the ones that are never defined in the input.
+///
+/// Maps the name used in our language to the name in the target language.
+pub type Builtins = BTreeMap<String, String>;
+
+/// The built-in types every compilation knows about.
+///
+/// # Examples
+///
+///     use simcom::codegen::default_builtins;
+///
+///     let builtins = default_builtins();
+///     assert_eq!(builtins["Entero"], "long");
+///     assert_eq!(builtins["Real"], "double");
+pub fn default_builtins() -> Builtins {
+    let mut builtins = Builtins::new();
+    builtins.insert(String::from("Entero"), String::from("long"));
+    builtins.insert(String::from("Real"), String::from("double"));
+    builtins.insert(String::from("Caracter"), String::from("char"));
+    builtins.insert(String::from("Logico"), String::from("int"));
+    builtins
+}
+
+/// Writes the analyzed definitions using the chosen backend.
+///
+/// # Examples
+///
+///     use simcom::codegen::{default_builtins, emit, Backend};
+///     use simcom::lexer::Lexer;
+///     use simcom::parser::Parser;
+///     use simcom::semantic::Semantic;
+///
+///     let content = "tipo Punto(x: Entero, y: Entero);";
+///     let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+///     let output = emit(&semantic, Backend::C, &default_builtins());
+///
+///     assert!(output.contains("typedef struct Punto {\n    long x;\n"));
+pub fn emit(semantic: &Semantic, backend: Backend, builtins: &Builtins) -> String {
+    trace_span!(INFO, "codegen", backend = ?backend);
+
+    match backend {
+        Backend::C => emit_c(semantic, builtins),
+    }
+}
+
+/// The name of a type in C, where there are no modules: `geometria.Punto` is
+/// `geometria_Punto`.
+fn c_name(name: &str) -> String {
+    name.replace('.', "_")
+}
+
+/// The C backend.
+///
+/// Types involved in a cycle can't contain each other by value, so they are
+/// declared upfront and referenced through pointers between them.
+///
+/// Only the [latest version][0] of the schema is written, without the
+/// definitions and fields that aren't in it, so a field that changes its
+/// type between versions is there once.
+///
+/// C has nothing like interfaces, so they aren't written: the semantic
+/// analyzer already checked that the types have their fields. Enumerations
+/// are C enums with every value written, and their variants are prefixed
+/// with the name of the enumeration, since C puts them all in the same
+/// scope.
+///
+/// [0]: ../versions/fn.latest.html
+fn emit_c(semantic: &Semantic, builtins: &Builtins) -> String {
+    let mut out = String::new();
+    let version = versions::latest(semantic);
+    let current = |range: Range| version.map(|version| range.contains(version)).unwrap_or(true);
+    let written = |name: &str| {
+        semantic.definitions.get(name)
+            .is_some_and(|d| !d.is_interface() && current(Range::of(&d.annotations)))
+    };
+
+    // Forward declarations, sorted so the output doesn't depend on the order
+    // of the HashSet.
+    let mut cycles: Vec<&String> = semantic.cycles.iter().filter(|name| written(name)).collect();
+    cycles.sort();
+    for name in &cycles {
+        out.push_str(&format!("typedef struct {0} {0};\n", c_name(name)));
+    }
+    if !cycles.is_empty() {
+        out.push('\n');
+    }
+
+    let mut first = true;
+    for name in &semantic.order {
+        // Names without a definition are either built-ins or types that the
+        // user defines somewhere else.
+        let definition = match semantic.definitions.get(name) {
+            Some(definition) if written(name) => definition,
+            _ => continue,
+        };
+        trace_span!(TRACE, "definition", name = &name[..]);
+
+        if !first {
+            out.push('\n');
+        }
+        first = false;
+
+        if definition.kind == DefinitionKind::Enum {
+            out.push_str(&format!("typedef enum {} {{\n", c_name(name)));
+            for (variant, value) in definition.variants.iter().zip(definition.values()) {
+                out.push_str(&format!("    {}_{} = {},\n", c_name(name), variant.name, value));
+            }
+            out.push_str(&format!("}} {};\n", c_name(name)));
+            continue;
+        }
+
+        out.push_str(&format!("typedef struct {} {{\n", c_name(name)));
+        let fields = definition.parameters.iter().filter(|p| current(Range::of(&p.annotations)));
+        for parameter in fields {
+            let typename = &parameter.typename[..];
+            let ctype = builtins.get(typename).cloned().unwrap_or_else(|| c_name(typename));
+            let pointer = semantic.cycles.contains(name)
+                && semantic.cycles.contains(typename);
+
+            let unit = match unit(parameter) {
+                // A `*/` would end the comment.
+                Some(unit) => format!(" /* {} */", unit.replace("*/", "* /")),
+                None => String::new(),
+            };
+            if pointer {
+                out.push_str(&format!("    {} *{};{}\n", ctype, parameter.name, unit));
+            } else {
+                out.push_str(&format!("    {} {};{}\n", ctype, parameter.name, unit));
+            }
+        }
+        out.push_str(&format!("}} {};\n", c_name(name)));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    use super::super::lexer::Lexer;
+    use super::super::parser::Parser;
+    use super::*;
+
+    fn get_c(content: &str) -> String {
+        let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+        emit(&semantic, Backend::C, &default_builtins())
+    }
+
+    /// Checks that `cc` accepts `code`.
+    fn assert_compiles(code: &str) {
+        let mut child = Command::new("cc")
+            .args(["-fsyntax-only", "-x", "c", "-"])
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|e| panic!("can't run cc: {}", e));
+
+        child.stdin.take().unwrap().write_all(code.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "{}\n{}", code, String::from_utf8_lossy(&output.stderr));
+    }
+
+    #[test]
+    fn readme_example() {
+        let output = get_c("tipo Circulo(centro: Punto, radio: Real);\
+                            tipo Punto(x: Entero, y: Entero);");
+
+        assert_eq!(output, "typedef struct Punto {\n\
+                            \x20   long x;\n\
+                            \x20   long y;\n\
+                            } Punto;\n\
+                            \n\
+                            typedef struct Circulo {\n\
+                            \x20   Punto centro;\n\
+                            \x20   double radio;\n\
+                            } Circulo;\n");
+    }
+
+    #[test]
+    fn modules() {
+        let output = get_c("tipo Id(n: Entero); modulo red.ip; tipo Id(a: Entero); tipo Ruta(id: Id, otro: Id);");
+
+        assert!(output.contains("typedef struct red_ip_Id {\n    long a;\n} red_ip_Id;\n"));
+        assert!(output.contains("    red_ip_Id id;\n    red_ip_Id otro;\n} red_ip_Ruta;\n"));
+        assert!(output.contains("} Id;\n"));
+    }
+
+    #[test]
+    fn interfaces_are_not_written() {
+        let output = get_c("interfaz Nombrado(nombre: Texto, otro: Gato); tipo Gato(nombre: Texto) implementa Nombrado;");
+
+        assert_eq!(output, "typedef struct Gato {\n    Texto nombre;\n} Gato;\n");
+    }
+
+    #[test]
+    fn enums() {
+        let output = get_c("modulo red; enum Estado(Activo = 1, Inactivo, Borrado = -1); tipo Nodo(estado: Estado);");
+
+        assert_eq!(output, "typedef enum red_Estado {\n\
+                            \x20   red_Estado_Activo = 1,\n\
+                            \x20   red_Estado_Inactivo = 2,\n\
+                            \x20   red_Estado_Borrado = -1,\n\
+                            } red_Estado;\n\
+                            \n\
+                            typedef struct red_Nodo {\n\
+                            \x20   red_Estado estado;\n\
+                            } red_Nodo;\n");
+    }
+
+    #[test]
+    fn units_are_comments() {
+        let output = get_c("tipo Viaje(@unidad(\"km\") distancia: Real, @unidad(\"*/\") raro: Real, dias: Entero);");
+
+        assert!(output.contains("    double distancia; /* km */\n"));
+        assert!(output.contains("    double raro; /* * / */\n"));
+        assert!(output.contains("    long dias;\n"));
+    }
+
+    #[test]
+    fn cycles_use_pointers() {
+        let output = get_c("tipo A(b: B); tipo B(a: A, n: Entero);");
+
+        assert!(output.starts_with("typedef struct A A;\ntypedef struct B B;\n\n"));
+        assert!(output.contains("    B *b;\n"));
+        assert!(output.contains("    A *a;\n"));
+        assert!(output.contains("    long n;\n"));
+    }
+
+    #[test]
+    fn latest_version() {
+        // The example of the README.
+        let output = get_c("tipo Punto(x: Entero, @hasta(2) y: Entero, @desde(3) y: Real);");
+        assert_eq!(output, "typedef struct Punto {\n    long x;\n    double y;\n} Punto;\n");
+
+        let output = get_c("@hasta(1) tipo Viejo(x: Entero); tipo Nuevo(@desde(2) x: Entero, @hasta(2) y: Real);");
+        assert_eq!(output, "typedef struct Nuevo {\n    long x;\n    double y;\n} Nuevo;\n");
+    }
+
+    #[test]
+    fn disjoint_cycles() {
+        let output = get_c("tipo A(b: B); tipo B(a: A); tipo C(d: D); tipo D(c: C);");
+
+        assert!(output.starts_with("typedef struct A A;\ntypedef struct B B;\ntypedef struct C C;\ntypedef struct D D;\n\n"));
+        assert!(output.contains("    D *d;\n"));
+    }
+
+    #[test]
+    fn types_that_only_use_a_cycle() {
+        let output = get_c("tipo X(a: A, n: Entero); tipo A(b: B); tipo B(a: A);");
+
+        assert!(output.starts_with("typedef struct A A;\ntypedef struct B B;\n\n"));
+        assert!(output.contains("typedef struct X {\n    A a;\n    long n;\n} X;\n"));
+    }
+
+    /// Needs a C compiler, run it with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn c_compilers_accept_the_output() {
+        let inputs = [
+            "tipo Circulo(centro: Punto, radio: Real); tipo Punto(x: Entero, y: Entero);",
+            "tipo A(b: B); tipo B(a: A, n: Entero);",
+            "tipo A(b: B); tipo B(a: A); tipo C(d: D); tipo D(c: C);",
+            "tipo X(a: A, n: Entero); tipo A(b: B); tipo B(a: A);",
+            "tipo Punto(x: Entero, @hasta(2) y: Entero, @desde(3) y: Real);",
+        ];
+
+        for input in &inputs {
+            assert_compiles(&get_c(input));
+        }
+    }
+}
@@ -0,0 +1,447 @@
+//! Project configuration module.
+//!
+//! Instead of passing the same flags on every invocation, a project can have a
+//! `simcom.toml` file at its root. The compiler looks for it starting at the
+//! working directory and going up through its parents, the same way `cargo`
+//! finds `Cargo.toml`. Flags given in the command line always win.
+//!
+//! A complete file looks like this:
+//!
+//! ```toml
+//! inputs = ["schemas/geometria.sc", "schemas/personas", "vendor/**/*.sc"]
+//! extensions = ["sc"]
+//! language = "es"
+//! locale = "es"
+//! backend = "c"
+//! output = "include/tipos.h"
+//! stamp = true
+//! max_errors = 20
+//! jobs = 0
+//! cache = ".simcom-cache"
+//!
+//! [builtins]
+//! Entero = "long"
+//! Texto = "char *"
+//!
+//! [lints]
+//! naming = "deny"
+//!
+//! [fmt]
+//! max_width = 80
+//! trailing_comma = "vertical"
+//! ```
+
+mod toml;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::codegen::{default_builtins, Backend, Builtins};
+use super::inputs::DEFAULT_EXTENSION;
+use super::lexer::Language;
+use super::locale::Locale;
+
+use self::toml::{Table, Value};
+
+pub use super::fmt::TrailingComma;
+
+/// The name of the file we look for.
+pub const FILE_NAME: &str = "simcom.toml";
+
+/// How many errors are reported when nobody says otherwise. A badly broken
+/// file can produce an avalanche of them, and only the first ones are useful.
+pub const DEFAULT_MAX_ERRORS: usize = 20;
+
+/// How seriously a lint is taken.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    /// The lint isn't run.
+    Allow,
+    /// Findings are reported but the compilation goes on.
+    Warn,
+    /// Findings are reported as errors.
+    Deny,
+}
+
+impl Level {
+    /// Looks up a level by its name (`allow`, `warn` or `deny`).
+    pub fn from_name(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Settings of the formatter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FmtConfig {
+    /// Definitions longer than this are split, one parameter per line.
+    pub max_width: usize,
+    /// Whether the last parameter is followed by a comma.
+    pub trailing_comma: TrailingComma,
+}
+
+impl Default for FmtConfig {
+    fn default() -> Self {
+        FmtConfig { max_width: 80, trailing_comma: TrailingComma::default() }
+    }
+}
+
+/// Every setting of a compilation.
+///
+/// [`Config::default`][0] gives the settings used when there is no
+/// `simcom.toml` at all. Paths read from a file are relative to the directory
+/// the file is in, so the compiler can be run from anywhere in the project.
+///
+/// [0]: #method.default
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The files, directories or glob patterns to compile. When empty, the
+    /// input is read from stdin. See [`inputs::expand`][1].
+    ///
+    /// [1]: ../inputs/fn.expand.html
+    pub inputs: Vec<PathBuf>,
+    /// Extensions of the files picked up when an input is a directory.
+    pub extensions: Vec<String>,
+    /// Types that are never defined in the input, and what they are called in
+    /// the generated code.
+    pub builtins: Builtins,
+    /// The language of the keywords.
+    pub language: Language,
+    /// The language of the diagnostics. When `None`, the one of the
+    /// environment (`LANG`), or English.
+    pub locale: Option<Locale>,
+    /// Level for each lint, by name. Lints not listed use their default.
+    pub lints: BTreeMap<String, Level>,
+    /// The code generator to use.
+    pub backend: Backend,
+    /// Where to write the generated code. When `None`, it goes to stdout.
+    pub output: Option<PathBuf>,
+    /// Whether the generated code starts with a comment saying what it was
+    /// generated from.
+    pub stamp: bool,
+    /// How many syntax errors are reported before the rest are only
+    /// counted. 0 means there is no limit.
+    pub max_errors: usize,
+    /// How many threads parse the files. 0 means one for each CPU.
+    pub jobs: usize,
+    /// Where to keep what was parsed and analyzed between runs. When `None`,
+    /// nothing is kept.
+    pub cache: Option<PathBuf>,
+    /// How `simcom fmt` lays out the code.
+    pub fmt: FmtConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            inputs: Vec::new(),
+            extensions: vec![String::from(DEFAULT_EXTENSION)],
+            builtins: default_builtins(),
+            language: Language::default(),
+            locale: None,
+            lints: BTreeMap::new(),
+            backend: Backend::default(),
+            output: None,
+            stamp: false,
+            max_errors: DEFAULT_MAX_ERRORS,
+            jobs: 0,
+            cache: None,
+            fmt: FmtConfig::default(),
+        }
+    }
+}
+
+/// Reasons why a configuration couldn't be loaded.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file couldn't be read.
+    Io(PathBuf, io::Error),
+    /// The file was read but it has an error in the given line (0 when the
+    /// line isn't known).
+    Invalid(PathBuf, usize, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => {
+                write!(f, "{}: {}", path.display(), error)
+            },
+            ConfigError::Invalid(path, 0, message) => {
+                write!(f, "{}: {}", path.display(), message)
+            },
+            ConfigError::Invalid(path, line, message) => {
+                write!(f, "{}:{}: {}", path.display(), line, message)
+            },
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(_, error) => Some(error),
+            ConfigError::Invalid(..) => None,
+        }
+    }
+}
+
+impl Config {
+    /// Looks for a `simcom.toml` in `dir` and its ancestors, and loads the
+    /// first one found. Returns `Ok(None)` if there isn't any.
+    pub fn discover(dir: &Path) -> Result<Option<Config>, ConfigError> {
+        for ancestor in dir.ancestors() {
+            let path = ancestor.join(FILE_NAME);
+            if path.is_file() {
+                return Config::load(&path).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Loads the configuration from the file at `path`.
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+
+        Config::parse(&content, base)
+            .map_err(|(line, message)| ConfigError::Invalid(path.to_path_buf(), line, message))
+    }
+
+    /// Reads the configuration from the contents of a `simcom.toml`. Relative
+    /// paths are resolved against `base`.
+    ///
+    /// On error, returns the line and a description of the problem.
+    ///
+    /// # Examples
+    ///
+    ///     use std::path::Path;
+    ///     use simcom::config::Config;
+    ///     use simcom::lexer::Language;
+    ///
+    ///     let content = "language = \"en\"\n[builtins]\nTexto = \"char *\"\n";
+    ///     let config = Config::parse(content, Path::new("project")).unwrap();
+    ///
+    ///     assert_eq!(config.language, Language::English);
+    ///     assert_eq!(config.builtins["Texto"], "char *");
+    ///     assert_eq!(config.builtins["Entero"], "long");
+    pub fn parse(content: &str, base: &Path) -> Result<Config, (usize, String)> {
+        let root = toml::parse(content).map_err(|e| (e.line, e.message))?;
+        let mut config = Config::default();
+
+        // The tiny TOML reader doesn't keep track of the line of each value,
+        // so errors found from here on are reported as line 0.
+        let invalid = |message: String| (0, message);
+
+        for (key, value) in root {
+            match (&key[..], value) {
+                ("inputs", value) => {
+                    for input in strings(&key, value).map_err(invalid)? {
+                        config.inputs.push(base.join(input));
+                    }
+                },
+                ("extensions", value) => {
+                    config.extensions = strings(&key, value).map_err(invalid)?;
+                },
+                ("language", Value::String(code)) => {
+                    config.language = Language::from_code(&code)
+                        .ok_or_else(|| invalid(format!("unknown language `{}`", code)))?;
+                },
+                ("locale", Value::String(code)) => {
+                    config.locale = Some(Locale::from_code(&code)
+                        .ok_or_else(|| invalid(format!("unknown locale `{}`", code)))?);
+                },
+                ("backend", Value::String(name)) => {
+                    config.backend = Backend::from_name(&name)
+                        .ok_or_else(|| invalid(format!("unknown backend `{}`", name)))?;
+                },
+                ("output", Value::String(path)) => {
+                    config.output = Some(base.join(path));
+                },
+                ("stamp", Value::Boolean(stamp)) => {
+                    config.stamp = stamp;
+                },
+                ("cache", Value::String(path)) => {
+                    config.cache = Some(base.join(path));
+                },
+                ("max_errors", Value::Integer(max)) if max >= 0 => {
+                    config.max_errors = max as usize;
+                },
+                ("max_errors", _) => {
+                    return Err(invalid(String::from("`max_errors` must be a non-negative integer")));
+                },
+                ("jobs", Value::Integer(jobs)) if jobs >= 0 => {
+                    config.jobs = jobs as usize;
+                },
+                ("jobs", _) => {
+                    return Err(invalid(String::from("`jobs` must be a non-negative integer")));
+                },
+                ("builtins", Value::Table(table)) => {
+                    for (name, target) in string_table(&key, table).map_err(invalid)? {
+                        config.builtins.insert(name, target);
+                    }
+                },
+                ("lints", Value::Table(table)) => {
+                    for (name, level) in string_table(&key, table).map_err(invalid)? {
+                        let level = Level::from_name(&level)
+                            .ok_or_else(|| invalid(format!("unknown lint level `{}`", level)))?;
+                        config.lints.insert(name, level);
+                    }
+                },
+                ("fmt", Value::Table(table)) => {
+                    config.fmt = fmt_config(table).map_err(invalid)?;
+                },
+                ("language", _) | ("locale", _) | ("backend", _) | ("output", _) | ("stamp", _) | ("cache", _)
+                | ("builtins", _) | ("lints", _) | ("fmt", _) => {
+                    return Err(invalid(format!("`{}` has the wrong type", key)));
+                },
+                _ => return Err(invalid(format!("unknown setting `{}`", key))),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Reads the `[fmt]` table.
+fn fmt_config(table: Table) -> Result<FmtConfig, String> {
+    let mut fmt = FmtConfig::default();
+
+    for (key, value) in table {
+        match (&key[..], value) {
+            ("max_width", Value::Integer(width)) if width > 0 => {
+                fmt.max_width = width as usize;
+            },
+            ("trailing_comma", Value::String(name)) => {
+                fmt.trailing_comma = TrailingComma::from_name(&name)
+                    .ok_or_else(|| format!("unknown trailing comma policy `{}`", name))?;
+            },
+            ("max_width", _) => return Err(String::from("`fmt.max_width` must be a positive integer")),
+            ("trailing_comma", _) => return Err(String::from("`fmt.trailing_comma` must be a string")),
+            _ => return Err(format!("unknown setting `fmt.{}`", key)),
+        }
+    }
+
+    Ok(fmt)
+}
+
+/// Expects `value` to be an array of strings.
+fn strings(key: &str, value: Value) -> Result<Vec<String>, String> {
+    match value {
+        Value::Array(values) => values.into_iter()
+            .map(|value| match value {
+                Value::String(s) => Ok(s),
+                _ => Err(format!("`{}` must only contain strings", key)),
+            })
+            .collect(),
+        _ => Err(format!("`{}` must be an array of strings", key)),
+    }
+}
+
+/// Expects every value in `table` to be a string.
+fn string_table(key: &str, table: Table) -> Result<Vec<(String, String)>, String> {
+    table.into_iter()
+        .map(|(name, value)| match value {
+            Value::String(s) => Ok((name, s)),
+            _ => Err(format!("`{}.{}` must be a string", key, name)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_config(content: &str) -> Result<Config, (usize, String)> {
+        Config::parse(content, Path::new("base"))
+    }
+
+    #[test]
+    fn empty_is_default() {
+        assert_eq!(get_config("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn full() {
+        let config = get_config("inputs = [\"a.sc\", \"dir/b.sc\"]\n\
+                                 extensions = [\"sc\", \"schema\"]\n\
+                                 language = \"en\"\n\
+                                 locale = \"es\"\n\
+                                 backend = \"c\"\n\
+                                 output = \"out.h\"\n\
+                                 stamp = true\n\
+                                 max_errors = 5\n\
+                                 jobs = 4\n\
+                                 cache = \"cache\"\n\
+                                 [builtins]\n\
+                                 Texto = \"char *\"\n\
+                                 [lints]\n\
+                                 naming = \"deny\"\n\
+                                 [fmt]\n\
+                                 max_width = 100\n\
+                                 trailing_comma = \"never\"\n").unwrap();
+
+        assert_eq!(config.inputs, vec![
+            PathBuf::from("base/a.sc"),
+            PathBuf::from("base/dir/b.sc"),
+        ]);
+        assert_eq!(config.extensions, vec![String::from("sc"), String::from("schema")]);
+        assert_eq!(config.language, Language::English);
+        assert_eq!(config.locale, Some(Locale::Spanish));
+        assert_eq!(config.backend, Backend::C);
+        assert_eq!(config.output, Some(PathBuf::from("base/out.h")));
+        assert!(config.stamp);
+        assert_eq!(config.max_errors, 5);
+        assert_eq!(config.jobs, 4);
+        assert_eq!(config.cache, Some(PathBuf::from("base/cache")));
+        assert_eq!(config.builtins["Texto"], "char *");
+        assert_eq!(config.lints["naming"], Level::Deny);
+        assert_eq!(config.fmt, FmtConfig {
+            max_width: 100,
+            trailing_comma: TrailingComma::Never,
+        });
+    }
+
+    #[test]
+    fn unknown_values() {
+        let (_, message) = get_config("colour = \"red\"\n").unwrap_err();
+        assert_eq!(message, "unknown setting `colour`");
+
+        let (_, message) = get_config("language = \"fr\"\n").unwrap_err();
+        assert_eq!(message, "unknown language `fr`");
+
+        let (_, message) = get_config("locale = \"fr\"\n").unwrap_err();
+        assert_eq!(message, "unknown locale `fr`");
+
+        let (_, message) = get_config("[lints]\nnaming = \"loud\"\n").unwrap_err();
+        assert_eq!(message, "unknown lint level `loud`");
+    }
+
+    #[test]
+    fn wrong_types() {
+        let (_, message) = get_config("inputs = \"a.sc\"\n").unwrap_err();
+        assert_eq!(message, "`inputs` must be an array of strings");
+
+        let (_, message) = get_config("output = 3\n").unwrap_err();
+        assert_eq!(message, "`output` has the wrong type");
+
+        let (_, message) = get_config("max_errors = -1\n").unwrap_err();
+        assert_eq!(message, "`max_errors` must be a non-negative integer");
+    }
+
+    #[test]
+    fn syntax_error_line() {
+        let (line, _) = get_config("backend = \"c\"\n\noutput = \n").unwrap_err();
+        assert_eq!(line, 3);
+    }
+}
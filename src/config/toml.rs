@@ -0,0 +1,350 @@
+//! A tiny TOML reader.
+//!
+//! It only understands the part of TOML that `simcom.toml` needs: comments,
+//! `[tables]`, `key = value` pairs and values that are strings, integers,
+//! booleans or arrays of those. Anything else is reported as an error instead
+//! of being silently misread.
+
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// How many arrays can be inside each other. Arrays are read recursively,
+/// and `simcom.toml` never needs more than one anyway.
+const MAX_DEPTH: usize = 32;
+
+/// A value on the right hand side of a `key = value` pair.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+/// A set of keys and their values. The document itself is a table too.
+pub type Table = BTreeMap<String, Value>;
+
+/// Where and why reading the document failed.
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+type ReadResult<T> = Result<T, Error>;
+
+/// Reads a whole document into its root table.
+pub fn parse(input: &str) -> ReadResult<Table> {
+    let mut root = Table::new();
+    let mut current: Option<String> = None;
+
+    // Arrays can span several lines, so we can't just work line by line. We
+    // keep a character iterator around and count newlines as we go.
+    let mut reader = Reader { input: input.chars().peekable(), line: 1, depth: 0 };
+
+    loop {
+        reader.skip_blank();
+
+        match reader.peek() {
+            None => break,
+            Some('[') => {
+                reader.next();
+                let name = reader.key()?;
+                reader.skip_spaces();
+                reader.expect(']')?;
+                reader.end_of_line()?;
+
+                if root.contains_key(&name) {
+                    return Err(reader.error(format!("table `{}` defined twice", name)));
+                }
+                root.insert(name.clone(), Value::Table(Table::new()));
+                current = Some(name);
+            },
+            Some(_) => {
+                let key = reader.key()?;
+                reader.skip_spaces();
+                reader.expect('=')?;
+                reader.skip_spaces();
+                let value = reader.value()?;
+                reader.end_of_line()?;
+
+                let table = match current {
+                    Some(ref name) => match root.get_mut(name) {
+                        Some(Value::Table(table)) => table,
+                        _ => unreachable!(),
+                    },
+                    None => &mut root,
+                };
+
+                if table.insert(key.clone(), value).is_some() {
+                    return Err(reader.error(format!("key `{}` defined twice", key)));
+                }
+            },
+        }
+    }
+
+    Ok(root)
+}
+
+struct Reader<'a> {
+    input: Peekable<Chars<'a>>,
+    line: usize,
+    /// Arrays being read.
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, message: String) -> Error {
+        Error { line: self.line, message }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.input.peek().cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.input.next();
+        if ch == Some('\n') {
+            self.line += 1;
+        }
+        ch
+    }
+
+    fn expect(&mut self, expected: char) -> ReadResult<()> {
+        match self.next() {
+            Some(ch) if ch == expected => Ok(()),
+            Some(ch) => Err(self.error(format!("expected `{}`, found `{}`", expected, ch))),
+            None => Err(self.error(format!("expected `{}`, found the end of the file", expected))),
+        }
+    }
+
+    /// Spaces and tabs, but not newlines.
+    fn skip_spaces(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\r') = self.peek() {
+            self.next();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if let Some('#') = self.peek() {
+            while let Some(ch) = self.peek() {
+                if ch == '\n' {
+                    break;
+                }
+                self.next();
+            }
+        }
+    }
+
+    /// Whitespace, newlines and comments.
+    fn skip_blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(ch) if ch.is_whitespace() => { self.next(); },
+                Some('#') => self.skip_comment(),
+                _ => break,
+            }
+        }
+    }
+
+    /// After a pair or a table header only a comment may follow.
+    fn end_of_line(&mut self) -> ReadResult<()> {
+        self.skip_spaces();
+        self.skip_comment();
+        match self.next() {
+            None | Some('\n') => Ok(()),
+            Some(ch) => Err(self.error(format!("expected a new line, found `{}`", ch))),
+        }
+    }
+
+    fn key(&mut self) -> ReadResult<String> {
+        self.skip_spaces();
+
+        if let Some('"') = self.peek() {
+            self.next();
+            return self.basic_string();
+        }
+
+        let mut key = String::new();
+        while let Some(ch) = self.peek() {
+            if !(ch.is_ascii_alphanumeric() || ch == '_' || ch == '-') {
+                break;
+            }
+            self.next();
+            key.push(ch);
+        }
+
+        if key.is_empty() {
+            Err(self.error(String::from("expected a key")))
+        } else {
+            Ok(key)
+        }
+    }
+
+    fn value(&mut self) -> ReadResult<Value> {
+        match self.peek() {
+            Some('"') => {
+                self.next();
+                self.basic_string().map(Value::String)
+            },
+            Some('\'') => {
+                self.next();
+                self.literal_string().map(Value::String)
+            },
+            Some('[') if self.depth == MAX_DEPTH => {
+                Err(self.error(format!("more than {} nested arrays", MAX_DEPTH)))
+            },
+            Some('[') => {
+                self.next();
+                self.depth += 1;
+                let array = self.array();
+                self.depth -= 1;
+                array
+            },
+            Some(ch) if ch == '-' || ch == '+' || ch.is_ascii_digit() => self.integer(),
+            Some(ch) if ch.is_ascii_alphabetic() => {
+                let word = self.key()?;
+                match &word[..] {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    _ => Err(self.error(format!("unknown value `{}`", word))),
+                }
+            },
+            Some(ch) => Err(self.error(format!("unexpected `{}` in a value", ch))),
+            None => Err(self.error(String::from("expected a value"))),
+        }
+    }
+
+    /// A "double quoted" string, the opening quote was already consumed.
+    fn basic_string(&mut self) -> ReadResult<String> {
+        let mut content = String::new();
+
+        loop {
+            if let Some('\n') = self.peek() {
+                break;
+            }
+
+            match self.next() {
+                Some('"') => return Ok(content),
+                Some('\\') => match self.next() {
+                    Some('n') => content.push('\n'),
+                    Some('t') => content.push('\t'),
+                    Some('"') => content.push('"'),
+                    Some('\\') => content.push('\\'),
+                    Some(ch) => return Err(self.error(format!("unknown escape `\\{}`", ch))),
+                    None => break,
+                },
+                Some(ch) => content.push(ch),
+                None => break,
+            }
+        }
+
+        Err(self.error(String::from("unterminated string")))
+    }
+
+    /// A 'single quoted' string, without escapes.
+    fn literal_string(&mut self) -> ReadResult<String> {
+        let mut content = String::new();
+
+        loop {
+            match self.peek() {
+                Some('\'') => {
+                    self.next();
+                    return Ok(content);
+                },
+                Some('\n') | None => break,
+                Some(ch) => {
+                    self.next();
+                    content.push(ch);
+                },
+            }
+        }
+
+        Err(self.error(String::from("unterminated string")))
+    }
+
+    fn integer(&mut self) -> ReadResult<Value> {
+        let mut digits = String::new();
+        while let Some(ch) = self.peek() {
+            if !(ch.is_ascii_digit() || ch == '-' || ch == '+' || ch == '_') {
+                break;
+            }
+            self.next();
+            if ch != '_' {
+                digits.push(ch);
+            }
+        }
+
+        digits.parse()
+            .map(Value::Integer)
+            .map_err(|_| self.error(format!("invalid integer `{}`", digits)))
+    }
+
+    /// An array, the opening bracket was already consumed. Newlines, comments
+    /// and a trailing comma are allowed inside.
+    fn array(&mut self) -> ReadResult<Value> {
+        let mut values = Vec::new();
+
+        loop {
+            self.skip_blank();
+            if let Some(']') = self.peek() {
+                self.next();
+                return Ok(Value::Array(values));
+            }
+
+            values.push(self.value()?);
+
+            self.skip_blank();
+            match self.next() {
+                Some(',') => (),
+                Some(']') => return Ok(Value::Array(values)),
+                Some(ch) => return Err(self.error(format!("expected `,` or `]`, found `{}`", ch))),
+                None => return Err(self.error(String::from("unterminated array"))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pairs_and_tables() {
+        let root = parse("# A comment\n\
+                          name = \"value\" # another one\n\
+                          number = 42\n\
+                          \n\
+                          [table]\n\
+                          flag = true\n\
+                          list = [\n  'a',\n  \"b\", # here\n]\n").unwrap();
+
+        assert_eq!(root["name"], Value::String(String::from("value")));
+        assert_eq!(root["number"], Value::Integer(42));
+
+        let mut table = Table::new();
+        table.insert(String::from("flag"), Value::Boolean(true));
+        table.insert(String::from("list"), Value::Array(vec![
+            Value::String(String::from("a")),
+            Value::String(String::from("b")),
+        ]));
+        assert_eq!(root["table"], Value::Table(table));
+    }
+
+    #[test]
+    fn errors_have_lines() {
+        let error = parse("a = 1\nb = \"open\n").unwrap_err();
+        assert_eq!(error.line, 2);
+
+        let error = parse("a = 1\na = 2\n").unwrap_err();
+        assert_eq!(error.message, "key `a` defined twice");
+
+        let error = parse("a = 1 2\n").unwrap_err();
+        assert_eq!(error.line, 1);
+
+        let deep = format!("a = {}{}\n", "[".repeat(100_000), "]".repeat(100_000));
+        assert_eq!(parse(&deep).unwrap_err().message, "more than 32 nested arrays");
+    }
+}
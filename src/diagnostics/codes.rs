@@ -0,0 +1,397 @@
+//! The code of every kind of diagnostic.
+//!
+//! Codes never change once given, so they can be searched and linked to.
+//! They start with `E` for problems that are always errors and with `W` for
+//! lints, which are warnings unless denied. The first two digits are the
+//! phase: `00` the syntax, `01` the analysis, `02` the lints, `03` the
+//! [limits][0] and `04` the [compatibility][1] between versions.
+//!
+//! [0]: ../../limits/index.html
+//! [1]: ../../versions/fn.compatibility.html
+
+/// A token where the grammar doesn't allow it.
+pub const UNEXPECTED_TOKEN: &str = "E0001";
+/// A character that isn't part of the language.
+pub const UNKNOWN_CHARACTER: &str = "E0002";
+
+/// Two definitions of the same type.
+pub const TYPE_REDEFINED: &str = "E0101";
+/// An `implementa` with something that isn't an interface.
+pub const NOT_AN_INTERFACE: &str = "E0102";
+/// A type without a field of an interface it implements.
+pub const MISSING_FIELD: &str = "E0103";
+/// A field with another type than the one its interface requires.
+pub const FIELD_TYPE_MISMATCH: &str = "E0104";
+/// An interface used as the type of a field.
+pub const INTERFACE_AS_TYPE: &str = "E0105";
+/// Two variants of an enumeration with the same name.
+pub const VARIANT_REDEFINED: &str = "E0106";
+/// Two variants of an enumeration with the same value.
+pub const VARIANT_VALUE_REPEATED: &str = "E0107";
+/// A variant with a value that doesn't fit in a C `int`.
+pub const VARIANT_VALUE_OUT_OF_RANGE: &str = "E0108";
+/// Two fields of a type with the same number.
+pub const FIELD_NUMBER_REPEATED: &str = "E0109";
+/// A field number that binary formats don't allow.
+pub const FIELD_NUMBER_OUT_OF_RANGE: &str = "E0110";
+/// A field without a number in a type whose other fields have one.
+pub const FIELD_NUMBER_MISSING: &str = "E0111";
+/// A `@desde` or `@hasta` without a version.
+pub const VERSION_ARGUMENT: &str = "E0112";
+/// A `@desde` after the `@hasta`.
+pub const EMPTY_VERSION_RANGE: &str = "E0113";
+/// A `@unidad` without a unit, or on something that isn't a number.
+pub const UNIT_ARGUMENT: &str = "E0114";
+
+/// The `unused_types` lint.
+pub const UNUSED_TYPE: &str = "W0201";
+/// The `naming` lint.
+pub const NAMING: &str = "W0202";
+/// The `duplicate_fields` lint.
+pub const DUPLICATE_FIELD: &str = "W0203";
+/// The `deprecated` lint.
+pub const DEPRECATED: &str = "W0204";
+/// The `misspelled_types` lint.
+pub const MISSPELLED_TYPE: &str = "W0205";
+/// The `field_number_gaps` lint.
+pub const FIELD_NUMBER_GAP: &str = "W0206";
+/// The `conflicting_units` lint.
+pub const CONFLICTING_UNITS: &str = "W0207";
+
+/// Over `max_definitions`.
+pub const TOO_MANY_DEFINITIONS: &str = "E0301";
+/// Over `max_parameters_per_type`.
+pub const TOO_MANY_PARAMETERS: &str = "E0302";
+/// Over `max_identifier_len`.
+pub const NAME_TOO_LONG: &str = "E0303";
+/// Over `max_errors`.
+pub const TOO_MANY_ERRORS: &str = "E0304";
+/// Over `max_dependency_depth`.
+pub const DEPENDENCIES_TOO_DEEP: &str = "E0305";
+
+/// A type that a later version removes.
+pub const TYPE_REMOVED: &str = "E0401";
+/// A field that a later version removes.
+pub const FIELD_REMOVED: &str = "E0402";
+/// A field that has another type in a later version.
+pub const FIELD_RETYPED: &str = "E0403";
+
+/// Every code and its explanation.
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (UNEXPECTED_TOKEN, "\
+A token appeared where the grammar doesn't allow it.
+
+Every definition has the form `tipo Name(field: Type, ...);`, optionally
+preceded by doc comments and annotations. A missing colon, parenthesis or
+semicolon makes the parser find something else instead:
+
+    tipo Punto(x Entero);
+    tipo Punto(x: Entero)
+
+The first one has `Entero` where `:` should be, and the second one ends
+where `;` should be.
+
+After the error, the parser skips to the next `;` and goes on from there.
+"),
+    (UNKNOWN_CHARACTER, "\
+The input has a character that isn't part of the language.
+
+Names are made of letters, digits and `_`, numbers of digits (maybe after
+a `-`), and the only punctuation is `(`, `)`, `:`, `;`, `,`, `=` and `@`.
+Anything else is an error:
+
+    tipo Punto(x: Entero, y: Entero)!;
+
+Doc comments start with `///`, so `/` followed by anything else is an
+unknown character too.
+"),
+    (TYPE_REDEFINED, "\
+A type is defined more than once, in the same file or in different ones:
+
+    tipo Punto(x: Entero, y: Entero);
+    tipo Punto(x: Real, y: Real);
+
+The error points to the second definition and says where the first one is.
+Remove one of them or rename it.
+"),
+    (NOT_AN_INTERFACE, "\
+A type implements something that isn't an interface: a type, or a name
+that isn't defined at all.
+
+    tipo Animal(nombre: Texto);
+    tipo Gato(nombre: Texto) implementa Animal, Mascota;
+
+`Animal` is a type and `Mascota` isn't defined. Define them with `interfaz`
+instead of `tipo`:
+
+    interfaz Animal(nombre: Texto);
+"),
+    (MISSING_FIELD, "\
+A type implements an interface, but doesn't have one of its fields:
+
+    interfaz Nombrado(nombre: Texto);
+    tipo Gato(edad: Entero) implementa Nombrado;
+
+`Gato` needs a field `nombre` of type `Texto`. It can have more fields than
+the interface asks for.
+"),
+    (FIELD_TYPE_MISMATCH, "\
+A type implements an interface, but one of the fields of the interface has
+another type in it:
+
+    interfaz Nombrado(nombre: Texto);
+    tipo Gato(nombre: Entero) implementa Nombrado;
+
+The field must have the same type as in the interface, here `Texto`.
+"),
+    (INTERFACE_AS_TYPE, "\
+A field has an interface as its type:
+
+    interfaz Nombrado(nombre: Texto);
+    tipo Registro(quien: Nombrado);
+
+Interfaces only say which fields a type must have, there are no values of
+an interface. Use one of the types that implement it.
+"),
+    (VARIANT_REDEFINED, "\
+An enumeration has two variants with the same name:
+
+    enum Estado(Activo, Inactivo, Activo);
+
+Remove one of them or rename it.
+"),
+    (VARIANT_VALUE_REPEATED, "\
+Two variants of an enumeration have the same value, so they can't be told
+apart once written:
+
+    enum Estado(Activo = 1, Inactivo = 2, Borrado = 1);
+
+A variant without a value has the next one after the variant before it
+(the first one is 0), which can repeat a value written later:
+
+    enum Estado(Nuevo = 1, Activo, Inactivo = 2);
+
+Here `Activo` is 2 too. Give every variant its own value, and don't change
+them once they are in use: other programs may have stored them.
+"),
+    (VARIANT_VALUE_OUT_OF_RANGE, "\
+The value of a variant doesn't fit in 32 bits, from -2147483648 to
+2147483647, which is what enums are in C:
+
+    enum Grande(Mucho = 3000000000);
+"),
+    (FIELD_NUMBER_REPEATED, "\
+Two fields of a type have the same number:
+
+    tipo Punto(x: Entero #1, y: Entero #1);
+
+Numbers identify the fields in binary formats, so each field needs its own.
+Don't change them once they are in use, not even after renaming the field:
+other programs may have stored data with them.
+"),
+    (FIELD_NUMBER_OUT_OF_RANGE, "\
+A field number is 0, negative or over 536870911, the largest one Protobuf
+allows:
+
+    tipo Punto(x: Entero #0);
+
+Numbers start at 1.
+"),
+    (FIELD_NUMBER_MISSING, "\
+Some fields of a type have a number and others don't:
+
+    tipo Punto(x: Entero #1, y: Entero);
+
+Number all of them, or none.
+"),
+    (VERSION_ARGUMENT, "\
+A `@desde` or `@hasta` annotation doesn't have a version as its argument:
+
+    @desde tipo Punto(x: Entero);
+
+Versions are numbers from 0 up, `@desde(2)`.
+"),
+    (EMPTY_VERSION_RANGE, "\
+The first version of a definition or a field is after its last one, so it
+isn't in any version:
+
+    tipo Punto(@desde(3) @hasta(2) x: Entero);
+"),
+    (UNIT_ARGUMENT, "\
+A `@unidad` annotation doesn't have a unit as its argument, or it's on a
+type or on a field that isn't an `Entero` or a `Real`:
+
+    tipo Viaje(@unidad distancia: Real, @unidad(\"h\") nombre: Texto);
+
+Units are texts, `@unidad(\"km\")`, and only numbers have them.
+"),
+    (UNUSED_TYPE, "\
+A type that no other type uses (the `unused_types` lint).
+
+    tipo Punto(x: Entero);
+    tipo Viejo(p: Punto);
+
+Nothing uses `Viejo`. The roots of a schema are never used by another
+type, so this lint is allowed by default. Enable it with
+`--warn unused_types` to look for leftovers.
+"),
+    (NAMING, "\
+A name that doesn't follow the conventions (the `naming` lint).
+
+Types are written in `UpperCamelCase` and fields start in lowercase:
+
+    tipo punto_2d(X: Entero);
+
+The type should be `Punto2d` and the field `x`. Allow it with
+`--allow naming` when the names come from somewhere else.
+"),
+    (DUPLICATE_FIELD, "\
+A type has two fields with the same name (the `duplicate_fields` lint).
+
+    tipo Punto(x: Entero, x: Real);
+
+The generated C code wouldn't compile, so deny this lint with
+`--deny duplicate_fields` to stop before generating anything.
+"),
+    (DEPRECATED, "\
+A field uses a type annotated with `@obsoleto` (the `deprecated` lint).
+
+    @obsoleto tipo Viejo(x: Entero);
+    tipo Punto(v: Viejo);
+
+Use the type that replaces it, or allow the lint with `--allow deprecated`
+while migrating.
+"),
+    (MISSPELLED_TYPE, "\
+A field uses a type that isn't defined, but whose name is almost the name of
+one that is (the `misspelled_types` lint).
+
+    tipo Punto(x: Entero, y: Entero);
+    tipo Linea(a: Punto, b: Punot);
+
+`Punot` is probably `Punto`. Types that aren't defined anywhere are taken
+as built-in or external ones, so only names one typo away from a defined
+type are reported. Allow the lint with `--allow misspelled_types` if a
+built-in type looks like one of yours.
+"),
+    (FIELD_NUMBER_GAP, "\
+The numbers of the fields of a type skip some (the `field_number_gaps`
+lint).
+
+    tipo Punto(x: Entero #1, z: Entero #3);
+
+That's usually a field that was removed, and then its number must not be
+used again, since old data may still have it. Allow the lint with
+`--allow field_number_gaps` if that's the case.
+"),
+    (CONFLICTING_UNITS, "\
+Fields with the same name have different units in different types (the
+`conflicting_units` lint).
+
+    tipo Viaje(@unidad(\"km\") distancia: Real);
+    tipo Carrera(@unidad(\"m\") distancia: Real);
+
+Copying a `distancia` from one to the other would be wrong by a factor of
+1000. Use the same unit, or allow the lint with `--allow conflicting_units`
+if the fields don't measure the same thing.
+"),
+    (TOO_MANY_DEFINITIONS, "\
+The input has more definitions than the limits allow.
+
+Limits protect services that compile untrusted input. Parsing stops at the
+first definition over `max_definitions`. Raise the limit if the schema
+really is that big.
+"),
+    (TOO_MANY_PARAMETERS, "\
+A definition has more parameters than `max_parameters_per_type` allows.
+
+Split the type in smaller ones, or raise the limit.
+"),
+    (NAME_TOO_LONG, "\
+A name, a type or an annotation has more characters than
+`max_identifier_len` allows.
+"),
+    (TOO_MANY_ERRORS, "\
+There were more errors than `max_errors` allows, so the compilation stopped
+without looking at the rest of the input. Fix the errors shown first.
+"),
+    (DEPENDENCIES_TOO_DEEP, "\
+A chain of types that contain each other is longer than
+`max_dependency_depth` allows:
+
+    tipo A(b: B);
+    tipo B(c: C);
+    tipo C(x: Entero);
+
+Here `A` is 3 types deep.
+
+Types in a cycle only count once.
+"),
+    (TYPE_REMOVED, "\
+A type is in the old version but not in the new one:
+
+    @hasta(2) tipo Punto(x: Entero);
+
+Programs written for the old version may still use it. Keep it in the new
+version, maybe with `@obsoleto`.
+"),
+    (FIELD_REMOVED, "\
+A field is in the old version but not in the new one:
+
+    tipo Punto(x: Entero, @hasta(2) y: Entero);
+
+Every field is required, so data from the new version can't be read by
+programs written for the old one.
+"),
+    (FIELD_RETYPED, "\
+A field has another type in the new version:
+
+    tipo Punto(@hasta(2) x: Entero, @desde(3) x: Real);
+
+Add a field with another name instead.
+"),
+];
+
+/// Every code, in order.
+pub fn all() -> impl Iterator<Item = &'static str> {
+    EXPLANATIONS.iter().map(|&(code, _)| code)
+}
+
+/// A longer description of the diagnostics with `code`, with examples.
+///
+/// # Examples
+///
+///     use simcom::diagnostics::explain;
+///
+///     assert!(explain("E0001").unwrap().starts_with("A token appeared where"));
+///     assert!(explain("W0202").unwrap().contains("UpperCamelCase"));
+///     assert_eq!(explain("E9999"), None);
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS.iter()
+        .find(|&&(c, _)| c.eq_ignore_ascii_case(code))
+        .map(|&(_, explanation)| explanation)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{all, explain};
+
+    #[test]
+    fn every_code_is_explained_once() {
+        let codes: Vec<&str> = all().collect();
+        for (i, code) in codes.iter().enumerate() {
+            assert_eq!(code.len(), 5, "{}", code);
+            assert!(code.starts_with('E') || code.starts_with('W'), "{}", code);
+            assert!(!codes[i + 1..].contains(code), "{}", code);
+            assert!(explain(code).unwrap().ends_with('\n'), "{}", code);
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        assert_eq!(explain("e0002"), explain("E0002"));
+        assert!(explain("W0203").unwrap().contains("duplicate_fields"));
+        assert_eq!(explain(""), None);
+        assert_eq!(explain("naming"), None);
+    }
+}
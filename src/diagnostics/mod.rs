@@ -0,0 +1,368 @@
+//! Diagnostics module.
+//!
+//! Every phase has its own way of finding problems: the lexer doesn't know a
+//! character, the parser doesn't expect a token, a lint doesn't like a name.
+//! They all end up as a [`Diagnostic`][0], so there is a single way to show
+//! them to people (or to programs, as JSON).
+//!
+//! Each kind of diagnostic has a stable [code][1], like `E0001`, and
+//! [`explain`][2] says more about it. Some come with [suggestions][3] that
+//! fix them, and some point to more than one place with [labels][4], like
+//! the two definitions of a type defined twice.
+//!
+//! [0]: struct.Diagnostic.html
+//! [1]: codes/index.html
+//! [2]: fn.explain.html
+//! [3]: struct.Suggestion.html
+//! [4]: struct.Label.html
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::json::{Json, ToJson};
+use super::lexer::{Span, Token};
+use super::locale::{translate, unexpected, Locale};
+
+pub mod codes;
+mod suggestion;
+
+pub use self::codes::explain;
+pub use self::suggestion::{closest, missing_semicolon, Applicability, Suggestion, TextEdit};
+
+/// How bad a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Prevents the compilation from producing anything.
+    Error,
+    /// Something suspicious, but the code is still generated.
+    Warning,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// Another place a diagnostic points to, saying what it has to do with the
+/// problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl ToJson for Label {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("start", Json::from(self.span.start)),
+            ("end", Json::from(self.span.end)),
+            ("message", Json::from(&self.message[..])),
+        ])
+    }
+}
+
+/// A problem found somewhere in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Identifies the kind of problem, one of the [codes][0]. Not every
+    /// diagnostic has one.
+    ///
+    /// [0]: codes/index.html
+    pub code: Option<String>,
+    pub message: String,
+    /// Where the problem is.
+    pub span: Span,
+    /// Other places that have to do with the problem, in any file.
+    pub labels: Vec<Label>,
+    /// Extra explanations, shown after the message.
+    pub notes: Vec<String>,
+    /// Ways to fix the problem.
+    pub suggestions: Vec<Suggestion>,
+    /// The token the parser didn't expect, for the diagnostics of
+    /// [`unexpected`][0]. Their message is written from it in other
+    /// locales.
+    ///
+    /// [0]: #method.unexpected
+    pub token: Option<Token>,
+}
+
+impl Diagnostic {
+    pub fn error<M: Into<String>>(message: M, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            code: None,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            suggestions: Vec::new(),
+            token: None,
+        }
+    }
+
+    pub fn warning<M: Into<String>>(message: M, span: Span) -> Diagnostic {
+        Diagnostic { severity: Severity::Warning, ..Diagnostic::error(message, span) }
+    }
+
+    /// The error for a token the parser didn't expect. Tokens the lexer
+    /// didn't recognize get their own message.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::diagnostics::Diagnostic;
+    ///     use simcom::lexer::{Span, Token};
+    ///
+    ///     let diagnostic = Diagnostic::unexpected(&Token::Semicolon, Span::new(4, 5));
+    ///     assert_eq!(diagnostic.message, "unexpected `;`");
+    ///     assert_eq!(diagnostic.code.as_deref(), Some("E0001"));
+    ///
+    ///     let diagnostic = Diagnostic::unexpected(&Token::Illegal, Span::new(0, 1));
+    ///     assert_eq!(diagnostic.message, "unknown character");
+    pub fn unexpected(token: &Token, span: Span) -> Diagnostic {
+        match *token {
+            Token::Illegal => Diagnostic::error("unknown character", span).with_code(codes::UNKNOWN_CHARACTER),
+            _ => Diagnostic {
+                token: Some(token.clone()),
+                ..Diagnostic::error(format!("unexpected {}", token), span).with_code(codes::UNEXPECTED_TOKEN)
+            },
+        }
+    }
+
+    /// Like [`unexpected`][0], with a suggestion when `source`, the text the
+    /// span is in, shows what's missing.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::diagnostics::Diagnostic;
+    ///     use simcom::lexer::{Span, Token};
+    ///
+    ///     let source = "tipo A(x: B)\ntipo C(y: A);";
+    ///     let diagnostic = Diagnostic::unexpected_in(source, &Token::Type, Span::new(13, 17));
+    ///     assert_eq!(diagnostic.suggestions[0].message, "insert `;`");
+    ///
+    /// [0]: #method.unexpected
+    pub fn unexpected_in(source: &str, token: &Token, span: Span) -> Diagnostic {
+        let diagnostic = Diagnostic::unexpected(token, span);
+        match missing_semicolon(source, token, span) {
+            Some(suggestion) => diagnostic.with_suggestion(suggestion),
+            None => diagnostic,
+        }
+    }
+
+    pub fn with_code<C: Into<String>>(mut self, code: C) -> Diagnostic {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn with_label<M: Into<String>>(mut self, span: Span, message: M) -> Diagnostic {
+        self.labels.push(Label { span, message: message.into() });
+        self
+    }
+
+    pub fn with_note<N: Into<String>>(mut self, note: N) -> Diagnostic {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Diagnostic {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// The message in `locale`, as far as the [catalog][0] goes.
+    ///
+    /// [0]: ../locale/index.html
+    pub fn message_in(&self, locale: Locale) -> String {
+        match self.token {
+            Some(ref token) if locale != Locale::English => unexpected(token, locale),
+            _ => translate(&self.message, locale),
+        }
+    }
+
+    /// The diagnostic as people read it, given where its span is (usually
+    /// `file:line:column`). Labels come after the message, then the notes
+    /// and then the suggestions. Ends with a new line.
+    ///
+    /// Labels only say their byte offsets here, [`render_with`][0] can say
+    /// where they are.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::diagnostics::{Diagnostic, Suggestion};
+    ///     use simcom::lexer::Span;
+    ///
+    ///     let diagnostic = Diagnostic::warning("type `a` is never used", Span::new(5, 6))
+    ///         .with_code("W0201")
+    ///         .with_note("it can be removed")
+    ///         .with_suggestion(Suggestion::replace("remove it", Span::new(0, 14), ""));
+    ///
+    ///     assert_eq!(diagnostic.render("a.sc:1:6"), "\
+    ///     warning[W0201]: a.sc:1:6: type `a` is never used
+    ///         = note: it can be removed
+    ///         = help: remove it
+    ///     ");
+    ///
+    /// [0]: #method.render_with
+    pub fn render(&self, location: &str) -> String {
+        self.render_in(location, Locale::English)
+    }
+
+    /// Like [`render`][0], translated to `locale` as far as the [catalog][1]
+    /// goes. Codes stay the same, since they are names.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::diagnostics::Diagnostic;
+    ///     use simcom::lexer::{Span, Token};
+    ///     use simcom::locale::Locale;
+    ///
+    ///     let diagnostic = Diagnostic::unexpected(&Token::Semicolon, Span::new(13, 14));
+    ///     assert_eq!(diagnostic.render_in("a.sc:1:14", Locale::Spanish), "error[E0001]: a.sc:1:14: no se esperaba `;`\n");
+    ///
+    /// [0]: #method.render
+    /// [1]: ../locale/index.html
+    pub fn render_in(&self, location: &str, locale: Locale) -> String {
+        self.render_located(location, &|span| format!("{}..{}", span.start, span.end), locale)
+    }
+
+    /// Like [`render_in`][0], with `locate` saying where the span and the
+    /// labels are.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::diagnostics::Diagnostic;
+    ///     use simcom::lexer::Span;
+    ///     use simcom::locale::Locale;
+    ///
+    ///     let diagnostic = Diagnostic::error("type `A` is defined more than once", Span::new(19, 20))
+    ///         .with_label(Span::new(5, 6), "previous definition of `A` is here");
+    ///
+    ///     let locate = |span: Span| format!("a.sc:{}", span.start);
+    ///     assert_eq!(diagnostic.render_with(locate, Locale::English), "\
+    ///     error: a.sc:19: type `A` is defined more than once
+    ///         --> a.sc:5: previous definition of `A` is here
+    ///     ");
+    ///
+    /// [0]: #method.render_in
+    pub fn render_with<F: Fn(Span) -> String>(&self, locate: F, locale: Locale) -> String {
+        self.render_located(&locate(self.span), &locate, locale)
+    }
+
+    fn render_located(&self, location: &str, locate: &dyn Fn(Span) -> String, locale: Locale) -> String {
+        let mut out = translate(self.severity.name(), locale);
+        if let Some(ref code) = self.code {
+            out.push_str(&format!("[{}]", code));
+        }
+        out.push_str(&format!(": {}: {}\n", location, self.message_in(locale)));
+        for label in &self.labels {
+            out.push_str(&format!("    --> {}: {}\n", locate(label.span), translate(&label.message, locale)));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("    = {}: {}\n", translate("note", locale), translate(note, locale)));
+        }
+        for suggestion in &self.suggestions {
+            out.push_str(&format!("    = {}: {}\n", translate("help", locale), translate(&suggestion.message, locale)));
+        }
+        out
+    }
+
+    /// The diagnostic for other programs. Spans are byte offsets.
+    pub fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("severity", Json::from(self.severity.name())),
+            ("code", Json::from(self.code.clone())),
+            ("message", Json::from(&self.message[..])),
+            ("span", Json::object(vec![
+                ("start", Json::from(self.span.start)),
+                ("end", Json::from(self.span.end)),
+            ])),
+            ("labels", Json::Array(self.labels.iter().map(ToJson::to_json).collect())),
+            ("notes", Json::Array(self.notes.iter().map(|n| Json::from(&n[..])).collect())),
+            ("suggestions", Json::Array(self.suggestions.iter().map(ToJson::to_json).collect())),
+        ])
+    }
+}
+
+/// The severity, the code and the message, without the location:
+/// `warning[W0202]: type `punto` should...`.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.severity.name())?;
+        if let Some(ref code) = self.code {
+            write!(f, "[{}]", code)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Diagnostic {}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::Span;
+    use super::super::locale::Locale;
+    use super::{Diagnostic, Severity, Suggestion};
+
+    #[test]
+    fn render_without_code() {
+        let diagnostic = Diagnostic::error("unexpected `;`", Span::new(7, 8));
+        assert_eq!(diagnostic.render("b.sc:1:8"), "error: b.sc:1:8: unexpected `;`\n");
+    }
+
+    #[test]
+    fn render_in_spanish() {
+        let diagnostic = Diagnostic::warning("type `a` is never used", Span::new(5, 6))
+            .with_code("unused_types")
+            .with_note("not translated");
+        assert_eq!(diagnostic.render_in("a.sc:1:6", Locale::Spanish), "\
+            aviso[unused_types]: a.sc:1:6: el tipo `a` no se usa nunca\n    = nota: not translated\n");
+    }
+
+    #[test]
+    fn display() {
+        let diagnostic = Diagnostic::warning("bad name", Span::new(5, 10)).with_code("naming");
+        assert_eq!(diagnostic.to_string(), "warning[naming]: bad name");
+    }
+
+    #[test]
+    fn to_json() {
+        let diagnostic = Diagnostic::warning("bad name", Span::new(5, 10)).with_code("naming");
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.to_json().to_string(), "{\"severity\":\"warning\",\
+            \"code\":\"naming\",\"message\":\"bad name\",\
+            \"span\":{\"start\":5,\"end\":10},\"labels\":[],\"notes\":[],\"suggestions\":[]}");
+    }
+
+    #[test]
+    fn suggestions() {
+        let diagnostic = Diagnostic::warning("unknown type `Punot`", Span::new(10, 15))
+            .with_suggestion(Suggestion::replace("did you mean `Punto`?", Span::new(10, 15), "Punto"));
+        assert_eq!(diagnostic.render_in("a.sc:1:11", Locale::Spanish), "\
+            aviso: a.sc:1:11: unknown type `Punot`\n    = ayuda: ¿quisiste decir `Punto`?\n");
+        assert!(diagnostic.to_json().to_string().ends_with("\"suggestions\":[{\"message\":\"did you mean `Punto`?\",\
+            \"edits\":[{\"start\":10,\"end\":15,\"replacement\":\"Punto\"}],\"applicability\":\"machine-applicable\"}]}"));
+    }
+
+    #[test]
+    fn labels() {
+        let diagnostic = Diagnostic::error("type `A` is defined more than once", Span::new(19, 20))
+            .with_label(Span::new(5, 6), "previous definition of `A` is here")
+            .with_note("a note");
+        assert_eq!(diagnostic.render("a.sc:2:6"), "\
+            error: a.sc:2:6: type `A` is defined more than once\n    --> 5..6: previous definition of `A` is here\n\
+            \x20   = note: a note\n");
+        assert!(diagnostic.to_json().to_string().contains("\"labels\":[{\"start\":5,\"end\":6,\
+            \"message\":\"previous definition of `A` is here\"}]"));
+    }
+}
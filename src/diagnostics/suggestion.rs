@@ -0,0 +1,212 @@
+//! Fixes that come with a diagnostic.
+//!
+//! When the fix for a problem is clear, like a missing `;`, the diagnostic
+//! carries it as a [`Suggestion`][0]: a message for people and the edits that
+//! apply it, so editors can offer them and tools can apply them.
+//!
+//! [0]: struct.Suggestion.html
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::super::json::{Json, ToJson};
+use super::super::lexer::{Span, Token};
+
+/// A change to the text of a file: what `span` covers is replaced. The span
+/// says which file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub replacement: String,
+}
+
+impl ToJson for TextEdit {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("start", Json::from(self.span.start)),
+            ("end", Json::from(self.span.end)),
+            ("replacement", Json::from(&self.replacement[..])),
+        ])
+    }
+}
+
+/// Whether a suggestion can be applied without asking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Applicability {
+    /// The suggestion is certainly what was meant, so `--fix` applies it.
+    MachineApplicable,
+    /// The suggestion is a guess, like the name of a similar type. Someone
+    /// has to look at it first.
+    MaybeIncorrect,
+}
+
+impl Applicability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Applicability::MachineApplicable => "machine-applicable",
+            Applicability::MaybeIncorrect => "maybe-incorrect",
+        }
+    }
+}
+
+/// A way to fix a diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// What the fix does, like "insert `;`".
+    pub message: String,
+    pub edits: Vec<TextEdit>,
+    pub applicability: Applicability,
+}
+
+impl Suggestion {
+    /// A suggestion that replaces what `span` covers with `replacement`.
+    /// It's machine applicable unless said [otherwise][0].
+    ///
+    /// [0]: #method.maybe_incorrect
+    pub fn replace<M: Into<String>, R: Into<String>>(message: M, span: Span, replacement: R) -> Suggestion {
+        Suggestion {
+            message: message.into(),
+            edits: vec![TextEdit { span, replacement: replacement.into() }],
+            applicability: Applicability::MachineApplicable,
+        }
+    }
+
+    /// The same suggestion, marked as a guess.
+    pub fn maybe_incorrect(self) -> Suggestion {
+        Suggestion { applicability: Applicability::MaybeIncorrect, ..self }
+    }
+
+    pub fn is_machine_applicable(&self) -> bool {
+        self.applicability == Applicability::MachineApplicable
+    }
+}
+
+impl ToJson for Suggestion {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("message", Json::from(&self.message[..])),
+            ("edits", Json::Array(self.edits.iter().map(ToJson::to_json).collect())),
+            ("applicability", Json::from(self.applicability.name())),
+        ])
+    }
+}
+
+/// The `;` that ends a definition, if it's missing before an unexpected
+/// `token` at `span`: the token starts the next definition (or a module
+/// declaration), or is the end of the file, and the last thing before it, in
+/// `source`, is a `)`.
+///
+/// # Examples
+///
+///     use simcom::diagnostics::missing_semicolon;
+///     use simcom::lexer::{Span, Token};
+///
+///     let source = "tipo A(x: B)\ntipo C(y: A);";
+///     let suggestion = missing_semicolon(source, &Token::Type, Span::new(13, 17)).unwrap();
+///     assert_eq!(suggestion.edits[0].span, Span::new(12, 12));
+///     assert_eq!(suggestion.edits[0].replacement, ";");
+///
+///     let suggestion = missing_semicolon("tipo A(x: B)\n", &Token::EOF, Span::new(13, 13)).unwrap();
+///     assert_eq!(suggestion.edits[0].span, Span::new(12, 12));
+///
+///     assert_eq!(missing_semicolon("tipo A(x B);", &Token::Ident(String::from("B")), Span::new(9, 10)), None);
+pub fn missing_semicolon(source: &str, token: &Token, span: Span) -> Option<Suggestion> {
+    match *token {
+        Token::Type | Token::Interface | Token::Enum | Token::Module | Token::At | Token::DocComment(_) | Token::EOF => (),
+        _ => return None,
+    }
+
+    let before = source.get(..span.start)?.trim_end();
+    if !before.ends_with(')') {
+        return None;
+    }
+
+    let end = Span { start: before.len(), end: before.len(), ..span };
+    Some(Suggestion::replace("insert `;`", end, ";"))
+}
+
+/// The candidate closest to `name`, if one is close enough to be a typo of
+/// it: at most one edit (inserting, removing, changing or swapping two
+/// characters) every three characters. Ties go to the first candidate in
+/// alphabetical order, so the order of `candidates` doesn't matter.
+///
+/// # Examples
+///
+///     use simcom::diagnostics::closest;
+///
+///     let names = ["Punto", "Linea", "Circulo"];
+///     assert_eq!(closest("Punot", names.iter().cloned()), Some("Punto"));
+///     assert_eq!(closest("Lineas", names.iter().cloned()), Some("Linea"));
+///     assert_eq!(closest("Poligono", names.iter().cloned()), None);
+pub fn closest<'a, I: IntoIterator<Item = &'a str>>(name: &str, candidates: I) -> Option<&'a str> {
+    let most = name.chars().count() / 3;
+
+    candidates.into_iter()
+        .filter(|&candidate| candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= most)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// How many characters have to be inserted, removed, changed or swapped with
+/// the next one to turn `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // Three rows of the table: two back, the last one and the current one.
+    let mut before: Vec<usize> = Vec::new();
+    let mut last: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            row[j] = (last[j] + 1).min(row[j - 1] + 1).min(last[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        before = last;
+        last = row;
+    }
+
+    last[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::lexer::{Span, Token};
+    use super::{closest, distance, missing_semicolon};
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("", ""), 0);
+        assert_eq!(distance("abc", ""), 3);
+        assert_eq!(distance("Punto", "Punot"), 1);
+        assert_eq!(distance("Entero", "Entro"), 1);
+        assert_eq!(distance("Real", "Reales"), 2);
+        assert_eq!(distance("año", "ano"), 1);
+    }
+
+    #[test]
+    fn only_close_names() {
+        let names = ["A", "Ab", "Entero", "Entera"];
+        assert_eq!(closest("B", names.iter().cloned()), None);
+        assert_eq!(closest("Enteri", names.iter().cloned()), Some("Entera"));
+        assert_eq!(closest("Aa", names.iter().cloned()), None);
+    }
+
+    #[test]
+    fn semicolons() {
+        let source = "tipo A(x: B)  \n/// C.\ntipo C(y: A); @x";
+        assert_eq!(missing_semicolon(source, &Token::DocComment(String::from("C.")), Span::new(15, 21))
+            .map(|s| s.edits[0].span), Some(Span::new(12, 12)));
+        assert_eq!(missing_semicolon(source, &Token::At, Span::new(35, 36)), None);
+        assert_eq!(missing_semicolon(source, &Token::Type, Span::new(100, 104)), None);
+        assert_eq!(missing_semicolon("tipo A(x: B)  \r\n", &Token::EOF, Span::new(16, 16))
+            .map(|s| s.edits[0].span), Some(Span::new(12, 12)));
+        assert_eq!(missing_semicolon("tipo A(x: B", &Token::EOF, Span::new(11, 11)), None);
+    }
+}
@@ -0,0 +1,545 @@
+//! AST diff module.
+//!
+//! Compares two versions of a schema by what they define, not by how they
+//! are written: moving a definition around, reformatting it or rewording its
+//! doc comment isn't a change, but adding a field, changing its type or its
+//! number, or changing the value of a variant is. A type that disappears
+//! while another one of the same kind with the very same fields or variants
+//! shows up is taken as renamed.
+//!
+//! [`diff_schemas`][0] compares two analyzed schemas instead, which also says
+//! which types end up in a cycle. That's what `simcom diff` prints.
+//!
+//! [0]: fn.diff_schemas.html
+//!
+//! # Examples
+//!
+//!     use simcom::diff::{diff_asts, AstChange};
+//!     use simcom::lexer::Lexer;
+//!     use simcom::parser::{Ast, Parser};
+//!
+//!     let old: Vec<Ast> = Parser::new(Lexer::new("tipo Punto(x: Entero, y: Entero);")).collect();
+//!     let new: Vec<Ast> = Parser::new(Lexer::new("tipo Punto(x: Real, z: Entero);")).collect();
+//!
+//!     let changes = diff_asts(&old, &new);
+//!     assert_eq!(changes[0].to_string(), "removed field `Punto.y`");
+//!     assert_eq!(changes[1].to_string(), "changed the type of `Punto.x` from `Entero` to `Real`");
+//!     assert_eq!(changes[2], AstChange::FieldAdded {
+//!         definition: String::from("Punto"),
+//!         field: String::from("z"),
+//!         typename: String::from("Entero"),
+//!     });
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::json::{Json, ToJson};
+use super::parser::{Ast, DefinitionKind, Parameter, TypeDefinition};
+#[cfg(feature = "std")]
+use super::semantic::Semantic;
+
+/// A difference between two versions of a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AstChange {
+    TypeAdded { name: String },
+    TypeRemoved { name: String },
+    /// A type was removed and another one of the same kind, with the same
+    /// fields or variants, added.
+    TypeRenamed { old: String, new: String },
+    /// A type became an enum, an interface became a type...
+    KindChanged { name: String, old: DefinitionKind, new: DefinitionKind },
+    FieldAdded { definition: String, field: String, typename: String },
+    FieldRemoved { definition: String, field: String },
+    FieldRetyped { definition: String, field: String, old: String, new: String },
+    FieldRenumbered { definition: String, field: String, old: Option<i64>, new: Option<i64> },
+    VariantAdded { definition: String, variant: String, value: i64 },
+    VariantRemoved { definition: String, variant: String },
+    VariantRevalued { definition: String, variant: String, old: i64, new: i64 },
+    /// A type that wasn't in a cycle is now, so the code generated for it
+    /// changes (the C backend uses pointers).
+    CycleIntroduced { name: String },
+}
+
+impl AstChange {
+    /// Whether the change breaks the programs written for the old version:
+    /// anything but adding types, fields and variants. Every field is
+    /// required, so an added one is always there.
+    pub fn is_breaking(&self) -> bool {
+        !matches!(*self, AstChange::TypeAdded { .. } | AstChange::FieldAdded { .. } | AstChange::VariantAdded { .. })
+    }
+}
+
+/// `#3`, or "no number".
+struct Number(Option<i64>);
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(number) => write!(f, "#{}", number),
+            None => write!(f, "no number"),
+        }
+    }
+}
+
+/// One line, like "added type `Punto`".
+impl fmt::Display for AstChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AstChange::TypeAdded { ref name } => write!(f, "added type `{}`", name),
+            AstChange::TypeRemoved { ref name } => write!(f, "removed type `{}`", name),
+            AstChange::TypeRenamed { ref old, ref new } => write!(f, "renamed type `{}` to `{}`", old, new),
+            AstChange::KindChanged { ref name, old, new } => {
+                write!(f, "changed `{}` from {} to {}", name, old.name(), new.name())
+            },
+            AstChange::FieldAdded { ref definition, ref field, ref typename } => {
+                write!(f, "added field `{}.{}: {}`", definition, field, typename)
+            },
+            AstChange::FieldRemoved { ref definition, ref field } => {
+                write!(f, "removed field `{}.{}`", definition, field)
+            },
+            AstChange::FieldRetyped { ref definition, ref field, ref old, ref new } => {
+                write!(f, "changed the type of `{}.{}` from `{}` to `{}`", definition, field, old, new)
+            },
+            AstChange::FieldRenumbered { ref definition, ref field, old, new } => {
+                write!(f, "changed the number of `{}.{}` from {} to {}", definition, field, Number(old), Number(new))
+            },
+            AstChange::VariantAdded { ref definition, ref variant, value } => {
+                write!(f, "added variant `{}.{} = {}`", definition, variant, value)
+            },
+            AstChange::VariantRemoved { ref definition, ref variant } => {
+                write!(f, "removed variant `{}.{}`", definition, variant)
+            },
+            AstChange::VariantRevalued { ref definition, ref variant, old, new } => {
+                write!(f, "changed the value of `{}.{}` from {} to {}", definition, variant, old, new)
+            },
+            AstChange::CycleIntroduced { ref name } => write!(f, "type `{}` is now part of a cycle", name),
+        }
+    }
+}
+
+/// `{"FieldRemoved":{"definition":"Punto","field":"y"}}`, with one key per
+/// field of the variant. Kinds are their names, like `"enum"`, and missing
+/// field numbers are `null`.
+impl ToJson for AstChange {
+    fn to_json(&self) -> Json {
+        let (variant, fields) = match *self {
+            AstChange::TypeAdded { ref name } => ("TypeAdded", vec![("name", name.to_json())]),
+            AstChange::TypeRemoved { ref name } => ("TypeRemoved", vec![("name", name.to_json())]),
+            AstChange::TypeRenamed { ref old, ref new } => {
+                ("TypeRenamed", vec![("old", old.to_json()), ("new", new.to_json())])
+            },
+            AstChange::KindChanged { ref name, old, new } => ("KindChanged", vec![
+                ("name", name.to_json()),
+                ("old", Json::from(old.name())),
+                ("new", Json::from(new.name())),
+            ]),
+            AstChange::FieldAdded { ref definition, ref field, ref typename } => ("FieldAdded", vec![
+                ("definition", definition.to_json()),
+                ("field", field.to_json()),
+                ("typename", typename.to_json()),
+            ]),
+            AstChange::FieldRemoved { ref definition, ref field } => {
+                ("FieldRemoved", vec![("definition", definition.to_json()), ("field", field.to_json())])
+            },
+            AstChange::FieldRetyped { ref definition, ref field, ref old, ref new } => ("FieldRetyped", vec![
+                ("definition", definition.to_json()),
+                ("field", field.to_json()),
+                ("old", old.to_json()),
+                ("new", new.to_json()),
+            ]),
+            AstChange::FieldRenumbered { ref definition, ref field, old, new } => ("FieldRenumbered", vec![
+                ("definition", definition.to_json()),
+                ("field", field.to_json()),
+                ("old", old.to_json()),
+                ("new", new.to_json()),
+            ]),
+            AstChange::VariantAdded { ref definition, ref variant, value } => ("VariantAdded", vec![
+                ("definition", definition.to_json()),
+                ("variant", variant.to_json()),
+                ("value", value.to_json()),
+            ]),
+            AstChange::VariantRemoved { ref definition, ref variant } => {
+                ("VariantRemoved", vec![("definition", definition.to_json()), ("variant", variant.to_json())])
+            },
+            AstChange::VariantRevalued { ref definition, ref variant, old, new } => ("VariantRevalued", vec![
+                ("definition", definition.to_json()),
+                ("variant", variant.to_json()),
+                ("old", old.to_json()),
+                ("new", new.to_json()),
+            ]),
+            AstChange::CycleIntroduced { ref name } => ("CycleIntroduced", vec![("name", name.to_json())]),
+        };
+
+        Json::object(vec![(variant, Json::object(fields))])
+    }
+}
+
+/// The definitions by name. Unexpected tokens are ignored and, like in the
+/// semantic analyzer, the last definition of a name wins.
+fn definitions<'a, 'src>(asts: &'a [Ast<'src>]) -> BTreeMap<&'a str, &'a TypeDefinition<'src>> {
+    asts.iter()
+        .filter_map(|ast| match *ast {
+            Ast::TypeDefinition(ref definition) => Some((&definition.name[..], definition)),
+            Ast::Unexpected(..) => None,
+        })
+        .collect()
+}
+
+/// The definitions of an analyzed schema by their qualified name.
+#[cfg(feature = "std")]
+fn analyzed(semantic: &Semantic) -> BTreeMap<&str, &TypeDefinition<'static>> {
+    semantic.definitions.iter().map(|(name, definition)| (&name[..], definition)).collect()
+}
+
+/// What a definition has inside, sorted to compare it regardless of the
+/// order: its fields as `(name, type, number)` and its variants as
+/// `(name, value)`.
+type Body<'a> = (DefinitionKind, Vec<(&'a str, &'a str, Option<i64>)>, Vec<(&'a str, i64)>);
+
+fn body<'a>(definition: &'a TypeDefinition) -> Body<'a> {
+    let mut fields: Vec<(&str, &str, Option<i64>)> = definition.parameters.iter()
+        .map(|p| (&p.name[..], &p.typename[..], p.number))
+        .collect();
+    fields.sort();
+
+    let mut variants: Vec<(&str, i64)> = definition.variants.iter()
+        .zip(definition.values())
+        .map(|(variant, value)| (&variant.name[..], value))
+        .collect();
+    variants.sort();
+
+    (definition.kind, fields, variants)
+}
+
+/// Whether `new` is `old` with another name: the same kind and the same
+/// fields or variants. Empty definitions are all alike, so they are never
+/// taken as renamed.
+fn same_body(old: &TypeDefinition, new: &TypeDefinition) -> bool {
+    let old = body(old);
+    (!old.1.is_empty() || !old.2.is_empty()) && old == body(new)
+}
+
+/// What changed from `old` to `new`.
+///
+/// Changes to types come first (removed, renamed and added, each sorted by
+/// name), then the changes inside the types in both, by type name. A type
+/// whose kind changed only says so. Within a type, removed fields go first,
+/// then the ones with a new type, the ones with a new number, in the order
+/// they are written, and then the added ones; variants follow the same
+/// order, with new values instead of new types.
+pub fn diff_asts(old: &[Ast], new: &[Ast]) -> Vec<AstChange> {
+    diff_definitions(&definitions(old), &definitions(new))
+}
+
+/// What changed from `old` to `new`, in the same order as
+/// [`diff_asts`][0], followed by the types of `old` that are part of a
+/// cycle in `new` but weren't in `old`, sorted. Types are compared by their
+/// qualified name.
+///
+/// # Examples
+///
+///     use simcom::diff::diff_schemas;
+///     use simcom::lexer::Lexer;
+///     use simcom::parser::Parser;
+///     use simcom::semantic::Semantic;
+///
+///     let old = Semantic::analyze(Parser::new(Lexer::new("tipo A(b: B); tipo B(n: Entero);"))).unwrap();
+///     let new = Semantic::analyze(Parser::new(Lexer::new("tipo A(b: B); tipo B(a: A);"))).unwrap();
+///
+///     let changes: Vec<String> = diff_schemas(&old, &new).iter().map(ToString::to_string).collect();
+///     assert_eq!(changes, [
+///         "removed field `B.n`",
+///         "added field `B.a: A`",
+///         "type `A` is now part of a cycle",
+///         "type `B` is now part of a cycle",
+///     ]);
+///
+/// [0]: fn.diff_asts.html
+#[cfg(feature = "std")]
+pub fn diff_schemas(old: &Semantic, new: &Semantic) -> Vec<AstChange> {
+    let mut changes = diff_definitions(&analyzed(old), &analyzed(new));
+
+    let mut cycles: Vec<&String> = new.cycles.iter()
+        .filter(|name| old.definitions.contains_key(*name) && !old.cycles.contains(*name))
+        .collect();
+    cycles.sort();
+    changes.extend(cycles.into_iter().map(|name| AstChange::CycleIntroduced { name: name.clone() }));
+
+    changes
+}
+
+fn diff_definitions(old: &BTreeMap<&str, &TypeDefinition>, new: &BTreeMap<&str, &TypeDefinition>) -> Vec<AstChange> {
+    let mut changes = Vec::new();
+
+    let mut added: Vec<&str> = new.keys().filter(|name| !old.contains_key(*name)).cloned().collect();
+    let mut renamed = Vec::new();
+
+    for (&name, definition) in old {
+        if new.contains_key(name) {
+            continue;
+        }
+
+        match added.iter().position(|added| same_body(definition, new[added])) {
+            Some(i) => renamed.push(AstChange::TypeRenamed { old: name.to_string(), new: added.remove(i).to_string() }),
+            None => changes.push(AstChange::TypeRemoved { name: name.to_string() }),
+        }
+    }
+
+    changes.extend(renamed);
+    changes.extend(added.into_iter().map(|name| AstChange::TypeAdded { name: name.to_string() }));
+
+    for (&name, old) in old {
+        let new = match new.get(name) {
+            Some(new) => new,
+            None => continue,
+        };
+        if old.kind != new.kind {
+            changes.push(AstChange::KindChanged { name: name.to_string(), old: old.kind, new: new.kind });
+            continue;
+        }
+
+        fields(name, old, new, &mut changes);
+        variants(name, old, new, &mut changes);
+    }
+
+    changes
+}
+
+/// The field of `definition` called `name`.
+fn find<'a, 'src>(definition: &'a TypeDefinition<'src>, name: &str) -> Option<&'a Parameter<'src>> {
+    definition.parameters.iter().find(|p| p.name == name)
+}
+
+fn fields(name: &str, old: &TypeDefinition, new: &TypeDefinition, changes: &mut Vec<AstChange>) {
+    for parameter in &old.parameters {
+        if find(new, &parameter.name).is_none() {
+            changes.push(AstChange::FieldRemoved {
+                definition: name.to_string(),
+                field: parameter.name.to_string(),
+            });
+        }
+    }
+
+    for parameter in &old.parameters {
+        if let Some(retyped) = find(new, &parameter.name).filter(|p| p.typename != parameter.typename) {
+            changes.push(AstChange::FieldRetyped {
+                definition: name.to_string(),
+                field: parameter.name.to_string(),
+                old: parameter.typename.to_string(),
+                new: retyped.typename.to_string(),
+            });
+        }
+    }
+
+    for parameter in &old.parameters {
+        if let Some(renumbered) = find(new, &parameter.name).filter(|p| p.number != parameter.number) {
+            changes.push(AstChange::FieldRenumbered {
+                definition: name.to_string(),
+                field: parameter.name.to_string(),
+                old: parameter.number,
+                new: renumbered.number,
+            });
+        }
+    }
+
+    for parameter in &new.parameters {
+        if find(old, &parameter.name).is_none() {
+            changes.push(AstChange::FieldAdded {
+                definition: name.to_string(),
+                field: parameter.name.to_string(),
+                typename: parameter.typename.to_string(),
+            });
+        }
+    }
+}
+
+fn variants(name: &str, old: &TypeDefinition, new: &TypeDefinition, changes: &mut Vec<AstChange>) {
+    let values = |definition: &TypeDefinition| -> Vec<(String, i64)> {
+        definition.variants.iter()
+            .map(|variant| variant.name.to_string())
+            .zip(definition.values())
+            .collect()
+    };
+    let (old, new) = (values(old), values(new));
+    let value_of = |variants: &[(String, i64)], variant: &str| {
+        variants.iter().find(|(name, _)| name == variant).map(|&(_, value)| value)
+    };
+
+    for (variant, _) in &old {
+        if value_of(&new, variant).is_none() {
+            changes.push(AstChange::VariantRemoved { definition: name.to_string(), variant: variant.clone() });
+        }
+    }
+
+    for &(ref variant, value) in &old {
+        if let Some(revalued) = value_of(&new, variant).filter(|&v| v != value) {
+            changes.push(AstChange::VariantRevalued {
+                definition: name.to_string(),
+                variant: variant.clone(),
+                old: value,
+                new: revalued,
+            });
+        }
+    }
+
+    for &(ref variant, value) in &new {
+        if value_of(&old, variant).is_none() {
+            changes.push(AstChange::VariantAdded { definition: name.to_string(), variant: variant.clone(), value });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::Lexer;
+    use super::super::parser::{Ast, Parser};
+    use super::*;
+
+    fn diff(old: &str, new: &str) -> Vec<String> {
+        let old: Vec<Ast> = Parser::new(Lexer::new(old)).collect();
+        let new: Vec<Ast> = Parser::new(Lexer::new(new)).collect();
+        diff_asts(&old, &new).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn only_the_meaning_matters() {
+        let old = "/// Uno.\ntipo A(x: E, y: F); tipo B(z: A);";
+        let new = "tipo B(\n    z: A,\n);\n\n/// Otro.\n@obsoleto tipo A(x: E, y: F);";
+        assert!(diff(old, new).is_empty());
+    }
+
+    #[test]
+    fn types() {
+        let old = "tipo A(x: E); tipo B(y: E); tipo C(z: E);";
+        let new = "tipo A(x: E); tipo D(z: E); tipo F(w: E);";
+        assert_eq!(diff(old, new), [
+            "removed type `B`",
+            "renamed type `C` to `D`",
+            "added type `F`",
+        ]);
+    }
+
+    #[test]
+    fn renames_need_the_same_fields() {
+        let old = "tipo A(x: E, y: E);";
+        let new = "tipo B(y: E, x: E); tipo C(x: E, y: E);";
+        assert_eq!(diff(old, new), ["renamed type `A` to `B`", "added type `C`"]);
+
+        let old = "tipo A(x: E);";
+        let new = "tipo B(x: F);";
+        assert_eq!(diff(old, new), ["removed type `A`", "added type `B`"]);
+    }
+
+    #[test]
+    fn fields() {
+        let old = "tipo A(x: E, y: E, z: E); tipo B(x: E);";
+        let new = "tipo A(w: E, z: F, x: E); tipo B(x: A);";
+        assert_eq!(diff(old, new), [
+            "removed field `A.y`",
+            "changed the type of `A.z` from `E` to `F`",
+            "added field `A.w: E`",
+            "changed the type of `B.x` from `E` to `A`",
+        ]);
+    }
+
+    #[test]
+    fn field_numbers() {
+        let old = "tipo P(x: Entero #1, y: Entero #2, z: Entero);";
+        let new = "tipo P(x: Entero #7, y: Entero #2, z: Entero #3);";
+        assert_eq!(diff(old, new), [
+            "changed the number of `P.x` from #1 to #7",
+            "changed the number of `P.z` from no number to #3",
+        ]);
+    }
+
+    #[test]
+    fn variants() {
+        let old = "enum E(A = 1, B = 2, D);";
+        let new = "enum E(A = 1, B = 3, C);";
+        assert_eq!(diff(old, new), [
+            "removed variant `E.D`",
+            "changed the value of `E.B` from 2 to 3",
+            "added variant `E.C = 4`",
+        ]);
+
+        // Values that aren't written count too.
+        assert_eq!(diff("enum E(A, B);", "enum E(Z, A, B);"), [
+            "changed the value of `E.A` from 0 to 1",
+            "changed the value of `E.B` from 1 to 2",
+            "added variant `E.Z = 0`",
+        ]);
+    }
+
+    #[test]
+    fn kinds() {
+        assert_eq!(diff("enum E(A, B);", "tipo E(a: A);"), ["changed `E` from enum to type"]);
+
+        // Every enum has no fields, but they aren't the same.
+        assert_eq!(diff("enum E(A, B);", "enum F(X, Y, Z);"), ["removed type `E`", "added type `F`"]);
+        assert_eq!(diff("enum E(A, B);", "enum F(B = 1, A = 0);"), ["renamed type `E` to `F`"]);
+        assert_eq!(diff("enum E(A, B);", "enum F(A, B = 2);"), ["removed type `E`", "added type `F`"]);
+        assert_eq!(diff("tipo A(x: E);", "interfaz B(x: E);"), ["removed type `A`", "added type `B`"]);
+        assert_eq!(diff("tipo A(x: E #1);", "tipo B(x: E #2);"), ["removed type `A`", "added type `B`"]);
+    }
+
+    #[test]
+    fn breaking_changes() {
+        let old: Vec<Ast> = Parser::new(Lexer::new("tipo A(x: E); tipo B(y: E);")).collect();
+        let new: Vec<Ast> = Parser::new(Lexer::new("tipo A(x: E, z: E); tipo C(w: E);")).collect();
+        let breaking: Vec<(String, bool)> = diff_asts(&old, &new).iter()
+            .map(|change| (change.to_string(), change.is_breaking()))
+            .collect();
+        assert_eq!(breaking, [
+            (String::from("removed type `B`"), true),
+            (String::from("added type `C`"), false),
+            (String::from("added field `A.z: E`"), false),
+        ]);
+    }
+
+    #[test]
+    fn json() {
+        let change = AstChange::FieldRetyped {
+            definition: String::from("A"),
+            field: String::from("x"),
+            old: String::from("E"),
+            new: String::from("F"),
+        };
+        assert_eq!(change.to_json().to_string(), r#"{"FieldRetyped":{"definition":"A","field":"x","old":"E","new":"F"}}"#);
+
+        let change = AstChange::FieldRenumbered {
+            definition: String::from("A"),
+            field: String::from("x"),
+            old: None,
+            new: Some(2),
+        };
+        assert_eq!(change.to_json().to_string(), r#"{"FieldRenumbered":{"definition":"A","field":"x","old":null,"new":2}}"#);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn new_cycles() {
+        let schema = |content| Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+        let old = schema("tipo X(a: A); tipo A(b: B); tipo B(n: Entero); tipo C(c: C);");
+        let new = schema("tipo X(a: A); tipo A(b: B); tipo B(a: A); tipo C(c: C);");
+
+        // `X` only uses the cycle, and `C` was already in one.
+        for _ in 0..4 {
+            let changes: Vec<String> = diff_schemas(&old, &new).iter().map(ToString::to_string).collect();
+            assert_eq!(changes, [
+                "removed field `B.n`",
+                "added field `B.a: A`",
+                "type `A` is now part of a cycle",
+                "type `B` is now part of a cycle",
+            ]);
+        }
+    }
+
+    #[test]
+    fn errors_are_ignored() {
+        let old = "tipo A(x: E); tipo ;";
+        let new = "?; tipo A(x: E);";
+        assert!(diff(old, new).is_empty());
+    }
+}
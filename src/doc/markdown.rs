@@ -0,0 +1,248 @@
+//! Documentation as Markdown.
+//!
+//! The same content as the HTML pages, for a docs repository or a wiki: every
+//! type with its doc comment, its fields, what it depends on, what depends on
+//! it and a Mermaid diagram of those types. It can be a page per type plus an
+//! index, like the HTML documentation, or a [single page][0] with everything.
+//!
+//! Doc comments are copied as they are, since they're usually written in
+//! Markdown already.
+//!
+//! [0]: enum.Layout.html#variant.SinglePage
+
+use std::borrow::Cow;
+
+use super::super::graph::{Format, Graph};
+use super::super::parser::{DefinitionKind, TypeDefinition};
+use super::super::semantic::Semantic;
+use super::super::units::unit;
+use super::{dependencies, dependents, heading, implementors, interfaces, summary, Page};
+
+/// How the documentation is split in files.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Layout {
+    /// `index.md`, with every type, and a page for each one.
+    #[default]
+    PagePerType,
+    /// Only `index.md`, with a section for each type.
+    SinglePage,
+}
+
+/// The file name of the page of a type.
+///
+/// # Examples
+///
+///     use simcom::doc::markdown;
+///
+///     assert_eq!(markdown::page_name("Punto"), "type.Punto.md");
+pub fn page_name(name: &str) -> String {
+    format!("type.{}.md", name)
+}
+
+/// Renders every definition and the index.
+///
+/// # Examples
+///
+///     use simcom::doc::markdown::{self, Layout};
+///     use simcom::lexer::Lexer;
+///     use simcom::parser::Parser;
+///     use simcom::semantic::Semantic;
+///
+///     let content = "/// Un punto.\ntipo Punto(x: Entero);";
+///     let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+///
+///     let pages = markdown::render(&semantic, "Tipos", Layout::PagePerType);
+///     assert_eq!(pages[0].path, "index.md");
+///     assert!(pages[0].content.contains("| [`Punto`](type.Punto.md) | Un punto. |"));
+///     assert!(pages[1].content.contains("\n# Type `Punto`\n"));
+///
+///     let pages = markdown::render(&semantic, "Tipos", Layout::SinglePage);
+///     assert_eq!(pages.len(), 1);
+///     assert!(pages[0].content.contains("| [`Punto`](#type.Punto) | Un punto. |"));
+pub fn render(semantic: &Semantic, title: &str, layout: Layout) -> Vec<Page> {
+    let mut names: Vec<&String> = semantic.definitions.keys().collect();
+    names.sort();
+
+    let graph = Graph::new(semantic);
+    let mut index = format!("# {}\n\n{}", title, table(semantic, layout, &names));
+    let mut pages = Vec::new();
+
+    for name in names {
+        let definition = &semantic.definitions[name];
+        match layout {
+            Layout::PagePerType => pages.push(Page {
+                path: page_name(name),
+                content: format!("[{}](index.md)\n\n{}", title, definition_section(semantic, &graph, definition, layout)),
+            }),
+            Layout::SinglePage => {
+                index.push_str(&format!("\n<a id=\"type.{}\"></a>\n\n", name));
+                index.push_str(&definition_section(semantic, &graph, definition, layout));
+            },
+        }
+    }
+
+    pages.insert(0, Page { path: String::from("index.md"), content: index });
+    pages
+}
+
+/// Every type with the first paragraph of its doc comment.
+fn table(semantic: &Semantic, layout: Layout, names: &[&String]) -> String {
+    let mut out = String::from("| Type | Summary |\n| --- | --- |\n");
+    for name in names {
+        let summary = summary(&semantic.definitions[*name].docs);
+        out.push_str(&format!("| {} | {} |\n", type_link(semantic, layout, name), cell(&summary)));
+    }
+    out
+}
+
+/// The page of a type, or its section of the single page, which is the same
+/// one level deeper.
+fn definition_section(semantic: &Semantic, graph: &Graph, definition: &TypeDefinition, layout: Layout) -> String {
+    let name = definition.qualified_name();
+    let name = &name[..];
+    let level = match layout {
+        Layout::PagePerType => "#",
+        Layout::SinglePage => "##",
+    };
+    let mut out = format!("{} {} `{}`\n\n", level, heading(definition), name);
+
+    if !definition.annotations.is_empty() {
+        let annotations: Vec<String> = definition.annotations.iter()
+            .map(|a| format!("`{}`", a))
+            .collect();
+        out.push_str(&format!("{}\n\n", annotations.join(" ")));
+    }
+
+    out.push_str(&paragraphs(&definition.docs));
+
+    if semantic.cycles.contains(name) {
+        out.push_str("> This type is part of a cycle of dependencies, so it is referred to \
+                      through pointers.\n\n");
+    }
+
+    if definition.kind == DefinitionKind::Enum {
+        out.push_str(&format!("{}# Variants\n\n| Variant | Value | Description |\n| --- | --- | --- |\n", level));
+        for (variant, value) in definition.variants.iter().zip(definition.values()) {
+            out.push_str(&format!("| `{}` | `{}` | {} |\n", variant.name, value, cell(&variant.docs.join(" "))));
+        }
+        out.push('\n');
+    } else if definition.parameters.is_empty() {
+        out.push_str(&format!("{}# Fields\n\nNone.\n\n", level));
+    } else {
+        out.push_str(&format!("{}# Fields\n\n| Field | Type | Description |\n| --- | --- | --- |\n", level));
+        for parameter in &definition.parameters {
+            let unit = unit(parameter).map(|unit| format!(" ({})", cell(unit))).unwrap_or_default();
+            out.push_str(&format!("| `{}` | {}{} | {} |\n",
+                parameter.name,
+                type_link(semantic, layout, &parameter.typename),
+                unit,
+                cell(&parameter.docs.join(" "))));
+        }
+        out.push('\n');
+    }
+
+    out.push_str(&type_list(semantic, layout, level, "Depends on", &dependencies(definition)));
+    out.push_str(&type_list(semantic, layout, level, "Used by", &dependents(semantic, name)));
+    out.push_str(&type_list(semantic, layout, level, "Implements", &interfaces(definition)));
+    out.push_str(&type_list(semantic, layout, level, "Implemented by", &implementors(semantic, name)));
+
+    if let Some(neighborhood) = graph.neighborhood(name).filter(|graph| !graph.edges.is_empty()) {
+        out.push_str(&format!("{}# Graph\n\n```mermaid\n{}```\n\n", level, neighborhood.render(Format::Mermaid)));
+    }
+
+    out
+}
+
+/// A section with a list of types, if there are any.
+fn type_list(semantic: &Semantic, layout: Layout, level: &str, heading: &str, names: &[&str]) -> String {
+    if names.is_empty() {
+        return String::new();
+    }
+
+    let items: String = names.iter()
+        .map(|name| format!("- {}\n", type_link(semantic, layout, name)))
+        .collect();
+    format!("{}# {}\n\n{}\n", level, heading, items)
+}
+
+/// A type's name, linked to its page or section if we have one (built-in
+/// types don't).
+fn type_link(semantic: &Semantic, layout: Layout, name: &str) -> String {
+    match (semantic.definitions.contains_key(name), layout) {
+        (false, _) => format!("`{}`", name),
+        (true, Layout::PagePerType) => format!("[`{}`]({})", name, page_name(name)),
+        (true, Layout::SinglePage) => format!("[`{}`](#type.{})", name, name),
+    }
+}
+
+/// A doc comment, as it is. Empty lines separate paragraphs.
+fn paragraphs(docs: &[Cow<str>]) -> String {
+    docs.split(|line| line.trim().is_empty())
+        .filter(|lines| !lines.is_empty())
+        .map(|lines| format!("{}\n\n", lines.join("\n")))
+        .collect()
+}
+
+/// Text for a table cell, where a `|` would end the cell.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::lexer::Lexer;
+    use super::super::super::parser::Parser;
+    use super::*;
+
+    fn get_pages(content: &str, layout: Layout) -> Vec<Page> {
+        let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+        render(&semantic, "Tipos", layout)
+    }
+
+    fn page<'a>(pages: &'a [Page], path: &str) -> &'a str {
+        &pages.iter().find(|p| p.path == path).unwrap().content
+    }
+
+    #[test]
+    fn pages() {
+        let pages = get_pages("tipo B(x: Entero); tipo A(b: B);", Layout::PagePerType);
+        let paths: Vec<&str> = pages.iter().map(|p| &p.path[..]).collect();
+        assert_eq!(paths, vec!["index.md", "type.A.md", "type.B.md"]);
+
+        let pages = get_pages("tipo B(x: Entero); tipo A(b: B);", Layout::SinglePage);
+        let index = page(&pages, "index.md");
+        assert!(index.find("<a id=\"type.A\"></a>\n\n## Type `A`").unwrap()
+            < index.find("<a id=\"type.B\"></a>\n\n## Type `B`").unwrap());
+        assert!(index.contains("### Fields\n\n| Field | Type | Description |\n| --- | --- | --- |\n\
+                                | `b` | [`B`](#type.B) |  |\n"));
+    }
+
+    #[test]
+    fn sections() {
+        let pages = get_pages("/// Un `A` *raro*.\n/// Sigue.\n///\n/// Otro | párrafo.\n\
+                               @obsoleto tipo A(/// Un | campo.\nx: Entero, @unidad(\"m|s\") b: B); tipo B(y: Real);", Layout::PagePerType);
+
+        let a = page(&pages, "type.A.md");
+        assert_eq!(a, "[Tipos](index.md)\n\n# Type `A`\n\n`@obsoleto`\n\n\
+            Un `A` *raro*.\nSigue.\n\nOtro | párrafo.\n\n\
+            ## Fields\n\n| Field | Type | Description |\n| --- | --- | --- |\n\
+            | `x` | `Entero` | Un \\| campo. |\n| `b` | [`B`](type.B.md) (m\\|s) |  |\n\n\
+            ## Depends on\n\n- `Entero`\n- [`B`](type.B.md)\n\n\
+            ## Graph\n\n```mermaid\ngraph LR\n\
+            \x20   n0[\"A\"]\n    n1[\"B\"]\n    n2([\"Entero\"])\n\
+            \x20   n0 -->|\"x\"| n2\n    n0 -->|\"b\"| n1\n```\n\n");
+
+        let index = page(&pages, "index.md");
+        assert!(index.contains("| [`A`](type.A.md) | Un `A` *raro*. Sigue. |\n"));
+        assert!(page(&pages, "type.B.md").contains("## Used by\n\n- [`A`](type.A.md)\n\n"));
+    }
+
+    #[test]
+    fn cycles() {
+        let pages = get_pages("tipo A(b: B); tipo B(a: A); tipo C(x: Entero);", Layout::PagePerType);
+
+        assert!(page(&pages, "type.A.md").contains("> This type is part of a cycle"));
+        assert!(page(&pages, "type.A.md").contains("classDef cycle"));
+        assert!(!page(&pages, "type.C.md").contains("cycle"));
+    }
+}
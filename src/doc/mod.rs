@@ -0,0 +1,335 @@
+//! Documentation module.
+//!
+//! Renders the analyzed definitions as a small static website, a bit like
+//! `rustdoc` does: an index with every type and a page for each one, with its
+//! doc comment, its fields, what it depends on and what depends on it. Types
+//! are linked to each other wherever they show up, and interfaces to the
+//! types that implement them.
+//!
+//! The same documentation can be written as [Markdown][0] too, to commit it
+//! next to the code.
+//!
+//! [0]: markdown/index.html
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use super::parser::{DefinitionKind, TypeDefinition};
+use super::semantic::Semantic;
+use super::units::unit;
+
+pub mod markdown;
+
+/// Shared by every page.
+const STYLE: &str = "\
+body { font-family: sans-serif; max-width: 50em; margin: 2em auto; padding: 0 1em; color: #222; }
+code { font-family: monospace; background: #f3f3f3; padding: 0 .2em; }
+a { color: #2a5db0; text-decoration: none; }
+a:hover { text-decoration: underline; }
+table { border-collapse: collapse; }
+td, th { text-align: left; padding: .3em 1em .3em 0; vertical-align: top; }
+.note { border-left: 3px solid #e0a800; padding-left: .8em; }
+";
+
+/// A file of the generated documentation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page {
+    /// Relative to the output directory.
+    pub path: String,
+    pub content: String,
+}
+
+/// The file name of the page of a type.
+///
+/// # Examples
+///
+///     use simcom::doc;
+///
+///     assert_eq!(doc::page_name("Punto"), "type.Punto.html");
+pub fn page_name(name: &str) -> String {
+    format!("type.{}.html", name)
+}
+
+/// Renders every definition, plus the index and the stylesheet.
+///
+/// # Examples
+///
+///     use simcom::doc;
+///     use simcom::lexer::Lexer;
+///     use simcom::parser::Parser;
+///     use simcom::semantic::Semantic;
+///
+///     let content = "/// Un punto.\ntipo Punto(x: Entero);";
+///     let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+///     let pages = doc::render(&semantic, "Tipos");
+///
+///     assert_eq!(pages[0].path, "index.html");
+///     assert!(pages[0].content.contains("<a href=\"type.Punto.html\">Punto</a>"));
+///     assert!(pages[1].content.contains("<p>Un punto.</p>"));
+pub fn render(semantic: &Semantic, title: &str) -> Vec<Page> {
+    let mut names: Vec<&String> = semantic.definitions.keys().collect();
+    names.sort();
+
+    let mut pages = vec![Page {
+        path: String::from("index.html"),
+        content: index(semantic, &names, title),
+    }];
+
+    for name in names {
+        let definition = &semantic.definitions[name];
+        pages.push(Page {
+            path: page_name(name),
+            content: definition_page(semantic, definition, title),
+        });
+    }
+
+    pages.push(Page {
+        path: String::from("style.css"),
+        content: String::from(STYLE),
+    });
+
+    pages
+}
+
+fn index(semantic: &Semantic, names: &[&String], title: &str) -> String {
+    let mut body = format!("<h1>{}</h1>\n<table>\n", escape(title));
+
+    for name in names {
+        let summary = summary(&semantic.definitions[*name].docs);
+        body.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>\n",
+            type_link(semantic, name), inline(&summary)));
+    }
+
+    body.push_str("</table>\n");
+    html(title, &body)
+}
+
+fn definition_page(semantic: &Semantic, definition: &TypeDefinition, title: &str) -> String {
+    let name = definition.qualified_name();
+    let name = &name[..];
+    let mut body = format!("<p><a href=\"index.html\">{}</a></p>\n", escape(title));
+    body.push_str(&format!("<h1>{} <code>{}</code></h1>\n", heading(definition), escape(name)));
+
+    if !definition.annotations.is_empty() {
+        let annotations: Vec<String> = definition.annotations.iter()
+            .map(|a| format!("<code>{}</code>", escape(&a.to_string())))
+            .collect();
+        body.push_str(&format!("<p>{}</p>\n", annotations.join(" ")));
+    }
+
+    body.push_str(&paragraphs(&definition.docs));
+
+    if semantic.cycles.contains(name) {
+        body.push_str("<p class=\"note\">This type is part of a cycle of dependencies, \
+                       so it is referred to through pointers.</p>\n");
+    }
+
+    if definition.kind == DefinitionKind::Enum {
+        body.push_str("<h2>Variants</h2>\n<table>\n");
+        for (variant, value) in definition.variants.iter().zip(definition.values()) {
+            body.push_str(&format!("<tr id=\"variant.{0}\"><td><code>{0}</code></td><td><code>{1}</code></td>\
+                                    <td>{2}</td></tr>\n",
+                escape(&variant.name), value, paragraphs(&variant.docs)));
+        }
+        body.push_str("</table>\n");
+    } else if definition.parameters.is_empty() {
+        body.push_str("<h2>Fields</h2>\n<p>None.</p>\n");
+    } else {
+        body.push_str("<h2>Fields</h2>\n<table>\n");
+        for parameter in &definition.parameters {
+            let unit = unit(parameter).map(|unit| format!(" ({})", escape(unit))).unwrap_or_default();
+            body.push_str(&format!("<tr id=\"field.{0}\"><td><code>{0}</code></td><td>{1}{2}</td><td>{3}</td></tr>\n",
+                escape(&parameter.name),
+                type_link(semantic, &parameter.typename),
+                unit,
+                paragraphs(&parameter.docs)));
+        }
+        body.push_str("</table>\n");
+    }
+
+    body.push_str(&type_list(semantic, "Depends on", &dependencies(definition)));
+    body.push_str(&type_list(semantic, "Used by", &dependents(semantic, name)));
+    body.push_str(&type_list(semantic, "Implements", &interfaces(definition)));
+    body.push_str(&type_list(semantic, "Implemented by", &implementors(semantic, name)));
+
+    html(&format!("{} - {}", name, title), &body)
+}
+
+/// What the definition is, at the top of its page.
+fn heading(definition: &TypeDefinition) -> &'static str {
+    match definition.kind {
+        DefinitionKind::Type => "Type",
+        DefinitionKind::Interface => "Interface",
+        DefinitionKind::Enum => "Enum",
+    }
+}
+
+/// The first paragraph of a doc comment, in a single line.
+fn summary(docs: &[Cow<str>]) -> String {
+    docs.iter()
+        .take_while(|line| !line.trim().is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The types of the fields of `definition`, in order and without repeats.
+fn dependencies<'a>(definition: &'a TypeDefinition) -> Vec<&'a str> {
+    let mut dependencies = Vec::new();
+    for parameter in &definition.parameters {
+        if !dependencies.contains(&&parameter.typename[..]) {
+            dependencies.push(&parameter.typename[..]);
+        }
+    }
+    dependencies
+}
+
+/// The types with a field of type `name`, sorted.
+fn dependents<'a>(semantic: &'a Semantic, name: &str) -> Vec<&'a str> {
+    let dependents: BTreeSet<&str> = semantic.definitions.values()
+        .filter(|other| other.parameters.iter().any(|p| p.typename == name))
+        .map(|other| &other.name[..])
+        .collect();
+    dependents.into_iter().collect()
+}
+
+/// The interfaces that `definition` implements, in order.
+fn interfaces<'a>(definition: &'a TypeDefinition) -> Vec<&'a str> {
+    definition.implements.iter().map(|i| &i.name[..]).collect()
+}
+
+/// The types that implement the interface `name`, sorted.
+fn implementors<'a>(semantic: &'a Semantic, name: &str) -> Vec<&'a str> {
+    let implementors: BTreeSet<&str> = semantic.definitions.iter()
+        .filter(|(_, other)| other.implements.iter().any(|i| i.name == name))
+        .map(|(other, _)| &other[..])
+        .collect();
+    implementors.into_iter().collect()
+}
+
+/// A section with a list of types, if there are any.
+fn type_list(semantic: &Semantic, heading: &str, names: &[&str]) -> String {
+    if names.is_empty() {
+        return String::new();
+    }
+
+    let items: String = names.iter()
+        .map(|name| format!("<li>{}</li>\n", type_link(semantic, name)))
+        .collect();
+    format!("<h2>{}</h2>\n<ul>\n{}</ul>\n", heading, items)
+}
+
+/// A type's name, linked to its page if we have one (built-in types don't).
+fn type_link(semantic: &Semantic, name: &str) -> String {
+    if semantic.definitions.contains_key(name) {
+        format!("<a href=\"{}\">{}</a>", page_name(name), escape(name))
+    } else {
+        format!("<code>{}</code>", escape(name))
+    }
+}
+
+/// A whole page with the common header.
+fn html(title: &str, body: &str) -> String {
+    format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>{}</title>\n<link rel=\"stylesheet\" href=\"style.css\">\n\
+             </head>\n<body>\n{}</body>\n</html>\n", escape(title), body)
+}
+
+/// A doc comment as HTML paragraphs. Empty lines separate paragraphs.
+fn paragraphs(docs: &[Cow<str>]) -> String {
+    docs.split(|line| line.trim().is_empty())
+        .filter(|lines| !lines.is_empty())
+        .map(|lines| format!("<p>{}</p>\n", inline(&lines.join(" "))))
+        .collect()
+}
+
+/// Escapes the text, turning anything between backticks into code.
+fn inline(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(i, piece)| match i % 2 {
+            0 => escape(piece),
+            _ => format!("<code>{}</code>", escape(piece)),
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::Lexer;
+    use super::super::parser::Parser;
+    use super::*;
+
+    fn get_pages(content: &str) -> Vec<Page> {
+        let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+        render(&semantic, "Tipos")
+    }
+
+    fn page<'a>(pages: &'a [Page], path: &str) -> &'a str {
+        &pages.iter().find(|p| p.path == path).unwrap().content
+    }
+
+    #[test]
+    fn pages() {
+        let pages = get_pages("tipo B(x: Entero); tipo A(b: B);");
+        let paths: Vec<&str> = pages.iter().map(|p| &p.path[..]).collect();
+
+        assert_eq!(paths, vec!["index.html", "type.A.html", "type.B.html", "style.css"]);
+    }
+
+    #[test]
+    fn cross_links() {
+        let pages = get_pages("tipo A(b: B, c: B, n: Entero); tipo B(x: Entero); tipo C(b: B);");
+
+        let a = page(&pages, "type.A.html");
+        assert!(a.contains("<tr id=\"field.b\"><td><code>b</code></td><td><a href=\"type.B.html\">B</a></td>"));
+        assert!(a.contains("<h2>Depends on</h2>\n<ul>\n<li><a href=\"type.B.html\">B</a></li>\n\
+                            <li><code>Entero</code></li>\n</ul>"));
+        assert!(!a.contains("Used by"));
+
+        let b = page(&pages, "type.B.html");
+        assert!(b.contains("<h2>Used by</h2>\n<ul>\n<li><a href=\"type.A.html\">A</a></li>\n\
+                            <li><a href=\"type.C.html\">C</a></li>\n</ul>"));
+    }
+
+    #[test]
+    fn docs() {
+        let pages = get_pages("/// Un `A` <raro>.\n/// Sigue.\n///\n/// Otro párrafo.\n\
+                               @obsoleto tipo A(/// Un campo.\n@unidad(\"<m>\") x: Entero);");
+
+        let a = page(&pages, "type.A.html");
+        assert!(a.contains("<p><code>@obsoleto</code></p>"));
+        assert!(a.contains("<p>Un <code>A</code> &lt;raro&gt;. Sigue.</p>\n<p>Otro párrafo.</p>"));
+        assert!(a.contains("<p>Un campo.</p>"));
+        assert!(a.contains("<code>Entero</code> (&lt;m&gt;)</td>"));
+
+        let index = page(&pages, "index.html");
+        assert!(index.contains("<td>Un <code>A</code> &lt;raro&gt;. Sigue.</td>"));
+    }
+
+    #[test]
+    fn cycles() {
+        let pages = get_pages("tipo A(b: B); tipo B(a: A); tipo C(x: Entero);");
+
+        assert!(page(&pages, "type.A.html").contains("cycle"));
+        assert!(!page(&pages, "type.C.html").contains("cycle"));
+    }
+
+    #[test]
+    fn interfaces() {
+        let pages = get_pages("interfaz N(n: Texto); tipo B(n: Texto) implementa N; tipo A(n: Texto) implementa N;");
+
+        let n = page(&pages, "type.N.html");
+        assert!(n.contains("<h1>Interface <code>N</code></h1>"));
+        assert!(n.contains("<h2>Implemented by</h2>\n<ul>\n<li><a href=\"type.A.html\">A</a></li>\n\
+                            <li><a href=\"type.B.html\">B</a></li>\n</ul>"));
+        assert!(page(&pages, "type.A.html").contains("<h2>Implements</h2>\n<ul>\n<li><a href=\"type.N.html\">N</a></li>"));
+    }
+}
@@ -0,0 +1,826 @@
+//! Driver module.
+//!
+//! Every tool built on the compiler does the same first steps: lex and parse
+//! some files, analyze the definitions and, maybe, generate code or run the
+//! lints. A [`Compilation`][0] wires those phases together, collects what went
+//! wrong in each of them as [`Diagnostic`s][1] and keeps everything it
+//! produced, so callers only have to decide what to do with the result.
+//!
+//! What it produces (the definitions, the [`Semantic`][2], the diagnostics)
+//! is `Send` and `Sync`: plain owned data, without reference counting or
+//! anything that changes behind a shared reference. A language server or a
+//! code generator running on several threads can share a result by reference
+//! (or in an `Arc`), no mutex needed. The tests make sure it stays that way.
+//!
+//! [0]: struct.Compilation.html
+//! [1]: ../diagnostics/struct.Diagnostic.html
+//! [2]: ../semantic/struct.Semantic.html
+
+use std::collections::BTreeMap;
+use std::io;
+use std::num::NonZeroUsize;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use super::arena::Arena;
+use super::cache;
+use super::codegen::metadata::{Input, Metadata};
+use super::codegen::{self, default_builtins, Backend, Builtins};
+use super::config::{Config, Level};
+use super::diagnostics::Diagnostic;
+use super::graph::Graph;
+use super::json::{Json, ToJson};
+use super::lexer::{Language, Lexer, Span};
+use super::limits::Limits;
+use super::locale::Locale;
+use super::lint;
+use super::loader::FileLoader;
+use super::pass::PassManager;
+use super::parser::arena;
+use super::parser::{Ast, Parser, TypeDefinition};
+use super::semantic::Semantic;
+use super::source_map::{FileId, SourceFile, SourceMap};
+use super::stats::{HeapSize, PhaseStats, Stats};
+
+/// What a compilation needs to know besides the files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    /// The language of the keywords.
+    pub language: Language,
+    /// The code generator to use.
+    pub backend: Backend,
+    /// Built-in types and their names in the generated code.
+    pub builtins: Builtins,
+    /// Level for each lint, by name. Lints not listed use their default.
+    pub lints: BTreeMap<String, Level>,
+    /// How many threads parse the files. 0 means one for each CPU.
+    pub jobs: usize,
+    /// Where to keep the ASTs of the files and the semantic result between
+    /// runs, if anywhere. See the [cache module][0].
+    ///
+    /// [0]: ../cache/index.html
+    pub cache: Option<PathBuf>,
+    /// The language diagnostics are rendered in.
+    pub locale: Locale,
+    /// Where the generated code goes. When `None`, it's meant for stdout.
+    pub output: Option<PathBuf>,
+    /// Whether the generated code starts with a comment saying what it was
+    /// generated from. See [`Compilation::metadata`][1].
+    ///
+    /// [1]: struct.Compilation.html#method.metadata
+    pub stamp: bool,
+    /// How much input is too much. See the [limits module][0].
+    ///
+    /// [0]: ../limits/index.html
+    pub limits: Limits,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            language: Language::default(),
+            backend: Backend::default(),
+            builtins: default_builtins(),
+            lints: BTreeMap::new(),
+            jobs: 0,
+            cache: None,
+            locale: Locale::default(),
+            output: None,
+            stamp: false,
+            limits: Limits::default(),
+        }
+    }
+}
+
+impl<'a> From<&'a Config> for Options {
+    fn from(config: &'a Config) -> Self {
+        Options {
+            language: config.language,
+            backend: config.backend,
+            builtins: config.builtins.clone(),
+            lints: config.lints.clone(),
+            jobs: config.jobs,
+            cache: config.cache.clone(),
+            locale: config.locale.unwrap_or_default(),
+            output: config.output.clone(),
+            stamp: config.stamp,
+            limits: Limits::default(),
+        }
+    }
+}
+
+/// Everything a compilation produced.
+#[derive(Debug)]
+pub struct CompilationResult {
+    /// Every definition that could be parsed, in the order of the files.
+    pub definitions: Vec<TypeDefinition<'static>>,
+    /// The problems found by every phase: syntax errors first, then what
+    /// the lints found, sorted by position.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The analyzed definitions. Only when there were no syntax errors.
+    pub semantic: Option<Semantic>,
+    /// The generated code. Only when there were no errors.
+    pub code: Option<String>,
+}
+
+impl CompilationResult {
+    /// Whether nothing prevented the compilation from producing code. Denied
+    /// lints count as errors too.
+    pub fn is_ok(&self) -> bool {
+        !self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+}
+
+/// A file produced by a compilation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmittedFile {
+    /// Where it goes, from [`options.output`][0]. `None` is stdout.
+    ///
+    /// [0]: struct.Options.html#structfield.output
+    pub path: Option<PathBuf>,
+    pub content: String,
+}
+
+/// Everything [`Driver::compile`][0] produced, for programs that only want
+/// the results and decide themselves what to print or write.
+///
+/// [0]: struct.Driver.html#method.compile
+#[derive(Debug)]
+pub struct CompileOutput {
+    /// Every definition that could be parsed, in the order of the files.
+    pub ast: Vec<TypeDefinition<'static>>,
+    /// The analyzed definitions. Only when there were no syntax errors.
+    pub semantic: Option<Semantic>,
+    /// The problems found by every phase, like in a [`CompilationResult`][1].
+    ///
+    /// [1]: struct.CompilationResult.html
+    pub diagnostics: Vec<Diagnostic>,
+    /// The generated code. Empty if there were errors.
+    pub emitted_files: Vec<EmittedFile>,
+}
+
+impl CompileOutput {
+    /// Whether nothing prevented the compilation from producing code.
+    pub fn is_ok(&self) -> bool {
+        !self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+}
+
+/// The whole compiler in one call: from some inputs to what to write.
+///
+/// # Examples
+///
+///     use simcom::driver::{Driver, Options};
+///
+///     let inputs = vec![
+///         ("punto.sc", "tipo Punto(x: Entero, y: Entero);"),
+///         ("linea.sc", "tipo Linea(a: Punto, b: Punto);"),
+///     ];
+///     let output = Driver::compile(inputs, &Options::default());
+///
+///     assert!(output.is_ok());
+///     assert_eq!(output.ast[1].name, "Linea");
+///     assert_eq!(output.emitted_files[0].path, None);
+///     assert!(output.emitted_files[0].content.contains("typedef struct Linea {"));
+#[derive(Debug, Clone, Copy)]
+pub struct Driver;
+
+impl Driver {
+    /// Compiles `inputs`, pairs of name and content, together. Nothing is
+    /// printed or written.
+    pub fn compile<I, N, C>(inputs: I, options: &Options) -> CompileOutput
+        where I: IntoIterator<Item = (N, C)>, N: Into<String>, C: Into<String>
+    {
+        let mut compilation = Compilation::new(options.clone());
+        for (name, content) in inputs {
+            compilation.add_file(name, content);
+        }
+
+        let result = compilation.run();
+        let emitted_files = result.code.into_iter()
+            .map(|content| EmittedFile { path: options.output.clone(), content })
+            .collect();
+
+        CompileOutput {
+            ast: result.definitions,
+            semantic: result.semantic,
+            diagnostics: result.diagnostics,
+            emitted_files,
+        }
+    }
+}
+
+/// A set of files compiled together, so types can refer to types in other
+/// files.
+///
+/// # Examples
+///
+///     use simcom::driver::{Compilation, Options};
+///
+///     let mut compilation = Compilation::new(Options::default());
+///     compilation.add_file("punto.sc", "tipo Punto(x: Entero, y: Entero);");
+///     compilation.add_file("linea.sc", "tipo Linea(a: Punto, b: Punto);");
+///
+///     let result = compilation.run();
+///     assert!(result.is_ok());
+///     assert_eq!(result.semantic.unwrap().order, vec!["Entero", "Punto", "Linea"]);
+///     assert!(result.code.unwrap().contains("typedef struct Linea {"));
+#[derive(Debug, Clone, Default)]
+pub struct Compilation {
+    pub options: Options,
+    sources: SourceMap,
+}
+
+impl Compilation {
+    pub fn new(options: Options) -> Compilation {
+        Compilation { options, sources: SourceMap::new() }
+    }
+
+    /// Adds a file to the compilation, after the rest.
+    pub fn add_file<N: Into<String>, C: Into<String>>(&mut self, name: N, content: C) -> FileId {
+        self.sources.add(name, content)
+    }
+
+    /// Reads the file at `path` with `loader` and adds it, named after its
+    /// path.
+    ///
+    /// # Examples
+    ///
+    ///     use std::path::Path;
+    ///     use simcom::driver::{Compilation, Options};
+    ///     use simcom::loader::MemoryLoader;
+    ///
+    ///     let mut loader = MemoryLoader::new();
+    ///     loader.insert("punto.sc", "tipo Punto(x: Entero);");
+    ///
+    ///     let mut compilation = Compilation::new(Options::default());
+    ///     compilation.load_file(&loader, Path::new("punto.sc")).unwrap();
+    ///     assert!(compilation.load_file(&loader, Path::new("linea.sc")).is_err());
+    ///     assert_eq!(compilation.run().definitions[0].name, "Punto");
+    pub fn load_file<L: FileLoader + ?Sized>(&mut self, loader: &L, path: &Path) -> io::Result<FileId> {
+        let content = loader.load(path)?;
+        Ok(self.add_file(path.display().to_string(), content))
+    }
+
+    /// The files of the compilation.
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    /// Where `span` is, as `file:line:column`.
+    pub fn locate(&self, span: Span) -> String {
+        self.sources.locate(span)
+    }
+
+    /// The diagnostic as people read it, pointing to its file, line and
+    /// column (and those of its labels), in the locale of the options.
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        diagnostic.render_with(|span| self.locate(span), self.options.locale)
+    }
+
+    /// The diagnostic for other programs, with its location too, and the
+    /// one of each label.
+    pub fn to_json(&self, diagnostic: &Diagnostic) -> Json {
+        let located = |json: Json, span: Span| match json {
+            Json::Object(mut fields) => {
+                fields.push((String::from("location"), Json::from(self.locate(span))));
+                Json::Object(fields)
+            },
+            json => json,
+        };
+
+        match diagnostic.to_json() {
+            Json::Object(mut fields) => {
+                let labels = diagnostic.labels.iter().map(|label| located(label.to_json(), label.span)).collect();
+                if let Some(field) = fields.iter_mut().find(|field| field.0 == "labels") {
+                    field.1 = Json::Array(labels);
+                }
+                located(Json::Object(fields), diagnostic.span)
+            },
+            json => json,
+        }
+    }
+
+    /// What the generated code depends on: the version of the compiler, the
+    /// files and the options.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::driver::{Compilation, Options};
+    ///
+    ///     let mut compilation = Compilation::new(Options { stamp: true, ..Options::default() });
+    ///     compilation.add_file("punto.sc", "tipo Punto(x: Entero);");
+    ///
+    ///     let metadata = compilation.metadata();
+    ///     assert_eq!(metadata.inputs[0].name, "punto.sc");
+    ///     assert!(compilation.run().code.unwrap().starts_with(&metadata.header()));
+    pub fn metadata(&self) -> Metadata {
+        let options = &self.options;
+        let mut metadata = Metadata::new(options.language, options.backend, options.builtins.clone());
+        metadata.inputs = self.sources.iter().map(|file| Input::new(&file.name[..], &file.content)).collect();
+        metadata
+    }
+
+    /// A lexer for `file`, with the right language.
+    pub fn lexer<'a>(&self, file: &'a SourceFile) -> Lexer<'a> {
+        Lexer::with_language(&file.content, self.options.language).in_file(file.id)
+    }
+
+    /// Parses every file, returning the definitions and the syntax errors.
+    ///
+    /// Each file is parsed on its own, so they are split between
+    /// [`options.jobs`][0] threads. The results are in the order of the files
+    /// anyway.
+    ///
+    /// [0]: struct.Options.html#structfield.jobs
+    pub fn parse(&self) -> (Vec<TypeDefinition<'_>>, Vec<Diagnostic>) {
+        let files: Vec<&SourceFile> = self.sources.iter().collect();
+        let jobs = match self.options.jobs {
+            0 => thread::available_parallelism().map(NonZeroUsize::get).unwrap_or(1),
+            jobs => jobs,
+        };
+        let jobs = jobs.min(files.len());
+
+        if jobs <= 1 {
+            let (mut definitions, mut errors) = self.parse_files(&files);
+            self.options.limits.truncate(&mut definitions, &mut errors);
+            return (definitions, errors);
+        }
+
+        let parsed: Vec<_> = thread::scope(|scope| {
+            let threads: Vec<_> = files.chunks(files.len().div_ceil(jobs))
+                .map(|files| scope.spawn(move || self.parse_files(files)))
+                .collect();
+
+            threads.into_iter()
+                .map(|thread| thread.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        });
+
+        let mut definitions = Vec::new();
+        let mut errors = Vec::new();
+        for (mut d, mut e) in parsed {
+            definitions.append(&mut d);
+            errors.append(&mut e);
+        }
+
+        self.options.limits.truncate(&mut definitions, &mut errors);
+        (definitions, errors)
+    }
+
+    /// Parses some of the files, one after the other, until there are more
+    /// definitions or errors than the limits allow.
+    fn parse_files<'a>(&'a self, files: &[&'a SourceFile]) -> (Vec<TypeDefinition<'a>>, Vec<Diagnostic>) {
+        let mut definitions = Vec::new();
+        let mut errors = Vec::new();
+
+        let limits = &self.options.limits;
+
+        for file in files {
+            trace_span!(DEBUG, "file", name = &file.name[..], bytes = file.content.len());
+
+            for ast in self.parse_file(file) {
+                match ast {
+                    Ast::TypeDefinition(definition) => {
+                        errors.extend(limits.check_definition(&definition));
+                        definitions.push(definition);
+                    },
+                    Ast::Unexpected(token, span) => errors.push(Diagnostic::unexpected_in(&file.content, &token, span)),
+                }
+
+                if limits.exceeded(definitions.len(), errors.len()) {
+                    return (definitions, errors);
+                }
+            }
+        }
+
+        (definitions, errors)
+    }
+
+    /// Parses a file, or loads its AST from the cache if there is one. A
+    /// cache that can't be written is only a missed chance, not an error.
+    ///
+    /// Without a cache, the file is parsed as the definitions are needed, so
+    /// nothing after the limits is parsed at all.
+    fn parse_file<'a>(&self, file: &'a SourceFile) -> Box<dyn Iterator<Item = Ast<'a>> + 'a> {
+        let dir = match self.options.cache {
+            Some(ref dir) => dir,
+            None => return Box::new(Parser::new(self.lexer(file))),
+        };
+
+        let language = self.options.language;
+        if let Some(asts) = cache::load_cached(dir, &file.content, language, file.id) {
+            trace_event!(DEBUG, "cached");
+            return Box::new(asts.into_iter());
+        }
+
+        let asts: Vec<Ast> = Parser::new(self.lexer(file)).collect();
+        let _ = cache::store_cached(dir, &file.content, language, &asts);
+        Box::new(asts.into_iter())
+    }
+
+    /// Like [`parse`][0], but the definitions are allocated in `arena`, which
+    /// is much cheaper for big inputs. Dropping the arena drops them all.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::arena::Arena;
+    ///     use simcom::driver::{Compilation, Options};
+    ///
+    ///     let mut compilation = Compilation::new(Options::default());
+    ///     compilation.add_file("punto.sc", "tipo Punto(x: Entero, y: Entero);");
+    ///
+    ///     let arena = Arena::new();
+    ///     let (definitions, errors) = compilation.parse_in(&arena);
+    ///     assert_eq!(definitions[0].parameters[1].name, "y");
+    ///     assert!(errors.is_empty());
+    ///
+    /// [0]: #method.parse
+    pub fn parse_in<'arena>(&self, arena: &'arena Arena) -> (Vec<arena::TypeDefinition<'arena>>, Vec<Diagnostic>) {
+        let mut definitions = Vec::new();
+        let mut errors = Vec::new();
+
+        for file in self.sources.iter() {
+            for ast in arena::Parser::new(self.lexer(file), arena) {
+                match ast {
+                    arena::Ast::TypeDefinition(definition) => definitions.push(definition),
+                    arena::Ast::Unexpected(token, span) => errors.push(Diagnostic::unexpected_in(&file.content, &token, span)),
+                }
+            }
+        }
+
+        (definitions, errors)
+    }
+
+    /// Runs every phase. The later ones only run if there were no syntax
+    /// errors, since they would work on an incomplete program, nor after
+    /// going over the [limits][0].
+    ///
+    /// [0]: ../limits/index.html
+    pub fn run(&self) -> CompilationResult {
+        self.run_with(&mut PassManager::new())
+    }
+
+    /// Like [`run`][0], running `passes` between the analysis and the lints.
+    ///
+    /// [0]: #method.run
+    pub fn run_with(&self, passes: &mut PassManager) -> CompilationResult {
+        trace_span!(INFO, "compilation", files = self.sources.len());
+
+        let (definitions, diagnostics) = {
+            trace_span!(INFO, "parse");
+            self.parse()
+        };
+        let mut result = CompilationResult {
+            definitions: definitions.into_iter().map(TypeDefinition::into_owned).collect(),
+            diagnostics,
+            semantic: None,
+            code: None,
+        };
+
+        if !result.is_ok() {
+            return result;
+        }
+
+        let ast = result.definitions.iter().cloned().map(Ast::TypeDefinition);
+        let mut semantic = match self.analyze(ast) {
+            Ok(semantic) => semantic,
+            Err(errors) => {
+                result.diagnostics.extend(errors);
+                return result;
+            },
+        };
+
+        if let Some(error) = self.options.limits.check_depth(&semantic) {
+            result.diagnostics.push(error);
+            return result;
+        }
+
+        passes.run(&mut result.definitions, &mut semantic, &mut result.diagnostics);
+
+        let findings = {
+            trace_span!(INFO, "lint");
+            lint::run(&semantic, &self.options.lints)
+        };
+        result.diagnostics.extend(findings.into_iter().map(Diagnostic::from));
+        if result.is_ok() {
+            let mut code = codegen::emit(&semantic, self.options.backend, &self.options.builtins);
+            if self.options.stamp {
+                code.insert_str(0, &self.metadata().header());
+            }
+            result.code = Some(code);
+        }
+        result.semantic = Some(semantic);
+        result
+    }
+
+    /// Analyzes the AST of the files, like [`Semantic::check`][0]. With a
+    /// cache, the result is loaded from it when no file changed since it was
+    /// stored.
+    ///
+    /// [0]: ../semantic/struct.Semantic.html#method.check
+    pub fn analyze<'src, I: IntoIterator<Item = Ast<'src>>>(&self, ast: I) -> Result<Semantic, Vec<Diagnostic>> {
+        let dir = match self.options.cache {
+            Some(ref dir) => dir,
+            None => return Semantic::check(ast),
+        };
+
+        let keys: Vec<u64> = self.sources.iter()
+            .map(|file| cache::key(&file.content, self.options.language))
+            .collect();
+        let key = cache::semantic_key(&keys);
+        if let Some(semantic) = cache::load_semantic(dir, key) {
+            return Ok(semantic);
+        }
+
+        let semantic = Semantic::check(ast)?;
+        let _ = cache::store_semantic(dir, key, &semantic);
+        Ok(semantic)
+    }
+
+    /// How much each phase produces for these files and roughly how much
+    /// memory it takes, to see where it goes with very big inputs.
+    ///
+    /// Nothing is kept between phases, so this runs them all again (without
+    /// the cache) and keeps every token in memory, which a normal run doesn't.
+    /// The symbols and the edges are zero if there are syntax errors, since
+    /// the analysis doesn't run then.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::driver::{Compilation, Options};
+    ///
+    ///     let mut compilation = Compilation::new(Options::default());
+    ///     compilation.add_file("linea.sc", "tipo Linea(a: Punto, b: Punto); tipo Punto(x: Entero);");
+    ///
+    ///     let stats = compilation.stats();
+    ///     assert_eq!(stats.tokens.count, 20);
+    ///     assert_eq!(stats.ast.count, 5);
+    ///     assert_eq!(stats.symbols.count, 2);
+    ///     assert_eq!(stats.edges.count, 3);
+    ///     assert!(stats.total_bytes() > 0);
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        let mut asts = Vec::new();
+
+        for file in self.sources.iter() {
+            stats.sources.count += 1;
+            stats.sources.bytes += file.content.heap_size();
+
+            let tokens: Vec<_> = self.lexer(file).collect();
+            stats.tokens.count += tokens.len();
+            stats.tokens.bytes += tokens.heap_size();
+
+            let ast: Vec<Ast> = Parser::new(self.lexer(file)).collect();
+            stats.ast.count += ast.iter().map(nodes).sum::<usize>();
+            stats.ast.bytes += ast.heap_size();
+            asts.extend(ast);
+        }
+
+        if let Ok(semantic) = Semantic::check(asts) {
+            stats.symbols = PhaseStats { count: semantic.definitions.len(), bytes: semantic.heap_size() };
+
+            let graph = Graph::new(&semantic);
+            stats.edges = PhaseStats { count: graph.edges.len(), bytes: graph.heap_size() };
+        }
+
+        stats
+    }
+}
+
+/// The nodes of an AST: the definition, its parameters and their
+/// annotations.
+fn nodes(ast: &Ast) -> usize {
+    match *ast {
+        Ast::TypeDefinition(ref definition) => {
+            let parameters = definition.parameters.iter().map(|p| 1 + p.annotations.len()).sum::<usize>();
+            1 + definition.annotations.len() + parameters
+        },
+        Ast::Unexpected(..) => 1,
+    }
+}
+
+/// Compiles a single piece of source code.
+///
+/// # Examples
+///
+///     use simcom::driver::{compile, Options};
+///
+///     let result = compile("tipo A(x: Entero);;", &Options::default());
+///     assert_eq!(result.diagnostics[0].message, "unexpected `;`");
+///     assert!(result.code.is_none());
+pub fn compile(source: &str, options: &Options) -> CompilationResult {
+    let mut compilation = Compilation::new(options.clone());
+    compilation.add_file("<input>", source);
+    compilation.run()
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::path::PathBuf;
+    use std::thread;
+
+    use super::super::codegen::{self, default_builtins, Backend};
+    use super::super::config::Level;
+    use super::super::diagnostics::{Diagnostic, Severity};
+    use super::super::diff::AstChange;
+    use super::super::json::Json;
+    use super::super::lexer::{Span, Token, TokenStream};
+    use super::super::limits::Limits;
+    use super::super::parser::Ast;
+    use super::super::semantic::Semantic;
+    use super::super::source_map::SourceMap;
+    use super::{compile, Compilation, CompilationResult, Driver, Options};
+
+    #[test]
+    fn spans_know_their_file() {
+        let mut compilation = Compilation::new(Options::default());
+        let a = compilation.add_file("a.sc", "tipo A(x: Entero);");
+        let b = compilation.add_file("b.sc", "tipo B(y: A);");
+
+        let (definitions, _) = compilation.parse();
+        assert_eq!(definitions[0].name_span, Span::new(5, 6).in_file(a));
+        assert_eq!(definitions[1].name_span, Span::new(5, 6).in_file(b));
+        assert_eq!(compilation.locate(definitions[1].name_span), "b.sc:1:6");
+    }
+
+    /// Doesn't compile unless `T` can be shared between threads.
+    fn shareable<T: Send + Sync>() {}
+
+    #[test]
+    fn results_can_be_shared_between_threads() {
+        shareable::<Compilation>();
+        shareable::<CompilationResult>();
+        shareable::<Semantic>();
+        shareable::<Ast<'static>>();
+        shareable::<Diagnostic>();
+        shareable::<SourceMap>();
+        shareable::<TokenStream<'static>>();
+        shareable::<AstChange>();
+
+        let result = compile("tipo A(x: B); tipo B(y: Entero);", &Options::default());
+        let semantic = result.semantic.as_ref().unwrap();
+        let code = thread::scope(|scope| {
+            let threads: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| codegen::emit(semantic, Backend::C, &default_builtins())))
+                .collect();
+            threads.into_iter().map(|thread| thread.join().unwrap()).collect::<Vec<_>>()
+        });
+
+        assert!(code.iter().all(|code| Some(code) == result.code.as_ref()));
+    }
+
+    #[test]
+    fn parallel_parsing_keeps_the_order() {
+        let mut compilation = Compilation::new(Options { jobs: 3, ..Options::default() });
+        for i in 0..10 {
+            compilation.add_file(format!("{}.sc", i), format!("tipo T{}(x: Entero); tipo ;", i));
+        }
+
+        let (definitions, errors) = compilation.parse();
+        let names: Vec<&str> = definitions.iter().map(|d| &d.name[..]).collect();
+        assert_eq!(names, ["T0", "T1", "T2", "T3", "T4", "T5", "T6", "T7", "T8", "T9"]);
+        assert_eq!(errors.len(), 10);
+        assert!(errors.windows(2).all(|pair| pair[0].span.file < pair[1].span.file));
+
+        let mut sequential = compilation.clone();
+        sequential.options.jobs = 1;
+        assert_eq!(sequential.parse(), (definitions, errors));
+    }
+
+    #[test]
+    fn cache() {
+        let dir = env::temp_dir().join(format!("simcom-driver-cache-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let options = Options { cache: Some(dir.clone()), ..Options::default() };
+
+        let first = compile("tipo Punto(x: Entero);", &options);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        let second = compile("tipo Punto(x: Entero);", &options);
+        assert_eq!(second.definitions, first.definitions);
+        assert_eq!(second.code, first.code);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        let changed = compile("tipo Punto(y: Entero);", &options);
+        assert_eq!(changed.definitions[0].parameters[0].name, "y");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 4);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn syntax_errors_stop_the_later_phases() {
+        let mut compilation = Compilation::new(Options::default());
+        compilation.add_file("a.sc", "tipo A(x: Entero);");
+        let b = compilation.add_file("b.sc", "tipo B(;");
+
+        let result = compilation.run();
+        assert_eq!(result.definitions.len(), 1);
+        let span = Span::new(7, 8).in_file(b);
+        assert_eq!(result.diagnostics, vec![Diagnostic::unexpected(&Token::Semicolon, span)]);
+        assert_eq!(compilation.render(&result.diagnostics[0]), "error[E0001]: b.sc:1:8: unexpected `;`\n");
+        assert!(result.semantic.is_none());
+        assert!(result.code.is_none());
+        assert!(!result.is_ok());
+    }
+
+    #[test]
+    fn redefinitions_point_to_both_files() {
+        let mut compilation = Compilation::new(Options::default());
+        compilation.add_file("a.sc", "tipo A(x: Entero);");
+        compilation.add_file("b.sc", "tipo B(a: A);\ntipo A(y: Real);");
+
+        let result = compilation.run();
+        assert!(result.semantic.is_none());
+        assert_eq!(compilation.render(&result.diagnostics[0]), "\
+            error[E0101]: b.sc:2:6: type `A` is defined more than once\n\
+            \x20   --> a.sc:1:6: previous definition of `A` is here\n");
+        let json = compilation.to_json(&result.diagnostics[0]);
+        assert_eq!(json.pointer(&["labels"]).and_then(Json::as_array).unwrap()[0].get("location"),
+            Some(&Json::from("a.sc:1:6")));
+    }
+
+    #[test]
+    fn lints() {
+        let result = compile("tipo punto(x: Entero);", &Options::default());
+        assert_eq!(result.diagnostics[0].code, Some(String::from("W0202")));
+        assert_eq!(result.diagnostics[0].severity, Severity::Warning);
+        assert!(result.is_ok());
+        assert!(result.code.is_some());
+
+        let mut options = Options::default();
+        options.lints.insert(String::from("naming"), Level::Deny);
+        let result = compile("tipo punto(x: Entero);", &options);
+        assert!(!result.is_ok());
+        assert!(result.semantic.is_some());
+        assert!(result.code.is_none());
+    }
+
+    #[test]
+    fn stats() {
+        let mut compilation = Compilation::new(Options::default());
+        compilation.add_file("a.sc", "tipo A(x: Entero, @obsoleto y: B);");
+        compilation.add_file("b.sc", "/// Uno.\ntipo B(z: Entero);");
+
+        let stats = compilation.stats();
+        assert_eq!(stats.sources.count, 2);
+        assert_eq!(stats.ast.count, 6);
+        assert_eq!(stats.symbols.count, 2);
+        assert_eq!(stats.edges.count, 3);
+        assert!(stats.ast.bytes > 0);
+        assert!(stats.edges.bytes > 0);
+
+        compilation.add_file("c.sc", "tipo C(x: );");
+        let stats = compilation.stats();
+        assert_eq!(stats.sources.count, 3);
+        assert_eq!(stats.symbols, Default::default());
+        assert_eq!(stats.edges, Default::default());
+    }
+
+    #[test]
+    fn limits() {
+        let limits = Limits { max_definitions: 3, max_errors: 2, ..Limits::default() };
+        let options = Options { limits, jobs: 1, ..Options::default() };
+
+        let source = "tipo A(x: E); tipo B(x: E); tipo C(x: E); tipo D(x: E); tipo E(x: E);";
+        let result = compile(source, &options);
+        assert_eq!(result.definitions.len(), 3);
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].message, "too many definitions, the limit is 3");
+        assert!(result.semantic.is_none());
+
+        let mut compilation = Compilation::new(Options { limits, jobs: 2, ..Options::default() });
+        compilation.add_file("a.sc", "?; ?; ?;");
+        compilation.add_file("b.sc", "tipo A(x: E);");
+        let (_, errors) = compilation.parse();
+        let messages: Vec<&str> = errors.iter().map(|e| &e.message[..]).collect();
+        assert_eq!(messages, ["unknown character", "unknown character", "too many errors, the limit is 2"]);
+
+        let limits = Limits { max_dependency_depth: 2, ..Limits::default() };
+        let result = compile("tipo A(x: B); tipo B(x: C); tipo C(x: Entero);", &Options { limits, ..Options::default() });
+        assert_eq!(result.diagnostics[0].message, "`A` depends on a chain of 3 types, the limit is 2");
+        assert!(result.code.is_none());
+    }
+
+    #[test]
+    fn driver() {
+        let options = Options { output: Some(PathBuf::from("tipos.h")), ..Options::default() };
+        let output = Driver::compile(vec![("a.sc", "tipo A(x: B);"), ("b.sc", "tipo B(y: Entero);")], &options);
+        assert!(output.is_ok());
+        assert_eq!(output.ast.len(), 2);
+        assert_eq!(output.semantic.unwrap().order, ["Entero", "B", "A"]);
+        assert_eq!(output.emitted_files.len(), 1);
+        assert_eq!(output.emitted_files[0].path, Some(PathBuf::from("tipos.h")));
+
+        let output = Driver::compile(vec![("a.sc", "tipo A(x: B);;")], &options);
+        assert!(!output.is_ok());
+        assert_eq!(output.ast.len(), 1);
+        assert!(output.semantic.is_none());
+        assert!(output.emitted_files.is_empty());
+    }
+}
@@ -0,0 +1,393 @@
+//! Formatter module.
+//!
+//! Rewrites the input in the one true style: one definition per line, a
+//! single space after each `:` and `,`, and parameters in their own lines when
+//! a definition gets too long (or when any of them has a doc comment). Doc
+//! comments and annotations of a definition go in their own lines before it,
+//! annotations of a parameter right before its name. Blank lines
+//! between definitions are kept (but never more than one in a row), as they
+//! usually separate groups of types. Module declarations go between blank
+//! lines, and only where the module changes: one with no definitions after it
+//! says nothing, so it's left out.
+//!
+//! Doc comments are the only comments of the language, and they are kept
+//! word for word. `simcom fmt` is a thin layer over [`format_source`][0], so
+//! editors can format on save by calling it directly.
+//!
+//! [0]: fn.format_source.html
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use super::config::Config;
+use super::diagnostics::Diagnostic;
+use super::lexer::{Cursor, Language, Lexer, Span, Token, TokenStream};
+use super::parser::{Annotation, Ast, Parser};
+
+/// When the formatter writes a comma after the last parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TrailingComma {
+    /// Always, even if the definition fits in one line.
+    Always,
+    /// Never.
+    Never,
+    /// Only when each parameter goes in its own line.
+    #[default]
+    Vertical,
+}
+
+impl TrailingComma {
+    /// Looks up a policy by its name (`always`, `never` or `vertical`).
+    pub fn from_name(name: &str) -> Option<TrailingComma> {
+        match name {
+            "always" => Some(TrailingComma::Always),
+            "never" => Some(TrailingComma::Never),
+            "vertical" => Some(TrailingComma::Vertical),
+            _ => None,
+        }
+    }
+}
+
+/// How to format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FormatOptions {
+    /// The language of the keywords, both to read and to write them.
+    pub language: Language,
+    /// Definitions longer than this are split, one parameter per line.
+    pub max_width: usize,
+    /// Whether the last parameter is followed by a comma.
+    pub trailing_comma: TrailingComma,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            language: Language::default(),
+            max_width: 80,
+            trailing_comma: TrailingComma::default(),
+        }
+    }
+}
+
+/// The language and the `[fmt]` table of `simcom.toml`.
+#[cfg(feature = "std")]
+impl<'a> From<&'a Config> for FormatOptions {
+    fn from(config: &'a Config) -> Self {
+        FormatOptions {
+            language: config.language,
+            max_width: config.fmt.max_width,
+            trailing_comma: config.fmt.trailing_comma,
+        }
+    }
+}
+
+/// Formats `source`.
+///
+/// Code we don't understand is never rewritten: if the parser finds anything
+/// unexpected, the errors are returned instead.
+///
+/// # Examples
+///
+///     use simcom::fmt::{format_source, FormatOptions};
+///
+///     let formatted = format_source("tipo   Punto(x:Entero ,y:Entero);", FormatOptions::default());
+///     assert_eq!(formatted.unwrap(), "tipo Punto(x: Entero, y: Entero);\n");
+///
+///     let errors = format_source("tipo Punto(x Entero);", FormatOptions::default()).unwrap_err();
+///     assert_eq!(errors[0].message, "unexpected `Entero`");
+pub fn format_source(source: &str, options: FormatOptions) -> Result<String, Vec<Diagnostic>> {
+    // The parser skips the whitespace, but it tells where the blank lines are.
+    let language = options.language;
+    let tokens = TokenStream::new(Lexer::with_language(source, language).preserving_trivia());
+    let mut definitions = Vec::new();
+    let mut errors = Vec::new();
+
+    // Whatever is after the end the lexer found would be lost.
+    if let Some(&(_, end)) = tokens.tokens().last() {
+        if end.end < source.len() {
+            errors.push(Diagnostic::unexpected(&Token::Illegal, Span::new(end.end, source.len())));
+        }
+    }
+
+    for ast in Parser::from_cursor(tokens.cursor()) {
+        match ast {
+            Ast::TypeDefinition(definition) => definitions.push(definition),
+            Ast::Unexpected(token, span) => errors.push(Diagnostic::unexpected_in(source, &token, span)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let blank_lines = blank_lines(tokens.cursor());
+    let module_keyword = language.spelling(&Token::Module).unwrap_or("modulo");
+    let implements_keyword = language.spelling(&Token::Implements).unwrap_or("implementa");
+    let mut module = None;
+    let mut out = String::new();
+
+    for (i, definition) in definitions.iter().enumerate() {
+        if definition.module.as_deref() != module {
+            module = definition.module.as_deref();
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("{} {};\n\n", module_keyword, module.unwrap_or_default()));
+        } else if i > 0 && blank_lines.get(i).cloned().unwrap_or(false) {
+            out.push('\n');
+        }
+
+        write_docs(&mut out, "", &definition.docs);
+        for annotation in &definition.annotations {
+            out.push_str(&format!("{}\n", annotation));
+        }
+
+        let parameters = definition.parameters.iter()
+            .map(|p| Field {
+                docs: &p.docs,
+                text: match p.number {
+                    Some(number) => format!("{}{}: {} #{}", annotations(&p.annotations), p.name, p.typename, number),
+                    None => format!("{}{}: {}", annotations(&p.annotations), p.name, p.typename),
+                },
+            });
+        let variants = definition.variants.iter()
+            .map(|v| Field {
+                docs: &v.docs,
+                text: match v.value {
+                    Some(value) => format!("{}{} = {}", annotations(&v.annotations), v.name, value),
+                    None => format!("{}{}", annotations(&v.annotations), v.name),
+                },
+            });
+        let fields: Vec<Field> = parameters.chain(variants).collect();
+
+        let keyword = language.spelling(&definition.kind.keyword()).unwrap_or_default();
+        let implements = match definition.implements.len() {
+            0 => String::new(),
+            _ => {
+                let names: Vec<&str> = definition.implements.iter().map(|i| &i.name[..]).collect();
+                format!(" {} {}", implements_keyword, names.join(", "))
+            },
+        };
+        write_definition(&mut out, keyword, &definition.name, &fields, &implements, &options);
+    }
+
+    Ok(out)
+}
+
+/// A parameter or a variant, ready to be written.
+struct Field<'a> {
+    docs: &'a [Cow<'a, str>],
+    text: String,
+}
+
+/// Writes a doc comment, one line at a time.
+fn write_docs(out: &mut String, indent: &str, docs: &[Cow<str>]) {
+    for line in docs {
+        match &line[..] {
+            "" => out.push_str(&format!("{}///\n", indent)),
+            line => out.push_str(&format!("{}/// {}\n", indent, line)),
+        }
+    }
+}
+
+/// Annotations of a parameter, each one followed by a space.
+fn annotations(annotations: &[Annotation]) -> String {
+    annotations.iter()
+        .map(|a| format!("{} ", a))
+        .collect()
+}
+
+/// Writes a single definition, in one line if it fits. Doc comments of the
+/// fields need their own lines, so they always make it vertical. The
+/// `implements` clause goes after the fields, as it is.
+fn write_definition(
+    out: &mut String,
+    keyword: &str,
+    name: &str,
+    fields: &[Field],
+    implements: &str,
+    options: &FormatOptions,
+) {
+    let trailing = match options.trailing_comma {
+        TrailingComma::Always if !fields.is_empty() => ",",
+        _ => "",
+    };
+    let texts: Vec<&str> = fields.iter().map(|f| &f.text[..]).collect();
+    let line = format!("{} {}({}{}){};", keyword, name, texts.join(", "), trailing, implements);
+    let docs = fields.iter().any(|f| !f.docs.is_empty());
+
+    if !docs && (line.chars().count() <= options.max_width || fields.len() < 2) {
+        out.push_str(&line);
+        out.push('\n');
+        return;
+    }
+
+    out.push_str(&format!("{} {}(\n", keyword, name));
+    for (i, field) in fields.iter().enumerate() {
+        let last = i + 1 == fields.len();
+        let comma = if !last || options.trailing_comma != TrailingComma::Never { "," } else { "" };
+        write_docs(out, "    ", field.docs);
+        out.push_str(&format!("    {}{}\n", field.text, comma));
+    }
+    out.push_str(&format!("){};\n", implements));
+}
+
+/// For every definition, whether there was a blank line before it.
+///
+/// The parser throws whitespace away, so we go through the tokens again,
+/// whitespace included. A definition starts with the first token after a
+/// semicolon (or the first one in the file), unless it's a module
+/// declaration.
+fn blank_lines(tokens: Cursor) -> Vec<bool> {
+    let mut blanks = Vec::new();
+    let mut blank = false;
+    let mut starting = true;
+
+    for (token, _) in tokens {
+        match *token {
+            Token::Whitespace(ref content) => {
+                blank = content.matches('\n').count() >= 2;
+            },
+            Token::Semicolon => starting = true,
+            Token::EOF => break,
+            Token::Module if starting => starting = false,
+            _ if starting => {
+                blanks.push(blank);
+                starting = false;
+            },
+            _ => (),
+        }
+    }
+
+    blanks
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::{Language, Span};
+    use super::{format_source, FormatOptions, TrailingComma};
+
+    fn get_format(source: &str) -> String {
+        format_source(source, FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn nul_characters_are_not_the_end() {
+        let errors = format_source("tipo A(x: Entero);\0tipo B(y: A);", FormatOptions::default()).unwrap_err();
+        assert_eq!(errors[0].message, "unknown character");
+        assert_eq!(errors[0].span, Span::new(18, 19));
+    }
+
+    #[test]
+    fn normalizes_spacing() {
+        let output = get_format("tipo   Punto ( x :Entero,y:Entero ) ;tipo A(b: B);");
+        assert_eq!(output, "tipo Punto(x: Entero, y: Entero);\ntipo A(b: B);\n");
+    }
+
+    #[test]
+    fn keeps_one_blank_line() {
+        let output = get_format("\n\ntipo A(b: B);\n\n\n\ntipo B(a: A); tipo C(a: A);\n");
+        assert_eq!(output, "tipo A(b: B);\n\ntipo B(a: A);\ntipo C(a: A);\n");
+    }
+
+    #[test]
+    fn long_definitions_are_split() {
+        let options = FormatOptions { max_width: 20, ..FormatOptions::default() };
+        let output = format_source("tipo Punto(x: Entero, y: Entero,);", options);
+
+        assert_eq!(output.unwrap(), "tipo Punto(\n    x: Entero,\n    y: Entero,\n);\n");
+    }
+
+    #[test]
+    fn trailing_comma_policies() {
+        let always = FormatOptions { trailing_comma: TrailingComma::Always, ..FormatOptions::default() };
+        let output = format_source("tipo A(b: B, c: C);", always);
+        assert_eq!(output.unwrap(), "tipo A(b: B, c: C,);\n");
+
+        let never = FormatOptions {
+            max_width: 10,
+            trailing_comma: TrailingComma::Never,
+            ..FormatOptions::default()
+        };
+        let output = format_source("tipo A(b: B, c: C,);", never);
+        assert_eq!(output.unwrap(), "tipo A(\n    b: B,\n    c: C\n);\n");
+    }
+
+    #[test]
+    fn annotations() {
+        let output = get_format("@obsoleto   @otra\n\ntipo A(@obsoleto  b: B, c: C);\n\n@x tipo B(a: A);");
+        assert_eq!(output, "@obsoleto\n@otra\ntipo A(@obsoleto b: B, c: C);\n\n@x\ntipo B(a: A);\n");
+    }
+
+    #[test]
+    fn doc_comments() {
+        let output = get_format("///   Un punto.\n///\n@x tipo P(///Horizontal.\nx: E, y: E);");
+        assert_eq!(output, "///   Un punto.\n///\n@x\ntipo P(\n    /// Horizontal.\n    x: E,\n    y: E,\n);\n");
+    }
+
+    #[test]
+    fn english_keywords() {
+        let options = FormatOptions { language: Language::English, ..FormatOptions::default() };
+        let output = format_source("type A(b: B); module m; type C(d: D);", options);
+        assert_eq!(output.unwrap(), "type A(b: B);\n\nmodule m;\n\ntype C(d: D);\n");
+    }
+
+    #[test]
+    fn modules() {
+        let output = get_format("modulo  geo;tipo A(b: B);\n\n\ntipo C(d: geo.D);\nmodulo vacio;\nmodulo red;\n\ntipo E(a: geo.A);");
+        assert_eq!(output, "modulo geo;\n\ntipo A(b: B);\n\ntipo C(d: geo.D);\n\nmodulo red;\n\ntipo E(a: geo.A);\n");
+
+        assert_eq!(get_format("tipo A(b: B); modulo nada;"), "tipo A(b: B);\n");
+    }
+
+    #[test]
+    fn interfaces() {
+        let output = get_format("interfaz  N(n: T);\ntipo A(n: T)implementa N ,M;");
+        assert_eq!(output, "interfaz N(n: T);\ntipo A(n: T) implementa N, M;\n");
+
+        let narrow = FormatOptions { max_width: 20, ..FormatOptions::default() };
+        let output = format_source("tipo A(n: T, m: T) implementa N;", narrow);
+        assert_eq!(output.unwrap(), "tipo A(\n    n: T,\n    m: T,\n) implementa N;\n");
+
+        let options = FormatOptions { language: Language::English, ..FormatOptions::default() };
+        let output = format_source("interface N(n: T); type A(n: T) implements N;", options);
+        assert_eq!(output.unwrap(), "interface N(n: T);\ntype A(n: T) implements N;\n");
+    }
+
+    #[test]
+    fn enums() {
+        let output = get_format("enum   Estado(Activo=1 ,Inactivo,@obsoleto Borrado = -01,);");
+        assert_eq!(output, "enum Estado(Activo = 1, Inactivo, @obsoleto Borrado = -1);\n");
+
+        let output = get_format("enum E(/// Uno.\nA, B);");
+        assert_eq!(output, "enum E(\n    /// Uno.\n    A,\n    B,\n);\n");
+    }
+
+    #[test]
+    fn annotation_arguments() {
+        let output = get_format("@desde( 2 )tipo Punto(@hasta(3)x: Entero,@x(1,2) y: Entero);");
+        assert_eq!(output, "@desde(2)\ntipo Punto(@hasta(3) x: Entero, @x(1, 2) y: Entero);\n");
+
+        let output = get_format("tipo Viaje(@unidad( \"km\" )distancia: Real,@x(\"\\\\\") y: Real);");
+        assert_eq!(output, "tipo Viaje(@unidad(\"km\") distancia: Real, @x(\"\\\\\") y: Real);\n");
+    }
+
+    #[test]
+    fn field_numbers() {
+        let output = get_format("tipo Punto(x: Entero#1,y: Entero   # 02);");
+        assert_eq!(output, "tipo Punto(x: Entero #1, y: Entero #2);\n");
+    }
+
+    #[test]
+    fn refuses_broken_code() {
+        let errors = format_source("tipo A(b B);", FormatOptions::default()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span, Span::new(9, 10));
+
+        // The fix comes with the error.
+        let errors = format_source("tipo A(b: B)\ntipo C(d: D);", FormatOptions::default()).unwrap_err();
+        assert_eq!(errors[0].suggestions[0].message, "insert `;`");
+    }
+}
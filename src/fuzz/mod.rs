@@ -0,0 +1,291 @@
+//! Fuzzing module.
+//!
+//! The lexer, the parser and the semantic analyzer are supposed to survive
+//! any input, reporting errors instead of panicking. Random bytes rarely get
+//! past the lexer, so this module also makes up inputs that look like
+//! programs: [`TokenStream`][0] is a list of definitions, most of them
+//! valid, with a few tokens removed, added or moved around.
+//!
+//! Only available with the `arbitrary` feature. The fuzz targets in `fuzz/`
+//! call [`run`][1] with whatever the fuzzer comes up with; start them with
+//! `cargo fuzz run pipeline`.
+//!
+//! [0]: struct.TokenStream.html
+//! [1]: fn.run.html
+
+use std::str;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use super::lexer::{quote, Language, Lexer, Token};
+use super::parser::{Ast, Parser};
+use super::semantic::Semantic;
+
+/// Names the generators pick from. Only a few, so types often use each other
+/// (or themselves) and the same field appears twice.
+const NAMES: [&str; 6] = ["Punto", "Entero", "Real", "x", "y", "_nombre2"];
+
+/// Annotations the generators pick from.
+const ANNOTATIONS: [&str; 2] = ["obsoleto", "otra"];
+
+/// Any token, with names that the lexer would read back as identifiers.
+impl<'a> Arbitrary<'a> for Token {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=19)? {
+            0 => Token::Illegal,
+            1 => Token::EOF,
+            2 => Token::Ident(String::from(*u.choose(&NAMES)?)),
+            3 => Token::ParL,
+            4 => Token::ParR,
+            5 => Token::Colon,
+            6 => Token::Semicolon,
+            7 => Token::Comma,
+            8 => Token::At,
+            9 => Token::Type,
+            10 => Token::DocComment(doc_line(u)?),
+            11 => Token::Module,
+            12 => Token::Interface,
+            13 => Token::Implements,
+            14 => Token::Number(u.int_in_range(-3..=300i32)?.to_string()),
+            15 => Token::Equals,
+            16 => Token::Enum,
+            17 => Token::Hash,
+            18 => Token::Text(doc_line(u)?),
+            _ => {
+                let count = u.int_in_range(1..=4)?;
+                let mut whitespace = String::new();
+                for _ in 0..count {
+                    whitespace.push(*u.choose(&[' ', '\t', '\n', '\u{a0}'])?);
+                }
+                Token::Whitespace(whitespace)
+            },
+        })
+    }
+}
+
+/// Tokens shaped like a program: definitions (with doc comments and
+/// annotations) that are valid, until up to three random edits break some of
+/// them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenStream(pub Vec<Token>);
+
+impl TokenStream {
+    /// Writes the tokens as source code in `language`, which the lexer reads
+    /// back as the same tokens, ignoring whitespace. The source ends at the
+    /// first `Token::EOF`.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::fuzz::TokenStream;
+    ///     use simcom::lexer::{Language, Token};
+    ///
+    ///     let tokens = TokenStream(vec![
+    ///         Token::DocComment(String::from("Uno.")),
+    ///         Token::Type,
+    ///         Token::Ident(String::from("A")),
+    ///         Token::Illegal,
+    ///     ]);
+    ///     assert_eq!(tokens.source(Language::English), "/// Uno.\ntype A ?");
+    pub fn source(&self, language: Language) -> String {
+        let mut source = String::new();
+
+        for token in &self.0 {
+            if !(source.is_empty() || source.ends_with('\n')) {
+                source.push(' ');
+            }
+
+            match *token {
+                Token::Illegal => source.push('?'),
+                Token::EOF => break,
+                Token::Ident(ref name) | Token::Number(ref name) => source.push_str(name),
+                Token::ParL => source.push('('),
+                Token::ParR => source.push(')'),
+                Token::Colon => source.push(':'),
+                Token::Semicolon => source.push(';'),
+                Token::Comma => source.push(','),
+                Token::At => source.push('@'),
+                Token::Equals => source.push('='),
+                Token::Hash => source.push('#'),
+                Token::Text(ref text) => source.push_str(&quote(text)),
+                Token::Type | Token::Module | Token::Interface | Token::Implements | Token::Enum => {
+                    source.push_str(language.spelling(token).unwrap_or_default())
+                },
+                Token::DocComment(ref line) => {
+                    source.push_str("/// ");
+                    source.push_str(line);
+                    source.push('\n');
+                },
+                Token::Whitespace(ref whitespace) => source.push_str(whitespace),
+            }
+        }
+
+        source
+    }
+}
+
+impl<'a> Arbitrary<'a> for TokenStream {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut tokens = Vec::new();
+
+        for _ in 0..u.int_in_range(0..=8)? {
+            definition(u, &mut tokens)?;
+        }
+
+        for _ in 0..u.int_in_range(0..=3)? {
+            if tokens.is_empty() {
+                break;
+            }
+
+            let i = u.choose_index(tokens.len())?;
+            match u.int_in_range(0..=2)? {
+                0 => {
+                    tokens.remove(i);
+                },
+                1 => tokens.insert(i, Token::arbitrary(u)?),
+                _ => {
+                    let j = u.choose_index(tokens.len())?;
+                    tokens.swap(i, j);
+                },
+            }
+        }
+
+        Ok(TokenStream(tokens))
+    }
+}
+
+/// Any text that fits in a doc comment line.
+fn doc_line(u: &mut Unstructured) -> Result<String> {
+    let line = String::arbitrary(u)?;
+    Ok(line.chars().filter(|&c| c != '\n' && c != '\r').collect())
+}
+
+/// Pushes the doc comment lines and annotations that go before a definition
+/// or a parameter.
+fn attributes(u: &mut Unstructured, tokens: &mut Vec<Token>) -> Result<()> {
+    for _ in 0..u.int_in_range(0..=2)? {
+        tokens.push(Token::DocComment(doc_line(u)?));
+    }
+
+    for _ in 0..u.int_in_range(0..=2)? {
+        tokens.push(Token::At);
+        tokens.push(Token::Ident(String::from(*u.choose(&ANNOTATIONS)?)));
+        if u.arbitrary()? {
+            tokens.push(Token::ParL);
+            tokens.push(match u.arbitrary()? {
+                true => Token::Number(u.int_in_range(0..=9i32)?.to_string()),
+                false => Token::Text(doc_line(u)?),
+            });
+            tokens.push(Token::ParR);
+        }
+    }
+
+    Ok(())
+}
+
+/// Pushes a valid type definition.
+fn definition(u: &mut Unstructured, tokens: &mut Vec<Token>) -> Result<()> {
+    attributes(u, tokens)?;
+    tokens.push(Token::Type);
+    tokens.push(Token::Ident(String::from(*u.choose(&NAMES)?)));
+    tokens.push(Token::ParL);
+
+    for i in 0..u.int_in_range(1..=4)? {
+        if i > 0 {
+            tokens.push(Token::Comma);
+        }
+        attributes(u, tokens)?;
+        tokens.push(Token::Ident(String::from(*u.choose(&NAMES)?)));
+        tokens.push(Token::Colon);
+        tokens.push(Token::Ident(String::from(*u.choose(&NAMES)?)));
+    }
+
+    if u.arbitrary()? {
+        tokens.push(Token::Comma);
+    }
+    tokens.push(Token::ParR);
+    tokens.push(Token::Semicolon);
+    Ok(())
+}
+
+/// Lexes, parses and analyzes `source`. Panics if any of them does, or if an
+/// error points outside of `source`.
+pub fn check(source: &str) {
+    let lexer = Lexer::new(source);
+    let asts: Vec<Ast> = Parser::new(Lexer::new(source)).collect();
+
+    for ast in &asts {
+        if let Some(diagnostic) = ast.diagnostic() {
+            // Panics if the span is out of bounds or not at char boundaries.
+            lexer.text(diagnostic.span);
+        }
+    }
+
+    let _ = Semantic::check(asts);
+}
+
+/// The fuzz targets' entry point. Checks `data` as a [`TokenStream`][0], and
+/// as source code if it is UTF-8.
+///
+/// [0]: struct.TokenStream.html
+pub fn run(data: &[u8]) {
+    let mut u = Unstructured::new(data);
+    if let Ok(tokens) = TokenStream::arbitrary(&mut u) {
+        check(&tokens.source(Language::default()));
+    }
+
+    if let Ok(source) = str::from_utf8(data) {
+        check(source);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use super::super::lexer::{Language, Lexer, Token};
+    use super::{run, TokenStream};
+
+    /// Pseudo-random bytes, the same in every run.
+    fn bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        (0..len).map(|_| {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        }).collect()
+    }
+
+    #[test]
+    fn the_lexer_reads_the_tokens_back() {
+        for seed in 0..500 {
+            let data = bytes(seed, 512);
+            let tokens = TokenStream::arbitrary(&mut Unstructured::new(&data)).unwrap();
+
+            for &language in &[Language::Spanish, Language::English] {
+                let source = tokens.source(language);
+                // The source ends at the first EOF, and the lexer drops the
+                // whitespace.
+                let expected: Vec<Token> = tokens.0.iter()
+                    .take_while(|&token| *token != Token::EOF)
+                    .filter(|token| !matches!(**token, Token::Whitespace(_)))
+                    .cloned()
+                    .collect();
+
+                let found: Vec<Token> = Lexer::with_language(&source, language).collect();
+                assert_eq!(found, expected, "{:?}", source);
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_panics() {
+        for seed in 0..2000 {
+            run(&bytes(seed, (seed % 300) as usize));
+        }
+
+        run(b"");
+        run("tipo \u{a0}A(x: ñ, ;; /// é\r\n@".as_bytes());
+    }
+}
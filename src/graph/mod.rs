@@ -0,0 +1,332 @@
+//! Dependency graph module.
+//!
+//! The semantic analyzer already walks the dependencies between types to sort
+//! them. This module keeps them around as a graph, so they can be drawn: every
+//! type is a node and every field an edge, from the type that has the field to
+//! the type of the field.
+
+use std::collections::{BTreeSet, HashSet};
+
+use super::json::Json;
+use super::semantic::Semantic;
+
+/// The formats a graph can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Format {
+    /// Graphviz.
+    #[default]
+    Dot,
+    /// Mermaid flowcharts, which many Markdown renderers understand.
+    Mermaid,
+    Json,
+}
+
+impl Format {
+    /// Looks up a format by its name (`dot`, `mermaid` or `json`).
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "dot" => Some(Format::Dot),
+            "mermaid" => Some(Format::Mermaid),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A type in the graph.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,
+    /// Whether there is a definition for it. Built-in types don't have one.
+    pub defined: bool,
+    /// Whether it is part of a cycle of dependencies.
+    pub cycle: bool,
+}
+
+/// A field of a type, pointing to the type of the field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub field: String,
+}
+
+/// The dependency graph. Nodes are sorted by name and edges go in the order
+/// of the fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// Builds the graph of every definition.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::graph::{Format, Graph};
+    ///     use simcom::lexer::Lexer;
+    ///     use simcom::parser::Parser;
+    ///     use simcom::semantic::Semantic;
+    ///
+    ///     let content = "tipo Circulo(centro: Punto); tipo Punto(x: Entero);";
+    ///     let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+    ///     let graph = Graph::new(&semantic);
+    ///
+    ///     assert_eq!(graph.nodes.len(), 3);
+    ///     assert!(graph.render(Format::Dot).contains("\"Circulo\" -> \"Punto\" [label=\"centro\"];"));
+    pub fn new(semantic: &Semantic) -> Graph {
+        let mut names = BTreeSet::new();
+        let mut edges = Vec::new();
+
+        let mut definitions: Vec<_> = semantic.definitions.iter().collect();
+        definitions.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (name, definition) in definitions {
+            names.insert(&name[..]);
+            for parameter in &definition.parameters {
+                names.insert(&parameter.typename[..]);
+                edges.push(Edge {
+                    from: name.clone(),
+                    to: parameter.typename.to_string(),
+                    field: parameter.name.to_string(),
+                });
+            }
+        }
+
+        let nodes = names.into_iter()
+            .map(|name| Node {
+                name: String::from(name),
+                defined: semantic.definitions.contains_key(name),
+                cycle: semantic.cycles.contains(name),
+            })
+            .collect();
+
+        Graph { nodes, edges }
+    }
+
+    /// Only the part of the graph that can be reached from `root`, or `None`
+    /// if there is no such type.
+    pub fn closure(&self, root: &str) -> Option<Graph> {
+        if !self.nodes.iter().any(|node| node.name == root) {
+            return None;
+        }
+
+        let mut reached = HashSet::new();
+        let mut pending = vec![root];
+        while let Some(name) = pending.pop() {
+            if reached.insert(name) {
+                pending.extend(self.edges.iter()
+                    .filter(|edge| edge.from == name)
+                    .map(|edge| &edge.to[..]));
+            }
+        }
+
+        Some(Graph {
+            nodes: self.nodes.iter()
+                .filter(|node| reached.contains(&node.name[..]))
+                .cloned()
+                .collect(),
+            edges: self.edges.iter()
+                .filter(|edge| reached.contains(&edge.from[..]))
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// Only `name`, the types it depends on and the ones that depend on it,
+    /// with the edges between `name` and them, or `None` if there is no
+    /// such type.
+    pub fn neighborhood(&self, name: &str) -> Option<Graph> {
+        if !self.nodes.iter().any(|node| node.name == name) {
+            return None;
+        }
+
+        let edges: Vec<Edge> = self.edges.iter()
+            .filter(|edge| edge.from == name || edge.to == name)
+            .cloned()
+            .collect();
+
+        Some(Graph {
+            nodes: self.nodes.iter()
+                .filter(|node| node.name == name || edges.iter().any(|e| e.from == node.name || e.to == node.name))
+                .cloned()
+                .collect(),
+            edges,
+        })
+    }
+
+    /// Writes the graph in the given format.
+    pub fn render(&self, format: Format) -> String {
+        match format {
+            Format::Dot => self.dot(),
+            Format::Mermaid => self.mermaid(),
+            Format::Json => format!("{}\n", self.to_json()),
+        }
+    }
+
+    /// Built-in types are drawn without a box, types in cycles in red.
+    fn dot(&self) -> String {
+        let mut out = String::from("digraph simcom {\n");
+
+        for node in &self.nodes {
+            let mut attributes = Vec::new();
+            if !node.defined {
+                attributes.push("shape=plaintext");
+            }
+            if node.cycle {
+                attributes.push("color=red");
+            }
+
+            match attributes.len() {
+                0 => out.push_str(&format!("    {};\n", quote(&node.name))),
+                _ => out.push_str(&format!("    {} [{}];\n", quote(&node.name), attributes.join(", "))),
+            }
+        }
+
+        for edge in &self.edges {
+            out.push_str(&format!("    {} -> {} [label={}];\n",
+                quote(&edge.from), quote(&edge.to), quote(&edge.field)));
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    /// Mermaid is picky about identifiers, so nodes are called by their
+    /// position and the name goes in the label.
+    fn mermaid(&self) -> String {
+        let mut out = String::from("graph LR\n");
+        let id = |name: &str| self.nodes.iter().position(|node| node.name == name).unwrap_or(0);
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            let label = mermaid_label(&node.name);
+            match node.defined {
+                true => out.push_str(&format!("    n{}[\"{}\"]\n", i, label)),
+                false => out.push_str(&format!("    n{}([\"{}\"])\n", i, label)),
+            }
+        }
+
+        for edge in &self.edges {
+            out.push_str(&format!("    n{} -->|\"{}\"| n{}\n",
+                id(&edge.from), mermaid_label(&edge.field), id(&edge.to)));
+        }
+
+        let cycles: Vec<String> = self.nodes.iter()
+            .enumerate()
+            .filter(|(_, node)| node.cycle)
+            .map(|(i, _)| format!("n{}", i))
+            .collect();
+        if !cycles.is_empty() {
+            out.push_str("    classDef cycle stroke:red\n");
+            out.push_str(&format!("    class {} cycle\n", cycles.join(",")));
+        }
+
+        out
+    }
+
+    /// The graph as `{"nodes": [...], "edges": [...]}`.
+    pub fn to_json(&self) -> Json {
+        let nodes = self.nodes.iter()
+            .map(|node| Json::object(vec![
+                ("name", Json::from(&node.name[..])),
+                ("defined", Json::from(node.defined)),
+                ("cycle", Json::from(node.cycle)),
+            ]))
+            .collect();
+
+        let edges = self.edges.iter()
+            .map(|edge| Json::object(vec![
+                ("from", Json::from(&edge.from[..])),
+                ("to", Json::from(&edge.to[..])),
+                ("field", Json::from(&edge.field[..])),
+            ]))
+            .collect();
+
+        Json::object(vec![
+            ("nodes", Json::Array(nodes)),
+            ("edges", Json::Array(edges)),
+        ])
+    }
+}
+
+/// A Graphviz string.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Mermaid labels can't have quotes, but they can have entities.
+fn mermaid_label(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::Lexer;
+    use super::super::parser::Parser;
+    use super::*;
+
+    fn get_graph(content: &str) -> Graph {
+        let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+        Graph::new(&semantic)
+    }
+
+    #[test]
+    fn dot() {
+        let graph = get_graph("tipo B(x: Entero); tipo A(b: B, c: A);");
+
+        assert_eq!(graph.render(Format::Dot), "digraph simcom {\n\
+            \x20   \"A\" [color=red];\n\
+            \x20   \"B\";\n\
+            \x20   \"Entero\" [shape=plaintext];\n\
+            \x20   \"A\" -> \"B\" [label=\"b\"];\n\
+            \x20   \"A\" -> \"A\" [label=\"c\"];\n\
+            \x20   \"B\" -> \"Entero\" [label=\"x\"];\n\
+            }\n");
+    }
+
+    #[test]
+    fn mermaid() {
+        let graph = get_graph("tipo A(b: B); tipo B(x: Entero);");
+
+        assert_eq!(graph.render(Format::Mermaid), "graph LR\n\
+            \x20   n0[\"A\"]\n\
+            \x20   n1[\"B\"]\n\
+            \x20   n2([\"Entero\"])\n\
+            \x20   n0 -->|\"b\"| n1\n\
+            \x20   n1 -->|\"x\"| n2\n");
+    }
+
+    #[test]
+    fn json() {
+        let graph = get_graph("tipo A(x: Entero);");
+
+        assert_eq!(graph.render(Format::Json), "{\"nodes\":[\
+            {\"name\":\"A\",\"defined\":true,\"cycle\":false},\
+            {\"name\":\"Entero\",\"defined\":false,\"cycle\":false}],\
+            \"edges\":[{\"from\":\"A\",\"to\":\"Entero\",\"field\":\"x\"}]}\n");
+    }
+
+    #[test]
+    fn closure() {
+        let graph = get_graph("tipo A(b: B); tipo B(x: Entero); tipo C(a: A, r: Real);");
+
+        let names = |graph: &Graph| graph.nodes.iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&graph.closure("A").unwrap()), vec!["A", "B", "Entero"]);
+        assert_eq!(graph.closure("C").unwrap().edges.len(), 4);
+        assert_eq!(graph.closure("Entero").unwrap().edges.len(), 0);
+        assert_eq!(graph.closure("Nope"), None);
+    }
+
+    #[test]
+    fn neighborhood() {
+        let graph = get_graph("tipo A(b: B); tipo B(x: Entero, y: B); tipo C(a: A, r: Real);");
+
+        let b = graph.neighborhood("B").unwrap();
+        let names: Vec<&str> = b.nodes.iter().map(|n| &n.name[..]).collect();
+        assert_eq!(names, vec!["A", "B", "Entero"]);
+        assert_eq!(b.edges.len(), 3);
+        assert_eq!(graph.neighborhood("Real").unwrap().nodes.len(), 2);
+        assert_eq!(graph.neighborhood("Nope"), None);
+    }
+}
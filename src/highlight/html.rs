@@ -0,0 +1,143 @@
+//! Highlighted listings as HTML.
+//!
+//! Every token is wrapped in a `<span>` with the [name][0] of its class, and
+//! the text between them (whitespace) is copied as it is, so the listing looks
+//! exactly like the source. Each type gets an anchor, `type.Name`, where it's
+//! defined, and the fields of that type link to it.
+//!
+//! [0]: ../enum.Class.html#method.name
+
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+
+use super::super::lexer::{Language, Lexer};
+use super::super::parser::{Ast, Parser};
+use super::{classify, Class};
+
+/// Colors for a standalone page. Every rule is under `pre.simcom`, so it
+/// doesn't change the rest of a site the listing is embedded in.
+pub const STYLE: &str = "\
+pre.simcom { font-family: monospace; background: #f8f8f8; padding: 1em; line-height: 1.4; }
+pre.simcom a { color: inherit; text-decoration: none; }
+pre.simcom a:hover { text-decoration: underline; }
+pre.simcom .keyword { color: #a626a4; font-weight: bold; }
+pre.simcom .type-name { color: #c18401; }
+pre.simcom .field-name { color: #4078f2; }
+pre.simcom .number { color: #0184bc; }
+pre.simcom .text { color: #50a14f; }
+pre.simcom .annotation { color: #986801; }
+pre.simcom .doc-comment { color: #a0a1a7; font-style: italic; }
+pre.simcom .error { color: #e45649; text-decoration: underline wavy; }
+";
+
+/// `source` as a `<pre class="simcom">` element, to embed in a page.
+///
+/// # Examples
+///
+///     use simcom::highlight::html;
+///     use simcom::lexer::Language;
+///
+///     let listing = html::listing("tipo A(b: A);", Language::Spanish);
+///     assert_eq!(listing, "<pre class=\"simcom\"><span class=\"keyword\">tipo</span> \
+///         <span class=\"type-name\" id=\"type.A\">A</span><span class=\"punctuation\">(</span>\
+///         <span class=\"field-name\">b</span><span class=\"punctuation\">:</span> \
+///         <a class=\"type-name\" href=\"#type.A\">A</a><span class=\"punctuation\">)</span>\
+///         <span class=\"punctuation\">;</span></pre>\n");
+pub fn listing(source: &str, language: Language) -> String {
+    // Where each type is defined, the first time.
+    let mut defined = BTreeSet::new();
+    let mut anchors = BTreeSet::new();
+    for ast in Parser::new(Lexer::with_language(source, language)) {
+        if let Ast::TypeDefinition(definition) = ast {
+            if defined.insert(definition.name.into_owned()) {
+                anchors.insert(definition.name_span.start);
+            }
+        }
+    }
+
+    let mut out = String::from("<pre class=\"simcom\">");
+    let mut copied = 0;
+
+    for highlight in classify(source, language) {
+        let span = highlight.span;
+        let text = &source[span.range()];
+        let class = highlight.class.name();
+
+        out.push_str(&escape(&source[copied..span.start]));
+        if anchors.contains(&span.start) {
+            out.push_str(&format!("<span class=\"{}\" id=\"type.{}\">{}</span>", class, text, escape(text)));
+        } else if highlight.class == Class::TypeName && defined.contains(text) {
+            out.push_str(&format!("<a class=\"{}\" href=\"#type.{}\">{}</a>", class, text, escape(text)));
+        } else {
+            out.push_str(&format!("<span class=\"{}\">{}</span>", class, escape(text)));
+        }
+        copied = span.end;
+    }
+
+    out.push_str(&escape(&source[copied..]));
+    out.push_str("</pre>\n");
+    out
+}
+
+/// A whole HTML page with the [listing][0] of `source` and its
+/// [style][1].
+///
+/// [0]: fn.listing.html
+/// [1]: constant.STYLE.html
+pub fn page(source: &str, language: Language, title: &str) -> String {
+    format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title), STYLE, listing(source, language))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::lexer::Language;
+    use super::{listing, page};
+
+    /// The listing without its tags.
+    fn text(html: &str) -> String {
+        let mut out = String::new();
+        let mut tag = false;
+        for ch in html.chars() {
+            match ch {
+                '<' => tag = true,
+                '>' if tag => tag = false,
+                _ if !tag => out.push(ch),
+                _ => (),
+            }
+        }
+        out.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&amp;", "&")
+    }
+
+    #[test]
+    fn text_is_kept() {
+        let source = "/// Un <punto> & \"más\".\n@obsoleto\ttipo   P(\r\n  x: E,\n);\n\n? tipo Q(p: P);  ";
+        // The element ends with a new line.
+        assert_eq!(text(&listing(source, Language::Spanish)), format!("{}\n", source));
+    }
+
+    #[test]
+    fn anchors_and_links() {
+        let html = listing("tipo A(b: B, c: Entero);\ntipo B(a: A);\ntipo B(x: X);", Language::Spanish);
+        assert_eq!(html.matches("id=\"type.A\"").count(), 1);
+        assert_eq!(html.matches("id=\"type.B\"").count(), 1);
+        assert!(html.contains("<a class=\"type-name\" href=\"#type.B\">B</a>"));
+        assert!(html.contains("<span class=\"type-name\">Entero</span>"));
+    }
+
+    #[test]
+    fn standalone_pages() {
+        let html = page("tipo A(b: A);", Language::Spanish, "a.sc <1>");
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>a.sc &lt;1&gt;</title>"));
+        assert!(html.contains("pre.simcom .keyword"));
+    }
+}
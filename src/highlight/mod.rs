@@ -0,0 +1,292 @@
+//! Highlighting module.
+//!
+//! A regular expression can tell a keyword from a name, but not which names
+//! are types and which are fields: in `tipo Linea(a: Punto)` both `Linea` and
+//! `Punto` are types and `a` is a field, while in `tipo a(Linea: Punto)` it's
+//! the other way around. [`classify`][0] parses the text, so editors can color
+//! each token by what it is, even when there are errors. The [`html`][1]
+//! module turns the result into a listing for web pages.
+//!
+//! [0]: fn.classify.html
+//! [1]: html/index.html
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+use super::json::{Json, ToJson};
+use super::lexer::{Language, Lexer, Span, Token, TokenStream};
+use super::parser::{Ast, Parser};
+
+pub mod html;
+
+/// What a token is, for its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    /// A keyword, like `tipo`.
+    Keyword,
+    /// The name of a type, where it's defined or as the type of a field.
+    TypeName,
+    /// The name of a field, or of a variant of an enumeration.
+    FieldName,
+    Number,
+    /// A text between quotes, the quotes included.
+    Text,
+    /// An annotation, the `@` included.
+    Annotation,
+    DocComment,
+    Punctuation,
+    /// A name the parser skipped because of an error before it.
+    Identifier,
+    /// A token the parser didn't expect or the lexer didn't know.
+    Error,
+}
+
+impl Class {
+    /// The name of the class, in kebab case, like `type-name`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Class::Keyword => "keyword",
+            Class::TypeName => "type-name",
+            Class::FieldName => "field-name",
+            Class::Number => "number",
+            Class::Text => "text",
+            Class::Annotation => "annotation",
+            Class::DocComment => "doc-comment",
+            Class::Punctuation => "punctuation",
+            Class::Identifier => "identifier",
+            Class::Error => "error",
+        }
+    }
+}
+
+/// A token of the source and its class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Highlight {
+    pub span: Span,
+    pub class: Class,
+}
+
+impl ToJson for Highlight {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("class", Json::from(self.class.name())),
+            ("start", Json::from(self.span.start)),
+            ("end", Json::from(self.span.end)),
+        ])
+    }
+}
+
+/// Every token of `source`, in order, with its class. Whitespace isn't a
+/// token, so it isn't included.
+///
+/// # Examples
+///
+///     use simcom::highlight::{classify, Class};
+///     use simcom::lexer::Language;
+///
+///     let source = "tipo Linea(a: Punto);";
+///     let classes: Vec<Class> = classify(source, Language::Spanish).iter().map(|h| h.class).collect();
+///     assert_eq!(classes, [
+///         Class::Keyword, Class::TypeName, Class::Punctuation, Class::FieldName,
+///         Class::Punctuation, Class::TypeName, Class::Punctuation, Class::Punctuation,
+///     ]);
+pub fn classify(source: &str, language: Language) -> Vec<Highlight> {
+    // The names the parser found, by where they start. Annotations cover
+    // their `@` and their name, which are two tokens, and their arguments,
+    // which are highlighted as usual.
+    let tokens = TokenStream::new(Lexer::with_language(source, language));
+    let mut names = BTreeMap::new();
+    let mut annotations = Vec::new();
+    let mut errors = BTreeSet::new();
+
+    for ast in Parser::from_cursor(tokens.cursor()) {
+        let definition = match ast {
+            Ast::TypeDefinition(definition) => definition,
+            Ast::Unexpected(_, span) => {
+                errors.insert(span.start);
+                continue;
+            },
+        };
+
+        names.insert(definition.name_span.start, Class::TypeName);
+        annotations.extend(definition.annotations.iter().map(|a| a.span));
+        for parameter in &definition.parameters {
+            names.insert(parameter.name_span.start, Class::FieldName);
+            names.insert(parameter.type_span.start, Class::TypeName);
+            annotations.extend(parameter.annotations.iter().map(|a| a.span));
+        }
+        for implementation in &definition.implements {
+            names.insert(implementation.span.start, Class::TypeName);
+        }
+        for variant in &definition.variants {
+            names.insert(variant.name_span.start, Class::FieldName);
+            annotations.extend(variant.annotations.iter().map(|a| a.span));
+        }
+    }
+
+    // Tokens come in order, so the annotation a token can be in is always
+    // the first one that doesn't end before it.
+    annotations.sort_by_key(|a| a.start);
+    let mut annotations = annotations.into_iter().peekable();
+
+    tokens.tokens().iter()
+        .filter(|(token, _)| *token != Token::EOF)
+        .map(|&(ref token, span)| {
+            while annotations.peek().is_some_and(|a| a.end <= span.start) {
+                annotations.next();
+            }
+
+            let class = if errors.contains(&span.start) {
+                Class::Error
+            } else if matches!(*token, Token::At | Token::Ident(_))
+                && annotations.peek().is_some_and(|a| a.start <= span.start && span.end <= a.end)
+            {
+                Class::Annotation
+            } else {
+                match *token {
+                    Token::Type | Token::Module | Token::Interface | Token::Implements | Token::Enum => {
+                        Class::Keyword
+                    },
+                    Token::Number(_) => Class::Number,
+                    Token::Text(_) => Class::Text,
+                    Token::Ident(_) => names.get(&span.start).cloned().unwrap_or(Class::Identifier),
+                    Token::DocComment(_) => Class::DocComment,
+                    Token::Illegal => Class::Error,
+                    _ => Class::Punctuation,
+                }
+            };
+            Highlight { span, class }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::{Language, Span};
+    use super::{classify, Class, Highlight};
+
+    fn classes(source: &str) -> Vec<(&str, Class)> {
+        classify(source, Language::Spanish).into_iter()
+            .map(|Highlight { span, class }| (&source[span.range()], class))
+            .collect()
+    }
+
+    #[test]
+    fn names_by_position() {
+        assert_eq!(classes("tipo a(Linea: Punto);"), [
+            ("tipo", Class::Keyword),
+            ("a", Class::TypeName),
+            ("(", Class::Punctuation),
+            ("Linea", Class::FieldName),
+            (":", Class::Punctuation),
+            ("Punto", Class::TypeName),
+            (")", Class::Punctuation),
+            (";", Class::Punctuation),
+        ]);
+    }
+
+    #[test]
+    fn annotations_and_docs() {
+        assert_eq!(classes("/// Un punto.\n@obsoleto tipo P(@x y: E);"), [
+            ("/// Un punto.", Class::DocComment),
+            ("@", Class::Annotation),
+            ("obsoleto", Class::Annotation),
+            ("tipo", Class::Keyword),
+            ("P", Class::TypeName),
+            ("(", Class::Punctuation),
+            ("@", Class::Annotation),
+            ("x", Class::Annotation),
+            ("y", Class::FieldName),
+            (":", Class::Punctuation),
+            ("E", Class::TypeName),
+            (")", Class::Punctuation),
+            (";", Class::Punctuation),
+        ]);
+
+        assert_eq!(classes("@desde(2) tipo P(@unidad(\"m\") x: E);")[..], [
+            ("@", Class::Annotation),
+            ("desde", Class::Annotation),
+            ("(", Class::Punctuation),
+            ("2", Class::Number),
+            (")", Class::Punctuation),
+            ("tipo", Class::Keyword),
+            ("P", Class::TypeName),
+            ("(", Class::Punctuation),
+            ("@", Class::Annotation),
+            ("unidad", Class::Annotation),
+            ("(", Class::Punctuation),
+            ("\"m\"", Class::Text),
+            (")", Class::Punctuation),
+            ("x", Class::FieldName),
+            (":", Class::Punctuation),
+            ("E", Class::TypeName),
+            (")", Class::Punctuation),
+            (";", Class::Punctuation),
+        ]);
+    }
+
+    #[test]
+    fn interfaces() {
+        assert_eq!(classes("interfaz N(n: T); tipo A(n: T) implementa N, M;")[8..], [
+            ("tipo", Class::Keyword),
+            ("A", Class::TypeName),
+            ("(", Class::Punctuation),
+            ("n", Class::FieldName),
+            (":", Class::Punctuation),
+            ("T", Class::TypeName),
+            (")", Class::Punctuation),
+            ("implementa", Class::Keyword),
+            ("N", Class::TypeName),
+            (",", Class::Punctuation),
+            ("M", Class::TypeName),
+            (";", Class::Punctuation),
+        ]);
+    }
+
+    #[test]
+    fn enums() {
+        assert_eq!(classes("enum E(A = -1, @x B);"), [
+            ("enum", Class::Keyword),
+            ("E", Class::TypeName),
+            ("(", Class::Punctuation),
+            ("A", Class::FieldName),
+            ("=", Class::Punctuation),
+            ("-1", Class::Number),
+            (",", Class::Punctuation),
+            ("@", Class::Annotation),
+            ("x", Class::Annotation),
+            ("B", Class::FieldName),
+            (")", Class::Punctuation),
+            (";", Class::Punctuation),
+        ]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(classes("tipo A(x B); tipo C(y: E); ?"), [
+            ("tipo", Class::Keyword),
+            ("A", Class::Identifier),
+            ("(", Class::Punctuation),
+            ("x", Class::Identifier),
+            ("B", Class::Error),
+            (")", Class::Punctuation),
+            (";", Class::Punctuation),
+            ("tipo", Class::Keyword),
+            ("C", Class::TypeName),
+            ("(", Class::Punctuation),
+            ("y", Class::FieldName),
+            (":", Class::Punctuation),
+            ("E", Class::TypeName),
+            (")", Class::Punctuation),
+            (";", Class::Punctuation),
+            ("?", Class::Error),
+        ]);
+    }
+
+    #[test]
+    fn english_keywords() {
+        let highlights = classify("type tipo(x: E);", Language::English);
+        assert_eq!(highlights[0].class, Class::Keyword);
+        assert_eq!(highlights[1], Highlight { span: Span::new(5, 9), class: Class::TypeName });
+    }
+}
@@ -0,0 +1,766 @@
+//! IDE module.
+//!
+//! The queries an editor asks about the code under the cursor, answered from
+//! the text of the files in a [`SourceMap`][0], whether they compile or not.
+//! The [language server][1] is built on top of them, but they don't know
+//! anything about its protocol.
+//!
+//! All the files of a source map are compiled together, so a type can be
+//! used in one file and defined in another.
+//!
+//! [0]: ../source_map/struct.SourceMap.html
+//! [1]: https://microsoft.github.io/language-server-protocol/
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::lexer::{Language, Lexer, Span, Token};
+use super::parser::{Ast, DefinitionKind, Parser, TypeDefinition};
+use super::source_map::{FileId, SourceFile, SourceMap};
+
+pub use super::diagnostics::TextEdit;
+
+/// A place in a file, like where the cursor is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub file: FileId,
+    /// Byte offset in the file.
+    pub offset: usize,
+}
+
+impl Position {
+    pub fn new(file: FileId, offset: usize) -> Position {
+        Position { file, offset }
+    }
+
+    /// Whether the position is inside `span` or right after it, which is
+    /// where the cursor is after typing a name.
+    fn touches(self, span: Span) -> bool {
+        span.file == self.file && span.start <= self.offset && self.offset <= span.end
+    }
+}
+
+/// Where a type is defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    /// The name of the type, where editors put the cursor.
+    pub name_span: Span,
+    /// The whole definition.
+    pub span: Span,
+}
+
+/// The definitions of a file that parsed, skipping the errors.
+fn definitions(file: &SourceFile, language: Language) -> impl Iterator<Item = TypeDefinition<'_>> {
+    Parser::new(Lexer::with_language(&file.content, language).in_file(file.id))
+        .filter_map(|ast| match ast {
+            Ast::TypeDefinition(definition) => Some(definition),
+            Ast::Unexpected(..) => None,
+        })
+}
+
+/// The types of every file of a source map: where they are defined and
+/// where they are used, which is the reverse of the dependencies. Building it
+/// parses every file once, so it's worth keeping for many queries.
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    /// Where each type is defined, in the order of the files. Usually once.
+    definitions: BTreeMap<String, Vec<Location>>,
+    /// Where each type is the type of a parameter, in the order of the files.
+    references: BTreeMap<String, Vec<Span>>,
+    /// Every name of a type, defined or used, and where it is.
+    names: Vec<(Span, String)>,
+    /// The language of the keywords, which can't be used as names.
+    language: Language,
+}
+
+impl Index {
+    /// Indexes the files, with the keywords in the default language.
+    pub fn new(source_map: &SourceMap) -> Index {
+        Index::with_language(source_map, Language::default())
+    }
+
+    pub fn with_language(source_map: &SourceMap, language: Language) -> Index {
+        let mut index = Index { language, ..Index::default() };
+
+        for file in source_map.iter() {
+            for definition in definitions(file, language) {
+                let location = Location { name_span: definition.name_span, span: definition.span };
+                index.definitions.entry(definition.name.to_string()).or_default().push(location);
+                index.names.push((definition.name_span, definition.name.to_string()));
+
+                for parameter in &definition.parameters {
+                    index.references.entry(parameter.typename.to_string()).or_default().push(parameter.type_span);
+                    index.names.push((parameter.type_span, parameter.typename.to_string()));
+                }
+                for implementation in &definition.implements {
+                    index.references.entry(implementation.name.to_string()).or_default().push(implementation.span);
+                    index.names.push((implementation.span, implementation.name.to_string()));
+                }
+            }
+        }
+
+        index
+    }
+
+    /// The name of the type at `position`: the type of a parameter, an
+    /// interface after `implementa` or the name of a definition.
+    pub fn name_at(&self, position: Position) -> Option<&str> {
+        self.names.iter()
+            .find(|&&(span, _)| position.touches(span))
+            .map(|(_, name)| &name[..])
+    }
+
+    /// Where the type is defined. If it's defined more than once, the
+    /// definition in `file` wins, then the first one.
+    pub fn definition(&self, name: &str, file: FileId) -> Option<Location> {
+        let locations = self.definitions.get(name)?;
+        locations.iter()
+            .find(|location| location.name_span.file == file)
+            .or_else(|| locations.first())
+            .cloned()
+    }
+
+    /// Every parameter whose type is `name`, and every `implementa` of it,
+    /// in the order of the files. Definitions aren't included.
+    pub fn references(&self, name: &str) -> &[Span] {
+        self.references.get(name).map(|spans| &spans[..]).unwrap_or(&[])
+    }
+
+    /// The edits that rename the type `name` to `new_name` everywhere: its
+    /// definitions and every parameter of that type, in the order of the
+    /// files. Nothing changes until the edits are applied (with
+    /// [`rewrite::apply_all`][0], for example), so they can be checked first.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::ide::{Index, RenameError};
+    ///     use simcom::rewrite;
+    ///     use simcom::source_map::SourceMap;
+    ///
+    ///     let mut sources = SourceMap::new();
+    ///     sources.add("a.sc", "tipo Punto(x: Entero);");
+    ///     sources.add("b.sc", "tipo Linea(a: Punto, b: Punto);");
+    ///     let index = Index::new(&sources);
+    ///
+    ///     let edits = index.rename("Punto", "Vector").unwrap();
+    ///     assert_eq!(edits.len(), 3);
+    ///     assert_eq!(edits[2].span.range(), 24..29);
+    ///     assert_eq!(edits[2].replacement, "Vector");
+    ///
+    ///     assert_eq!(index.rename("Punto", "Linea").unwrap_err().to_string(), "`Linea` is already defined");
+    ///     assert_eq!(index.rename("Entero", "Numero"), Err(RenameError::Undefined(String::from("Entero"))));
+    ///
+    ///     let rewritten = rewrite::apply_all(&sources, &edits).unwrap();
+    ///     assert_eq!(rewritten[1].1, "tipo Linea(a: Vector, b: Vector);");
+    ///
+    /// [0]: ../rewrite/fn.apply_all.html
+    pub fn rename(&self, name: &str, new_name: &str) -> Result<Vec<TextEdit>, RenameError> {
+        let definitions = self.definitions.get(name)
+            .ok_or_else(|| RenameError::Undefined(String::from(name)))?;
+
+        let tokens: Vec<Token> = Lexer::with_language(new_name, self.language).collect();
+        if tokens != [Token::Ident(String::from(new_name))] || new_name.contains('.') {
+            return Err(RenameError::InvalidName(String::from(new_name)));
+        }
+        if new_name != name {
+            if let Some(location) = self.definitions.get(new_name).and_then(|l| l.first()) {
+                return Err(RenameError::Collision(String::from(new_name), *location));
+            }
+        }
+
+        let mut spans: Vec<Span> = definitions.iter().map(|location| location.name_span).collect();
+        spans.extend_from_slice(self.references(name));
+        spans.sort_by_key(|span| (span.file, span.start));
+
+        Ok(spans.into_iter()
+            .map(|span| TextEdit { span, replacement: String::from(new_name) })
+            .collect())
+    }
+}
+
+/// Why a type can't be renamed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    /// The type isn't defined in any file, like the built-in ones.
+    Undefined(String),
+    /// The new name isn't a name, like `2d` or a keyword.
+    InvalidName(String),
+    /// There's already a type with the new name, defined there.
+    Collision(String, Location),
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RenameError::Undefined(ref name) => write!(f, "`{}` isn't defined in any file", name),
+            RenameError::InvalidName(ref name) => write!(f, "`{}` isn't a valid name", name),
+            RenameError::Collision(ref name, _) => write!(f, "`{}` is already defined", name),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for RenameError {}
+
+/// The edits that rename the type `name` in every file. See
+/// [`Index::rename`][0].
+///
+/// [0]: struct.Index.html#method.rename
+pub fn rename(source_map: &SourceMap, name: &str, new_name: &str) -> Result<Vec<TextEdit>, RenameError> {
+    Index::new(source_map).rename(name, new_name)
+}
+
+/// Where the type at `position` is defined, with the keywords in the default
+/// language. See [`definition_at_with_language`][0].
+///
+/// [0]: fn.definition_at_with_language.html
+pub fn definition_at(source_map: &SourceMap, position: Position) -> Option<Location> {
+    definition_at_with_language(source_map, position, Language::default())
+}
+
+/// Where the type at `position` is defined: the type of a parameter or the
+/// name of a definition, which is its own. A type defined in the file of the
+/// position wins over the rest. `None` if there's no type there or it isn't
+/// defined anywhere, like the built-in ones.
+///
+/// # Examples
+///
+///     use simcom::ide::{definition_at, Position};
+///     use simcom::source_map::SourceMap;
+///
+///     let mut sources = SourceMap::new();
+///     let a = sources.add("a.sc", "tipo Linea(a: Punto, b: Punto);");
+///     let b = sources.add("b.sc", "tipo Punto(x: Entero);");
+///
+///     let location = definition_at(&sources, Position::new(a, 15)).unwrap();
+///     assert_eq!(location.name_span.file, b);
+///     assert_eq!(location.name_span.range(), 5..10);
+///
+///     assert_eq!(definition_at(&sources, Position::new(b, 16)), None);
+pub fn definition_at_with_language(source_map: &SourceMap, position: Position, language: Language) -> Option<Location> {
+    let index = Index::with_language(source_map, language);
+    index.definition(index.name_at(position)?, position.file)
+}
+
+/// Every place where the type at `position` is the type of a parameter, in
+/// any file. Empty if there's no type there.
+///
+/// # Examples
+///
+///     use simcom::ide::{references_at, Position};
+///     use simcom::source_map::SourceMap;
+///
+///     let mut sources = SourceMap::new();
+///     let a = sources.add("a.sc", "tipo Punto(x: Entero);");
+///     let b = sources.add("b.sc", "tipo Linea(a: Punto, b: Punto);");
+///
+///     let references = references_at(&sources, Position::new(a, 5));
+///     assert_eq!(references.len(), 2);
+///     assert_eq!(references[1].file, b);
+///     assert_eq!(references[1].range(), 24..29);
+pub fn references_at(source_map: &SourceMap, position: Position) -> Vec<Span> {
+    let index = Index::new(source_map);
+    match index.name_at(position) {
+        Some(name) => index.references(name).to_vec(),
+        None => Vec::new(),
+    }
+}
+
+/// What a completion candidate is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A type defined in one of the files.
+    Type,
+    /// A built-in type.
+    Builtin,
+}
+
+/// Something that can be written at the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    /// The doc comment of a defined type or, for built-ins, what they are in
+    /// the generated code.
+    pub documentation: Option<String>,
+}
+
+/// What [`completions_at`][0] may suggest.
+///
+/// [0]: fn.completions_at.html
+#[derive(Debug, Clone, Default)]
+pub struct CompletionOptions {
+    pub language: Language,
+    /// Built-in types, with their name in the generated code, like
+    /// `codegen::default_builtins()`. None by default.
+    pub builtins: BTreeMap<String, String>,
+    /// Leave out the type whose parameters are being written, since a type
+    /// that contains itself is a cycle.
+    pub exclude_enclosing: bool,
+}
+
+/// The type whose parameter list has the cursor, when the cursor is where
+/// the type of a parameter goes: right after a `:`, maybe with part of the
+/// name already written. `Some(None)` if the type has no name yet.
+fn type_position(file: &SourceFile, offset: usize, language: Language) -> Option<Option<String>> {
+    let mut before: Vec<(Token, Span)> = Lexer::with_language(&file.content, language).spanned()
+        .take_while(|&(ref token, span)| *token != Token::EOF && span.start < offset)
+        .collect();
+
+    // A name that is being written.
+    if let Some(&(Token::Ident(_), span)) = before.last() {
+        if offset <= span.end {
+            before.pop();
+        }
+    }
+
+    match before.last() {
+        Some(&(Token::Colon, span)) if span.end <= offset => {},
+        _ => return None,
+    }
+
+    for (i, (token, _)) in before.iter().enumerate().rev() {
+        match *token {
+            Token::ParL => {
+                let name = match i.checked_sub(1).map(|i| &before[i].0) {
+                    Some(Token::Ident(name)) => Some(name.clone()),
+                    _ => None,
+                };
+                return Some(name);
+            },
+            Token::ParR | Token::Semicolon | Token::Type | Token::Interface | Token::Enum => return None,
+            _ => {},
+        }
+    }
+    None
+}
+
+/// The types that can be written at `position`, if it's where the type of a
+/// parameter goes, sorted by name. Defined types come with their doc comment
+/// and hide built-ins with the same name, and they count even if their
+/// definition has errors. Empty anywhere else.
+///
+/// # Examples
+///
+///     use simcom::codegen::default_builtins;
+///     use simcom::ide::{completions_at, CompletionKind, CompletionOptions, Position};
+///     use simcom::source_map::SourceMap;
+///
+///     let mut sources = SourceMap::new();
+///     let a = sources.add("a.sc", "/// Un punto.\ntipo Punto(x: Entero);\ntipo Linea(a: P");
+///
+///     let options = CompletionOptions { builtins: default_builtins(), ..CompletionOptions::default() };
+///     let completions = completions_at(&sources, Position::new(a, 52), &options);
+///     let labels: Vec<&str> = completions.iter().map(|c| &c.label[..]).collect();
+///     assert_eq!(labels, ["Caracter", "Entero", "Linea", "Logico", "Punto", "Real"]);
+///
+///     assert_eq!(completions[4].kind, CompletionKind::Type);
+///     assert_eq!(completions[4].documentation.as_deref(), Some("Un punto."));
+///     assert!(completions_at(&sources, Position::new(a, 47), &options).is_empty());
+pub fn completions_at(source_map: &SourceMap, position: Position, options: &CompletionOptions) -> Vec<Completion> {
+    let file = match source_map.get(position.file) {
+        Some(file) => file,
+        None => return Vec::new(),
+    };
+    let enclosing = match type_position(file, position.offset, options.language) {
+        Some(enclosing) => enclosing,
+        None => return Vec::new(),
+    };
+
+    let mut candidates = BTreeMap::new();
+    for (name, target) in &options.builtins {
+        let documentation = format!("Built-in, `{}` in the generated code.", target);
+        candidates.insert(name.clone(), (CompletionKind::Builtin, Some(documentation)));
+    }
+
+    // The definition being written doesn't parse yet, and maybe others
+    // don't either, so names come from every `tipo Name` and docs from the
+    // definitions that parse. The file of the cursor goes last, so its docs
+    // win.
+    let others = source_map.iter().filter(|other| other.id != file.id);
+    for file in others.chain(Some(file)) {
+        let tokens: Vec<Token> = Lexer::with_language(&file.content, options.language).collect();
+        for pair in tokens.windows(2) {
+            if let (Token::Type | Token::Enum, Token::Ident(name)) = (&pair[0], &pair[1]) {
+                if candidates.get(name).map(|c| c.0) != Some(CompletionKind::Type) {
+                    candidates.insert(name.clone(), (CompletionKind::Type, None));
+                }
+            }
+        }
+
+        for definition in definitions(file, options.language).filter(|d| !d.docs.is_empty()) {
+            candidates.insert(definition.name.to_string(), (CompletionKind::Type, Some(definition.docs.join("\n"))));
+        }
+    }
+
+    if options.exclude_enclosing {
+        if let Some(ref enclosing) = enclosing {
+            candidates.remove(enclosing);
+        }
+    }
+
+    candidates.into_iter()
+        .map(|(label, (kind, documentation))| Completion { label, kind, documentation })
+        .collect()
+}
+
+/// What a symbol of the outline is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Type,
+    Interface,
+    Enum,
+    Field,
+    Variant,
+}
+
+/// An entry of the outline of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    /// The type of a field, or the value of a variant.
+    pub detail: Option<String>,
+    pub kind: SymbolKind,
+    /// The whole definition or field.
+    pub span: Span,
+    pub name_span: Span,
+    /// The fields of a type or an interface, or the variants of an
+    /// enumeration, in order.
+    pub children: Vec<Symbol>,
+}
+
+impl<'a, 'src> From<&'a TypeDefinition<'src>> for Symbol {
+    fn from(definition: &TypeDefinition) -> Symbol {
+        let fields = definition.parameters.iter()
+            .map(|parameter| Symbol {
+                name: parameter.name.to_string(),
+                detail: Some(parameter.typename.to_string()),
+                kind: SymbolKind::Field,
+                span: parameter.span,
+                name_span: parameter.name_span,
+                children: Vec::new(),
+            });
+        let variants = definition.variants.iter().zip(definition.values())
+            .map(|(variant, value)| Symbol {
+                name: variant.name.to_string(),
+                detail: Some(value.to_string()),
+                kind: SymbolKind::Variant,
+                span: variant.span,
+                name_span: variant.name_span,
+                children: Vec::new(),
+            });
+        let children = fields.chain(variants).collect();
+
+        Symbol {
+            name: definition.name.to_string(),
+            detail: None,
+            kind: match definition.kind {
+                DefinitionKind::Type => SymbolKind::Type,
+                DefinitionKind::Interface => SymbolKind::Interface,
+                DefinitionKind::Enum => SymbolKind::Enum,
+            },
+            span: definition.span,
+            name_span: definition.name_span,
+            children,
+        }
+    }
+}
+
+/// Every definition of `source` that parses, in order, with its fields as
+/// children.
+///
+/// # Examples
+///
+///     use simcom::ide::{outline, SymbolKind};
+///     use simcom::lexer::Language;
+///
+///     let symbols = outline("tipo Punto(x: Entero, y: Entero);\ntipo A(b B);", Language::Spanish);
+///     assert_eq!(symbols.len(), 1);
+///     assert_eq!(symbols[0].name, "Punto");
+///     assert_eq!(symbols[0].kind, SymbolKind::Type);
+///
+///     let field = &symbols[0].children[1];
+///     assert_eq!((&field.name[..], field.detail.as_deref()), ("y", Some("Entero")));
+///     assert_eq!(field.span.range(), 22..31);
+pub fn outline(source: &str, language: Language) -> Vec<Symbol> {
+    Parser::new(Lexer::with_language(source, language))
+        .filter_map(|ast| match ast {
+            Ast::TypeDefinition(ref definition) => Some(Symbol::from(definition)),
+            Ast::Unexpected(..) => None,
+        })
+        .collect()
+}
+
+/// What can be folded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldingKind {
+    /// A whole type definition, from its annotations to the `;`.
+    Definition,
+    /// Doc comments on consecutive lines.
+    Comment,
+}
+
+/// A part of a file that editors can collapse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldingRange {
+    pub span: Span,
+    pub kind: FoldingKind,
+}
+
+/// What can be folded in `source`, sorted by where it starts: every
+/// definition that parses and every run of doc comments, when they take more
+/// than one line. A comment run can be inside a definition.
+///
+/// # Examples
+///
+///     use simcom::ide::{folding_ranges, FoldingKind};
+///     use simcom::lexer::Language;
+///
+///     let source = "/// Un punto.\n/// En el plano.\ntipo Punto(\n    x: Entero,\n    y: Entero,\n);\ntipo A(b: Punto);";
+///     let ranges = folding_ranges(source, Language::Spanish);
+///
+///     assert_eq!(ranges.len(), 2);
+///     assert_eq!(ranges[0].kind, FoldingKind::Comment);
+///     assert_eq!(&source[ranges[0].span.range()], "/// Un punto.\n/// En el plano.");
+///     assert_eq!(ranges[1].kind, FoldingKind::Definition);
+///     assert!(source[ranges[1].span.range()].starts_with("tipo Punto("));
+pub fn folding_ranges(source: &str, language: Language) -> Vec<FoldingRange> {
+    let mut ranges = Vec::new();
+    let multiline = |span: Span| source[span.range()].contains('\n');
+
+    let mut run: Option<Span> = None;
+    for (token, span) in Lexer::with_language(source, language).spanned() {
+        let last = match (token, run) {
+            (Token::DocComment(_), Some(last)) if source[last.end..span.start].matches('\n').count() == 1 => {
+                run = Some(Span { end: span.end, ..last });
+                continue;
+            },
+            (Token::DocComment(_), last) => {
+                run = Some(span);
+                last
+            },
+            (_, last) => {
+                run = None;
+                last
+            },
+        };
+        if let Some(last) = last.filter(|&last| multiline(last)) {
+            ranges.push(FoldingRange { span: last, kind: FoldingKind::Comment });
+        }
+    }
+
+    for ast in Parser::new(Lexer::with_language(source, language)) {
+        if let Ast::TypeDefinition(definition) = ast {
+            if multiline(definition.span) {
+                ranges.push(FoldingRange { span: definition.span, kind: FoldingKind::Definition });
+            }
+        }
+    }
+
+    ranges.sort_by_key(|range| (range.span.start, range.span.end));
+    ranges
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::{Language, Span};
+    use super::super::source_map::SourceMap;
+    use super::{completions_at, definition_at, definition_at_with_language, references_at};
+    use super::{folding_ranges, outline, rename, CompletionKind, CompletionOptions, FoldingKind, Index, Location};
+    use super::{Position, RenameError, SymbolKind};
+
+    #[test]
+    fn same_file() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.sc", "tipo A(x: B);\ntipo B(y: A);");
+
+        let b = Location { name_span: Span::new(19, 20).in_file(a), span: Span::new(14, 27).in_file(a) };
+        assert_eq!(definition_at(&sources, Position::new(a, 10)), Some(b));
+        // Right after the name, and on the name of the definition itself.
+        assert_eq!(definition_at(&sources, Position::new(a, 11)), Some(b));
+        assert_eq!(definition_at(&sources, Position::new(a, 19)), Some(b));
+        // On a field name or punctuation.
+        assert_eq!(definition_at(&sources, Position::new(a, 7)), None);
+        assert_eq!(definition_at(&sources, Position::new(a, 12)), None);
+    }
+
+    #[test]
+    fn the_same_file_goes_first() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.sc", "tipo P(x: Entero);");
+        let b = sources.add("b.sc", "tipo Q(p: P);\ntipo P(y: Entero);");
+
+        let location = definition_at(&sources, Position::new(b, 10)).unwrap();
+        assert_eq!(location.name_span, Span::new(19, 20).in_file(b));
+        let location = definition_at(&sources, Position::new(a, 5)).unwrap();
+        assert_eq!(location.name_span.file, a);
+    }
+
+    #[test]
+    fn broken_files() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.sc", "tipo A(x B);\ntipo C(y: D);");
+        let b = sources.add("b.sc", "tipo D(z: Entero);");
+
+        // The definitions after an error are still there.
+        let location = definition_at(&sources, Position::new(a, 23)).unwrap();
+        assert_eq!(location.span, Span::new(0, 18).in_file(b));
+        assert_eq!(definition_at(&sources, Position::new(a, 9)), None);
+    }
+
+    #[test]
+    fn english_keywords() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.sc", "type A(x: A);");
+        assert!(definition_at(&sources, Position::new(a, 10)).is_none());
+        let location = definition_at_with_language(&sources, Position::new(a, 10), Language::English);
+        assert_eq!(location.unwrap().name_span, Span::new(5, 6).in_file(a));
+    }
+
+    #[test]
+    fn references() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.sc", "tipo P(x: Entero);\ntipo Q(p: P, q: Q);");
+        let b = sources.add("b.sc", "tipo R(p: P);");
+
+        let index = Index::new(&sources);
+        assert_eq!(index.references("P"), [Span::new(29, 30).in_file(a), Span::new(10, 11).in_file(b)]);
+        assert_eq!(index.references("Entero"), [Span::new(10, 16).in_file(a)]);
+        assert_eq!(index.references("R"), []);
+        assert_eq!(index.references("Nada"), []);
+
+        // From a definition, a reference or the name of a field.
+        assert_eq!(references_at(&sources, Position::new(a, 5)).len(), 2);
+        assert_eq!(references_at(&sources, Position::new(b, 11)).len(), 2);
+        assert_eq!(references_at(&sources, Position::new(a, 36)), [Span::new(35, 36).in_file(a)]);
+        assert_eq!(references_at(&sources, Position::new(a, 7)), []);
+    }
+
+    fn labels(source: &str, offset: usize, options: &CompletionOptions) -> Vec<String> {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.sc", source);
+        completions_at(&sources, Position::new(a, offset), options).into_iter().map(|c| c.label).collect()
+    }
+
+    #[test]
+    fn where_types_go() {
+        let options = CompletionOptions::default();
+        let source = "tipo A(x: B, y: ); tipo B(z:";
+        assert_eq!(labels(source, 10, &options), ["A", "B"]);
+        assert_eq!(labels(source, 11, &options), ["A", "B"]);
+        assert_eq!(labels(source, 16, &options), ["A", "B"]);
+        assert_eq!(labels(source, 28, &options), ["A", "B"]);
+        // After the name of a field, a comma or outside of the parameters.
+        assert!(labels(source, 8, &options).is_empty());
+        assert!(labels(source, 12, &options).is_empty());
+        assert!(labels(source, 19, &options).is_empty());
+        assert!(labels("tipo A: ", 8, &options).is_empty());
+    }
+
+    #[test]
+    fn without_the_enclosing_type() {
+        let mut options = CompletionOptions { exclude_enclosing: true, ..CompletionOptions::default() };
+        options.builtins.insert(String::from("Entero"), String::from("long"));
+        assert_eq!(labels("tipo A(x: B, y: ); tipo B(z:", 16, &options), ["B", "Entero"]);
+        assert_eq!(labels("tipo A(x: B, y: ); tipo B(z:", 28, &options), ["A", "Entero"]);
+    }
+
+    #[test]
+    fn defined_types_hide_builtins() {
+        let mut sources = SourceMap::new();
+        sources.add("b.sc", "/// Otro.\ntipo Entero(x: Real);");
+        let a = sources.add("a.sc", "tipo A(x: ");
+
+        let mut options = CompletionOptions::default();
+        options.builtins.insert(String::from("Entero"), String::from("long"));
+        options.builtins.insert(String::from("Real"), String::from("double"));
+        let completions = completions_at(&sources, Position::new(a, 10), &options);
+        assert_eq!(completions[0].label, "A");
+        assert_eq!(completions[0].documentation, None);
+        assert_eq!(completions[1].label, "Entero");
+        assert_eq!(completions[1].kind, CompletionKind::Type);
+        assert_eq!(completions[1].documentation.as_deref(), Some("Otro."));
+        assert_eq!(completions[2].kind, CompletionKind::Builtin);
+        assert_eq!(completions[2].documentation.as_deref(), Some("Built-in, `double` in the generated code."));
+    }
+
+    #[test]
+    fn renames() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.sc", "tipo P(p: P, q: Q);\ntipo Q(x: Entero);");
+        let b = sources.add("b.sc", "tipo R(p: P);");
+
+        let spans: Vec<Span> = rename(&sources, "P", "Punto").unwrap().into_iter().map(|e| e.span).collect();
+        assert_eq!(spans, [Span::new(5, 6).in_file(a), Span::new(10, 11).in_file(a), Span::new(10, 11).in_file(b)]);
+        assert_eq!(rename(&sources, "P", "P").unwrap().len(), 3);
+
+        let q = Location { name_span: Span::new(25, 26).in_file(a), span: Span::new(20, 38).in_file(a) };
+        assert_eq!(rename(&sources, "P", "Q"), Err(RenameError::Collision(String::from("Q"), q)));
+        for name in &["", "2d", "tipo", "A B", "A;"] {
+            assert_eq!(rename(&sources, "P", name), Err(RenameError::InvalidName(String::from(*name))));
+        }
+    }
+
+    #[test]
+    fn folding() {
+        let source = "/// Uno.\n\n/// Dos.\n/// Tres.\n@obsoleto\ntipo A(\n    /// Cuatro.\n    /// Cinco.\n    x: Entero,\n);\n\
+                      tipo B(x: A); tipo C(\nx B);";
+        let ranges: Vec<(FoldingKind, &str)> = folding_ranges(source, Language::Spanish).into_iter()
+            .map(|range| (range.kind, &source[range.span.range()]))
+            .collect();
+
+        assert_eq!(ranges, [
+            (FoldingKind::Comment, "/// Dos.\n/// Tres."),
+            (FoldingKind::Definition, "@obsoleto\ntipo A(\n    /// Cuatro.\n    /// Cinco.\n    x: Entero,\n);"),
+            (FoldingKind::Comment, "/// Cuatro.\n    /// Cinco."),
+        ]);
+        assert!(folding_ranges("", Language::Spanish).is_empty());
+    }
+
+    #[test]
+    fn outlines() {
+        let source = "tipo B(x: Entero);\n/// Docs.\n@obsoleto tipo A(@otra b: B, c: C);\ntipo R(;";
+        let symbols = outline(source, Language::Spanish);
+
+        let names: Vec<&str> = symbols.iter().map(|s| &s.name[..]).collect();
+        assert_eq!(names, ["B", "A"]);
+        let a = &symbols[1];
+        assert_eq!(&source[a.span.range()], "@obsoleto tipo A(@otra b: B, c: C);");
+        assert_eq!(&source[a.name_span.range()], "A");
+        assert_eq!(a.detail, None);
+        assert_eq!(a.children.iter().map(|c| &source[c.span.range()]).collect::<Vec<_>>(), ["@otra b: B", "c: C"]);
+        assert!(a.children.iter().all(|c| c.kind == SymbolKind::Field && c.children.is_empty()));
+    }
+
+    #[test]
+    fn interfaces() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.sc", "interfaz N(n: T);\ntipo A(n: T) implementa N;");
+
+        assert_eq!(references_at(&sources, Position::new(a, 9)), [Span::new(42, 43).in_file(a)]);
+        assert_eq!(definition_at(&sources, Position::new(a, 42)).unwrap().name_span, Span::new(9, 10).in_file(a));
+        // Interfaces can't be the type of a field.
+        assert_eq!(labels("interfaz N(n: T); tipo A(n: ", 28, &CompletionOptions::default()), ["A"]);
+
+        let kinds: Vec<SymbolKind> = outline(&sources.get(a).unwrap().content, Language::Spanish).into_iter()
+            .map(|symbol| symbol.kind)
+            .collect();
+        assert_eq!(kinds, [SymbolKind::Interface, SymbolKind::Type]);
+    }
+
+    #[test]
+    fn enums() {
+        let symbols = outline("enum Color(Rojo, Verde = 4, Azul);
+tipo A(c: ", Language::Spanish);
+        assert_eq!(symbols[0].kind, SymbolKind::Enum);
+        let variants: Vec<(&str, Option<&str>)> = symbols[0].children.iter()
+            .map(|c| (&c.name[..], c.detail.as_ref().map(|d| &d[..])))
+            .collect();
+        assert_eq!(variants, [("Rojo", Some("0")), ("Verde", Some("4")), ("Azul", Some("5"))]);
+        assert!(symbols[0].children.iter().all(|c| c.kind == SymbolKind::Variant));
+
+        assert_eq!(labels("enum Color(Rojo); tipo A(c: ", 28, &CompletionOptions::default()), ["A", "Color"]);
+    }
+}
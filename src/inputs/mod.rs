@@ -0,0 +1,305 @@
+//! Input discovery module.
+//!
+//! Inputs can be plain files, directories or glob patterns. This module turns
+//! all of them into a list of files. Directories are walked recursively
+//! looking for files with one of the given extensions.
+//!
+//! The order of the resulting list only depends on the names of the files, not
+//! on the order the operating system happens to return them, so the same tree
+//! always compiles the same way.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// The extension of the files in our language.
+pub const DEFAULT_EXTENSION: &str = "sc";
+
+/// Expands every input into the files it stands for.
+///
+/// - A file is returned as is, whatever its extension.
+/// - A directory is replaced by every file inside it (recursively) whose
+///   extension is one of `extensions`, sorted by path.
+/// - A path with `*`, `?` or `**` components is matched against the file
+///   system, and the files it matches are sorted by path too. Matched
+///   directories are expanded like above.
+///
+/// Links to directories found on the way aren't followed, since one to a
+/// parent would have the same files read again and again.
+///
+/// Inputs are expanded in the given order and a file is only returned once,
+/// even if several inputs include it. A glob without matches or a path that
+/// doesn't exist is an error, since it's most likely a typo.
+pub fn expand(inputs: &[PathBuf], extensions: &[String]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+
+    for input in inputs {
+        let mut found = Vec::new();
+
+        if is_glob(input) {
+            glob(input, extensions, &mut found)?;
+            // `**` finds the files of a directory after those of its
+            // subdirectories.
+            found.sort();
+            if found.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::NotFound,
+                    format!("{}: no files match this pattern", input.display())));
+            }
+        } else if input.is_dir() {
+            collect(input, extensions, &mut found)?;
+        } else if input.is_file() {
+            found.push(input.clone());
+        } else {
+            return Err(io::Error::new(io::ErrorKind::NotFound,
+                format!("{}: no such file or directory", input.display())));
+        }
+
+        for file in found {
+            if seen.insert(file.clone()) {
+                files.push(file);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Does `ch` have a special meaning in a glob?
+fn is_wildcard(ch: char) -> bool {
+    ch == '*' || ch == '?'
+}
+
+fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().chars().any(is_wildcard)
+}
+
+/// Pushes every file under `dir` with a matching extension, sorted.
+fn collect(dir: &Path, extensions: &[String], out: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in sorted_entries(dir)? {
+        if is_dir_link(&entry) {
+            continue;
+        } else if entry.is_dir() {
+            collect(&entry, extensions, out)?;
+        } else if has_extension(&entry, extensions) {
+            out.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+/// Is `path` a symbolic link to a directory?
+fn is_dir_link(path: &Path) -> bool {
+    let link = fs::symlink_metadata(path).map(|m| m.file_type().is_symlink()).unwrap_or(false);
+    link && path.is_dir()
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    match path.extension() {
+        Some(ext) => extensions.iter().any(|e| ext == &e[..]),
+        None => false,
+    }
+}
+
+/// The contents of `dir`, sorted by name.
+fn sorted_entries(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    Ok(entries)
+}
+
+/// Expands a glob pattern.
+///
+/// The components before the first one with a wildcard are the directory the
+/// search starts from, the rest are matched one by one while walking down.
+fn glob(pattern: &Path, extensions: &[String], out: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut base = PathBuf::new();
+    let mut rest = Vec::new();
+
+    for component in pattern.components() {
+        let text = match component {
+            Component::Normal(text) => text.to_string_lossy().into_owned(),
+            other => {
+                if rest.is_empty() {
+                    base.push(other.as_os_str());
+                    continue;
+                }
+                other.as_os_str().to_string_lossy().into_owned()
+            },
+        };
+
+        if rest.is_empty() && !text.chars().any(is_wildcard) {
+            base.push(text);
+        } else {
+            rest.push(text);
+        }
+    }
+
+    walk(&base, &rest, extensions, out)
+}
+
+fn walk(path: &Path, rest: &[String], extensions: &[String], out: &mut Vec<PathBuf>) -> io::Result<()> {
+    let (head, tail) = match rest.split_first() {
+        Some(split) => split,
+        None => {
+            if path.is_dir() {
+                return collect(path, extensions, out);
+            } else if path.is_file() {
+                out.push(path.to_path_buf());
+            }
+            return Ok(());
+        },
+    };
+
+    // Reading the empty path fails, it means the working directory.
+    let dir = if path.as_os_str().is_empty() { Path::new(".") } else { path };
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    for entry in sorted_entries(dir)? {
+        let name = match entry.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        // Keep the paths as the user wrote them, without a leading `./`.
+        let entry = path.join(&name);
+        if is_dir_link(&entry) {
+            continue;
+        }
+
+        if head == "**" {
+            // `**` matches any number of directories, including none. The
+            // "none" case is handled below, after the loop.
+            if entry.is_dir() {
+                walk(&entry, rest, extensions, out)?;
+            }
+        } else if matches(head, &name) {
+            walk(&entry, tail, extensions, out)?;
+        }
+    }
+
+    if head == "**" {
+        walk(path, tail, extensions, out)?;
+    }
+
+    Ok(())
+}
+
+/// Matches a file name against a pattern where `*` stands for any number of
+/// characters and `?` for exactly one.
+///
+/// # Examples
+///
+///     use simcom::inputs::matches;
+///
+///     assert!(matches("*.sc", "geometria.sc"));
+///     assert!(matches("p?nto*", "punto.sc"));
+///     assert!(!matches("*.sc", "geometria.h"));
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    matches_chars(&pattern, &name)
+}
+
+fn matches_chars(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => {
+            (0..=name.len()).any(|skip| matches_chars(rest, &name[skip..]))
+        },
+        Some(('?', rest)) => !name.is_empty() && matches_chars(rest, &name[1..]),
+        Some((ch, rest)) => {
+            name.first() == Some(ch) && matches_chars(rest, &name[1..])
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::env;
+
+    use super::*;
+
+    /// Builds a little tree of files in a fresh temporary directory.
+    fn tree(name: &str, files: &[&str]) -> PathBuf {
+        let root = env::temp_dir().join(format!("simcom-inputs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        for file in files {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        root
+    }
+
+    fn sc() -> Vec<String> {
+        vec![String::from(DEFAULT_EXTENSION)]
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "aXXbYc"));
+        assert!(!matches("a*b*c", "aXXbY"));
+        assert!(matches("???.sc", "abc.sc"));
+        assert!(!matches("??.sc", "abc.sc"));
+    }
+
+    #[test]
+    fn directory_is_recursive_and_sorted() {
+        let root = tree("dir", &["b.sc", "a/z.sc", "a/notes.txt", "c/d/e.sc"]);
+        let files = expand(std::slice::from_ref(&root), &sc()).unwrap();
+
+        assert_eq!(files, vec![
+            root.join("a/z.sc"),
+            root.join("b.sc"),
+            root.join("c/d/e.sc"),
+        ]);
+    }
+
+    #[test]
+    fn globs() {
+        let root = tree("glob", &["x/a.sc", "x/b.txt", "y/z/c.sc", "d.sc"]);
+
+        let files = expand(&[root.join("*/*.sc")], &sc()).unwrap();
+        assert_eq!(files, vec![root.join("x/a.sc")]);
+
+        let files = expand(&[root.join("**/*.sc")], &sc()).unwrap();
+        assert_eq!(files, vec![
+            root.join("d.sc"),
+            root.join("x/a.sc"),
+            root.join("y/z/c.sc"),
+        ]);
+        assert_eq!(files, expand(std::slice::from_ref(&root), &sc()).unwrap());
+
+        assert!(expand(&[root.join("*.h")], &sc()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_to_directories_are_not_followed() {
+        let root = tree("link", &["d/a.sc"]);
+        std::os::unix::fs::symlink("..", root.join("d/loop")).unwrap();
+
+        assert_eq!(expand(std::slice::from_ref(&root), &sc()).unwrap(), vec![root.join("d/a.sc")]);
+        assert_eq!(expand(&[root.join("**/*.sc")], &sc()).unwrap(), vec![root.join("d/a.sc")]);
+    }
+
+    #[test]
+    fn no_duplicates_and_plain_files() {
+        let root = tree("dup", &["a.sc", "b.schema"]);
+        let files = expand(&[root.join("b.schema"), root.join("a.sc"), root.clone()], &sc()).unwrap();
+
+        assert_eq!(files, vec![root.join("b.schema"), root.join("a.sc")]);
+        assert!(expand(&[root.join("missing.sc")], &sc()).is_err());
+    }
+}
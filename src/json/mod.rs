@@ -0,0 +1,598 @@
+//! A small JSON module.
+//!
+//! Enough to talk to editors and to let other tools read what the compiler
+//! produces, without leaving the standard library. Objects keep their keys in
+//! insertion order, so the output is stable and easy to read.
+//!
+//! Tools that already use serde can turn on the `serde` feature instead: the
+//! tokens, the AST and `Semantic` then implement `Serialize` and
+//! `Deserialize`, and are written the same way as with `ToJson`.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::Peekable;
+use core::str::Chars;
+
+/// How many arrays and objects can be inside each other. Reading is
+/// recursive, so without a limit a malicious document could overflow the
+/// stack.
+const MAX_DEPTH: usize = 128;
+
+/// Any JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Builds an object from its pairs.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::json::Json;
+    ///
+    ///     let json = Json::object(vec![("name", Json::from("Punto")), ("fields", Json::from(2))]);
+    ///     assert_eq!(json.to_string(), r#"{"name":"Punto","fields":2}"#);
+    pub fn object(pairs: Vec<(&str, Json)>) -> Json {
+        Json::Object(pairs.into_iter()
+            .map(|(key, value)| (String::from(key), value))
+            .collect())
+    }
+
+    /// Reads a JSON document.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::json::Json;
+    ///
+    ///     let json = Json::parse(r#"{"id": 1, "params": {"uri": "a.sc"}}"#).unwrap();
+    ///     assert_eq!(json.get("id").and_then(Json::as_f64), Some(1.0));
+    ///     assert_eq!(json.pointer(&["params", "uri"]).and_then(Json::as_str), Some("a.sc"));
+    pub fn parse(input: &str) -> Result<Json, String> {
+        let mut reader = Reader { input: input.chars().peekable(), depth: 0 };
+        let value = reader.value()?;
+
+        reader.skip_whitespace();
+        match reader.input.next() {
+            None => Ok(value),
+            Some(ch) => Err(format!("unexpected `{}` after the value", ch)),
+        }
+    }
+
+    /// The value of `key`, if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(pairs) => pairs.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Follows a path of keys through nested objects.
+    pub fn pointer(&self, path: &[&str]) -> Option<&Json> {
+        path.iter().try_fold(self, |json, key| json.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Json {
+    fn from(s: &'a str) -> Json {
+        Json::String(String::from(s))
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Json {
+        Json::String(s)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Json {
+        Json::Bool(b)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<i32> for Json {
+    fn from(n: i32) -> Json {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(n: f64) -> Json {
+        Json::Number(n)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(values: Vec<Json>) -> Json {
+        Json::Array(values)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Json {
+        match value {
+            Some(value) => value.into(),
+            None => Json::Null,
+        }
+    }
+}
+
+/// Types that can be written as JSON.
+///
+/// The types of the compiler (the AST, the results of the analysis...) are
+/// laid out the way serde does by default: structs are objects with a key per
+/// field and enums are tagged with the name of the variant.
+///
+/// # Examples
+///
+///     use simcom::json::ToJson;
+///     use simcom::lexer::{Lexer, Token};
+///     use simcom::parser::Parser;
+///
+///     assert_eq!(Token::Semicolon.to_json().to_string(), r#""Semicolon""#);
+///
+///     let ast = Parser::new(Lexer::new("tipo A(x: Entero);")).next().unwrap();
+///     let json = ast.to_json();
+///     assert_eq!(json.pointer(&["TypeDefinition", "name"]).unwrap().as_str(), Some("A"));
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+/// Types that can be read back from what [`ToJson`][0] wrote.
+///
+/// Errors say which key had the wrong value, like "`parameters`: `name`:
+/// expected a string".
+///
+/// # Examples
+///
+///     use simcom::json::{FromJson, ToJson};
+///     use simcom::lexer::Lexer;
+///     use simcom::parser::{Ast, Parser};
+///
+///     let ast = Parser::new(Lexer::new("tipo A(x: Entero);")).next().unwrap();
+///     assert_eq!(Ast::from_json(&ast.to_json()), Ok(ast));
+///
+/// [0]: trait.ToJson.html
+pub trait FromJson: Sized {
+    fn from_json(json: &Json) -> Result<Self, String>;
+}
+
+impl Json {
+    /// Reads the value of `key` as a `T`.
+    pub fn field<T: FromJson>(&self, key: &str) -> Result<T, String> {
+        let value = match self {
+            Json::Object(_) => self.get(key).ok_or_else(|| format!("missing `{}`", key))?,
+            _ => return Err(String::from("expected an object")),
+        };
+        T::from_json(value).map_err(|e| format!("`{}`: {}", key, e))
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> Json {
+        Json::from(&self[..])
+    }
+}
+
+impl FromJson for String {
+    fn from_json(json: &Json) -> Result<String, String> {
+        json.as_str().map(String::from).ok_or_else(|| String::from("expected a string"))
+    }
+}
+
+impl ToJson for Cow<'_, str> {
+    fn to_json(&self) -> Json {
+        Json::from(&self[..])
+    }
+}
+
+/// Always owned, since the JSON goes away.
+impl FromJson for Cow<'static, str> {
+    fn from_json(json: &Json) -> Result<Cow<'static, str>, String> {
+        String::from_json(json).map(Cow::Owned)
+    }
+}
+
+impl ToJson for usize {
+    fn to_json(&self) -> Json {
+        Json::from(*self)
+    }
+}
+
+impl FromJson for usize {
+    fn from_json(json: &Json) -> Result<usize, String> {
+        match json.as_f64() {
+            Some(n) if n >= 0.0 && n == (n as usize) as f64 => Ok(n as usize),
+            _ => Err(String::from("expected a non-negative integer")),
+        }
+    }
+}
+
+impl ToJson for i64 {
+    fn to_json(&self) -> Json {
+        Json::Number(*self as f64)
+    }
+}
+
+impl FromJson for i64 {
+    fn from_json(json: &Json) -> Result<i64, String> {
+        match json.as_f64() {
+            Some(n) if n == (n as i64) as f64 => Ok(n as i64),
+            _ => Err(String::from("expected an integer")),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> Json {
+        Json::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(json: &Json) -> Result<Vec<T>, String> {
+        json.as_array()
+            .ok_or_else(|| String::from("expected an array"))?
+            .iter()
+            .enumerate()
+            .map(|(i, value)| T::from_json(value).map_err(|e| format!("[{}]: {}", i, e)))
+            .collect()
+    }
+}
+
+/// `None` is `null`.
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> Json {
+        match *self {
+            Some(ref value) => value.to_json(),
+            None => Json::Null,
+        }
+    }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(json: &Json) -> Result<Option<T>, String> {
+        match *json {
+            Json::Null => Ok(None),
+            ref value => T::from_json(value).map(Some),
+        }
+    }
+}
+
+/// Writes the value in its compact form.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => {
+                // Integers are written without a decimal point. Checking it
+                // with a cast instead of `fract` keeps this working without
+                // the standard library.
+                if -1e15 < *n && *n < 1e15 && *n == (*n as i64) as f64 {
+                    write!(f, "{}", *n as i64)
+                } else if n.is_finite() {
+                    write!(f, "{}", n)
+                } else {
+                    write!(f, "null")
+                }
+            },
+            Json::String(s) => write_string(f, s),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(pairs) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Reader<'a> {
+    input: Peekable<Chars<'a>>,
+    /// Arrays and objects being read.
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(&ch) = self.input.peek() {
+            if !ch.is_whitespace() {
+                break;
+            }
+            self.input.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.input.next() {
+            Some(ch) if ch == expected => Ok(()),
+            Some(ch) => Err(format!("expected `{}`, found `{}`", expected, ch)),
+            None => Err(format!("expected `{}`, found the end of the input", expected)),
+        }
+    }
+
+    fn word(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+
+        match self.input.peek().cloned() {
+            Some('n') => self.word("null", Json::Null),
+            Some('t') => self.word("true", Json::Bool(true)),
+            Some('f') => self.word("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => self.nested(Reader::array),
+            Some('{') => self.nested(Reader::object),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => self.number(),
+            Some(ch) => Err(format!("unexpected `{}`", ch)),
+            None => Err(String::from("unexpected end of the input")),
+        }
+    }
+
+    /// Reads an array or an object with `read`, unless there are already too
+    /// many of them around it.
+    fn nested(&mut self, read: fn(&mut Self) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("more than {} nested arrays and objects", MAX_DEPTH));
+        }
+
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let mut text = String::new();
+        while let Some(&ch) = self.input.peek() {
+            if !(ch.is_ascii_digit() || "+-.eE".contains(ch)) {
+                break;
+            }
+            text.push(ch);
+            self.input.next();
+        }
+
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| format!("invalid number `{}`", text))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut content = String::new();
+
+        loop {
+            match self.input.next() {
+                Some('"') => return Ok(content),
+                Some('\\') => match self.input.next() {
+                    Some('"') => content.push('"'),
+                    Some('\\') => content.push('\\'),
+                    Some('/') => content.push('/'),
+                    Some('b') => content.push('\u{8}'),
+                    Some('f') => content.push('\u{c}'),
+                    Some('n') => content.push('\n'),
+                    Some('r') => content.push('\r'),
+                    Some('t') => content.push('\t'),
+                    Some('u') => content.push(self.unicode_escape()?),
+                    Some(ch) => return Err(format!("unknown escape `\\{}`", ch)),
+                    None => break,
+                },
+                Some(ch) => content.push(ch),
+                None => break,
+            }
+        }
+
+        Err(String::from("unterminated string"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.input.next()
+                .and_then(|ch| ch.to_digit(16))
+                .ok_or_else(|| String::from("invalid unicode escape"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    /// The part after `\u`, including surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+
+        let code = if (0xd800..0xdc00).contains(&high) {
+            self.expect('\\')?;
+            self.expect('u')?;
+            let low = self.hex4()?;
+            0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff)
+        } else {
+            high
+        };
+
+        Ok(char::from_u32(code).unwrap_or('\u{fffd}'))
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+
+        self.skip_whitespace();
+        if let Some(']') = self.input.peek() {
+            self.input.next();
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.input.next() {
+                Some(',') => (),
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err(String::from("expected `,` or `]` in an array")),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut pairs = Vec::new();
+
+        self.skip_whitespace();
+        if let Some('}') = self.input.peek() {
+            self.input.next();
+            return Ok(Json::Object(pairs));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            pairs.push((key, value));
+
+            self.skip_whitespace();
+            match self.input.next() {
+                Some(',') => (),
+                Some('}') => return Ok(Json::Object(pairs)),
+                _ => return Err(String::from("expected `,` or `}` in an object")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fields() {
+        let json = Json::parse(r#"{"name":"A","fields":[1,-2]}"#).unwrap();
+
+        assert_eq!(json.field::<String>("name"), Ok(String::from("A")));
+        assert_eq!(json.field::<Vec<usize>>("fields"), Err(String::from("`fields`: [1]: expected a non-negative integer")));
+        assert_eq!(json.field::<String>("docs"), Err(String::from("missing `docs`")));
+        assert_eq!(Json::Null.field::<String>("name"), Err(String::from("expected an object")));
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = r#"{"a":[1,2.5,-3],"b":{"c":null,"d":true},"e":"x\"y\\z\n"}"#;
+        let json = Json::parse(text).unwrap();
+
+        assert_eq!(json.to_string(), text);
+    }
+
+    #[test]
+    fn whitespace_and_escapes() {
+        let json = Json::parse(" [ \"\\u00f1\\ud83d\\ude00\" , false ] ").unwrap();
+
+        assert_eq!(json, Json::Array(vec![
+            Json::from("ñ😀"),
+            Json::Bool(false),
+        ]));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("tru").is_err());
+        assert!(Json::parse("1 2").is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert_eq!(Json::parse(&deep).unwrap_err(), "more than 128 nested arrays and objects");
+
+        let fine = format!("{}1{}", "[{\"a\":".repeat(64), "}]".repeat(64));
+        assert!(Json::parse(&fine).is_ok());
+    }
+
+    #[test]
+    fn control_characters_are_escaped() {
+        assert_eq!(Json::from("\u{1}").to_string(), "\"\\u0001\"");
+    }
+}
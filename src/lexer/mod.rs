@@ -3,11 +3,104 @@
 //! The simpler implementation of a lexer I could think of. It only takes a
 //! stream of characters and tansforms it into a tokens one.
 //!
-//! It doesn't keep track of the current line or column. In a real compiler that
-//! information is crucial when you write something wrong and don't know where.
+//! It doesn't keep track of the current line or column, only of the byte
+//! offset of each token (its [`Span`][0]). That is enough to point at the
+//! place where you wrote something wrong: lines and columns can be computed
+//! from the offsets when an error is actually shown, with a
+//! [`SourceMap`][1].
+//!
+//! [0]: struct.Span.html
+//! [1]: ../source_map/struct.SourceMap.html
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::json::{FromJson, Json, ToJson};
+use super::source_map::FileId;
+
+/// A region of a file, as byte offsets. `end` is not included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Span {
+    /// The file the offsets are in.
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Create a new span from its offsets, in the default file.
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { file: FileId::default(), start, end }
+    }
+
+    /// The same span, in `file`.
+    pub fn in_file(self, file: FileId) -> Span {
+        Span { file, ..self }
+    }
+
+    /// The offsets, to slice the text of the file.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::Span;
+    ///
+    ///     let source = "tipo Punto();";
+    ///     assert_eq!(&source[Span::new(5, 10).range()], "Punto");
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The smallest span that contains both `self` and `other`, which should
+    /// be in the same file.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end)).in_file(self.file)
+    }
+
+    /// The line and column (both starting at 1) where the span starts in
+    /// `source`. Columns count characters, not bytes.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::Span;
+    ///
+    ///     let source = "tipo A(x: B);\ntipo ñ(y: B);";
+    ///     assert_eq!(Span::new(5, 6).position(source), (1, 6));
+    ///     assert_eq!(Span::new(24, 25).position(source), (2, 10));
+    pub fn position(&self, source: &str) -> (usize, usize) {
+        let before = &source[..self.start.min(source.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let column = before[line_start..].chars().count() + 1;
+
+        (line, column)
+    }
+}
 
-use std::iter::Peekable;
-use std::str::Chars;
+impl ToJson for Span {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("file", self.file.to_json()),
+            ("start", Json::from(self.start)),
+            ("end", Json::from(self.end)),
+        ])
+    }
+}
+
+impl FromJson for Span {
+    fn from_json(json: &Json) -> Result<Span, String> {
+        Ok(Span {
+            file: json.field("file")?,
+            start: json.field("start")?,
+            end: json.field("end")?,
+        })
+    }
+}
 
 /// Token types that our language admits.
 ///
@@ -16,15 +109,23 @@ use std::str::Chars;
 /// token iterator as the output.
 ///
 /// The traits are mostly for tests.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Token {
     /// For anything that we don't recognize.
     Illegal,
     /// Last token, when the input has ended.
     EOF,
 
-    /// Any word: a variable name, a type name...
+    /// Any word: a variable name, a type name... Names of types in a module
+    /// are qualified with it, `geometria.Punto`, and they are a single
+    /// identifier.
     Ident(String),
+    /// An integer, like `42` or `-1`, as it was written.
+    Number(String),
+    /// A text between double quotes, like `"m"`. The value doesn't have the
+    /// quotes, and its escapes (`\"` and `\\`) are resolved.
+    Text(String),
 
     /// Left parenthesis.
     ParL,
@@ -36,19 +137,247 @@ pub enum Token {
     Semicolon,
     /// The ',' character.
     Comma,
+    /// The '@' character, that starts an annotation.
+    At,
+    /// The '=' character, before the value of a variant.
+    Equals,
+    /// The '#' character, before the number of a field.
+    Hash,
 
-    /// The only keyword we have in the language.
+    /// The keyword that starts a type definition.
     Type,
+    /// The keyword that declares the module of the definitions after it.
+    Module,
+    /// The keyword that starts an interface definition.
+    Interface,
+    /// The keyword before the interfaces a type implements.
+    Implements,
+    /// The keyword that starts an enumeration.
+    Enum,
+
+    /// A documentation comment, `/// like this one`, up to the end of the
+    /// line. The content doesn't include the slashes nor the space after them.
+    DocComment(String),
+
+    /// A run of whitespace. Only produced by lexers that [preserve
+    /// trivia][0], the parser never sees it.
+    ///
+    /// [0]: struct.Lexer.html#method.preserving_trivia
+    Whitespace(String),
+}
+
+/// How the token is called in messages, like "`;`" or "end of file".
+/// Keywords are shown in Spanish, the default language.
+///
+/// # Examples
+///
+///     use simcom::lexer::Token;
+///
+///     assert_eq!(Token::Semicolon.to_string(), "`;`");
+///     assert_eq!(Token::Ident(String::from("Punto")).to_string(), "`Punto`");
+///     assert_eq!(Token::EOF.to_string(), "end of file");
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Illegal => write!(f, "unknown character"),
+            Token::EOF => write!(f, "end of file"),
+            Token::Ident(ref name) | Token::Number(ref name) => write!(f, "`{}`", name),
+            Token::Text(ref text) => write!(f, "`{}`", quote(text)),
+            Token::ParL => write!(f, "`(`"),
+            Token::ParR => write!(f, "`)`"),
+            Token::Colon => write!(f, "`:`"),
+            Token::Semicolon => write!(f, "`;`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::At => write!(f, "`@`"),
+            Token::Equals => write!(f, "`=`"),
+            Token::Hash => write!(f, "`#`"),
+            Token::Type => {
+                write!(f, "`{}`", Language::default().spelling(self).unwrap_or("tipo"))
+            },
+            Token::Module | Token::Interface | Token::Implements | Token::Enum => {
+                write!(f, "`{}`", Language::default().spelling(self).unwrap_or_default())
+            },
+            Token::DocComment(_) => write!(f, "doc comment"),
+            Token::Whitespace(_) => write!(f, "whitespace"),
+        }
+    }
+}
+
+/// Tokens without a value are written as the name of the variant, the rest as
+/// an object with the name as the key: `{"Ident":"Punto"}`.
+impl ToJson for Token {
+    fn to_json(&self) -> Json {
+        let (name, value) = match *self {
+            Token::Illegal => ("Illegal", None),
+            Token::EOF => ("EOF", None),
+            Token::Ident(ref value) => ("Ident", Some(value)),
+            Token::Number(ref value) => ("Number", Some(value)),
+            Token::Text(ref value) => ("Text", Some(value)),
+            Token::ParL => ("ParL", None),
+            Token::ParR => ("ParR", None),
+            Token::Colon => ("Colon", None),
+            Token::Semicolon => ("Semicolon", None),
+            Token::Comma => ("Comma", None),
+            Token::At => ("At", None),
+            Token::Equals => ("Equals", None),
+            Token::Hash => ("Hash", None),
+            Token::Type => ("Type", None),
+            Token::Module => ("Module", None),
+            Token::Interface => ("Interface", None),
+            Token::Implements => ("Implements", None),
+            Token::Enum => ("Enum", None),
+            Token::DocComment(ref value) => ("DocComment", Some(value)),
+            Token::Whitespace(ref value) => ("Whitespace", Some(value)),
+        };
+
+        match value {
+            Some(value) => Json::object(vec![(name, Json::from(&value[..]))]),
+            None => Json::from(name),
+        }
+    }
+}
+
+impl FromJson for Token {
+    fn from_json(json: &Json) -> Result<Token, String> {
+        let (name, value) = match *json {
+            Json::String(ref name) => (&name[..], None),
+            Json::Object(ref pairs) if pairs.len() == 1 => (&pairs[0].0[..], Some(&pairs[0].1)),
+            _ => return Err(String::from("expected a token")),
+        };
+
+        let token = match (name, value) {
+            ("Illegal", None) => Token::Illegal,
+            ("EOF", None) => Token::EOF,
+            ("Ident", Some(value)) => Token::Ident(String::from_json(value)?),
+            ("Number", Some(value)) => Token::Number(String::from_json(value)?),
+            ("Text", Some(value)) => Token::Text(String::from_json(value)?),
+            ("ParL", None) => Token::ParL,
+            ("ParR", None) => Token::ParR,
+            ("Colon", None) => Token::Colon,
+            ("Semicolon", None) => Token::Semicolon,
+            ("Comma", None) => Token::Comma,
+            ("At", None) => Token::At,
+            ("Equals", None) => Token::Equals,
+            ("Hash", None) => Token::Hash,
+            ("Type", None) => Token::Type,
+            ("Module", None) => Token::Module,
+            ("Interface", None) => Token::Interface,
+            ("Implements", None) => Token::Implements,
+            ("Enum", None) => Token::Enum,
+            ("DocComment", Some(value)) => Token::DocComment(String::from_json(value)?),
+            ("Whitespace", Some(value)) => Token::Whitespace(String::from_json(value)?),
+            _ => return Err(format!("unknown token `{}`", name)),
+        };
+        Ok(token)
+    }
+}
+
+/// `text` between double quotes, as the lexer reads it back.
+///
+/// # Examples
+///
+///     use simcom::lexer::quote;
+///
+///     assert_eq!(quote("m/s"), "\"m/s\"");
+///     assert_eq!(quote("a \"b\" \\"), "\"a \\\"b\\\" \\\\\"");
+pub fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The natural language the keywords are written in.
+///
+/// The language was born with Spanish keywords (`tipo`), but the same grammar
+/// can be written in English (`type`) for those who prefer it. Identifiers are
+/// never affected, only the reserved words.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Language {
+    /// Spanish keywords, the default one.
+    #[default]
+    Spanish,
+    /// English keywords.
+    English,
+}
+
+impl Language {
+    /// Looks up the language from its short code (`es` or `en`).
+    pub fn from_code(code: &str) -> Option<Language> {
+        match code {
+            "es" => Some(Language::Spanish),
+            "en" => Some(Language::English),
+            _ => None,
+        }
+    }
+
+    /// The short code of the language, the one `from_code` takes.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::Spanish => "es",
+            Language::English => "en",
+        }
+    }
+
+    /// Every keyword in this language, with its token.
+    fn keywords(self) -> &'static [(&'static str, Token)] {
+        match self {
+            Language::Spanish => &[
+                ("tipo", Token::Type),
+                ("modulo", Token::Module),
+                ("interfaz", Token::Interface),
+                ("implementa", Token::Implements),
+                ("enum", Token::Enum),
+            ],
+            Language::English => &[
+                ("type", Token::Type),
+                ("module", Token::Module),
+                ("interface", Token::Interface),
+                ("implements", Token::Implements),
+                ("enum", Token::Enum),
+            ],
+        }
+    }
+
+    /// Returns the keyword token for `word`, if it is one in this language.
+    fn keyword(self, word: &str) -> Option<Token> {
+        self.keywords().iter()
+            .find(|&&(spelling, _)| spelling == word)
+            .map(|(_, token)| token.clone())
+    }
+
+    /// How a keyword token is written in this language.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::{Language, Token};
+    ///
+    ///     assert_eq!(Language::Spanish.spelling(&Token::Type), Some("tipo"));
+    ///     assert_eq!(Language::English.spelling(&Token::Type), Some("type"));
+    ///     assert_eq!(Language::English.spelling(&Token::Colon), None);
+    pub fn spelling(self, token: &Token) -> Option<&'static str> {
+        self.keywords().iter()
+            .find(|&(_, keyword)| keyword == token)
+            .map(|&(spelling, _)| spelling)
+    }
 }
 
 /// The lexer in our language.
 ///
 /// The lexer, also known as tokenizer, transforms the input text into tokens.
 pub struct Lexer<'a> {
-    input: Peekable<Chars<'a>>,
+    /// The whole input, to get the text of a span back.
+    source: &'a str,
+    language: Language,
+    trivia: bool,
+    /// The file the input comes from.
+    file: FileId,
+    /// Byte offset of the next character.
+    offset: usize,
+    /// Byte offset where the last token started.
+    start: usize,
+    /// Byte offset of the beginning of `source`.
+    base: usize,
 }
 
-impl Lexer<'_> {
+impl<'a> Lexer<'a> {
     /// Create a new lexer.
     ///
     /// To build the lexer, you feed it a `str` with the code. Then you can use
@@ -64,66 +393,267 @@ impl Lexer<'_> {
     ///     assert_eq!(tokens.next(), None);
     ///
     /// [1]: enum.Token.html
-    pub fn new(input: &'_ str) -> Lexer {
-        Lexer { input: input.chars().peekable() }
+    pub fn new(input: &str) -> Lexer<'_> {
+        Lexer::with_language(input, Language::default())
     }
 
-    /// The next char in the input, advances the internal iterator.
+    /// Create a new lexer that recognizes the keywords of `language`.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::{Language, Lexer, Token};
+    ///
+    ///     let mut tokens = Lexer::with_language("type tipo", Language::English);
+    ///     assert_eq!(tokens.next().unwrap(), Token::Type);
+    ///     assert_eq!(tokens.next().unwrap(), Token::Ident(String::from("tipo")));
+    pub fn with_language(input: &str, language: Language) -> Lexer<'_> {
+        Lexer {
+            source: input,
+            language,
+            trivia: false,
+            file: FileId::default(),
+            offset: 0,
+            start: 0,
+            base: 0,
+        }
+    }
+
+    /// Makes the offsets of the tokens start at `offset` instead of 0.
+    ///
+    /// Useful when the input is a piece of a bigger text.
+    pub fn starting_at(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self.start = offset;
+        self.base = offset;
+        self
+    }
+
+    /// Makes the spans of the tokens point to `file`.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::{Lexer, Span};
+    ///     use simcom::source_map::SourceMap;
+    ///
+    ///     let mut sources = SourceMap::new();
+    ///     let id = sources.add("a.sc", "tipo");
+    ///
+    ///     let (_, span) = Lexer::new("tipo").in_file(id).spanned().next().unwrap();
+    ///     assert_eq!(span, Span::new(0, 4).in_file(id));
+    pub fn in_file(mut self, file: FileId) -> Self {
+        self.file = file;
+        self
+    }
+
+    /// Turns the lexer into an iterator over tokens and their spans.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::{Lexer, Span, Token};
+    ///
+    ///     let mut tokens = Lexer::new("tipo  Punto").spanned();
+    ///     assert_eq!(tokens.next().unwrap(), (Token::Type, Span::new(0, 4)));
+    ///     assert_eq!(tokens.next().unwrap(),
+    ///                (Token::Ident(String::from("Punto")), Span::new(6, 11)));
+    ///     assert_eq!(tokens.next().unwrap(), (Token::EOF, Span::new(11, 11)));
+    ///     assert_eq!(tokens.next(), None);
+    pub fn spanned(self) -> Spanned<'a> {
+        Spanned { lexer: self, done: false }
+    }
+
+    /// The span of the last token returned.
+    pub fn span(&self) -> Span {
+        Span::new(self.start, self.offset).in_file(self.file)
+    }
+
+    /// The text of the input that `span` covers. The span must come from
+    /// this lexer.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::{Lexer, Span};
+    ///
+    ///     let lexer = Lexer::new("ignorado tipo Punto").starting_at(100);
+    ///     assert_eq!(lexer.text(Span::new(114, 119)), "Punto");
+    pub fn text(&self, span: Span) -> &'a str {
+        &self.source[span.start - self.base..span.end - self.base]
+    }
+
+    /// Makes the lexer keep the trivia (whitespace) as tokens instead of
+    /// throwing it away.
+    ///
+    /// The parser has no use for it, but tools that rewrite the source (like
+    /// the formatter) need to know where the blank lines were.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::{Lexer, Token};
+    ///
+    ///     let mut tokens = Lexer::new(":\n\n;").preserving_trivia();
+    ///     assert_eq!(tokens.next().unwrap(), Token::Colon);
+    ///     assert_eq!(tokens.next().unwrap(), Token::Whitespace(String::from("\n\n")));
+    ///     assert_eq!(tokens.next().unwrap(), Token::Semicolon);
+    pub fn preserving_trivia(mut self) -> Self {
+        self.trivia = true;
+        self
+    }
+
+    /// The part of the input that hasn't been read yet.
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset - self.base..]
+    }
+
+    /// The next char in the input, advances the offset.
     fn read_char(&mut self) -> Option<char> {
-        self.input.next()
+        let ch = self.peek_char();
+        if let Some(ch) = ch {
+            self.offset += ch.len_utf8();
+        }
+        ch
     }
 
-    /// The next char in the input. *doesn't* advance the internal iterator.
-    fn peek_char(&mut self) -> Option<&char> {
-        self.input.peek()
+    /// The next char in the input. *doesn't* advance the offset.
+    fn peek_char(&self) -> Option<char> {
+        self.rest().chars().next()
     }
 
-    /// If an alphabetic char was found, keep reading chars to build a
-    /// identifier. Finally, look if it was a keyword.
-    fn read_identifier(&mut self, ch: char) -> Token {
-        let content = {
-            // Since in ::next_token we need to call ::read_char the first
-            // char was consumed, so we add it here.
-            let mut content = String::new();
-            content.push(ch);
+    /// Advances the offset over the longest run of ASCII bytes for which
+    /// `accept` is true, without decoding them as chars. Most input is
+    /// ASCII, so this does the bulk of the work and the char by char loops
+    /// only see what is left, if anything.
+    fn skip_ascii(&mut self, accept: fn(&u8) -> bool) {
+        let bytes = self.rest().as_bytes();
+        // ASCII bytes are never part of a multibyte char, so stopping at the
+        // first one not accepted always leaves the offset at a char boundary.
+        self.offset += bytes.iter().position(|b| !accept(b)).unwrap_or(bytes.len());
+    }
 
-            // Keep building the string with allowed characters.
-            while let Some(&ch) = self.peek_char() {
-                if !(ch.is_alphabetic() || ch.is_digit(10)) {
-                    break;
-                }
+    /// If an alphabetic char (or an underscore) was found, keep reading chars
+    /// to build a identifier. A dot followed by another name qualifies it, and
+    /// it's read too. Finally, look if it was a keyword.
+    fn read_identifier(&mut self) -> Token {
+        // Since in ::next_token we need to call ::read_char the first char was
+        // consumed, but it is part of the identifier too.
+        let start = self.start - self.base;
 
-                // We consume the character we previously peeked. Then push it
-                // to the string we're building.
-                self.read_char();
-                content.push(ch);
+        // Keep reading allowed characters. Only non-ASCII letters need the
+        // slow loop.
+        loop {
+            self.skip_ascii(|&b| b.is_ascii_alphanumeric() || b == b'_');
+            match self.peek_char() {
+                Some(ch) if ch.is_alphabetic() => {
+                    self.read_char();
+                },
+                Some('.') if self.rest()[1..].starts_with(|ch: char| ch.is_alphabetic() || ch == '_') => {
+                    self.read_char();
+                },
+                _ => break,
             }
+        }
 
-            content
-        };
+        let content = &self.source[start..self.offset - self.base];
 
         // Match the identifier to all the known keywords to see if it is one
         // of them. Otherwise, return a normal identifier.
-        match &content[..] {
-            "tipo" => Token::Type,
-            _ => Token::Ident(content),
+        match self.language.keyword(content) {
+            Some(keyword) => keyword,
+            None => Token::Ident(String::from(content)),
         }
     }
 
-    /// Advance the internal iterator when we find whitespace.
-    fn consume_whitespace(&mut self) {
-        while let Some(&ch) = self.peek_char() {
-            if ch.is_whitespace() {
-                self.read_char();
-            } else {
-                break;
+    /// Reads the digits of a number, after its first character: a digit, or
+    /// a minus sign if a digit follows it.
+    fn read_number(&mut self) -> Token {
+        let start = self.start - self.base;
+        self.skip_ascii(u8::is_ascii_digit);
+        Token::Number(String::from(&self.source[start..self.offset - self.base]))
+    }
+
+    /// After the opening quote, reads a text up to the closing one. A `\`
+    /// makes the next `"` or `\` part of the text. Texts that don't end in
+    /// the same line, or have other escapes, are illegal.
+    fn read_text(&mut self) -> Token {
+        let mut text = String::new();
+        let mut illegal = false;
+
+        loop {
+            // The end of the line isn't part of the illegal token.
+            if let None | Some('\n') = self.peek_char() {
+                return Token::Illegal;
+            }
+            match self.read_char() {
+                Some('"') if illegal => return Token::Illegal,
+                Some('"') => return Token::Text(text),
+                Some('\\') => match self.peek_char() {
+                    Some(ch @ '"') | Some(ch @ '\\') => {
+                        self.read_char();
+                        text.push(ch);
+                    },
+                    // The rest of the text is part of the illegal token too.
+                    _ => illegal = true,
+                },
+                Some(ch) => text.push(ch),
+                None => return Token::Illegal,
             }
         }
     }
 
-    /// The basis for the iterator, matches the characters to Tokens.
+    /// After the first slash, reads the rest of a doc comment. Anything that
+    /// starts with a slash but isn't a doc comment is illegal.
+    fn read_doc_comment(&mut self) -> Token {
+        for _ in 0..2 {
+            match self.peek_char() {
+                Some('/') => self.read_char(),
+                _ => return Token::Illegal,
+            };
+        }
+
+        // The space after the slashes isn't part of the comment.
+        if let Some(' ') = self.peek_char() {
+            self.read_char();
+        }
+
+        let rest = self.rest();
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        self.offset += line.len();
+
+        Token::DocComment(String::from(line.strip_suffix('\r').unwrap_or(line)))
+    }
+
+    /// Advance the offset when we find whitespace, returning what was
+    /// skipped.
+    fn consume_whitespace(&mut self) -> &'a str {
+        let start = self.offset - self.base;
+
+        loop {
+            self.skip_ascii(u8::is_ascii_whitespace);
+            match self.peek_char() {
+                Some(ch) if ch.is_whitespace() => {
+                    self.read_char();
+                },
+                _ => break,
+            }
+        }
+
+        &self.source[start..self.offset - self.base]
+    }
+
+    /// The basis for the iterator: the next token, traced.
     fn next_token(&mut self) -> Token {
-        self.consume_whitespace();
+        let token = self.read_token();
+        trace_event!(TRACE, token = ?token, start = self.start, end = self.offset);
+        token
+    }
+
+    /// Matches the characters to Tokens.
+    fn read_token(&mut self) -> Token {
+        self.start = self.offset;
+        let whitespace = self.consume_whitespace();
+        if self.trivia && !whitespace.is_empty() {
+            return Token::Whitespace(String::from(whitespace));
+        }
+
+        self.start = self.offset;
 
         if let Some(ch) = self.read_char() {
             match ch {
@@ -132,11 +662,17 @@ impl Lexer<'_> {
                 ':' => Token::Colon,
                 ';' => Token::Semicolon,
                 ',' => Token::Comma,
-                '\0' => Token::EOF,
-                _ => if ch.is_alphabetic() {
-                    // Read the remainder part of the identifier, passing its
-                    // first character, as we already read it.
-                    self.read_identifier(ch)
+                '@' => Token::At,
+                '=' => Token::Equals,
+                '#' => Token::Hash,
+                '"' => self.read_text(),
+                '/' => self.read_doc_comment(),
+                '-' if self.peek_char().is_some_and(|ch| ch.is_ascii_digit()) => self.read_number(),
+                '0'..='9' => self.read_number(),
+                _ => if ch.is_alphabetic() || ch == '_' {
+                    // Read the remainder part of the identifier, which starts
+                    // with the character we already read.
+                    self.read_identifier()
                 } else {
                     Token::Illegal
                 }
@@ -159,3 +695,132 @@ impl Iterator for Lexer<'_> {
         }
     }
 }
+
+/// An iterator over the tokens of a [`Lexer`][0] and their spans.
+///
+/// Unlike the lexer itself, the last item is always `Token::EOF` (with an
+/// empty span at the end of the input), so whoever consumes it knows where the
+/// input ended. After it, the iterator returns `None`.
+///
+/// [0]: struct.Lexer.html#method.spanned
+pub struct Spanned<'a> {
+    lexer: Lexer<'a>,
+    done: bool,
+}
+
+impl<'a> Spanned<'a> {
+    /// The text of the input that `span` covers, like [`Lexer::text`][0].
+    ///
+    /// [0]: struct.Lexer.html#method.text
+    pub fn text(&self, span: Span) -> &'a str {
+        self.lexer.text(span)
+    }
+}
+
+impl Iterator for Spanned<'_> {
+    type Item = (Token, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let token = self.lexer.next_token();
+        if token == Token::EOF {
+            self.done = true;
+        }
+
+        Some((token, self.lexer.span()))
+    }
+}
+
+/// The whole output of a lexer (tokens and spans, ending with `Token::EOF`),
+/// kept in memory so it can be read many times.
+///
+/// Tools that need the same tokens more than once, like the formatter (which
+/// parses them and then looks for blank lines in between), lex the file once
+/// and read it with as many [`Cursor`s][0] as they want. Trivia is kept if
+/// the lexer [preserved it][1]; parsers skip it.
+///
+/// # Examples
+///
+///     use simcom::lexer::{Lexer, Token, TokenStream};
+///     use simcom::parser::Parser;
+///
+///     let tokens = TokenStream::new(Lexer::new("tipo A(x: B);").preserving_trivia());
+///     assert_eq!(tokens.cursor().filter(|(t, _)| *t == Token::Colon).count(), 1);
+///     assert_eq!(Parser::from_cursor(tokens.cursor()).count(), 1);
+///
+/// [0]: struct.Cursor.html
+/// [1]: struct.Lexer.html#method.preserving_trivia
+#[derive(Debug, Clone)]
+pub struct TokenStream<'a> {
+    tokens: Vec<(Token, Span)>,
+    source: &'a str,
+    base: usize,
+}
+
+impl<'a> TokenStream<'a> {
+    /// Runs `lexer` to the end.
+    pub fn new(lexer: Lexer<'a>) -> TokenStream<'a> {
+        let (source, base) = (lexer.source, lexer.base);
+        TokenStream { tokens: lexer.spanned().collect(), source, base }
+    }
+
+    /// A cursor at the first token.
+    pub fn cursor(&self) -> Cursor<'_> {
+        Cursor { tokens: &self.tokens, source: self.source, base: self.base }
+    }
+
+    /// Every token, with its span.
+    pub fn tokens(&self) -> &[(Token, Span)] {
+        &self.tokens
+    }
+
+    /// The text of the input that `span` covers, like [`Lexer::text`][0].
+    ///
+    /// [0]: struct.Lexer.html#method.text
+    pub fn text(&self, span: Span) -> &'a str {
+        &self.source[span.start - self.base..span.end - self.base]
+    }
+}
+
+/// A position in a [`TokenStream`][0]. It iterates over the tokens after it,
+/// and cloning it is as cheap as copying a reference.
+///
+/// [0]: struct.TokenStream.html
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    /// The tokens still ahead.
+    tokens: &'a [(Token, Span)],
+    source: &'a str,
+    base: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// The next token, without moving the cursor.
+    pub fn peek(&self) -> Option<&'a (Token, Span)> {
+        self.tokens.first()
+    }
+
+    /// The text of the input that `span` covers, like [`Lexer::text`][0].
+    ///
+    /// [0]: struct.Lexer.html#method.text
+    pub fn text(&self, span: Span) -> &'a str {
+        &self.source[span.start - self.base..span.end - self.base]
+    }
+}
+
+impl<'a> Iterator for Cursor<'a> {
+    type Item = &'a (Token, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (first, rest) = self.tokens.split_first()?;
+        self.tokens = rest;
+        Some(first)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.tokens.len(), Some(self.tokens.len()))
+    }
+}
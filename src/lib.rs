@@ -1,3 +1,124 @@
+//! The library behind `simcom`.
+//!
+//! Without the default `std` feature only the front end is available (the
+//! [lexer](lexer/index.html), the [parser](parser/index.html) and what they
+//! need, like the [arena](arena/index.html) for its AST), built on `core`
+//! and `alloc`, so it can be embedded where there is no standard library.
+//!
+//! For the usual cases there is no need to put the phases together by hand:
+//! [`tokenize`](fn.tokenize.html) and [`compile_str`](fn.compile_str.html) do
+//! it in one call, and the [prelude](prelude/index.html) imports the main
+//! types.
+
+// Tests always have the standard library, whatever the features.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+// `alloc` is the standard library when there is one, so the modules that work
+// without it can always import from `alloc`.
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std as alloc;
+#[cfg(any(feature = "std", test))]
+extern crate core;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+
+// Before the rest of modules, so they can use its macros.
+#[macro_use]
+mod trace;
+
+pub mod arena;
+#[cfg(feature = "cabi")]
+pub mod cabi;
+#[cfg(feature = "std")]
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod codegen;
+#[cfg(feature = "std")]
+pub mod config;
+pub mod diagnostics;
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod doc;
+#[cfg(feature = "std")]
+pub mod driver;
+pub mod fmt;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+#[cfg(feature = "std")]
+pub mod graph;
+pub mod highlight;
+pub mod ide;
+#[cfg(feature = "std")]
+pub mod inputs;
+pub mod json;
 pub mod lexer;
+#[cfg(feature = "std")]
+pub mod limits;
+#[cfg(feature = "std")]
+pub mod lint;
+#[cfg(feature = "std")]
+pub mod loader;
+pub mod locale;
 pub mod parser;
+#[cfg(feature = "std")]
+pub mod pass;
+#[cfg(feature = "std")]
+pub mod playground;
+pub mod prelude;
+pub mod rewrite;
+#[cfg(feature = "std")]
 pub mod semantic;
+pub mod source_map;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod units;
+#[cfg(feature = "std")]
+pub mod versions;
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use diagnostics::Diagnostic;
+use lexer::{Lexer, Token};
+#[cfg(feature = "std")]
+use parser::Parser;
+#[cfg(feature = "std")]
+use semantic::Semantic;
+
+/// The tokens of `source`, without whitespace or comments.
+///
+/// # Examples
+///
+///     use simcom::lexer::Token;
+///
+///     let tokens = simcom::tokenize("tipo A");
+///     assert_eq!(tokens, vec![Token::Type, Token::Ident(String::from("A"))]);
+pub fn tokenize(source: &str) -> Vec<Token> {
+    Lexer::new(source).collect()
+}
+
+/// Parses and analyzes `source`, returning every error found.
+///
+/// Nothing is generated; use the [driver](driver/index.html) for that.
+///
+/// # Examples
+///
+///     let semantic = simcom::compile_str("tipo Punto(x: Entero, y: Entero);").unwrap();
+///     assert_eq!(semantic.order, vec!["Entero", "Punto"]);
+///
+///     let errors = simcom::compile_str("tipo A(x: X);;").unwrap_err();
+///     assert_eq!(errors[0].message, "unexpected `;`");
+#[cfg(feature = "std")]
+pub fn compile_str(source: &str) -> Result<Semantic, Vec<Diagnostic>> {
+    Semantic::check(Parser::new(Lexer::new(source)))
+}
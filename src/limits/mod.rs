@@ -0,0 +1,230 @@
+//! Resource limits module.
+//!
+//! The compiler handles whatever it is given, however big, which isn't what
+//! a service compiling schemas uploaded by its users wants: a file with
+//! millions of definitions or a chain of ten thousand types is more likely an
+//! attack than a schema. [`Limits`][0] says how much is too much, and the
+//! [driver][1] stops with an error as soon as something goes over them.
+//!
+//! Every limit has its own [code][2], from `E0301` to `E0305`.
+//!
+//! # Examples
+//!
+//!     use simcom::driver::{compile, Options};
+//!     use simcom::limits::Limits;
+//!
+//!     let limits = Limits { max_parameters_per_type: 2, ..Limits::default() };
+//!     let options = Options { limits, ..Options::default() };
+//!
+//!     let result = compile("tipo Punto(x: Entero, y: Entero, z: Entero);", &options);
+//!     assert_eq!(result.diagnostics[0].message, "`Punto` has 3 parameters, the limit is 2");
+//!     assert!(result.code.is_none());
+//!
+//! [0]: struct.Limits.html
+//! [1]: ../driver/index.html
+//! [2]: ../diagnostics/codes/index.html
+
+use std::collections::HashMap;
+
+use super::diagnostics::{codes, Diagnostic};
+use super::lexer::Span;
+use super::parser::TypeDefinition;
+use super::semantic::Semantic;
+
+/// How much of each thing a compilation accepts. 0 means there's no limit,
+/// which is the default for all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// Definitions in all the files together. Parsing stops after them.
+    pub max_definitions: usize,
+    /// Parameters of a single definition.
+    pub max_parameters_per_type: usize,
+    /// Characters of a name, of a type or of an annotation.
+    pub max_identifier_len: usize,
+    /// Syntax errors and errors about limits. Parsing stops after them.
+    pub max_errors: usize,
+    /// Types in the longest chain of types that depend on each other, like
+    /// `A` in `A(x: B)`, `B(x: C)` and `C(x: Entero)`, which is 3 deep.
+    pub max_dependency_depth: usize,
+}
+
+/// Whether `value` is over `limit`.
+fn over(limit: usize, value: usize) -> bool {
+    limit > 0 && value > limit
+}
+
+/// An error about a limit.
+fn error(code: &str, message: String, span: Span) -> Diagnostic {
+    Diagnostic::error(message, span).with_code(code)
+}
+
+impl Limits {
+    /// The problems of a single definition: too many parameters or names
+    /// that are too long.
+    pub fn check_definition(&self, definition: &TypeDefinition) -> Vec<Diagnostic> {
+        let mut errors = Vec::new();
+
+        let parameters = definition.parameters.len();
+        if over(self.max_parameters_per_type, parameters) {
+            let message = format!(
+                "`{}` has {} parameters, the limit is {}",
+                definition.name, parameters, self.max_parameters_per_type,
+            );
+            errors.push(error(codes::TOO_MANY_PARAMETERS, message, definition.name_span));
+        }
+
+        let mut names = vec![(&definition.name, definition.name_span)];
+        names.extend(definition.annotations.iter().map(|a| (&a.name, a.span)));
+        for parameter in &definition.parameters {
+            names.push((&parameter.name, parameter.name_span));
+            names.push((&parameter.typename, parameter.type_span));
+            names.extend(parameter.annotations.iter().map(|a| (&a.name, a.span)));
+        }
+
+        for (name, span) in names {
+            let len = name.chars().count();
+            if over(self.max_identifier_len, len) {
+                let message = format!("name with {} characters, the limit is {}", len, self.max_identifier_len);
+                errors.push(error(codes::NAME_TOO_LONG, message, span));
+            }
+        }
+
+        errors
+    }
+
+    /// Whether there are already more definitions or errors than allowed, so
+    /// there is no point in parsing any more.
+    pub fn exceeded(&self, definitions: usize, errors: usize) -> bool {
+        over(self.max_definitions, definitions) || over(self.max_errors, errors)
+    }
+
+    /// Drops the definitions and the errors over the limits, adding an error
+    /// that says so instead.
+    pub fn truncate(&self, definitions: &mut Vec<TypeDefinition>, errors: &mut Vec<Diagnostic>) {
+        if over(self.max_definitions, definitions.len()) {
+            let span = definitions[self.max_definitions].name_span;
+            definitions.truncate(self.max_definitions);
+            errors.push(error(codes::TOO_MANY_DEFINITIONS, format!("too many definitions, the limit is {}", self.max_definitions), span));
+        }
+
+        if over(self.max_errors, errors.len()) {
+            let span = errors[self.max_errors].span;
+            errors.truncate(self.max_errors);
+            errors.push(error(codes::TOO_MANY_ERRORS, format!("too many errors, the limit is {}", self.max_errors), span));
+        }
+    }
+
+    /// The first type, in the order of the definitions, whose chain of
+    /// dependencies is too long. Types in a cycle only count once.
+    pub fn check_depth(&self, semantic: &Semantic) -> Option<Diagnostic> {
+        if self.max_dependency_depth == 0 {
+            return None;
+        }
+
+        // The order puts every type after the ones it needs, so their depth
+        // is known when we get to it. The only exception are cycles, which
+        // are taken as 0.
+        let mut depths: HashMap<&str, usize> = HashMap::new();
+        for name in &semantic.order {
+            let definition = match semantic.definitions.get(name) {
+                Some(definition) => definition,
+                None => continue,
+            };
+
+            let depth = 1 + definition.parameters.iter()
+                .map(|p| depths.get(&p.typename[..]).cloned().unwrap_or(0))
+                .max()
+                .unwrap_or(0);
+
+            if over(self.max_dependency_depth, depth) {
+                let message = format!(
+                    "`{}` depends on a chain of {} types, the limit is {}",
+                    name, depth, self.max_dependency_depth,
+                );
+                return Some(error(codes::DEPENDENCIES_TOO_DEEP, message, definition.name_span));
+            }
+            depths.insert(name, depth);
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::Lexer;
+    use super::super::parser::{Ast, Parser, TypeDefinition};
+    use super::super::semantic::Semantic;
+    use super::Limits;
+
+    fn definitions(input: &str) -> Vec<TypeDefinition<'_>> {
+        Parser::new(Lexer::new(input))
+            .filter_map(|ast| match ast {
+                Ast::TypeDefinition(definition) => Some(definition),
+                Ast::Unexpected(..) => None,
+            })
+            .collect()
+    }
+
+    fn messages(limits: &Limits, input: &str) -> Vec<String> {
+        definitions(input).iter()
+            .flat_map(|d| limits.check_definition(d))
+            .map(|d| d.message)
+            .collect()
+    }
+
+    #[test]
+    fn no_limits_by_default() {
+        let limits = Limits::default();
+        let input = format!("tipo {}(x: A, y: B, z: C);", "A".repeat(1000));
+        assert!(messages(&limits, &input).is_empty());
+        assert!(!limits.exceeded(1_000_000, 1_000_000));
+    }
+
+    #[test]
+    fn definitions_and_identifiers() {
+        let limits = Limits { max_parameters_per_type: 1, max_identifier_len: 5, ..Limits::default() };
+        assert_eq!(messages(&limits, "tipo Punto(x: Entero, @obsoleto yy: Real);"), [
+            "`Punto` has 2 parameters, the limit is 1",
+            "name with 6 characters, the limit is 5",
+            "name with 8 characters, the limit is 5",
+        ]);
+
+        let diagnostic = &limits.check_definition(&definitions("tipo Ñandúes(x: A);")[0])[0];
+        assert_eq!(diagnostic.message, "name with 7 characters, the limit is 5");
+        assert_eq!(diagnostic.code, Some(String::from("E0303")));
+    }
+
+    #[test]
+    fn counts() {
+        let limits = Limits { max_definitions: 2, max_errors: 1, ..Limits::default() };
+        assert!(!limits.exceeded(2, 1));
+        assert!(limits.exceeded(3, 0));
+
+        let mut parsed = definitions("tipo A(x: E); tipo B(x: E); tipo C(x: E);");
+        let third = parsed[2].name_span;
+        let mut errors = Vec::new();
+        limits.truncate(&mut parsed, &mut errors);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(errors[0].message, "too many definitions, the limit is 2");
+        assert_eq!(errors[0].span, third);
+
+        errors.push(errors[0].clone());
+        limits.truncate(&mut parsed, &mut errors);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[1].message, "too many errors, the limit is 1");
+    }
+
+    #[test]
+    fn dependency_depth() {
+        let input = "tipo A(x: B, y: Entero); tipo B(x: C); tipo C(x: Entero); tipo D(x: D);";
+        let semantic = Semantic::check(definitions(input).into_iter().map(Ast::TypeDefinition)).unwrap();
+
+        let limits = Limits { max_dependency_depth: 3, ..Limits::default() };
+        assert_eq!(limits.check_depth(&semantic), None);
+
+        let limits = Limits { max_dependency_depth: 2, ..Limits::default() };
+        let diagnostic = limits.check_depth(&semantic).unwrap();
+        assert_eq!(diagnostic.message, "`A` depends on a chain of 3 types, the limit is 2");
+    }
+}
@@ -0,0 +1,583 @@
+//! Lint module.
+//!
+//! Lints don't stop a program from compiling, they point at things that are
+//! probably a mistake or that make the schema harder to read. Each lint has a
+//! name and a [`Level`][0], that can be changed in `simcom.toml` or in the
+//! command line.
+//!
+//! [0]: ../config/enum.Level.html
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::config::Level;
+use super::diagnostics::{closest, codes, Diagnostic, Label, Suggestion, TextEdit};
+use super::lexer::Span;
+use super::semantic::Semantic;
+use super::units::unit;
+use super::versions::Range;
+
+/// Names of the annotation that marks a type as deprecated.
+pub const DEPRECATED: &[&str] = &["obsoleto", "deprecated"];
+
+/// Every lint we know about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// Types must be `UpperCamelCase` and fields must start in lowercase.
+    Naming,
+    /// Types that no other type uses. Allowed by default, since the roots of
+    /// a schema are never used.
+    UnusedTypes,
+    /// Two fields with the same name in the same type.
+    DuplicateFields,
+    /// Fields whose type is annotated with `@obsoleto`.
+    Deprecated,
+    /// Fields whose type isn't defined, but is almost the name of one that
+    /// is.
+    MisspelledTypes,
+    /// Numbers that the numbered fields of a type skip, starting at 1.
+    FieldNumberGaps,
+    /// Fields with the same name in different units, in different types.
+    ConflictingUnits,
+}
+
+impl Lint {
+    /// All the lints, in the order they are run.
+    pub const ALL: [Lint; 7] = [
+        Lint::Naming,
+        Lint::UnusedTypes,
+        Lint::DuplicateFields,
+        Lint::Deprecated,
+        Lint::MisspelledTypes,
+        Lint::FieldNumberGaps,
+        Lint::ConflictingUnits,
+    ];
+
+    /// The name used in the configuration and in the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::Naming => "naming",
+            Lint::UnusedTypes => "unused_types",
+            Lint::DuplicateFields => "duplicate_fields",
+            Lint::Deprecated => "deprecated",
+            Lint::MisspelledTypes => "misspelled_types",
+            Lint::FieldNumberGaps => "field_number_gaps",
+            Lint::ConflictingUnits => "conflicting_units",
+        }
+    }
+
+    /// The [code][0] of the diagnostics of the lint.
+    ///
+    /// [0]: ../diagnostics/codes/index.html
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::UnusedTypes => codes::UNUSED_TYPE,
+            Lint::Naming => codes::NAMING,
+            Lint::DuplicateFields => codes::DUPLICATE_FIELD,
+            Lint::Deprecated => codes::DEPRECATED,
+            Lint::MisspelledTypes => codes::MISSPELLED_TYPE,
+            Lint::FieldNumberGaps => codes::FIELD_NUMBER_GAP,
+            Lint::ConflictingUnits => codes::CONFLICTING_UNITS,
+        }
+    }
+
+    /// Looks up a lint by its name.
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.iter().cloned().find(|lint| lint.name() == name)
+    }
+
+    /// The level of the lint when nobody says otherwise.
+    pub fn default_level(self) -> Level {
+        match self {
+            Lint::UnusedTypes => Level::Allow,
+            _ => Level::Warn,
+        }
+    }
+
+    /// The level of the lint given the configured ones.
+    pub fn level(self, levels: &BTreeMap<String, Level>) -> Level {
+        levels.get(self.name()).cloned().unwrap_or_else(|| self.default_level())
+    }
+}
+
+/// Something a lint found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub lint: Lint,
+    /// Either `Warn` or `Deny`, allowed lints don't produce findings.
+    pub level: Level,
+    pub message: String,
+    pub span: Span,
+    /// Other places that have to do with it.
+    pub labels: Vec<Label>,
+    /// How to fix it, when it's clear.
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl From<Finding> for Diagnostic {
+    /// Denied lints are errors, the rest are warnings. The code is the one
+    /// of the lint, and a note has its name, which is what changes its level.
+    fn from(finding: Finding) -> Diagnostic {
+        let diagnostic = match finding.level {
+            Level::Deny => Diagnostic::error(finding.message, finding.span),
+            _ => Diagnostic::warning(finding.message, finding.span),
+        };
+        let mut diagnostic = diagnostic
+            .with_code(finding.lint.code())
+            .with_note(format!("from the `{}` lint", finding.lint.name()));
+        diagnostic.labels = finding.labels;
+        diagnostic.suggestions = finding.suggestions;
+        diagnostic
+    }
+}
+
+/// Runs every lint that isn't allowed. Findings are sorted by position.
+///
+/// # Examples
+///
+///     use std::collections::BTreeMap;
+///     use simcom::lexer::Lexer;
+///     use simcom::lint::{self, Lint};
+///     use simcom::parser::Parser;
+///     use simcom::semantic::Semantic;
+///
+///     let content = "tipo punto(x: Entero);";
+///     let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+///     let findings = lint::run(&semantic, &BTreeMap::new());
+///
+///     assert_eq!(findings[0].lint, Lint::Naming);
+///     assert_eq!(findings[0].message, "type `punto` should have an upper camel case name: `Punto`");
+pub fn run(semantic: &Semantic, levels: &BTreeMap<String, Level>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    for &lint in &Lint::ALL {
+        let level = lint.level(levels);
+        if level == Level::Allow {
+            continue;
+        }
+
+        let mut found = Vec::new();
+        match lint {
+            Lint::Naming => naming(semantic, &mut found),
+            Lint::UnusedTypes => unused_types(semantic, &mut found),
+            Lint::DuplicateFields => duplicate_fields(semantic, &mut found),
+            Lint::Deprecated => deprecated(semantic, &mut found),
+            Lint::MisspelledTypes => misspelled_types(semantic, &mut found),
+            Lint::FieldNumberGaps => field_number_gaps(semantic, &mut found),
+            Lint::ConflictingUnits => conflicting_units(semantic, &mut found),
+        }
+
+        findings.extend(found.into_iter().map(|(message, span, labels, suggestions)| {
+            Finding { lint, level, message, span, labels, suggestions }
+        }));
+    }
+
+    findings.sort_by_key(|f| (f.span.file, f.span.start, f.span.end));
+    findings
+}
+
+/// What a single lint returns: messages, where they point to, other places
+/// that have to do with them and how to fix them.
+type Found = Vec<(String, Span, Vec<Label>, Vec<Suggestion>)>;
+
+fn naming(semantic: &Semantic, found: &mut Found) {
+    for definition in semantic.definitions.values() {
+        let name = &definition.name;
+        let qualified = definition.qualified_name();
+        let upper = name.chars().next().map(char::is_uppercase).unwrap_or(true);
+
+        if !upper || name.contains('_') {
+            let fixed = upper_camel_case(name);
+
+            // The fields that use the type, and the types that implement it,
+            // are renamed too, qualified or not, as they were written.
+            let mut rename = Suggestion::replace(format!("rename it to `{}`", fixed), definition.name_span, &fixed[..]);
+            for other in semantic.definitions.values() {
+                let fields = other.parameters.iter()
+                    .filter(|p| p.typename == qualified)
+                    .map(|p| p.type_span);
+                let implementations = other.implements.iter()
+                    .filter(|i| i.name == qualified)
+                    .map(|i| i.span);
+                for span in fields.chain(implementations) {
+                    let replacement = match definition.module {
+                        Some(ref module) if span.end - span.start != name.len() => format!("{}.{}", module, fixed),
+                        _ => fixed.clone(),
+                    };
+                    rename.edits.push(TextEdit { span, replacement });
+                }
+            }
+            rename.edits.sort_by_key(|edit| (edit.span.file, edit.span.start));
+
+            found.push((
+                format!("type `{}` should have an upper camel case name: `{}`", name, fixed),
+                definition.name_span,
+                Vec::new(),
+                vec![rename],
+            ));
+        }
+
+        for parameter in &definition.parameters {
+            let name = &parameter.name;
+            if name.chars().next().map(char::is_uppercase).unwrap_or(false) {
+                let fixed = lower_first(name);
+                found.push((
+                    format!("field `{}` should start with a lowercase letter: `{}`", name, fixed),
+                    parameter.name_span,
+                    Vec::new(),
+                    vec![Suggestion::replace(format!("rename it to `{}`", fixed), parameter.name_span, fixed)],
+                ));
+            }
+        }
+    }
+}
+
+fn upper_camel_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn lower_first(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn unused_types(semantic: &Semantic, found: &mut Found) {
+    let mut used = HashSet::new();
+
+    for definition in semantic.definitions.values() {
+        for parameter in &definition.parameters {
+            // A type that only refers to itself is still unused.
+            if parameter.typename != definition.qualified_name() {
+                used.insert(&parameter.typename[..]);
+            }
+        }
+        for implementation in &definition.implements {
+            used.insert(&implementation.name[..]);
+        }
+    }
+
+    for (name, definition) in &semantic.definitions {
+        if !used.contains(&name[..]) {
+            found.push((
+                format!("type `{}` is never used", name),
+                definition.name_span,
+                Vec::new(),
+                Vec::new(),
+            ));
+        }
+    }
+}
+
+fn duplicate_fields(semantic: &Semantic, found: &mut Found) {
+    for (name, definition) in &semantic.definitions {
+        // Where each field was first, and in which versions. Fields with the
+        // same name in different versions are fine.
+        let mut seen: HashMap<&str, Vec<(Span, Range)>> = HashMap::new();
+
+        for parameter in &definition.parameters {
+            let range = Range::of(&parameter.annotations);
+            let previous = seen.get(&parameter.name[..])
+                .and_then(|previous| previous.iter().find(|&&(_, other)| other.overlaps(range)))
+                .map(|&(span, _)| span);
+            seen.entry(&parameter.name).or_default().push((parameter.name_span, range));
+
+            if let Some(previous) = previous {
+                found.push((
+                    format!("field `{}` is defined more than once in `{}`",
+                        parameter.name, name),
+                    parameter.name_span,
+                    vec![Label { span: previous, message: format!("previous field `{}` is here", parameter.name) }],
+                    Vec::new(),
+                ));
+            }
+        }
+    }
+}
+
+/// The annotation that marks the type `name` as deprecated, if it's marked.
+fn deprecation(semantic: &Semantic, name: &str) -> Option<Span> {
+    semantic.definitions.get(name)?.annotations.iter()
+        .find(|annotation| DEPRECATED.contains(&&annotation.name[..]))
+        .map(|annotation| annotation.span)
+}
+
+fn deprecated(semantic: &Semantic, found: &mut Found) {
+    for (name, definition) in &semantic.definitions {
+        // Deprecated types may use each other without complaints.
+        if deprecation(semantic, name).is_some() {
+            continue;
+        }
+
+        for parameter in &definition.parameters {
+            if let Some(annotation) = deprecation(semantic, &parameter.typename) {
+                found.push((
+                    format!("type `{}` of field `{}` is deprecated",
+                        parameter.typename, parameter.name),
+                    parameter.type_span,
+                    vec![Label { span: annotation, message: format!("`{}` is marked as deprecated here", parameter.typename) }],
+                    Vec::new(),
+                ));
+            }
+        }
+    }
+}
+
+fn misspelled_types(semantic: &Semantic, found: &mut Found) {
+    let defined = || semantic.definitions.keys().map(|name| &name[..]);
+
+    for definition in semantic.definitions.values() {
+        for parameter in &definition.parameters {
+            let typename = &parameter.typename[..];
+            if semantic.definitions.contains_key(typename) {
+                continue;
+            }
+
+            if let Some(name) = closest(typename, defined()) {
+                // Only a guess, the type could be a built-in one.
+                let suggestion = Suggestion::replace(format!("did you mean `{}`?", name), parameter.type_span, name);
+                found.push((
+                    format!("type `{}` of field `{}` isn't defined", typename, parameter.name),
+                    parameter.type_span,
+                    Vec::new(),
+                    vec![suggestion.maybe_incorrect()],
+                ));
+            }
+        }
+    }
+}
+
+fn field_number_gaps(semantic: &Semantic, found: &mut Found) {
+    for (name, definition) in &semantic.definitions {
+        let mut numbered: Vec<(i64, Span)> = definition.parameters.iter()
+            .filter_map(|p| p.number.map(|number| (number, p.span)))
+            .filter(|&(number, _)| number >= 1)
+            .collect();
+        numbered.sort_by_key(|&(number, _)| number);
+
+        let mut next = 1;
+        for (number, span) in numbered {
+            // Repeated numbers are left to the analysis.
+            let message = match number - next {
+                1 => Some(format!("fields of `{}` skip the number {}", name, next)),
+                skipped if skipped > 1 => Some(format!("fields of `{}` skip the numbers {} to {}", name, next, number - 1)),
+                _ => None,
+            };
+            if let Some(message) = message {
+                found.push((message, span, Vec::new(), Vec::new()));
+            }
+            next = next.max(number + 1);
+        }
+    }
+}
+
+fn conflicting_units(semantic: &Semantic, found: &mut Found) {
+    let mut names: Vec<&String> = semantic.definitions.keys().collect();
+    names.sort();
+
+    // The first unit of each field name, and where it is.
+    let mut units: HashMap<&str, (&str, &str, Span)> = HashMap::new();
+    for name in names {
+        for parameter in &semantic.definitions[name].parameters {
+            let unit = match unit(parameter) {
+                Some(unit) => unit,
+                None => continue,
+            };
+            match units.get(&parameter.name[..]) {
+                Some(&(first, other, span)) if first != unit && other != name => found.push((
+                    format!("field `{}` of `{}` is in `{}`, but in `{}` it's in `{}`",
+                        parameter.name, name, unit, other, first),
+                    parameter.span,
+                    vec![Label { span, message: format!("in `{}` here", first) }],
+                    Vec::new(),
+                )),
+                Some(_) => (),
+                None => {
+                    units.insert(&parameter.name, (unit, name, parameter.span));
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::Lexer;
+    use super::super::parser::Parser;
+    use super::*;
+
+    fn get_findings(content: &str, levels: &[(&str, Level)]) -> Vec<(Lint, String)> {
+        let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+        let levels = levels.iter()
+            .map(|&(name, level)| (String::from(name), level))
+            .collect();
+
+        run(&semantic, &levels).into_iter()
+            .map(|f| (f.lint, f.message))
+            .collect()
+    }
+
+    #[test]
+    fn clean() {
+        let findings = get_findings("tipo Punto(x: Entero, y: Entero);\
+                                     tipo Circulo(centro: Punto);", &[]);
+        assert!(findings.is_empty());
+    }
+
+    #[test]
+    fn naming() {
+        let findings = get_findings("tipo mi_punto(X: Entero, bien: Entero);", &[]);
+
+        assert_eq!(findings, vec![
+            (Lint::Naming, String::from("type `mi_punto` should have an upper camel case name: `MiPunto`")),
+            (Lint::Naming, String::from("field `X` should start with a lowercase letter: `x`")),
+        ]);
+    }
+
+    #[test]
+    fn unused_types_is_opt_in() {
+        let content = "tipo A(b: B); tipo B(x: Entero); tipo C(c: C);";
+        assert!(get_findings(content, &[]).is_empty());
+
+        let findings = get_findings(content, &[("unused_types", Level::Warn)]);
+        assert_eq!(findings, vec![
+            (Lint::UnusedTypes, String::from("type `A` is never used")),
+            (Lint::UnusedTypes, String::from("type `C` is never used")),
+        ]);
+    }
+
+    #[test]
+    fn duplicate_fields() {
+        let findings = get_findings("tipo A(x: Entero, y: Entero, x: Real);", &[]);
+
+        assert_eq!(findings, vec![
+            (Lint::DuplicateFields, String::from("field `x` is defined more than once in `A`")),
+        ]);
+    }
+
+    #[test]
+    fn fields_in_other_versions() {
+        let content = "tipo A(@hasta(2) x: Entero, @desde(3) x: Real, @desde(2) y: Entero, y: Real);";
+        assert_eq!(get_findings(content, &[]), vec![
+            (Lint::DuplicateFields, String::from("field `y` is defined more than once in `A`")),
+        ]);
+    }
+
+    #[test]
+    fn labels() {
+        let content = "tipo A(x: Entero, y: Entero, x: Real);\n@obsoleto tipo B(z: Entero); tipo C(b: B);";
+        let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+        let findings = run(&semantic, &BTreeMap::new());
+
+        let labels: Vec<(&str, &str)> = findings.iter()
+            .flat_map(|finding| &finding.labels)
+            .map(|label| (&content[label.span.range()], &label.message[..]))
+            .collect();
+        assert_eq!(labels, [("x", "previous field `x` is here"), ("@obsoleto", "`B` is marked as deprecated here")]);
+        assert_eq!(Diagnostic::from(findings[0].clone()).labels, findings[0].labels);
+    }
+
+    #[test]
+    fn deprecated() {
+        let content = "@obsoleto tipo Viejo(x: Entero);\
+                       @obsoleto tipo Antiguo(v: Viejo);\
+                       tipo Nuevo(v: Viejo);";
+
+        let findings = get_findings(content, &[]);
+        assert_eq!(findings, vec![
+            (Lint::Deprecated, String::from("type `Viejo` of field `v` is deprecated")),
+        ]);
+
+        assert!(get_findings(content, &[("deprecated", Level::Allow)]).is_empty());
+    }
+
+    #[test]
+    fn misspelled_types() {
+        let content = "tipo Punto(x: Entero); tipo Linea(a: Punot, b: Punto, c: Real, d: Lina);";
+        let findings = get_findings(content, &[]);
+        assert_eq!(findings, vec![
+            (Lint::MisspelledTypes, String::from("type `Punot` of field `a` isn't defined")),
+            (Lint::MisspelledTypes, String::from("type `Lina` of field `d` isn't defined")),
+        ]);
+
+        let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+        let suggestion = &run(&semantic, &BTreeMap::new())[0].suggestions[0];
+        assert_eq!(suggestion.message, "did you mean `Punto`?");
+        assert_eq!(suggestion.edits, vec![TextEdit { span: Span::new(37, 42), replacement: String::from("Punto") }]);
+    }
+
+    #[test]
+    fn field_number_gaps() {
+        let content = "tipo A(x: Entero #2, y: Entero #3, z: Real #7, w: Real #3); tipo B(x: Entero); tipo C(x: Entero #1);";
+        let findings = get_findings(content, &[]);
+        assert_eq!(findings, vec![
+            (Lint::FieldNumberGaps, String::from("fields of `A` skip the number 1")),
+            (Lint::FieldNumberGaps, String::from("fields of `A` skip the numbers 4 to 6")),
+        ]);
+    }
+
+    #[test]
+    fn conflicting_units() {
+        let content = "tipo Viaje(@unidad(\"km\") distancia: Real, @unidad(\"h\") tiempo: Real);\
+                       tipo Carrera(@unidad(\"m\") distancia: Real, @unidad(\"h\") tiempo: Real);\
+                       tipo Paseo(distancia: Real, @unidad(\"km\") @unidad(\"mi\") tiempo: Real);";
+        let findings = get_findings(content, &[]);
+        assert_eq!(findings, vec![
+            (Lint::ConflictingUnits, String::from("field `distancia` of `Viaje` is in `km`, but in `Carrera` it's in `m`")),
+            (Lint::ConflictingUnits, String::from("field `tiempo` of `Paseo` is in `km`, but in `Carrera` it's in `h`")),
+        ]);
+
+        assert!(get_findings(content, &[("conflicting_units", Level::Allow)]).is_empty());
+    }
+
+    #[test]
+    fn naming_renames_the_uses() {
+        let semantic = Semantic::analyze(Parser::new(Lexer::new("tipo punto(x: Entero); tipo L(a: punto, b: punto);"))).unwrap();
+        let findings = run(&semantic, &BTreeMap::new());
+
+        let edits: Vec<(usize, &str)> = findings[0].suggestions[0].edits.iter()
+            .map(|edit| (edit.span.start, &edit.replacement[..]))
+            .collect();
+        assert_eq!(edits, vec![(5, "Punto"), (33, "Punto"), (43, "Punto")]);
+
+        // Uses from other modules are qualified.
+        let content = "modulo geo; tipo punto(x: Entero); tipo L(a: punto); modulo red; tipo R(p: geo.punto);";
+        let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+        let findings = run(&semantic, &BTreeMap::new());
+
+        let edits: Vec<(usize, &str)> = findings[0].suggestions[0].edits.iter()
+            .map(|edit| (edit.span.start, &edit.replacement[..]))
+            .collect();
+        assert_eq!(edits, vec![(17, "Punto"), (45, "Punto"), (75, "geo.Punto")]);
+
+        // And so are the implementations of interfaces.
+        let content = "interfaz con_nombre(n: T); tipo A(n: T) implementa con_nombre;";
+        let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+        let findings = run(&semantic, &BTreeMap::new());
+
+        let edits: Vec<(usize, &str)> = findings[0].suggestions[0].edits.iter()
+            .map(|edit| (edit.span.start, &edit.replacement[..]))
+            .collect();
+        assert_eq!(edits, vec![(9, "ConNombre"), (51, "ConNombre")]);
+    }
+
+    #[test]
+    fn levels() {
+        let semantic = Semantic::analyze(Parser::new(Lexer::new("tipo a(x: B);"))).unwrap();
+        let mut levels = BTreeMap::new();
+        levels.insert(String::from("naming"), Level::Deny);
+
+        assert_eq!(run(&semantic, &levels)[0].level, Level::Deny);
+        assert_eq!(Lint::from_name("duplicate_fields"), Some(Lint::DuplicateFields));
+        assert_eq!(Lint::from_name("nope"), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! File loading module.
+//!
+//! The compiler doesn't care where the text of a file comes from. A
+//! [`FileLoader`][0] is what reads it: [`DiskLoader`][1] from the file system,
+//! as the command line does, and [`MemoryLoader`][2] from files kept in
+//! memory, which is what tests and editors (with unsaved changes) want.
+//!
+//! [0]: trait.FileLoader.html
+//! [1]: struct.DiskLoader.html
+//! [2]: struct.MemoryLoader.html
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Something that can read the text of a file.
+pub trait FileLoader {
+    fn load(&self, path: &Path) -> io::Result<String>;
+}
+
+/// Reads files from the file system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskLoader;
+
+impl FileLoader for DiskLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        decode(fs::read(path)?)
+    }
+}
+
+/// Turns the bytes of a file into text. If they aren't UTF-8, the error says
+/// where the first invalid byte is.
+///
+/// # Examples
+///
+///     use simcom::loader::decode;
+///
+///     assert_eq!(decode(b"tipo".to_vec()).unwrap(), "tipo");
+///
+///     let error = decode(b"tipo A(\n  x: \xff);".to_vec()).unwrap_err();
+///     assert_eq!(error.to_string(), "invalid UTF-8 at line 2, column 6");
+pub fn decode(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|error| {
+        let valid = &error.as_bytes()[..error.utf8_error().valid_up_to()];
+        // Everything before the first invalid byte is valid.
+        let valid = String::from_utf8_lossy(valid);
+        let line = valid.matches('\n').count() + 1;
+        let column = valid.rsplit('\n').next().map_or(0, |last| last.chars().count()) + 1;
+        io::Error::new(io::ErrorKind::InvalidData, format!("invalid UTF-8 at line {}, column {}", line, column))
+    })
+}
+
+/// A set of files that only exist in memory.
+///
+/// # Examples
+///
+///     use std::path::Path;
+///     use simcom::loader::{FileLoader, MemoryLoader};
+///
+///     let mut loader = MemoryLoader::new();
+///     loader.insert("schemas/punto.sc", "tipo Punto(x: Entero);");
+///
+///     assert_eq!(loader.load(Path::new("schemas/punto.sc")).unwrap(), "tipo Punto(x: Entero);");
+///     assert!(loader.load(Path::new("schemas/linea.sc")).is_err());
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLoader {
+    files: BTreeMap<PathBuf, String>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> MemoryLoader {
+        MemoryLoader::default()
+    }
+
+    /// Adds a file, or replaces its content if it was already there.
+    pub fn insert<P: Into<PathBuf>, C: Into<String>>(&mut self, path: P, content: C) {
+        self.files.insert(path.into(), content.into());
+    }
+
+    /// Removes a file, returning its content.
+    pub fn remove(&mut self, path: &Path) -> Option<String> {
+        self.files.remove(path)
+    }
+}
+
+/// Files that aren't there are `NotFound`, like in the file system.
+impl FileLoader for MemoryLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        self.files.get(path).cloned().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("{}: no such file", path.display()))
+        })
+    }
+}
+
+impl<L: FileLoader + ?Sized> FileLoader for &L {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        (**self).load(path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::path::Path;
+
+    use super::{decode, DiskLoader, FileLoader, MemoryLoader};
+
+    #[test]
+    fn memory() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("a.sc", "tipo A(x: Entero);");
+        loader.insert("a.sc", "tipo B(x: Entero);");
+
+        let loader: &dyn FileLoader = &loader;
+        assert_eq!(loader.load(Path::new("a.sc")).unwrap(), "tipo B(x: Entero);");
+        assert_eq!(loader.load(Path::new("b.sc")).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn disk() {
+        let error = DiskLoader.load(Path::new("there/is/no/such/file.sc")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert!(DiskLoader.load(Path::new("Cargo.toml")).unwrap().contains("simple-compiler"));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let error = decode(b"\xc3(".to_vec()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "invalid UTF-8 at line 1, column 1");
+
+        let error = decode("tipo Año(\n\n x: \u{a0}".bytes().chain(vec![0xe2, 0x82]).collect()).unwrap_err();
+        assert_eq!(error.to_string(), "invalid UTF-8 at line 3, column 6");
+    }
+}
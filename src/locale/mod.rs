@@ -0,0 +1,314 @@
+//! Locale module.
+//!
+//! The keywords of the language are Spanish, so it's often taught in Spanish,
+//! but diagnostics are written in English. The [`Locale`][0] of a
+//! compilation (`locale` in `simcom.toml`, `--locale` or the `LANG`
+//! environment variable) says in which language people read them:
+//! [`translate`][1] looks each message up in a small catalog, and messages
+//! that aren't in it stay in English.
+//!
+//! Diagnostics are always created in English, which is what the JSON output
+//! and the tests see. Only [rendering][2] translates them. Messages about a
+//! token are written from the token itself, with [`unexpected`][3].
+//!
+//! [0]: enum.Locale.html
+//! [1]: fn.translate.html
+//! [2]: ../diagnostics/struct.Diagnostic.html#method.render_in
+//! [3]: fn.unexpected.html
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::lexer::Token;
+
+/// The language diagnostics are shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Locale {
+    /// Looks up a locale from its short code (`en` or `es`).
+    pub fn from_code(code: &str) -> Option<Locale> {
+        match code {
+            "en" => Some(Locale::English),
+            "es" => Some(Locale::Spanish),
+            _ => None,
+        }
+    }
+
+    /// The short code of the locale, the one `from_code` takes.
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::Spanish => "es",
+        }
+    }
+
+    /// Reads a POSIX locale name, like `es_ES.UTF-8`. Only the language
+    /// matters.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::locale::Locale;
+    ///
+    ///     assert_eq!(Locale::from_posix("es_AR.UTF-8"), Some(Locale::Spanish));
+    ///     assert_eq!(Locale::from_posix("en"), Some(Locale::English));
+    ///     assert_eq!(Locale::from_posix("C"), None);
+    pub fn from_posix(name: &str) -> Option<Locale> {
+        let end = name.find(['_', '.', '@']).unwrap_or(name.len());
+        Locale::from_code(&name[..end])
+    }
+
+    /// The locale of the environment, from `LC_ALL`, `LC_MESSAGES` or `LANG`,
+    /// the first one that is set. `None` if it isn't one we know.
+    #[cfg(feature = "std")]
+    pub fn from_env() -> Option<Locale> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|name| ::std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Locale::from_posix(&value))
+    }
+}
+
+/// English messages and their Spanish translation. `{}` is the same text in
+/// both, like a name.
+const CATALOG: &[(&str, &str)] = &[
+    ("unknown character", "carácter desconocido"),
+    ("type `{}` should have an upper camel case name: `{}`",
+     "el nombre del tipo `{}` debería estar en UpperCamelCase: `{}`"),
+    ("field `{}` should start with a lowercase letter: `{}`",
+     "el campo `{}` debería empezar por minúscula: `{}`"),
+    ("type `{}` is never used", "el tipo `{}` no se usa nunca"),
+    ("type `{}` is defined more than once", "el tipo `{}` está definido más de una vez"),
+    ("previous definition of `{}` is here", "la definición anterior de `{}` está aquí"),
+    ("field `{}` is defined more than once in `{}`", "el campo `{}` está definido más de una vez en `{}`"),
+    ("previous field `{}` is here", "el campo `{}` anterior está aquí"),
+    ("interface `{}` isn't defined", "la interfaz `{}` no está definida"),
+    ("`{}` is a type, not an interface", "`{}` es un tipo, no una interfaz"),
+    ("`{}` is defined here", "`{}` está definido aquí"),
+    ("type `{}` doesn't have the field `{}` of `{}`", "el tipo `{}` no tiene el campo `{}` de `{}`"),
+    ("field `{}` of `{}` is `{}`, but `{}` requires `{}`",
+     "el campo `{}` de `{}` es `{}`, pero `{}` requiere `{}`"),
+    ("`{}` is required here", "`{}` se requiere aquí"),
+    ("variant `{}` is defined more than once in `{}`", "la variante `{}` está definida más de una vez en `{}`"),
+    ("previous variant `{}` is here", "la variante `{}` anterior está aquí"),
+    ("variant `{}` of `{}` has the value {}, like `{}`", "la variante `{}` de `{}` tiene el valor {}, como `{}`"),
+    ("`{}` has the value {}", "`{}` tiene el valor {}"),
+    ("a variant without a value has the next one after the variant before it",
+     "una variante sin valor tiene el siguiente al de la variante anterior"),
+    ("value {} of variant `{}` doesn't fit in 32 bits", "el valor {} de la variante `{}` no cabe en 32 bits"),
+    ("field `{}` of `{}` has the number {}, like `{}`", "el campo `{}` de `{}` tiene el número {}, como `{}`"),
+    ("`{}` has the number {}", "`{}` tiene el número {}"),
+    ("number {} of field `{}` isn't between 1 and {}", "el número {} del campo `{}` no está entre 1 y {}"),
+    ("field `{}` of `{}` has no number, but other fields do",
+     "el campo `{}` de `{}` no tiene número, pero otros campos sí"),
+    ("`@{}` takes a version, like `@{}(2)`", "`@{}` lleva una versión, como `@{}(2)`"),
+    ("version {} is after version {}", "la versión {} es posterior a la versión {}"),
+    ("the last version is {}", "la última versión es la {}"),
+    ("type `{}` of version {} isn't in version {}", "el tipo `{}` de la versión {} no está en la versión {}"),
+    ("field `{}` of `{}` of version {} isn't in version {}",
+     "el campo `{}` de `{}` de la versión {} no está en la versión {}"),
+    ("field `{}` of `{}` is `{}` in version {} and `{}` in version {}",
+     "el campo `{}` de `{}` es `{}` en la versión {} y `{}` en la versión {}"),
+    ("`{}` in version {}", "`{}` en la versión {}"),
+    ("`@{}` goes on fields, not on types", "`@{}` va en campos, no en tipos"),
+    ("`@{}` takes a unit, like `@{}(\"m\")`", "`@{}` lleva una unidad, como `@{}(\"m\")`"),
+    ("`@{}` goes on numeric fields, but `{}` is `{}`", "`@{}` va en campos numéricos, pero `{}` es `{}`"),
+    ("field `{}` of `{}` is in `{}`, but in `{}` it's in `{}`",
+     "el campo `{}` de `{}` está en `{}`, pero en `{}` está en `{}`"),
+    ("in `{}` here", "en `{}` aquí"),
+    ("fields of `{}` skip the number {}", "los campos de `{}` se saltan el número {}"),
+    ("fields of `{}` skip the numbers {} to {}", "los campos de `{}` se saltan los números del {} al {}"),
+    ("interface `{}` can't be the type of field `{}`",
+     "la interfaz `{}` no puede ser el tipo del campo `{}`"),
+    ("type `{}` of field `{}` is deprecated", "el tipo `{}` del campo `{}` está obsoleto"),
+    ("`{}` is marked as deprecated here", "`{}` se marca como obsoleto aquí"),
+    ("type `{}` of field `{}` isn't defined", "el tipo `{}` del campo `{}` no está definido"),
+    ("`{}` has {} parameters, the limit is {}", "`{}` tiene {} parámetros, el límite es {}"),
+    ("name with {} characters, the limit is {}", "nombre con {} caracteres, el límite es {}"),
+    ("too many definitions, the limit is {}", "demasiadas definiciones, el límite es {}"),
+    ("too many errors, the limit is {}", "demasiados errores, el límite es {}"),
+    ("`{}` depends on a chain of {} types, the limit is {}",
+     "`{}` depende de una cadena de {} tipos, el límite es {}"),
+    ("from the `{}` lint", "del lint `{}`"),
+    ("insert `{}`", "inserta `{}`"),
+    ("did you mean `{}`?", "¿quisiste decir `{}`?"),
+    ("rename it to `{}`", "cámbiale el nombre a `{}`"),
+    ("error", "error"),
+    ("warning", "aviso"),
+    ("note", "nota"),
+    ("help", "ayuda"),
+];
+
+/// The parts of `message` where `template` has `{}`, if it matches.
+fn captures<'a>(template: &str, message: &'a str) -> Option<Vec<&'a str>> {
+    let mut literals = template.split("{}");
+    let first = literals.next().unwrap_or("");
+    let mut rest = message.strip_prefix(first)?;
+    let mut captures = Vec::new();
+
+    let literals: Vec<&str> = literals.collect();
+    for (i, literal) in literals.iter().enumerate() {
+        let end = if i + 1 < literals.len() {
+            if literal.is_empty() {
+                return None;
+            }
+            rest.find(literal)?
+        } else if rest.ends_with(literal) {
+            // The last literal ends the message.
+            rest.len() - literal.len()
+        } else {
+            return None;
+        };
+        captures.push(&rest[..end]);
+        rest = &rest[end + literal.len()..];
+    }
+
+    if literals.is_empty() && !rest.is_empty() {
+        None
+    } else {
+        Some(captures)
+    }
+}
+
+/// How `token` is called in Spanish. Punctuation, names and keywords are
+/// quoted like in English.
+fn token(token: &Token) -> String {
+    match *token {
+        Token::Illegal => String::from("un carácter desconocido"),
+        Token::EOF => String::from("el final del archivo"),
+        Token::DocComment(_) => String::from("un comentario de documentación"),
+        Token::Whitespace(_) => String::from("un espacio en blanco"),
+        Token::Ident(_) | Token::Number(_) | Token::Text(_)
+        | Token::ParL | Token::ParR | Token::Colon | Token::Semicolon | Token::Comma
+        | Token::At | Token::Equals | Token::Hash
+        | Token::Type | Token::Module | Token::Interface | Token::Implements | Token::Enum => token.to_string(),
+    }
+}
+
+/// The message for a token the parser didn't expect, in `locale`.
+///
+/// # Examples
+///
+///     use simcom::lexer::Token;
+///     use simcom::locale::{unexpected, Locale};
+///
+///     assert_eq!(unexpected(&Token::Semicolon, Locale::Spanish), "no se esperaba `;`");
+///     assert_eq!(unexpected(&Token::EOF, Locale::Spanish), "no se esperaba el final del archivo");
+///     assert_eq!(unexpected(&Token::EOF, Locale::English), "unexpected end of file");
+pub fn unexpected(token: &Token, locale: Locale) -> String {
+    match locale {
+        Locale::English => format!("unexpected {}", token),
+        Locale::Spanish => format!("no se esperaba {}", self::token(token)),
+    }
+}
+
+/// The message in `locale`, or the same message if the catalog doesn't have
+/// it.
+///
+/// # Examples
+///
+///     use simcom::locale::{translate, Locale};
+///
+///     assert_eq!(translate("type `punto` is never used", Locale::Spanish), "el tipo `punto` no se usa nunca");
+///     assert_eq!(translate("type `punto` is never used", Locale::English), "type `punto` is never used");
+///     assert_eq!(translate("something new", Locale::Spanish), "something new");
+pub fn translate(message: &str, locale: Locale) -> String {
+    if locale == Locale::English {
+        return String::from(message);
+    }
+
+    for &(english, spanish) in CATALOG {
+        let captures = match captures(english, message) {
+            Some(captures) => captures,
+            None => continue,
+        };
+
+        let mut out = String::new();
+        for (i, literal) in spanish.split("{}").enumerate() {
+            if i > 0 {
+                out.push_str(captures[i - 1]);
+            }
+            out.push_str(literal);
+        }
+        return out;
+    }
+
+    String::from(message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::Token;
+    use super::{captures, translate, unexpected, Locale, CATALOG};
+
+    #[test]
+    fn codes() {
+        for &locale in &[Locale::English, Locale::Spanish] {
+            assert_eq!(Locale::from_code(locale.code()), Some(locale));
+        }
+        assert_eq!(Locale::from_code("fr"), None);
+        assert_eq!(Locale::from_posix("es@euro"), Some(Locale::Spanish));
+    }
+
+    #[test]
+    fn templates() {
+        assert_eq!(captures("a {} b {}", "a x b y z"), Some(vec!["x", "y z"]));
+        assert_eq!(captures("a {} b", "a x b c"), None);
+        assert_eq!(captures("a", "a"), Some(vec![]));
+        assert_eq!(captures("a", "ab"), None);
+        assert_eq!(captures("type `{}` is never used", "type `{}` of field `x` is deprecated"), None);
+    }
+
+    #[test]
+    fn every_message_has_the_same_holes() {
+        for &(english, spanish) in CATALOG {
+            assert_eq!(english.matches("{}").count(), spanish.matches("{}").count(), "{}", english);
+        }
+    }
+
+    #[test]
+    fn spanish() {
+        let es = |message| translate(message, Locale::Spanish);
+        assert_eq!(es("unknown character"), "carácter desconocido");
+        assert_eq!(es("field `X` should start with a lowercase letter: `x`"),
+            "el campo `X` debería empezar por minúscula: `x`");
+        assert_eq!(es("type `A` of field `b` is deprecated"), "el tipo `A` del campo `b` está obsoleto");
+        assert_eq!(es("too many errors, the limit is 20"), "demasiados errores, el límite es 20");
+    }
+
+    #[test]
+    fn every_token() {
+        // `token` has no catch-all arm, so a new variant has to be added
+        // there, and here.
+        let tokens = [
+            (Token::Illegal, "no se esperaba un carácter desconocido"),
+            (Token::EOF, "no se esperaba el final del archivo"),
+            (Token::Ident(String::from("Punto")), "no se esperaba `Punto`"),
+            (Token::Number(String::from("5")), "no se esperaba `5`"),
+            (Token::Text(String::from("m")), "no se esperaba `\"m\"`"),
+            (Token::ParL, "no se esperaba `(`"),
+            (Token::ParR, "no se esperaba `)`"),
+            (Token::Colon, "no se esperaba `:`"),
+            (Token::Semicolon, "no se esperaba `;`"),
+            (Token::Comma, "no se esperaba `,`"),
+            (Token::At, "no se esperaba `@`"),
+            (Token::Equals, "no se esperaba `=`"),
+            (Token::Hash, "no se esperaba `#`"),
+            (Token::Type, "no se esperaba `tipo`"),
+            (Token::Module, "no se esperaba `modulo`"),
+            (Token::Interface, "no se esperaba `interfaz`"),
+            (Token::Implements, "no se esperaba `implementa`"),
+            (Token::Enum, "no se esperaba `enum`"),
+            (Token::DocComment(String::from("x")), "no se esperaba un comentario de documentación"),
+            (Token::Whitespace(String::from(" ")), "no se esperaba un espacio en blanco"),
+        ];
+        for (token, spanish) in &tokens {
+            assert_eq!(unexpected(token, Locale::Spanish), *spanish);
+            assert_eq!(unexpected(token, Locale::English), format!("unexpected {}", token));
+        }
+    }
+}
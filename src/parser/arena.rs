@@ -0,0 +1,551 @@
+//! The AST, allocated in an [`Arena`][0].
+//!
+//! The same definitions as in the [parser module][1], but names are `&str`
+//! and lists are slices, all of them in the arena. Parsing doesn't allocate
+//! for every node and dropping the whole AST is dropping the arena. Use the
+//! `From` conversions to get the usual definitions, borrowing from the arena,
+//! when some other phase needs them.
+//!
+//! # Examples
+//!
+//!     use simcom::arena::Arena;
+//!     use simcom::lexer::Lexer;
+//!     use simcom::parser::arena::{Ast, Parser};
+//!
+//!     let arena = Arena::new();
+//!     let mut parser = Parser::new(Lexer::new("tipo Punto(x: Entero, y: Entero);"), &arena);
+//!
+//!     match parser.next().unwrap() {
+//!         Ast::TypeDefinition(definition) => {
+//!             assert_eq!(definition.name, "Punto");
+//!             assert_eq!(definition.parameters[1].typename, "Entero");
+//!         },
+//!         Ast::Unexpected(..) => panic!("expected a definition"),
+//!     }
+//!
+//! [0]: ../../arena/struct.Arena.html
+//! [1]: ../index.html
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::super::arena::Arena;
+use super::super::diagnostics::Diagnostic;
+use super::super::lexer::{Lexer, Span, Token};
+use super::{advance_until_semicolon, arguments, expect, keyword, numbered, DefinitionKind, ParseResult, Tokens};
+
+/// An annotation, like `@obsoleto`, attached to a definition or a parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Annotation<'arena> {
+    pub name: &'arena str,
+    pub arguments: &'arena [Argument<'arena>],
+    pub span: Span,
+}
+
+/// An argument of an annotation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Argument<'arena> {
+    Number(i64),
+    Text(&'arena str),
+}
+
+/// An interface that a type implements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Implementation<'arena> {
+    pub name: &'arena str,
+    pub span: Span,
+}
+
+/// A parameter inside a type definition, like `x: Entero`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parameter<'arena> {
+    pub name: &'arena str,
+    pub typename: &'arena str,
+    /// The number after `#`, if it's written.
+    pub number: Option<i64>,
+    /// The lines of its doc comment, if any.
+    pub docs: &'arena [&'arena str],
+    pub annotations: &'arena [Annotation<'arena>],
+    pub name_span: Span,
+    pub type_span: Span,
+    /// From the first annotation (or the name) to the number, or to the type.
+    pub span: Span,
+}
+
+/// A variant of an enumeration, like `Activo = 1`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Variant<'arena> {
+    pub name: &'arena str,
+    /// The value after `=`, if it's written.
+    pub value: Option<i64>,
+    /// The lines of its doc comment, if any.
+    pub docs: &'arena [&'arena str],
+    pub annotations: &'arena [Annotation<'arena>],
+    pub name_span: Span,
+    /// From the first annotation (or the name) to the value, or to the name
+    /// if there's no value.
+    pub span: Span,
+}
+
+/// A whole type definition, like `tipo Punto(x: Entero, y: Entero);`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TypeDefinition<'arena> {
+    pub name: &'arena str,
+    /// The module it's in, if a `modulo` declaration comes before it.
+    pub module: Option<&'arena str>,
+    pub kind: DefinitionKind,
+    pub parameters: &'arena [Parameter<'arena>],
+    /// The interfaces after `implementa`, only in types.
+    pub implements: &'arena [Implementation<'arena>],
+    /// The variants of an enumeration, which has no parameters.
+    pub variants: &'arena [Variant<'arena>],
+    /// The lines of its doc comment, if any.
+    pub docs: &'arena [&'arena str],
+    pub annotations: &'arena [Annotation<'arena>],
+    pub name_span: Span,
+    /// From the first annotation (or the keyword) to the semicolon.
+    pub span: Span,
+}
+
+impl TypeDefinition<'_> {
+    /// Whether the definition has an annotation called `name`.
+    pub fn has_annotation(&self, name: &str) -> bool {
+        self.annotations.iter().any(|a| a.name == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ast<'arena> {
+    TypeDefinition(TypeDefinition<'arena>),
+    Unexpected(Token, Span),
+}
+
+impl Ast<'_> {
+    /// The error, if this is one.
+    pub fn diagnostic(&self) -> Option<Diagnostic> {
+        match *self {
+            Ast::TypeDefinition(_) => None,
+            Ast::Unexpected(ref token, span) => Some(Diagnostic::unexpected(token, span)),
+        }
+    }
+}
+
+/// Borrows from the arena.
+impl<'arena> From<&Argument<'arena>> for super::Argument<'arena> {
+    fn from(argument: &Argument<'arena>) -> Self {
+        match *argument {
+            Argument::Number(number) => super::Argument::Number(number),
+            Argument::Text(text) => super::Argument::Text(Cow::Borrowed(text)),
+        }
+    }
+}
+
+/// Borrows from the arena.
+impl<'arena> From<&Annotation<'arena>> for super::Annotation<'arena> {
+    fn from(annotation: &Annotation<'arena>) -> Self {
+        super::Annotation {
+            name: Cow::Borrowed(annotation.name),
+            arguments: annotation.arguments.iter().map(From::from).collect(),
+            span: annotation.span,
+        }
+    }
+}
+
+impl<'arena> From<&Implementation<'arena>> for super::Implementation<'arena> {
+    fn from(implementation: &Implementation<'arena>) -> Self {
+        super::Implementation { name: Cow::Borrowed(implementation.name), span: implementation.span }
+    }
+}
+
+impl<'arena> From<&Parameter<'arena>> for super::Parameter<'arena> {
+    fn from(parameter: &Parameter<'arena>) -> Self {
+        super::Parameter {
+            name: Cow::Borrowed(parameter.name),
+            typename: Cow::Borrowed(parameter.typename),
+            number: parameter.number,
+            docs: parameter.docs.iter().map(|&line| Cow::Borrowed(line)).collect(),
+            annotations: parameter.annotations.iter().map(From::from).collect(),
+            name_span: parameter.name_span,
+            type_span: parameter.type_span,
+            span: parameter.span,
+        }
+    }
+}
+
+impl<'arena> From<&Variant<'arena>> for super::Variant<'arena> {
+    fn from(variant: &Variant<'arena>) -> Self {
+        super::Variant {
+            name: Cow::Borrowed(variant.name),
+            value: variant.value,
+            docs: variant.docs.iter().map(|&line| Cow::Borrowed(line)).collect(),
+            annotations: variant.annotations.iter().map(From::from).collect(),
+            name_span: variant.name_span,
+            span: variant.span,
+        }
+    }
+}
+
+impl<'arena> From<&TypeDefinition<'arena>> for super::TypeDefinition<'arena> {
+    fn from(definition: &TypeDefinition<'arena>) -> Self {
+        super::TypeDefinition {
+            name: Cow::Borrowed(definition.name),
+            module: definition.module.map(Cow::Borrowed),
+            kind: definition.kind,
+            parameters: definition.parameters.iter().map(From::from).collect(),
+            implements: definition.implements.iter().map(From::from).collect(),
+            variants: definition.variants.iter().map(From::from).collect(),
+            docs: definition.docs.iter().map(|&line| Cow::Borrowed(line)).collect(),
+            annotations: definition.annotations.iter().map(From::from).collect(),
+            name_span: definition.name_span,
+            span: definition.span,
+        }
+    }
+}
+
+impl<'arena> From<&Ast<'arena>> for super::Ast<'arena> {
+    fn from(ast: &Ast<'arena>) -> Self {
+        match *ast {
+            Ast::TypeDefinition(ref definition) => super::Ast::TypeDefinition(definition.into()),
+            Ast::Unexpected(ref token, span) => super::Ast::Unexpected(token.clone(), span),
+        }
+    }
+}
+
+/// A parser that puts the AST in an arena.
+///
+/// It recognizes the same language and recovers from errors in the same way
+/// as the [usual one][0].
+///
+/// [0]: ../struct.Parser.html
+pub struct Parser<'a, 'arena> {
+    tokens: Tokens<'a>,
+    arena: &'arena Arena,
+    /// The module of the last declaration.
+    module: Option<&'arena str>,
+    /// Lists being built, reused for every definition so only the arena
+    /// grows.
+    docs: Vec<&'arena str>,
+    annotations: Vec<Annotation<'arena>>,
+    arguments: Vec<Argument<'arena>>,
+    parameters: Vec<Parameter<'arena>>,
+    implements: Vec<Implementation<'arena>>,
+    variants: Vec<Variant<'arena>>,
+}
+
+impl<'a, 'arena> Parser<'a, 'arena> {
+    /// Create a new parser that allocates in `arena`.
+    pub fn new(tokens: Lexer<'a>, arena: &'arena Arena) -> Parser<'a, 'arena> {
+        Parser {
+            tokens: Tokens::new(tokens),
+            arena,
+            module: None,
+            docs: Vec::new(),
+            annotations: Vec::new(),
+            arguments: Vec::new(),
+            parameters: Vec::new(),
+            implements: Vec::new(),
+            variants: Vec::new(),
+        }
+    }
+
+    /// Consumes the next token, which must be an identifier, and copies it
+    /// into the arena.
+    fn identifier(&mut self) -> ParseResult<(&'arena str, Span)> {
+        match self.tokens.next() {
+            Some((Token::Ident(name), span)) => Ok((self.arena.alloc_str(&name), span)),
+            Some(unexpected) => Err(unexpected),
+            None => Err((Token::EOF, Span::default())),
+        }
+    }
+
+    /// Like [`identifier`][0], but the name can't be qualified.
+    ///
+    /// [0]: #method.identifier
+    fn name(&mut self) -> ParseResult<(&'arena str, Span)> {
+        match self.identifier()? {
+            (name, span) if name.contains('.') => Err((Token::Ident(String::from(name)), span)),
+            found => Ok(found),
+        }
+    }
+
+    /// Matches any number of doc comment lines.
+    fn docs(&mut self) -> &'arena [&'arena str] {
+        self.docs.clear();
+
+        while let Some(&(Token::DocComment(_), _)) = self.tokens.peek() {
+            if let Some((Token::DocComment(line), _)) = self.tokens.next() {
+                let line = self.arena.alloc_str(&line);
+                self.docs.push(line);
+            }
+        }
+
+        self.arena.alloc_slice(&self.docs)
+    }
+
+    /// Matches any number of annotations (`@name`, maybe with arguments).
+    fn annotations(&mut self) -> ParseResult<&'arena [Annotation<'arena>]> {
+        self.annotations.clear();
+
+        while let Some(&(Token::At, at)) = self.tokens.peek() {
+            self.tokens.next(); // Consume Token::At.
+            let (name, span) = self.identifier()?;
+            self.arguments.clear();
+            let arena = self.arena;
+            let end = arguments(&mut self.tokens, &mut self.arguments, |tokens| argument(tokens, arena))?.unwrap_or(span);
+            let arguments = self.arena.alloc_slice(&self.arguments);
+            self.annotations.push(Annotation { name, arguments, span: at.to(end) });
+        }
+
+        Ok(self.arena.alloc_slice(&self.annotations))
+    }
+
+    /// Matches a module declaration and makes it the current module.
+    fn module(&mut self) -> ParseResult<()> {
+        expect(&mut self.tokens, Token::Module)?;
+        let (module, _) = self.identifier()?;
+        expect(&mut self.tokens, Token::Semicolon)?;
+        self.module = Some(module);
+        Ok(())
+    }
+
+    /// Matches an entire type definition, like the usual parser does.
+    fn definition(&mut self) -> ParseResult<TypeDefinition<'arena>> {
+        let docs = self.docs();
+        let annotations = self.annotations()?;
+        let (kind, keyword) = keyword(&mut self.tokens)?;
+        let (name, name_span) = self.name()?;
+
+        expect(&mut self.tokens, Token::ParL)?;
+        let (parameters, variants) = match kind {
+            DefinitionKind::Enum => (&[][..], self.variants()?),
+            _ => (self.parameters()?, &[][..]),
+        };
+        expect(&mut self.tokens, Token::ParR)?;
+        let implements = match kind {
+            DefinitionKind::Type => self.implements()?,
+            _ => &[],
+        };
+        let end = expect(&mut self.tokens, Token::Semicolon)?;
+
+        let start = annotations.first().map(|a| a.span).unwrap_or(keyword);
+
+        Ok(TypeDefinition {
+            name,
+            module: self.module,
+            kind,
+            parameters,
+            implements,
+            variants,
+            docs,
+            annotations,
+            name_span,
+            span: start.to(end),
+        })
+    }
+
+    /// Matches the interfaces after Token::Implements, if it's there.
+    fn implements(&mut self) -> ParseResult<&'arena [Implementation<'arena>]> {
+        self.implements.clear();
+
+        if let Some(&(Token::Implements, _)) = self.tokens.peek() {
+            self.tokens.next(); // Consume Token::Implements.
+            loop {
+                let (name, span) = self.identifier()?;
+                self.implements.push(Implementation { name, span });
+                match self.tokens.peek() {
+                    Some(&(Token::Comma, _)) => self.tokens.next(),
+                    _ => break,
+                };
+            }
+        }
+
+        Ok(self.arena.alloc_slice(&self.implements))
+    }
+
+    /// Matches the parameters separated by commas, with an optional trailing
+    /// one.
+    fn parameters(&mut self) -> ParseResult<&'arena [Parameter<'arena>]> {
+        self.parameters.clear();
+
+        loop {
+            let parameter = self.parameter()?;
+            self.parameters.push(parameter);
+
+            match self.tokens.peek() {
+                Some(&(Token::Comma, _)) => {
+                    self.tokens.next(); // Consume Token::Comma.
+                    if let Some(&(Token::ParR, _)) = self.tokens.peek() {
+                        break;
+                    }
+                },
+                _ => break,
+            }
+        }
+
+        Ok(self.arena.alloc_slice(&self.parameters))
+    }
+
+    /// Matches the variants of an enumeration, like the parameters.
+    fn variants(&mut self) -> ParseResult<&'arena [Variant<'arena>]> {
+        self.variants.clear();
+
+        loop {
+            let variant = self.variant()?;
+            self.variants.push(variant);
+
+            match self.tokens.peek() {
+                Some(&(Token::Comma, _)) => {
+                    self.tokens.next(); // Consume Token::Comma.
+                    if let Some(&(Token::ParR, _)) = self.tokens.peek() {
+                        break;
+                    }
+                },
+                _ => break,
+            }
+        }
+
+        Ok(self.arena.alloc_slice(&self.variants))
+    }
+
+    /// Matches a variant and its value, if it has one.
+    fn variant(&mut self) -> ParseResult<Variant<'arena>> {
+        let docs = self.docs();
+        let annotations = self.annotations()?;
+        let (name, name_span) = self.name()?;
+        let value = numbered(&mut self.tokens, Token::Equals)?;
+
+        let start = annotations.first().map(|a| a.span).unwrap_or(name_span);
+        let end = value.map(|(_, span)| span).unwrap_or(name_span);
+
+        Ok(Variant {
+            name,
+            value: value.map(|(value, _)| value),
+            docs,
+            annotations,
+            name_span,
+            span: start.to(end),
+        })
+    }
+
+    /// Matches a parameter, optionally preceded by a doc comment and
+    /// annotations.
+    fn parameter(&mut self) -> ParseResult<Parameter<'arena>> {
+        let docs = self.docs();
+        let annotations = self.annotations()?;
+        let (name, name_span) = self.name()?;
+        expect(&mut self.tokens, Token::Colon)?;
+        let (typename, type_span) = self.identifier()?;
+        let number = numbered(&mut self.tokens, Token::Hash)?;
+
+        let start = annotations.first().map(|a| a.span).unwrap_or(name_span);
+        let end = number.map(|(_, span)| span).unwrap_or(type_span);
+
+        Ok(Parameter {
+            name,
+            typename,
+            number: number.map(|(number, _)| number),
+            docs,
+            annotations,
+            name_span,
+            type_span,
+            span: start.to(end),
+        })
+    }
+}
+
+/// Matches an argument of an annotation, copying texts into `arena`.
+fn argument<'arena>(tokens: &mut Tokens, arena: &'arena Arena) -> ParseResult<Argument<'arena>> {
+    match tokens.next() {
+        Some((Token::Number(number), span)) => match number.parse() {
+            Ok(number) => Ok(Argument::Number(number)),
+            Err(_) => Err((Token::Number(number), span)),
+        },
+        Some((Token::Text(text), _)) => Ok(Argument::Text(arena.alloc_str(&text))),
+        Some(unexpected) => Err(unexpected),
+        None => Err((Token::EOF, Span::default())),
+    }
+}
+
+impl<'arena> Iterator for Parser<'_, 'arena> {
+    type Item = Ast<'arena>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Like the usual parser, the end of the input is only an error in
+        // the middle of a definition, and module declarations aren't in the
+        // AST.
+        let result = loop {
+            match self.tokens.peek() {
+                None | Some(&(Token::EOF, _)) => return None,
+                Some(&(Token::Module, _)) => if let Err(error) = self.module() {
+                    break Err(error);
+                },
+                _ => break self.definition(),
+            }
+        };
+
+        match result {
+            Ok(definition) => Some(Ast::TypeDefinition(definition)),
+            Err((token, span)) => {
+                advance_until_semicolon(&mut self.tokens);
+                Some(Ast::Unexpected(token, span))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::arena::Arena;
+    use super::super::super::lexer::Lexer;
+    use super::super::Parser as UsualParser;
+    use super::*;
+
+    /// Parses `input` with both parsers, the arena one converted.
+    fn both(input: &str) -> (Vec<super::super::Ast<'static>>, Vec<super::super::Ast<'_>>) {
+        let arena = Arena::new();
+        let asts = Parser::new(Lexer::new(input), &arena)
+            .map(|ast| super::super::Ast::from(&ast).into_owned())
+            .collect();
+
+        (asts, UsualParser::new(Lexer::new(input)).collect())
+    }
+
+    #[test]
+    fn same_as_the_usual_parser() {
+        let inputs = [
+            "tipo Punto(x: Entero, y: Entero);",
+            "/// Un punto.\n@obsoleto @otra tipo P(\n/// Horizontal.\n@a x: E,\ny: E,\n);",
+            "tipo A(x B); tipo C(y: D);",
+            "tipo P(x: E,,); @ tipo; tipo P(x: E)",
+            "tipo P(x: /// No.\n E); ? tipo Q(q: R);",
+            "tipo A(x: B); modulo geo; tipo P(a: A, q: geo.Q); modulo ; tipo geo.R(); modulo geo.a; tipo S(s: S);",
+            "interfaz N(n: T) implementa M; interfaz N(n: T); tipo P(n: T) implementa N, geo.M; tipo Q(q: T) implementa;",
+            "enum E(A, /// B.\n@x B = -2,); enum F(A = 99999999999999999999); enum G(a: E); enum H(A = B); tipo I(A = 1);",
+        ];
+
+        for input in &inputs {
+            let (arena, usual) = both(input);
+            assert_eq!(arena, usual, "{}", input);
+        }
+    }
+
+    #[test]
+    fn lists_do_not_mix() {
+        let arena = Arena::new();
+        let input = "/// A.\n@a tipo A(/// x.\n@b x: E, @c y: E); tipo B(z: E);";
+        let asts: Vec<Ast> = Parser::new(Lexer::new(input), &arena).collect();
+
+        let a = match asts[0] {
+            Ast::TypeDefinition(definition) => definition,
+            _ => panic!("Expected a definition"),
+        };
+        assert_eq!(a.docs, ["A."]);
+        assert!(a.has_annotation("a"));
+        assert_eq!(a.parameters.len(), 2);
+        assert_eq!(a.parameters[0].docs, ["x."]);
+        assert_eq!(a.parameters[0].annotations[0].name, "b");
+        assert_eq!(a.parameters[1].annotations[0].name, "c");
+        assert_eq!(asts[1].diagnostic(), None);
+    }
+}
@@ -0,0 +1,285 @@
+//! Incremental reparsing.
+//!
+//! An editor changes a few characters at a time, and parsing the whole file
+//! on every keystroke is too slow once it has thousands of definitions. A
+//! [`ParsedFile`][0] keeps the text and its AST together and, after each
+//! [`Edit`][1], only parses again the definitions the edit touches.
+//!
+//! The parser only carries the module from one definition to the next, so it
+//! can start again right after any definition (after its `;`), in the module
+//! of that definition. It starts after the last definition that ends before
+//! the edit and stops as soon as it finishes a definition that ends where one
+//! of the old ones did, past the edit, and in the same module: from there on
+//! the text is the same, so the old nodes are kept, only moved.
+//!
+//! # Examples
+//!
+//!     use simcom::lexer::Language;
+//!     use simcom::parser::incremental::{Edit, ParsedFile};
+//!
+//!     let source = "tipo A(x: Entero);\ntipo B(y: A);\ntipo C(z: B);";
+//!     let mut file = ParsedFile::new(String::from(source), Language::Spanish);
+//!     assert_eq!(file.asts().len(), 3);
+//!
+//!     // Renaming `y` only parses `B` again.
+//!     let reparsed = file.edit(&Edit { range: 26..27, new_text: String::from("otro") });
+//!     assert_eq!(reparsed, 1..2);
+//!     assert_eq!(file.source(), "tipo A(x: Entero);\ntipo B(otro: A);\ntipo C(z: B);");
+//!
+//! [0]: struct.ParsedFile.html
+//! [1]: struct.Edit.html
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use super::super::lexer::{Language, Lexer, Span};
+use super::{Ast, Parser};
+
+/// A change to the text: the bytes in `range` are replaced by `new_text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub new_text: String,
+}
+
+/// The text of a file and what it parses to.
+#[derive(Debug, Clone)]
+pub struct ParsedFile {
+    source: String,
+    language: Language,
+    asts: Vec<Ast<'static>>,
+}
+
+/// Parses `source` from `offset`, which has to be the start or the end of a
+/// definition, in `module`.
+fn parse<'a>(source: &'a str, offset: usize, language: Language, module: Option<&'a str>) -> impl Iterator<Item = Ast<'static>> + 'a {
+    Parser::new(Lexer::with_language(&source[offset..], language).starting_at(offset))
+        .in_module(module.map(Cow::Borrowed))
+        .map(Ast::into_owned)
+}
+
+/// Where a definition ends, if it's one.
+fn end(ast: &Ast) -> Option<usize> {
+    match *ast {
+        Ast::TypeDefinition(ref definition) => Some(definition.span.end),
+        Ast::Unexpected(..) => None,
+    }
+}
+
+/// The module of a definition.
+fn module<'a>(ast: &'a Ast) -> Option<&'a str> {
+    match *ast {
+        Ast::TypeDefinition(ref definition) => definition.module.as_deref(),
+        Ast::Unexpected(..) => None,
+    }
+}
+
+/// `span`, `delta` bytes later.
+fn moved(span: Span, delta: isize) -> Span {
+    Span {
+        start: (span.start as isize + delta) as usize,
+        end: (span.end as isize + delta) as usize,
+        ..span
+    }
+}
+
+/// Moves every span of `ast` by `delta` bytes.
+fn shift(ast: &mut Ast, delta: isize) {
+    let definition = match *ast {
+        Ast::TypeDefinition(ref mut definition) => definition,
+        Ast::Unexpected(_, ref mut span) => {
+            *span = moved(*span, delta);
+            return;
+        },
+    };
+
+    definition.span = moved(definition.span, delta);
+    definition.name_span = moved(definition.name_span, delta);
+    for annotation in &mut definition.annotations {
+        annotation.span = moved(annotation.span, delta);
+    }
+    for implementation in &mut definition.implements {
+        implementation.span = moved(implementation.span, delta);
+    }
+    for variant in &mut definition.variants {
+        variant.span = moved(variant.span, delta);
+        variant.name_span = moved(variant.name_span, delta);
+        for annotation in &mut variant.annotations {
+            annotation.span = moved(annotation.span, delta);
+        }
+    }
+    for parameter in &mut definition.parameters {
+        parameter.span = moved(parameter.span, delta);
+        parameter.name_span = moved(parameter.name_span, delta);
+        parameter.type_span = moved(parameter.type_span, delta);
+        for annotation in &mut parameter.annotations {
+            annotation.span = moved(annotation.span, delta);
+        }
+    }
+}
+
+impl ParsedFile {
+    /// Parses the whole text.
+    pub fn new(source: String, language: Language) -> ParsedFile {
+        let asts = parse(&source, 0, language, None).collect();
+        ParsedFile { source, language, asts }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The definitions and the errors, in the order of the text, as the
+    /// [`Parser`][0] returns them.
+    ///
+    /// [0]: ../struct.Parser.html
+    pub fn asts(&self) -> &[Ast<'static>] {
+        &self.asts
+    }
+
+    /// Applies `edit` to the text and parses again what it touched. Returns
+    /// which of the [`asts`][0] are new, the rest were kept.
+    ///
+    /// # Panics
+    ///
+    /// If the range is out of the text or doesn't start and end at character
+    /// boundaries, like `String::replace_range`.
+    ///
+    /// [0]: #method.asts
+    pub fn edit(&mut self, edit: &Edit) -> Range<usize> {
+        let Edit { ref range, ref new_text } = *edit;
+        self.source.replace_range(range.clone(), new_text);
+
+        // The last definition that ends before the edit, and everything
+        // before it, stays as it was.
+        let kept = self.asts.iter()
+            .rposition(|ast| end(ast).is_some_and(|end| end <= range.start))
+            .map_or(0, |i| i + 1);
+        let restart = self.asts[..kept].last().and_then(end).unwrap_or(0);
+        let mut old = self.asts.split_off(kept).into_iter().peekable();
+        let restart_module = self.asts.last().and_then(module).map(String::from);
+
+        let delta = new_text.len() as isize - range.len() as isize;
+        let edit_end = range.start + new_text.len();
+
+        for ast in parse(&self.source, restart, self.language, restart_module.as_deref()) {
+            let synced = match end(&ast) {
+                Some(new_end) if new_end >= edit_end => {
+                    // The same place in the old text, after the edit.
+                    let old_end = (new_end as isize - delta) as usize;
+                    while old.peek().is_some_and(|old| end_or_start(old) < old_end) {
+                        old.next();
+                    }
+                    old.peek().and_then(end) == Some(old_end) && old.peek().and_then(module) == module(&ast)
+                },
+                _ => false,
+            };
+            self.asts.push(ast);
+
+            if synced {
+                let parsed = kept..self.asts.len();
+                self.asts.extend(old.skip(1).map(|mut ast| {
+                    shift(&mut ast, delta);
+                    ast
+                }));
+                return parsed;
+            }
+        }
+
+        kept..self.asts.len()
+    }
+}
+
+/// Where an old node is, to skip the ones before a position.
+fn end_or_start(ast: &Ast) -> usize {
+    match *ast {
+        Ast::TypeDefinition(ref definition) => definition.span.end,
+        Ast::Unexpected(_, span) => span.start,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::lexer::{Language, Lexer};
+    use super::super::{Ast, Parser};
+    use super::{Edit, ParsedFile};
+
+    /// Applies the edit and checks that the result is what parsing the whole
+    /// text gives.
+    fn check(source: &str, range: core::ops::Range<usize>, new_text: &str) -> core::ops::Range<usize> {
+        let mut file = ParsedFile::new(String::from(source), Language::Spanish);
+        let reparsed = file.edit(&Edit { range, new_text: String::from(new_text) });
+
+        let expected: Vec<Ast> = Parser::new(Lexer::new(file.source())).collect();
+        assert_eq!(file.asts(), &expected[..], "{}", file.source());
+        reparsed
+    }
+
+    const SOURCE: &str = "tipo A(x: Entero);\n/// Docs.\ntipo B(y: A);\n@obsoleto tipo C(@otra z: B);\ntipo D(w: C);";
+
+    const MODULES: &str = "tipo A(x: Entero);\nmodulo geo;\ntipo B(y: A);\nmodulo red;\ntipo C(z: geo.B);\ntipo D(w: C);";
+
+    #[test]
+    fn inside_a_definition() {
+        assert_eq!(check(SOURCE, 36..37, "otro"), 1..2);
+        assert_eq!(check(SOURCE, 60..66, ""), 2..3);
+        assert_eq!(check(SOURCE, 0..4, "tipo"), 0..1);
+        assert_eq!(check(SOURCE, 19..28, "/// Otros docs."), 1..2);
+    }
+
+    #[test]
+    fn between_definitions() {
+        assert_eq!(check(SOURCE, 18..18, " tipo N(n: A);"), 1..3);
+        assert_eq!(check(SOURCE, SOURCE.len()..SOURCE.len(), "\ntipo E(v: D);"), 4..5);
+        assert_eq!(check(SOURCE, 0..0, "tipo Z(z: Z);"), 0..2);
+    }
+
+    #[test]
+    fn breaking_and_fixing() {
+        // Removing a `;` joins two definitions in a single error.
+        assert_eq!(check(SOURCE, 17..18, ""), 0..2);
+        assert_eq!(check(SOURCE, 0..SOURCE.len(), ""), 0..0);
+
+        let mut file = ParsedFile::new(String::from("tipo A(x B); tipo C(y: A);"), Language::Spanish);
+        assert!(matches!(file.asts()[0], Ast::Unexpected(..)));
+        file.edit(&Edit { range: 8..8, new_text: String::from(":") });
+        assert_eq!(file.asts(), &Parser::new(Lexer::new(file.source())).collect::<Vec<_>>()[..]);
+        assert_eq!(file.asts().len(), 2);
+    }
+
+    #[test]
+    fn every_position() {
+        for i in 0..SOURCE.len() {
+            check(SOURCE, i..i + 1, "");
+            check(SOURCE, i..i, ";");
+            check(SOURCE, i..i, " tipo X(x: X) ");
+        }
+    }
+
+    #[test]
+    fn modules() {
+        // Declaring a module changes every definition after it.
+        assert_eq!(check(MODULES, 55..55, "es"), 2..4);
+        assert_eq!(check(MODULES, 19..31, ""), 1..3);
+        // But editing a definition doesn't change its module.
+        assert_eq!(check(MODULES, 64..65, "w"), 2..3);
+
+        for i in 0..MODULES.len() {
+            check(MODULES, i..i + 1, "");
+            check(MODULES, i..i, ";");
+            check(MODULES, i..i, " modulo m; ");
+        }
+    }
+
+    #[test]
+    fn many_edits() {
+        let mut file = ParsedFile::new(String::from(SOURCE), Language::Spanish);
+        for (i, &(start, end, text)) in [(29, 30, "yy"), (0, 0, "tipo Z(z: A);\n"), (80, 81, "W"), (5, 6, "AA")].iter().enumerate() {
+            file.edit(&Edit { range: start..end, new_text: String::from(text) });
+            let expected: Vec<Ast> = Parser::new(Lexer::new(file.source())).collect();
+            assert_eq!(file.asts(), &expected[..], "edit {}", i);
+        }
+    }
+}
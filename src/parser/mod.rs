@@ -1,25 +1,692 @@
-use std::iter::Peekable;
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
 
-use super::lexer::{Lexer, Token};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
-pub enum Ast {
-    TypeDefinition(String, Vec<Ast>),
-    Parameter(String, String),
-    Unexpected(Token),
-    Empty,
+use super::diagnostics::Diagnostic;
+use super::json::{FromJson, Json, ToJson};
+use super::lexer::{quote, Cursor, Lexer, Span, Spanned, Token};
+
+pub mod arena;
+pub mod incremental;
+
+/// An annotation, like `@obsoleto` or `@desde(2)`, attached to a definition
+/// or a parameter.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Annotation<'src> {
+    pub name: Cow<'src, str>,
+    /// What's between the parenthesis after the name, if there are any.
+    pub arguments: Vec<Argument<'src>>,
+    /// From the `@` to the name, or to the `)` of the arguments.
+    pub span: Span,
+}
+
+/// An argument of an annotation.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Argument<'src> {
+    Number(i64),
+    /// A text, like `"m"`, without the quotes.
+    Text(Cow<'src, str>),
+}
+
+/// An interface that a type implements, like `Nombrado` in
+/// `tipo Persona(nombre: Texto) implementa Nombrado;`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Implementation<'src> {
+    pub name: Cow<'src, str>,
+    pub span: Span,
+}
+
+/// Whether a definition is a type, with `tipo`, an interface, with
+/// `interfaz`, or an enumeration, with `enum`. Interfaces only list the
+/// fields that the types implementing them must have, and enumerations have
+/// variants instead of fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DefinitionKind {
+    #[default]
+    Type,
+    Interface,
+    Enum,
+}
+
+impl DefinitionKind {
+    /// The keyword that starts the definition.
+    pub fn keyword(self) -> Token {
+        match self {
+            DefinitionKind::Type => Token::Type,
+            DefinitionKind::Interface => Token::Interface,
+            DefinitionKind::Enum => Token::Enum,
+        }
+    }
+
+    /// How it's called in messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            DefinitionKind::Type => "type",
+            DefinitionKind::Interface => "interface",
+            DefinitionKind::Enum => "enum",
+        }
+    }
+}
+
+/// A parameter inside a type definition, like `x: Entero`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Parameter<'src> {
+    pub name: Cow<'src, str>,
+    pub typename: Cow<'src, str>,
+    /// The number after `#`, if it's written, which identifies the field in
+    /// binary formats even if it's renamed or moved.
+    pub number: Option<i64>,
+    /// The lines of its doc comment, if any.
+    pub docs: Vec<Cow<'src, str>>,
+    pub annotations: Vec<Annotation<'src>>,
+    pub name_span: Span,
+    pub type_span: Span,
+    /// From the first annotation (or the name) to the number, or to the type
+    /// if it has none.
+    pub span: Span,
+}
+
+/// A variant of an enumeration, like `Activo = 1`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Variant<'src> {
+    pub name: Cow<'src, str>,
+    /// The value after `=`, if it's written. See
+    /// [`TypeDefinition::values`][0] for the ones that aren't.
+    ///
+    /// [0]: struct.TypeDefinition.html#method.values
+    pub value: Option<i64>,
+    /// The lines of its doc comment, if any.
+    pub docs: Vec<Cow<'src, str>>,
+    pub annotations: Vec<Annotation<'src>>,
+    pub name_span: Span,
+    /// From the first annotation (or the name) to the value, or to the name
+    /// if there's no value.
+    pub span: Span,
+}
+
+/// A whole type definition, like `tipo Punto(x: Entero, y: Entero);`, an
+/// interface or an enumeration.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TypeDefinition<'src> {
+    pub name: Cow<'src, str>,
+    /// The module it's in, if a `modulo` declaration comes before it in its
+    /// file.
+    pub module: Option<Cow<'src, str>>,
+    pub kind: DefinitionKind,
+    pub parameters: Vec<Parameter<'src>>,
+    /// The interfaces after `implementa`, only in types.
+    pub implements: Vec<Implementation<'src>>,
+    /// The variants of an enumeration, which has no parameters.
+    pub variants: Vec<Variant<'src>>,
+    /// The lines of its doc comment, if any.
+    pub docs: Vec<Cow<'src, str>>,
+    pub annotations: Vec<Annotation<'src>>,
+    pub name_span: Span,
+    /// From the first annotation (or the keyword) to the semicolon.
+    pub span: Span,
+}
+
+/// Copies every borrowed line, so nothing points to the source anymore.
+fn owned_lines(lines: Vec<Cow<str>>) -> Vec<Cow<'static, str>> {
+    lines.into_iter().map(|line| Cow::Owned(line.into_owned())).collect()
+}
+
+impl Annotation<'_> {
+    /// The same annotation, not borrowing from the source.
+    pub fn into_owned(self) -> Annotation<'static> {
+        Annotation {
+            name: Cow::Owned(self.name.into_owned()),
+            arguments: self.arguments.into_iter().map(Argument::into_owned).collect(),
+            span: self.span,
+        }
+    }
+
+    /// The argument, if the annotation has exactly one and it's a number.
+    pub fn number(&self) -> Option<i64> {
+        match self.arguments[..] {
+            [Argument::Number(number)] => Some(number),
+            _ => None,
+        }
+    }
+
+    /// The argument, if the annotation has exactly one and it's a text.
+    pub fn text(&self) -> Option<&str> {
+        match self.arguments[..] {
+            [Argument::Text(ref text)] => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl Argument<'_> {
+    /// The same argument, not borrowing from the source.
+    pub fn into_owned(self) -> Argument<'static> {
+        match self {
+            Argument::Number(number) => Argument::Number(number),
+            Argument::Text(text) => Argument::Text(Cow::Owned(text.into_owned())),
+        }
+    }
+}
+
+/// As it's written: `@desde(2)`.
+impl fmt::Display for Annotation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "@{}", self.name)?;
+        if !self.arguments.is_empty() {
+            let arguments: Vec<String> = self.arguments.iter().map(ToString::to_string).collect();
+            write!(f, "({})", arguments.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Argument<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Argument::Number(number) => write!(f, "{}", number),
+            Argument::Text(ref text) => write!(f, "{}", quote(text)),
+        }
+    }
+}
+
+impl Implementation<'_> {
+    /// The same implementation, not borrowing from the source.
+    pub fn into_owned(self) -> Implementation<'static> {
+        Implementation { name: Cow::Owned(self.name.into_owned()), span: self.span }
+    }
+}
+
+impl Variant<'_> {
+    /// The same variant, not borrowing from the source.
+    pub fn into_owned(self) -> Variant<'static> {
+        Variant {
+            name: Cow::Owned(self.name.into_owned()),
+            value: self.value,
+            docs: owned_lines(self.docs),
+            annotations: self.annotations.into_iter().map(Annotation::into_owned).collect(),
+            name_span: self.name_span,
+            span: self.span,
+        }
+    }
+}
+
+impl Parameter<'_> {
+    /// The same parameter, not borrowing from the source.
+    pub fn into_owned(self) -> Parameter<'static> {
+        Parameter {
+            name: Cow::Owned(self.name.into_owned()),
+            typename: Cow::Owned(self.typename.into_owned()),
+            number: self.number,
+            docs: owned_lines(self.docs),
+            annotations: self.annotations.into_iter().map(Annotation::into_owned).collect(),
+            name_span: self.name_span,
+            type_span: self.type_span,
+            span: self.span,
+        }
+    }
+}
+
+impl TypeDefinition<'_> {
+    /// Whether the definition has an annotation called `name`.
+    pub fn has_annotation(&self, name: &str) -> bool {
+        self.annotations.iter().any(|a| a.name == name)
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.kind == DefinitionKind::Interface
+    }
+
+    /// The value of every variant of an enumeration, in order. Like in C, a
+    /// variant without a value has the next one after the variant before it,
+    /// and the first one is 0.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::parser::{Ast, Parser};
+    ///     use simcom::lexer::Lexer;
+    ///
+    ///     match Parser::new(Lexer::new("enum Estado(Nuevo, Activo = 5, Inactivo, Borrado = -1);")).next() {
+    ///         Some(Ast::TypeDefinition(estado)) => assert_eq!(estado.values(), vec![0, 5, 6, -1]),
+    ///         _ => panic!("expected a definition"),
+    ///     }
+    pub fn values(&self) -> Vec<i64> {
+        let mut next = 0i64;
+        self.variants.iter()
+            .map(|variant| {
+                let value = variant.value.unwrap_or(next);
+                next = value.saturating_add(1);
+                value
+            })
+            .collect()
+    }
+
+    /// The name with its module, like `geometria.Punto`, which is how it's
+    /// referred to from other modules.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::parser::{Ast, Parser};
+    ///     use simcom::lexer::Lexer;
+    ///
+    ///     let asts: Vec<Ast> = Parser::new(Lexer::new("tipo Id(x: Entero); modulo geometria; tipo Punto(x: Entero);")).collect();
+    ///     assert!(!asts.iter().any(|ast| matches!(*ast, Ast::Unexpected(..))));
+    ///     match asts[..] {
+    ///         [Ast::TypeDefinition(ref id), Ast::TypeDefinition(ref punto)] => {
+    ///             assert_eq!(id.qualified_name(), "Id");
+    ///             assert_eq!(punto.qualified_name(), "geometria.Punto");
+    ///         },
+    ///         _ => panic!("expected two definitions"),
+    ///     }
+    pub fn qualified_name(&self) -> Cow<'_, str> {
+        match self.module {
+            Some(ref module) => Cow::Owned(format!("{}.{}", module, self.name)),
+            None => Cow::Borrowed(&self.name),
+        }
+    }
+
+    /// The same definition, not borrowing from the source, so it can outlive
+    /// it.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::Lexer;
+    ///     use simcom::parser::{Ast, Parser, TypeDefinition};
+    ///
+    ///     fn parse(source: &str) -> TypeDefinition<'static> {
+    ///         match Parser::new(Lexer::new(source)).next() {
+    ///             Some(Ast::TypeDefinition(definition)) => definition.into_owned(),
+    ///             _ => panic!("expected a definition"),
+    ///         }
+    ///     }
+    ///
+    ///     let definition = parse(&String::from("tipo Punto(x: Entero);"));
+    ///     assert_eq!(definition.name, "Punto");
+    pub fn into_owned(self) -> TypeDefinition<'static> {
+        TypeDefinition {
+            name: Cow::Owned(self.name.into_owned()),
+            module: self.module.map(|module| Cow::Owned(module.into_owned())),
+            kind: self.kind,
+            parameters: self.parameters.into_iter().map(Parameter::into_owned).collect(),
+            implements: self.implements.into_iter().map(Implementation::into_owned).collect(),
+            variants: self.variants.into_iter().map(Variant::into_owned).collect(),
+            docs: owned_lines(self.docs),
+            annotations: self.annotations.into_iter().map(Annotation::into_owned).collect(),
+            name_span: self.name_span,
+            span: self.span,
+        }
+    }
+}
+
+/// What the parser produces for each definition in the input. Names and doc
+/// comments borrow from the source while they can; use
+/// [`into_owned`][0] to keep them longer than it.
+///
+/// [0]: #method.into_owned
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Ast<'src> {
+    TypeDefinition(TypeDefinition<'src>),
+    Unexpected(Token, Span),
+}
+
+impl Ast<'_> {
+    /// The same AST, not borrowing from the source.
+    pub fn into_owned(self) -> Ast<'static> {
+        match self {
+            Ast::TypeDefinition(definition) => Ast::TypeDefinition(definition.into_owned()),
+            Ast::Unexpected(token, span) => Ast::Unexpected(token, span),
+        }
+    }
+
+    /// The error, if this is one.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::Lexer;
+    ///     use simcom::parser::Parser;
+    ///
+    ///     let mut parser = Parser::new(Lexer::new("tipo A(x: Entero);;"));
+    ///     assert_eq!(parser.next().unwrap().diagnostic(), None);
+    ///
+    ///     let diagnostic = parser.next().unwrap().diagnostic().unwrap();
+    ///     assert_eq!(diagnostic.message, "unexpected `;`");
+    pub fn diagnostic(&self) -> Option<Diagnostic> {
+        match *self {
+            Ast::TypeDefinition(_) => None,
+            Ast::Unexpected(ref token, span) => Some(Diagnostic::unexpected(token, span)),
+        }
+    }
+}
+
+/// A definition that couldn't be parsed: the token that was misplaced and
+/// where it was.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub token: Token,
+    pub span: Span,
+}
+
+impl ParseError {
+    /// The error as a diagnostic, like [`Ast::diagnostic`][0].
+    ///
+    /// [0]: enum.Ast.html#method.diagnostic
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::unexpected(&self.token, self.span)
+    }
+}
+
+/// Like "unexpected `;`".
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.token {
+            Token::Illegal => write!(f, "unknown character"),
+            ref token => write!(f, "unexpected {}", token),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for ParseError {}
+
+impl ToJson for Annotation<'_> {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("name", self.name.to_json()),
+            ("arguments", self.arguments.to_json()),
+            ("span", self.span.to_json()),
+        ])
+    }
+}
+
+impl FromJson for Annotation<'static> {
+    fn from_json(json: &Json) -> Result<Annotation<'static>, String> {
+        Ok(Annotation {
+            name: json.field("name")?,
+            arguments: json.field("arguments")?,
+            span: json.field("span")?,
+        })
+    }
+}
+
+/// Like tokens, an object with the kind as the key: `{"Number":2}`.
+impl ToJson for Argument<'_> {
+    fn to_json(&self) -> Json {
+        match *self {
+            Argument::Number(number) => Json::object(vec![("Number", number.to_json())]),
+            Argument::Text(ref text) => Json::object(vec![("Text", text.to_json())]),
+        }
+    }
+}
+
+impl FromJson for Argument<'static> {
+    fn from_json(json: &Json) -> Result<Argument<'static>, String> {
+        match *json {
+            Json::Object(ref pairs) if pairs.len() == 1 => match &pairs[0].0[..] {
+                "Number" => Ok(Argument::Number(i64::from_json(&pairs[0].1)?)),
+                "Text" => Ok(Argument::Text(FromJson::from_json(&pairs[0].1)?)),
+                _ => Err(String::from("expected an argument")),
+            },
+            _ => Err(String::from("expected an argument")),
+        }
+    }
+}
+
+impl ToJson for Implementation<'_> {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("name", self.name.to_json()),
+            ("span", self.span.to_json()),
+        ])
+    }
+}
+
+impl FromJson for Implementation<'static> {
+    fn from_json(json: &Json) -> Result<Implementation<'static>, String> {
+        Ok(Implementation {
+            name: json.field("name")?,
+            span: json.field("span")?,
+        })
+    }
+}
+
+/// The name of the variant.
+impl ToJson for DefinitionKind {
+    fn to_json(&self) -> Json {
+        match *self {
+            DefinitionKind::Type => Json::from("Type"),
+            DefinitionKind::Interface => Json::from("Interface"),
+            DefinitionKind::Enum => Json::from("Enum"),
+        }
+    }
+}
+
+impl FromJson for DefinitionKind {
+    fn from_json(json: &Json) -> Result<DefinitionKind, String> {
+        match json.as_str() {
+            Some("Type") => Ok(DefinitionKind::Type),
+            Some("Interface") => Ok(DefinitionKind::Interface),
+            Some("Enum") => Ok(DefinitionKind::Enum),
+            _ => Err(String::from("expected `Type`, `Interface` or `Enum`")),
+        }
+    }
 }
 
-type ParseResult = Result<Ast, Token>;
+impl ToJson for Parameter<'_> {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("name", self.name.to_json()),
+            ("typename", self.typename.to_json()),
+            ("number", self.number.to_json()),
+            ("docs", self.docs.to_json()),
+            ("annotations", self.annotations.to_json()),
+            ("name_span", self.name_span.to_json()),
+            ("type_span", self.type_span.to_json()),
+            ("span", self.span.to_json()),
+        ])
+    }
+}
+
+impl FromJson for Parameter<'static> {
+    fn from_json(json: &Json) -> Result<Parameter<'static>, String> {
+        Ok(Parameter {
+            name: json.field("name")?,
+            typename: json.field("typename")?,
+            number: json.field("number")?,
+            docs: json.field("docs")?,
+            annotations: json.field("annotations")?,
+            name_span: json.field("name_span")?,
+            type_span: json.field("type_span")?,
+            span: json.field("span")?,
+        })
+    }
+}
+
+impl ToJson for Variant<'_> {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("name", self.name.to_json()),
+            ("value", self.value.to_json()),
+            ("docs", self.docs.to_json()),
+            ("annotations", self.annotations.to_json()),
+            ("name_span", self.name_span.to_json()),
+            ("span", self.span.to_json()),
+        ])
+    }
+}
+
+impl FromJson for Variant<'static> {
+    fn from_json(json: &Json) -> Result<Variant<'static>, String> {
+        Ok(Variant {
+            name: json.field("name")?,
+            value: json.field("value")?,
+            docs: json.field("docs")?,
+            annotations: json.field("annotations")?,
+            name_span: json.field("name_span")?,
+            span: json.field("span")?,
+        })
+    }
+}
+
+impl ToJson for TypeDefinition<'_> {
+    fn to_json(&self) -> Json {
+        Json::object(vec![
+            ("name", self.name.to_json()),
+            ("module", self.module.to_json()),
+            ("kind", self.kind.to_json()),
+            ("parameters", self.parameters.to_json()),
+            ("implements", self.implements.to_json()),
+            ("variants", self.variants.to_json()),
+            ("docs", self.docs.to_json()),
+            ("annotations", self.annotations.to_json()),
+            ("name_span", self.name_span.to_json()),
+            ("span", self.span.to_json()),
+        ])
+    }
+}
+
+impl FromJson for TypeDefinition<'static> {
+    fn from_json(json: &Json) -> Result<TypeDefinition<'static>, String> {
+        Ok(TypeDefinition {
+            name: json.field("name")?,
+            module: json.field("module")?,
+            kind: json.field("kind")?,
+            parameters: json.field("parameters")?,
+            implements: json.field("implements")?,
+            variants: json.field("variants")?,
+            docs: json.field("docs")?,
+            annotations: json.field("annotations")?,
+            name_span: json.field("name_span")?,
+            span: json.field("span")?,
+        })
+    }
+}
+
+/// `{"TypeDefinition":{...}}` or `{"Unexpected":[token, span]}`.
+impl ToJson for Ast<'_> {
+    fn to_json(&self) -> Json {
+        match *self {
+            Ast::TypeDefinition(ref definition) => {
+                Json::object(vec![("TypeDefinition", definition.to_json())])
+            },
+            Ast::Unexpected(ref token, span) => {
+                Json::object(vec![("Unexpected", Json::Array(vec![token.to_json(), span.to_json()]))])
+            },
+        }
+    }
+}
+
+impl FromJson for Ast<'static> {
+    fn from_json(json: &Json) -> Result<Ast<'static>, String> {
+        if let Some(definition) = json.get("TypeDefinition") {
+            let definition = TypeDefinition::from_json(definition)
+                .map_err(|e| format!("`TypeDefinition`: {}", e))?;
+            return Ok(Ast::TypeDefinition(definition));
+        }
+
+        match json.get("Unexpected").and_then(Json::as_array) {
+            Some([token, span]) => {
+                let token = Token::from_json(token).map_err(|e| format!("`Unexpected`: {}", e))?;
+                let span = Span::from_json(span).map_err(|e| format!("`Unexpected`: {}", e))?;
+                Ok(Ast::Unexpected(token, span))
+            },
+            _ => Err(String::from("expected `TypeDefinition` or `Unexpected`")),
+        }
+    }
+}
+
+/// The tokens the parser works on, which can be peeked, and the text they
+/// come from.
+struct Tokens<'a> {
+    source: Source<'a>,
+    peeked: Option<Option<(Token, Span)>>,
+}
+
+/// Where the tokens come from.
+enum Source<'a> {
+    Lexer(Spanned<'a>),
+    Cursor(Cursor<'a>),
+}
+
+impl<'a> Tokens<'a> {
+    fn new(lexer: Lexer<'a>) -> Tokens<'a> {
+        Tokens { source: Source::Lexer(lexer.spanned()), peeked: None }
+    }
+
+    fn from_cursor(cursor: Cursor<'a>) -> Tokens<'a> {
+        Tokens { source: Source::Cursor(cursor), peeked: None }
+    }
+
+    /// The next token from the source, skipping trivia.
+    fn advance(&mut self) -> Option<(Token, Span)> {
+        match self.source {
+            Source::Lexer(ref mut spanned) => spanned.find(|pair| !is_trivia(&pair.0)),
+            Source::Cursor(ref mut cursor) => cursor.find(|pair| !is_trivia(&pair.0)).cloned(),
+        }
+    }
+
+    /// The next token, without consuming it.
+    fn peek(&mut self) -> Option<&(Token, Span)> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.advance());
+        }
+        self.peeked.as_ref().and_then(Option::as_ref)
+    }
+
+    /// The text of the input that `span` covers.
+    fn text(&self, span: Span) -> &'a str {
+        match self.source {
+            Source::Lexer(ref spanned) => spanned.text(span),
+            Source::Cursor(ref cursor) => cursor.text(span),
+        }
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = (Token, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.advance(),
+        }
+    }
+}
+
+/// Whether the parser should skip `token`.
+fn is_trivia(token: &Token) -> bool {
+    matches!(*token, Token::Whitespace(_))
+}
+
+/// When something goes wrong, we get the token that was misplaced (thus
+/// unexpected) and where it was.
+type ParseResult<T> = Result<T, (Token, Span)>;
 
 /// The parser in out language.
 ///
 /// The parser transforms the input tokens into an AST.
+///
+/// Module declarations, `modulo geometria;`, aren't in the AST: they only
+/// say which [`module`][0] the definitions after them are in. That's the only
+/// thing the parser carries from one definition to the next.
+///
+/// [0]: struct.TypeDefinition.html#structfield.module
 pub struct Parser<'a> {
-    tokens: Peekable<Lexer<'a>>,
+    tokens: Tokens<'a>,
+    module: Option<Cow<'a, str>>,
 }
 
-impl Parser<'_> {
+impl<'a> Parser<'a> {
     /// Create a new parser.
     ///
     /// To build the parser, you need a [`Lexer`][0] with tokens. Then you can
@@ -27,291 +694,685 @@ impl Parser<'_> {
     ///
     /// # Examples
     ///
-    ///     use simcom::lexer::{Lexer, Token};
+    ///     use simcom::lexer::{Lexer, Span, Token};
     ///     use simcom::parser::{Ast, Parser};
     ///
     ///     let mut parser = Parser::new(Lexer::new("? Hello World"));
-    ///     assert_eq!(parser.next().unwrap(), Ast::Unexpected(Token::Illegal));
+    ///     assert_eq!(parser.next().unwrap(), Ast::Unexpected(Token::Illegal, Span::new(0, 1)));
     ///
     /// [0]: ../lexer/struct.Lexer.html
     /// [1]: enum.Ast.html
-    pub fn new(tokens: Lexer) -> Parser {
-        Parser { tokens: tokens.peekable() }
+    pub fn new(tokens: Lexer<'a>) -> Parser<'a> {
+        Parser { tokens: Tokens::new(tokens), module: None }
+    }
+
+    /// Create a parser that reads the tokens of a [`TokenStream`][0], so the
+    /// input doesn't have to be lexed again. Names borrow from the stream's
+    /// source for as long as the stream is borrowed.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::{Lexer, TokenStream};
+    ///     use simcom::parser::Parser;
+    ///
+    ///     let tokens = TokenStream::new(Lexer::new("tipo A(x: B); tipo C(y: D);"));
+    ///     let first: Vec<_> = Parser::from_cursor(tokens.cursor()).collect();
+    ///     let again: Vec<_> = Parser::from_cursor(tokens.cursor()).collect();
+    ///     assert_eq!(first.len(), 2);
+    ///     assert_eq!(first, again);
+    ///
+    /// [0]: ../lexer/struct.TokenStream.html
+    pub fn from_cursor(cursor: Cursor<'a>) -> Parser<'a> {
+        Parser { tokens: Tokens::from_cursor(cursor), module: None }
+    }
+
+    /// Makes the definitions before any module declaration be in `module`,
+    /// to parse from the middle of a file.
+    pub fn in_module(mut self, module: Option<Cow<'a, str>>) -> Self {
+        self.module = module;
+        self
+    }
+
+    /// The same definitions, as `Result`s instead of `Ast`s, for callers that
+    /// treat the errors apart. Collecting them into a `Result` stops at the
+    /// first error.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::{Lexer, Token};
+    ///     use simcom::parser::{Parser, TypeDefinition};
+    ///
+    ///     let parser = Parser::new(Lexer::new("tipo A(x: B); tipo ; tipo C(y: D);"));
+    ///     let errors = parser.results().filter(Result::is_err).count();
+    ///     assert_eq!(errors, 1);
+    ///
+    ///     let parser = Parser::new(Lexer::new("tipo A(x: B); tipo ; tipo C(y: D);"));
+    ///     let error = parser.results().collect::<Result<Vec<TypeDefinition>, _>>().unwrap_err();
+    ///     assert_eq!(error.token, Token::Semicolon);
+    ///     assert_eq!(error.to_string(), "unexpected `;`");
+    pub fn results(self) -> Results<'a> {
+        Results { parser: self }
     }
 }
 
-impl Iterator for Parser<'_> {
-    type Item = Ast;
+impl<'a> Iterator for Parser<'a> {
+    type Item = Ast<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match definition(&mut self.tokens) {
-            Ok(ast) => Some(ast),
-            Err(Token::EOF) => None,
-            Err(token) => {
+        trace_span!(DEBUG, "definition");
+
+        // The end of the input only ends the definitions before a new one
+        // starts. In the middle of one, it's an error, like any other token.
+        let result = loop {
+            match self.tokens.peek() {
+                None | Some(&(Token::EOF, _)) => return None,
+                Some(&(Token::Module, _)) => match module(&mut self.tokens) {
+                    Ok(module) => {
+                        trace_event!(DEBUG, module = &module[..], "module");
+                        self.module = Some(module);
+                    },
+                    Err(error) => break Err(error),
+                },
+                _ => break definition(&mut self.tokens, self.module.clone()),
+            }
+        };
+
+        match result {
+            Ok(definition) => {
+                trace_event!(DEBUG, name = &definition.name[..], parameters = definition.parameters.len());
+                Some(Ast::TypeDefinition(definition))
+            },
+            Err((token, span)) => {
+                trace_event!(DEBUG, token = ?token, start = span.start, end = span.end, "unexpected token");
                 advance_until_semicolon(&mut self.tokens);
-                Some(Ast::Unexpected(token))
+                Some(Ast::Unexpected(token, span))
             },
         }
     }
 }
 
+/// A parser that yields `Result`s, made with [`Parser::results`][0].
+///
+/// [0]: struct.Parser.html#method.results
+pub struct Results<'a> {
+    parser: Parser<'a>,
+}
+
+impl<'a> Iterator for Results<'a> {
+    type Item = Result<TypeDefinition<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parser.next().map(|ast| match ast {
+            Ast::TypeDefinition(definition) => Ok(definition),
+            Ast::Unexpected(token, span) => Err(ParseError { token, span }),
+        })
+    }
+}
+
 /// Advances the iterator until a semicolon is found, consuming it.
-/// Also, if we find a 'None' value, we stop because otherwise we will get stuck
-/// in a never ending loop.
-fn advance_until_semicolon(mut tokens: &mut Peekable<Lexer>) {
+/// Also, if we find the end of the input, we stop because otherwise we will get
+/// stuck in a never ending loop.
+fn advance_until_semicolon(tokens: &mut Tokens) {
+    loop {
+        match tokens.peek() {
+            None | Some(&(Token::EOF, _)) => return,
+            Some(&(Token::Semicolon, _)) => {
+                tokens.next();
+                return;
+            },
+            _ => {
+                tokens.next();
+            },
+        }
+    }
+}
+
+/// Consumes the next token, which must be `expected`. Returns its span.
+fn expect(tokens: &mut Tokens, expected: Token) -> ParseResult<Span> {
     match tokens.next() {
-        None | Some(Token::Semicolon) => (),
-        _ => advance_until_semicolon(&mut tokens),
+        Some((ref token, span)) if *token == expected => Ok(span),
+        Some(unexpected) => Err(unexpected),
+        None => Err((Token::EOF, Span::default())),
     }
 }
 
-/// Matches an entire type definition. From Token::Type to Token::Semicolon.
-/// Returns an Ast::TypeDefinition if everything went ok. Otherwise we get the
-/// Token that was misplaced (thus unexpected).
-///
-/// TODO: Try to write this function a bit nicer.
-fn definition(mut tokens: &mut Peekable<Lexer>) -> ParseResult {
+/// Consumes the next token, which must be an identifier. Its name is taken
+/// from the source, not from the token.
+fn identifier<'a>(tokens: &mut Tokens<'a>) -> ParseResult<(&'a str, Span)> {
     match tokens.next() {
-        Some(Token::Type) => (),
-        Some(t) => return Err(t),
-        None => return Err(Token::EOF),
+        Some((Token::Ident(_), span)) => Ok((tokens.text(span), span)),
+        Some(unexpected) => Err(unexpected),
+        None => Err((Token::EOF, Span::default())),
     }
+}
 
-    // Get the type's name from the first identifier.
-    let name = match tokens.next() {
-        Some(Token::Ident(name)) => name,
-        Some(t) => return Err(t),
-        None => return Err(Token::EOF),
+/// If the next token is `before`, consumes it and the number after it, like
+/// the `= 4` of a variant or the `#4` of a field. Returns the number and its
+/// span.
+fn numbered(tokens: &mut Tokens, before: Token) -> ParseResult<Option<(i64, Span)>> {
+    match tokens.peek() {
+        Some((token, _)) if *token == before => tokens.next(),
+        _ => return Ok(None),
     };
 
     match tokens.next() {
-        Some(Token::ParL) => (),
-        Some(t) => return Err(t),
-        None => return Err(Token::EOF),
+        Some((Token::Number(number), span)) => match number.parse() {
+            Ok(number) => Ok(Some((number, span))),
+            Err(_) => Err((Token::Number(number), span)),
+        },
+        Some(unexpected) => Err(unexpected),
+        None => Err((Token::EOF, Span::default())),
     }
+}
 
-    // Fill a parameter vector with the different vectors we find.
-    let mut pars = Vec::new();
-    if let Err(t) = parameters(&mut tokens, &mut pars) {
-        return Err(t);
+/// Consumes the next token, which must be an identifier that isn't
+/// qualified with a module: the name of a definition or a parameter.
+fn name<'a>(tokens: &mut Tokens<'a>) -> ParseResult<(&'a str, Span)> {
+    match identifier(tokens)? {
+        (name, span) if name.contains('.') => Err((Token::Ident(String::from(name)), span)),
+        found => Ok(found),
+    }
+}
+
+/// Matches a module declaration, `modulo geometria;`, and returns the name.
+fn module<'a>(tokens: &mut Tokens<'a>) -> ParseResult<Cow<'a, str>> {
+    expect(tokens, Token::Module)?;
+    let (name, _) = identifier(tokens)?;
+    expect(tokens, Token::Semicolon)?;
+    Ok(Cow::Borrowed(name))
+}
+
+/// Matches any number of doc comment lines.
+fn docs<'a>(tokens: &mut Tokens<'a>) -> Vec<Cow<'a, str>> {
+    let mut docs = Vec::new();
+
+    while let Some(&(Token::DocComment(_), _)) = tokens.peek() {
+        if let Some((Token::DocComment(line), span)) = tokens.next() {
+            // The line is at the end of the comment, maybe before a '\r'.
+            let text = tokens.text(span);
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            docs.push(Cow::Borrowed(&text[text.len() - line.len()..]));
+        }
+    }
+
+    docs
+}
+
+/// Matches any number of annotations (`@name`).
+fn annotations<'a>(tokens: &mut Tokens<'a>) -> ParseResult<Vec<Annotation<'a>>> {
+    let mut annotations = Vec::new();
+
+    while let Some(&(Token::At, at)) = tokens.peek() {
+        tokens.next(); // Consume Token::At.
+        let (name, span) = identifier(tokens)?;
+        let mut arguments = Vec::new();
+        let end = self::arguments(tokens, &mut arguments, argument)?.unwrap_or(span);
+        annotations.push(Annotation { name: Cow::Borrowed(name), arguments, span: at.to(end) });
     }
 
+    Ok(annotations)
+}
+
+/// Matches the arguments of an annotation, `(2, "m")`, if the next token is
+/// Token::ParL, and adds them to `res`. Each one is matched with `argument`.
+/// The list can't be empty. Returns the span of the Token::ParR.
+fn arguments<'a, A, F>(tokens: &mut Tokens<'a>, res: &mut Vec<A>, mut argument: F) -> ParseResult<Option<Span>>
+    where F: FnMut(&mut Tokens<'a>) -> ParseResult<A>
+{
+    match tokens.peek() {
+        Some(&(Token::ParL, _)) => tokens.next(), // Consume Token::ParL.
+        _ => return Ok(None),
+    };
+
+    loop {
+        res.push(argument(tokens)?);
+
+        match tokens.next() {
+            Some((Token::Comma, _)) => (),
+            Some((Token::ParR, span)) => return Ok(Some(span)),
+            Some(unexpected) => return Err(unexpected),
+            None => return Err((Token::EOF, Span::default())),
+        }
+    }
+}
+
+/// Matches an argument of an annotation: Token::Number or Token::Text. A text
+/// without escapes borrows from the source.
+fn argument<'a>(tokens: &mut Tokens<'a>) -> ParseResult<Argument<'a>> {
     match tokens.next() {
-        Some(Token::ParR) => (),
-        Some(t) => return Err(t),
-        None => return Err(Token::EOF),
+        Some((Token::Number(number), span)) => match number.parse() {
+            Ok(number) => Ok(Argument::Number(number)),
+            Err(_) => Err((Token::Number(number), span)),
+        },
+        Some((Token::Text(text), span)) => {
+            let quoted = tokens.text(span);
+            match quoted.get(1..quoted.len() - 1) {
+                Some(inner) if inner == text => Ok(Argument::Text(Cow::Borrowed(inner))),
+                _ => Ok(Argument::Text(Cow::Owned(text))),
+            }
+        },
+        Some(unexpected) => Err(unexpected),
+        None => Err((Token::EOF, Span::default())),
+    }
+}
+
+/// Matches an entire type definition, in `module`. From its doc comment,
+/// annotations and Token::Type (or Token::Interface, or Token::Enum) to
+/// Token::Semicolon. Returns the definition if everything went ok.
+fn definition<'a>(tokens: &mut Tokens<'a>, module: Option<Cow<'a, str>>) -> ParseResult<TypeDefinition<'a>> {
+    let docs = docs(tokens);
+    let annotations = annotations(tokens)?;
+    let (kind, keyword) = keyword(tokens)?;
+
+    // Get the type's name from the first identifier.
+    let (name, name_span) = name(tokens)?;
+
+    expect(tokens, Token::ParL)?;
+
+    // Fill a parameter vector with the different parameters we find, or
+    // the variants of an enumeration.
+    let mut pars = Vec::new();
+    let mut vars = Vec::new();
+    match kind {
+        DefinitionKind::Enum => variants(tokens, &mut vars)?,
+        _ => parameters(tokens, &mut pars)?,
     }
 
+    expect(tokens, Token::ParR)?;
+    let implements = match kind {
+        DefinitionKind::Type => implements(tokens)?,
+        _ => Vec::new(),
+    };
+    let end = expect(tokens, Token::Semicolon)?;
+
+    let start = annotations.first().map(|a| a.span).unwrap_or(keyword);
+
+    Ok(TypeDefinition {
+        name: Cow::Borrowed(name),
+        module,
+        kind,
+        parameters: pars,
+        implements,
+        variants: vars,
+        docs,
+        annotations,
+        name_span,
+        span: start.to(end),
+    })
+}
+
+/// Consumes the keyword that starts a definition, which says its kind.
+fn keyword(tokens: &mut Tokens) -> ParseResult<(DefinitionKind, Span)> {
     match tokens.next() {
-        Some(Token::Semicolon) => (),
-        Some(t) => return Err(t),
-        None => return Err(Token::EOF),
+        Some((Token::Type, span)) => Ok((DefinitionKind::Type, span)),
+        Some((Token::Interface, span)) => Ok((DefinitionKind::Interface, span)),
+        Some((Token::Enum, span)) => Ok((DefinitionKind::Enum, span)),
+        Some(unexpected) => Err(unexpected),
+        None => Err((Token::EOF, Span::default())),
     }
+}
+
+/// Matches the interfaces a type implements, if there are any: a
+/// Token::Implements and then their names, separated by commas.
+fn implements<'a>(tokens: &mut Tokens<'a>) -> ParseResult<Vec<Implementation<'a>>> {
+    let mut implements = Vec::new();
 
-    Ok(Ast::TypeDefinition(name, pars))
+    if let Some(&(Token::Implements, _)) = tokens.peek() {
+        tokens.next(); // Consume Token::Implements.
+        loop {
+            let (name, span) = identifier(tokens)?;
+            implements.push(Implementation { name: Cow::Borrowed(name), span });
+            match tokens.peek() {
+                Some(&(Token::Comma, _)) => tokens.next(),
+                _ => break,
+            };
+        }
+    }
+
+    Ok(implements)
 }
 
-/// Matches a series of parameters, separated by a comma (Token::Comma).
+/// Matches a series of parameters, separated by a comma (Token::Comma). The
+/// last one may be followed by a comma too, which is handy when each parameter
+/// goes in its own line.
 ///
 /// Fills the passed 'res' vector. Return is Err(_) when an unexpected token was
 /// found or when the 'tokens' iterator ends.
-fn parameters(mut tokens: &mut Peekable<Lexer>,
-    mut res: &mut Vec<Ast>) -> ParseResult {
+fn parameters<'a>(tokens: &mut Tokens<'a>, res: &mut Vec<Parameter<'a>>) -> ParseResult<()> {
+    loop {
+        res.push(parameter(tokens)?);
 
-    match parameter(tokens) {
-        Ok(x) => res.push(x),
-        Err(token) => return Err(token),
+        match tokens.peek() {
+            Some(&(Token::Comma, _)) => tokens.next(), // Consume Token::Comma.
+            _ => return Ok(()),
+        };
+        if let Some(&(Token::ParR, _)) = tokens.peek() {
+            return Ok(());
+        }
     }
+}
 
-    match tokens.peek() {
-        Some(&Token::Comma) => {
-            tokens.next(); // Consume Token::Comma.
-            parameters(&mut tokens, &mut res)
-        },
-        _ => Ok(Ast::Empty)
+/// Matches the variants of an enumeration, separated by commas, with an
+/// optional trailing one like parameters.
+fn variants<'a>(tokens: &mut Tokens<'a>, res: &mut Vec<Variant<'a>>) -> ParseResult<()> {
+    loop {
+        res.push(variant(tokens)?);
+
+        match tokens.peek() {
+            Some(&(Token::Comma, _)) => tokens.next(), // Consume Token::Comma.
+            _ => return Ok(()),
+        };
+        if let Some(&(Token::ParR, _)) = tokens.peek() {
+            return Ok(());
+        }
     }
 }
 
+/// Matches a variant: a name, maybe followed by Token::Equals and a number,
+/// optionally preceded by a doc comment and annotations.
+fn variant<'a>(tokens: &mut Tokens<'a>) -> ParseResult<Variant<'a>> {
+    let docs = docs(tokens);
+    let annotations = annotations(tokens)?;
+    let (name, name_span) = name(tokens)?;
+    let value = numbered(tokens, Token::Equals)?;
+
+    let start = annotations.first().map(|a| a.span).unwrap_or(name_span);
+    let end = value.map(|(_, span)| span).unwrap_or(name_span);
+
+    Ok(Variant {
+        name: Cow::Borrowed(name),
+        value: value.map(|(value, _)| value),
+        docs,
+        annotations,
+        name_span,
+        span: start.to(end),
+    })
+}
+
 /// Matches a parameter (the ones inside the type definition's parenthesis).
-/// Has the form (Token::Ident, Token::Colon, Token::Ident).
-fn parameter(tokens: &mut Peekable<Lexer>) -> ParseResult {
-    match tokens.next() {
-        Some(Token::Ident(name)) => match tokens.next() {
-            Some(Token::Colon) => match tokens.next() {
-                Some(Token::Ident(tname)) => Ok(Ast::Parameter(name, tname)),
-                None => Err(Token::EOF),
-                Some(x) => Err(x),
-            },
-            None => Err(Token::EOF),
-            Some(x) => Err(x),
-        },
-        None => Err(Token::EOF),
-        Some(x) => Err(x),
-    }
+/// Has the form (Token::Ident, Token::Colon, Token::Ident), maybe followed by
+/// Token::Hash and a number, optionally preceded by a doc comment and
+/// annotations.
+fn parameter<'a>(tokens: &mut Tokens<'a>) -> ParseResult<Parameter<'a>> {
+    let docs = docs(tokens);
+    let annotations = annotations(tokens)?;
+    let (name, name_span) = name(tokens)?;
+    expect(tokens, Token::Colon)?;
+    let (typename, type_span) = identifier(tokens)?;
+    let number = numbered(tokens, Token::Hash)?;
+
+    let start = annotations.first().map(|a| a.span).unwrap_or(name_span);
+    let end = number.map(|(_, span)| span).unwrap_or(type_span);
+
+    Ok(Parameter {
+        name: Cow::Borrowed(name),
+        typename: Cow::Borrowed(typename),
+        number: number.map(|(number, _)| number),
+        docs,
+        annotations,
+        name_span,
+        type_span,
+        span: start.to(end),
+    })
 }
 
 #[cfg(test)]
 mod test {
-    use super::super::lexer::Lexer;
+    use super::super::lexer::{Lexer, TokenStream};
     use super::*;
 
-    fn get_parameter(input: &str) -> ParseResult {
-        parameter(&mut Lexer::new(input).peekable())
+    fn tokens(input: &str) -> Tokens<'_> {
+        Tokens::new(Lexer::new(input))
+    }
+
+    fn get_parameter(input: &str) -> ParseResult<Parameter<'_>> {
+        parameter(&mut tokens(input))
     }
 
-    fn get_parameters(input: &str) -> (ParseResult, Vec<Ast>) {
+    fn get_parameters(input: &str) -> (ParseResult<()>, Vec<Parameter<'_>>) {
         let mut pars = Vec::new();
-        let r = parameters(&mut Lexer::new(input).peekable(), &mut pars);
+        let r = parameters(&mut tokens(input), &mut pars);
 
         (r, pars)
     }
 
-    fn get_definition(input: &str) -> ParseResult {
-        definition(&mut Lexer::new(input).peekable())
+    fn get_definition(input: &str) -> ParseResult<TypeDefinition<'_>> {
+        definition(&mut tokens(input), None)
+    }
+
+    /// Only the names of the parameters, to keep the tests short.
+    fn names<'a>(parameters: &'a [Parameter]) -> Vec<(&'a str, &'a str)> {
+        parameters.iter()
+            .map(|p| (&p.name[..], &p.typename[..]))
+            .collect()
     }
 
     #[test]
     fn semicolon1() {
-        let mut tokens = Lexer::new("Hola ,,();  )").peekable();
+        let mut tokens = tokens("Hola ,,();  )");
         advance_until_semicolon(&mut tokens);
 
-        assert_eq!(tokens.next().unwrap(), Token::ParR);
+        assert_eq!(tokens.next().unwrap().0, Token::ParR);
     }
 
     #[test]
     fn semicolon2() {
-        let mut tokens = Lexer::new(",,,,,,;;").peekable();
+        let mut tokens = tokens(",,,,,,;;");
         advance_until_semicolon(&mut tokens);
 
-        assert_eq!(tokens.next().unwrap(), Token::Semicolon);
+        assert_eq!(tokens.next().unwrap().0, Token::Semicolon);
     }
 
     #[test]
     fn semicolon3() {
-        let mut tokens = Lexer::new("Hola ,,(); ").peekable();
+        let mut tokens = tokens("Hola ,,(); ");
         advance_until_semicolon(&mut tokens);
 
-        assert_eq!(tokens.next(), None);
+        assert_eq!(tokens.next().unwrap().0, Token::EOF);
     }
 
     #[test]
     fn semicolon4() {
-        let mut tokens = Lexer::new("Hey").peekable();
+        let mut tokens = tokens("Hey");
         advance_until_semicolon(&mut tokens);
 
-        assert_eq!(tokens.next(), None);
+        assert_eq!(tokens.next().unwrap().0, Token::EOF);
     }
 
     #[test]
     fn good_definition() {
-        let d = get_definition("tipo Punto(x: Punto);");
+        let d = get_definition("tipo Punto(x: Punto);").unwrap();
 
-        assert_eq!(d.unwrap(), Ast::TypeDefinition(
-            String::from("Punto"),
-            vec![Ast::Parameter(
-                String::from("x"),
-                String::from("Punto"),
-            )],
-        ));
+        assert_eq!(d, TypeDefinition {
+            name: Cow::Borrowed("Punto"),
+            module: None,
+            kind: DefinitionKind::Type,
+            implements: vec![],
+            variants: vec![],
+            parameters: vec![Parameter {
+                name: Cow::Borrowed("x"),
+                typename: Cow::Borrowed("Punto"),
+                number: None,
+                docs: vec![],
+                annotations: vec![],
+                name_span: Span::new(11, 12),
+                type_span: Span::new(14, 19),
+                span: Span::new(11, 19),
+            }],
+            docs: vec![],
+            annotations: vec![],
+            name_span: Span::new(5, 10),
+            span: Span::new(0, 21),
+        });
     }
 
     #[test]
     fn good_definition_many() {
-        let d = get_definition("tipo Punto(x: Punto, x: P, x: P);");
-
-        assert_eq!(d.unwrap(), Ast::TypeDefinition(
-            String::from("Punto"),
-            vec![
-                Ast::Parameter(
-                    String::from("x"),
-                    String::from("Punto"),
-                ),
-                Ast::Parameter(
-                    String::from("x"),
-                    String::from("P"),
-                ),
-                Ast::Parameter(
-                    String::from("x"),
-                    String::from("P"),
-                ),
-            ],
-        ));
+        let d = get_definition("tipo Punto(x: Punto, x: P, x: P);").unwrap();
+
+        assert_eq!(d.name, "Punto");
+        assert_eq!(names(&d.parameters), vec![
+            ("x", "Punto"),
+            ("x", "P"),
+            ("x", "P"),
+        ]);
+    }
+
+    #[test]
+    fn annotations() {
+        let d = get_definition("@obsoleto @otra tipo A(@obsoleto x: P, y: P);").unwrap();
+
+        assert_eq!(d.annotations, vec![
+            Annotation { name: Cow::Borrowed("obsoleto"), arguments: vec![], span: Span::new(0, 9) },
+            Annotation { name: Cow::Borrowed("otra"), arguments: vec![], span: Span::new(10, 15) },
+        ]);
+        assert_eq!(d.span, Span::new(0, 45));
+        assert!(d.has_annotation("otra"));
+        assert_eq!(d.parameters[0].annotations.len(), 1);
+        assert_eq!(d.parameters[0].span, Span::new(23, 37));
+        assert!(d.parameters[1].annotations.is_empty());
+
+        let d = get_definition("@desde(2) tipo A(@x(1, -2) x: P);").unwrap();
+        assert_eq!(d.annotations[0].arguments, vec![Argument::Number(2)]);
+        assert_eq!(d.annotations[0].span, Span::new(0, 9));
+        assert_eq!(d.annotations[0].number(), Some(2));
+        assert_eq!(d.parameters[0].annotations[0].to_string(), "@x(1, -2)");
+        assert_eq!(d.parameters[0].annotations[0].number(), None);
+        assert_eq!(TypeDefinition::from_json(&d.to_json()), Ok(d));
+
+        let d = get_definition("tipo A(@unidad(\"m\") x: P, @x(\"\\\"\", 1) y: P);").unwrap();
+        assert_eq!(d.parameters[0].annotations[0].arguments, vec![Argument::Text(Cow::Borrowed("m"))]);
+        assert_eq!(d.parameters[0].annotations[0].text(), Some("m"));
+        assert_eq!(d.parameters[0].span, Span::new(7, 24));
+        assert_eq!(d.parameters[1].annotations[0].arguments, vec![Argument::Text(Cow::Borrowed("\"")), Argument::Number(1)]);
+        assert_eq!(d.parameters[1].annotations[0].to_string(), "@x(\"\\\"\", 1)");
+        assert_eq!(d.parameters[1].annotations[0].text(), None);
+        assert_eq!(TypeDefinition::from_json(&d.to_json()), Ok(d));
+
+        assert_eq!(get_definition("@desde() tipo A(x: P);").unwrap_err().0, Token::ParR);
+        assert_eq!(get_definition("@desde(2 tipo A(x: P);").unwrap_err().0, Token::Type);
+    }
+
+    #[test]
+    fn doc_comments() {
+        let d = get_definition("/// Un punto.\n///\n/// En 2D.\ntipo P(\n/// Horizontal.\nx: E);").unwrap();
+
+        assert_eq!(d.docs, vec!["Un punto.", "", "En 2D."]);
+        assert_eq!(d.parameters[0].docs, vec!["Horizontal."]);
+        assert_eq!(d.span.start, 29);
+
+        let d = get_definition("tipo P(x: /// No.\n E);");
+        assert_eq!(d.unwrap_err().0, Token::DocComment(String::from("No.")));
+    }
+
+    #[test]
+    fn borrows_from_the_source() {
+        let d = get_definition("/// Un punto.\r\n///\r\n@a tipo P(x: E);").unwrap();
+
+        assert!(matches!(d.name, Cow::Borrowed("P")));
+        assert!(matches!(d.docs[..], [Cow::Borrowed("Un punto."), Cow::Borrowed("")]));
+        assert!(matches!(d.annotations[0].name, Cow::Borrowed("a")));
+        assert!(matches!(d.parameters[0].typename, Cow::Borrowed("E")));
+
+        let owned = d.clone().into_owned();
+        assert!(matches!(owned.name, Cow::Owned(_)));
+        assert_eq!(owned, d);
+    }
+
+    #[test]
+    fn cursors_parse_like_lexers() {
+        let input = "/// A.\n@a tipo A(\n  x: B,\n);\n\ntipo ? C(y: D); tipo E(z: F)";
+        let stream = TokenStream::new(Lexer::new(input).preserving_trivia());
+
+        let from_lexer: Vec<Ast> = Parser::new(Lexer::new(input)).collect();
+        let from_cursor: Vec<Ast> = Parser::from_cursor(stream.cursor()).collect();
+        assert_eq!(from_cursor, from_lexer);
+        assert!(matches!(from_cursor[0], Ast::TypeDefinition(TypeDefinition { name: Cow::Borrowed("A"), .. })));
+    }
+
+    #[test]
+    fn bad_annotation() {
+        let d = get_definition("@ tipo A(x: P);");
+        assert_eq!(d.unwrap_err().0, Token::Type);
     }
 
     #[test]
     fn missing_keyword() {
         let d = get_definition("tiipo Punto");
-        assert_eq!(d.unwrap_err(), Token::Ident(String::from("tiipo")));
+        assert_eq!(d.unwrap_err(), (Token::Ident(String::from("tiipo")), Span::new(0, 5)));
     }
 
     #[test]
     fn missing_identifier_definition() {
         let d = get_definition("tipo (,,,");
-        assert_eq!(d.unwrap_err(), Token::ParL);
+        assert_eq!(d.unwrap_err().0, Token::ParL);
     }
 
     #[test]
     fn missing_parenthesis() {
         let d = get_definition("tipo x he");
-        assert_eq!(d.unwrap_err(), Token::Ident(String::from("he")));
+        assert_eq!(d.unwrap_err().0, Token::Ident(String::from("he")));
 
         let d = get_definition("tipo P(x: haha;");
-        assert_eq!(d.unwrap_err(), Token::Semicolon);
+        assert_eq!(d.unwrap_err().0, Token::Semicolon);
     }
 
     #[test]
     fn missing_semicolon() {
         let d = get_definition("tipo P(x: E)");
-        assert_eq!(d.unwrap_err(), Token::EOF);
+        assert_eq!(d.unwrap_err(), (Token::EOF, Span::new(12, 12)));
 
         let d = get_definition("tipo P(x: E) \n tipo");
-        assert_eq!(d.unwrap_err(), Token::Type);
+        assert_eq!(d.unwrap_err().0, Token::Type);
     }
 
     #[test]
     fn error_propagation() {
         let d = get_definition("tipo Punto(x Punto);");
-        assert_eq!(d.unwrap_err(), Token::Ident(String::from("Punto")));
+        assert_eq!(d.unwrap_err().0, Token::Ident(String::from("Punto")));
+    }
+
+    #[test]
+    fn trailing_comma() {
+        let d = get_definition("tipo P(x: E, y: E,);");
+        assert!(d.is_ok());
+
+        let d = get_definition("tipo P(x: E,,);");
+        assert_eq!(d.unwrap_err().0, Token::Comma);
     }
 
     #[test]
     fn good_parameter() {
-        let p = get_parameter("name: Type");
+        let p = get_parameter("name: Type").unwrap();
 
-        assert_eq!(p.unwrap(), Ast::Parameter(
-            String::from("name"),
-            String::from("Type"),
-        ));
+        assert_eq!(p.name, "name");
+        assert_eq!(p.typename, "Type");
+        assert_eq!(p.name_span, Span::new(0, 4));
+        assert_eq!(p.type_span, Span::new(6, 10));
     }
 
     #[test]
     fn missing_colon() {
         let p = get_parameter("name Type");
 
-        assert_eq!(p.unwrap_err(), Token::Ident(String::from("Type")));
+        assert_eq!(p.unwrap_err().0, Token::Ident(String::from("Type")));
     }
 
     #[test]
     fn missing_identifier() {
         let p = get_parameter("name: )");
 
-        assert_eq!(p.unwrap_err(), Token::ParR);
+        assert_eq!(p.unwrap_err().0, Token::ParR);
     }
 
     #[test]
     fn good_parameters() {
         let (res, ps) = get_parameters("name: Type, other: othert");
 
-        assert_eq!(res.unwrap(), Ast::Empty);
-        assert_eq!(ps, vec![
-            Ast::Parameter(
-                String::from("name"),
-                String::from("Type"),
-            ),
-            Ast::Parameter(
-                String::from("other"),
-                String::from("othert"),
-            ),
+        assert!(res.is_ok());
+        assert_eq!(names(&ps), vec![
+            ("name", "Type"),
+            ("other", "othert"),
         ]);
     }
 
@@ -322,12 +1383,9 @@ mod test {
         // Here the function doesn't report the next identifier token because
         // it ends if it doesn't find any Token::Comma's. The unexpected "other"
         // identifier will be reported by the ::definition function.
-        assert_eq!(res.unwrap(), Ast::Empty);
-        assert_eq!(ps, vec![
-            Ast::Parameter(
-                String::from("name"),
-                String::from("Type"),
-            ),
+        assert!(res.is_ok());
+        assert_eq!(names(&ps), vec![
+            ("name", "Type"),
         ]);
     }
 
@@ -336,6 +1394,152 @@ mod test {
         let (res, _) = get_parameters("name Type, other: othert");
 
         // Error propagates from ::parameter to ::parameters.
-        assert_eq!(res.unwrap_err(), Token::Ident(String::from("Type")));
+        assert_eq!(res.unwrap_err().0, Token::Ident(String::from("Type")));
+    }
+
+    #[test]
+    fn recovery() {
+        let ast: Vec<Ast> = Parser::new(Lexer::new("tipo A(x B); tipo C(y: D);")).collect();
+
+        assert_eq!(ast[0], Ast::Unexpected(Token::Ident(String::from("B")), Span::new(9, 10)));
+        match ast[1] {
+            Ast::TypeDefinition(ref d) => assert_eq!(d.name, "C"),
+            _ => panic!("Expected a definition"),
+        }
+        assert_eq!(ast.len(), 2);
+    }
+
+    #[test]
+    fn end_of_input() {
+        let ast: Vec<Ast> = Parser::new(Lexer::new("tipo A(x: B);  \n")).collect();
+        assert_eq!(ast.len(), 1);
+
+        // A definition can't be cut short by the end of the input.
+        let ast: Vec<Ast> = Parser::new(Lexer::new("tipo A(x: B); tipo C(y: D) ")).collect();
+        assert_eq!(ast[1], Ast::Unexpected(Token::EOF, Span::new(27, 27)));
+        assert_eq!(ast.len(), 2);
+
+        let ast: Vec<Ast> = Parser::new(Lexer::new("tipo A(x: B);\n/// Nada.")).collect();
+        assert_eq!(ast[1], Ast::Unexpected(Token::EOF, Span::new(23, 23)));
+    }
+
+    #[test]
+    fn modules() {
+        let content = "tipo A(x: B); modulo geo; tipo P(a: A, q: geo.Q); modulo geo.planos x; tipo Q(y: D); \
+                       modulo geo.planos; tipo R(z: D);";
+        let ast: Vec<Ast> = Parser::new(Lexer::new(content)).collect();
+
+        let modules: Vec<Option<&str>> = ast.iter()
+            .map(|ast| match *ast {
+                Ast::TypeDefinition(ref d) => d.module.as_deref(),
+                Ast::Unexpected(..) => Some("error"),
+            })
+            .collect();
+        assert_eq!(modules, vec![None, Some("geo"), Some("error"), Some("geo"), Some("geo.planos")]);
+        assert_eq!(ast[2], Ast::Unexpected(Token::Ident(String::from("x")), Span::new(68, 69)));
+
+        // Only types are qualified, not the names of the definitions.
+        assert_eq!(get_definition("tipo geo.P(x: E);").unwrap_err().0, Token::Ident(String::from("geo.P")));
+        assert_eq!(get_definition("tipo P(geo.x: E);").unwrap_err().0, Token::Ident(String::from("geo.x")));
+    }
+
+    #[test]
+    fn interfaces() {
+        let interface = get_definition("interfaz Nombrado(nombre: Texto);").unwrap();
+        assert_eq!(interface.kind, DefinitionKind::Interface);
+        assert!(interface.is_interface() && interface.implements.is_empty());
+
+        let definition = get_definition("tipo Gato(nombre: Texto) implementa Nombrado, geo.Animal;").unwrap();
+        let names: Vec<&str> = definition.implements.iter().map(|i| &i.name[..]).collect();
+        assert_eq!(names, vec!["Nombrado", "geo.Animal"]);
+        assert_eq!(definition.implements[1].span, Span::new(46, 56));
+        assert_eq!(definition.span, Span::new(0, 57));
+
+        // Interfaces don't implement other interfaces, and the list can't be
+        // empty or end with a comma.
+        assert_eq!(get_definition("interfaz N(n: T) implementa M;").unwrap_err().0, Token::Implements);
+        assert_eq!(get_definition("tipo A(n: T) implementa;").unwrap_err().0, Token::Semicolon);
+        assert_eq!(get_definition("tipo A(n: T) implementa M,;").unwrap_err().0, Token::Semicolon);
+
+        let json = definition.to_json();
+        assert_eq!(json.get("kind"), Some(&Json::from("Type")));
+        assert_eq!(TypeDefinition::from_json(&json), Ok(definition));
+    }
+
+    #[test]
+    fn enums() {
+        let definition = get_definition("enum Color(Rojo, /// Verde.\nVerde = 4, @obsoleto Azul,);").unwrap();
+        assert_eq!(definition.kind, DefinitionKind::Enum);
+        assert!(definition.parameters.is_empty());
+        let names: Vec<&str> = definition.variants.iter().map(|v| &v.name[..]).collect();
+        assert_eq!(names, vec!["Rojo", "Verde", "Azul"]);
+        assert_eq!(definition.variants[1].docs, vec!["Verde."]);
+        assert_eq!(definition.variants[1].span, Span::new(28, 37));
+        assert_eq!(definition.variants[2].span, Span::new(39, 53));
+        assert_eq!(definition.values(), vec![0, 4, 5]);
+
+        // Values are numbers, and the list can't be empty.
+        assert_eq!(get_definition("enum A(B = C);").unwrap_err().0, Token::Ident(String::from("C")));
+        assert_eq!(get_definition("enum A(B = 99999999999999999999);").unwrap_err().1, Span::new(11, 31));
+        assert_eq!(get_definition("enum A();").unwrap_err().0, Token::ParR);
+
+        let json = definition.to_json();
+        assert_eq!(json.get("kind"), Some(&Json::from("Enum")));
+        assert_eq!(TypeDefinition::from_json(&json), Ok(definition));
+    }
+
+    #[test]
+    fn field_numbers() {
+        let definition = get_definition("tipo Punto(x: Entero #1, @otra y: Entero #2);").unwrap();
+        let numbers: Vec<Option<i64>> = definition.parameters.iter().map(|p| p.number).collect();
+        assert_eq!(numbers, vec![Some(1), Some(2)]);
+        assert_eq!(definition.parameters[0].span, Span::new(11, 23));
+        assert_eq!(definition.parameters[1].type_span, Span::new(34, 40));
+
+        assert_eq!(get_definition("tipo A(x: B #);").unwrap_err().0, Token::ParR);
+        assert_eq!(get_definition("tipo A(x: B # c);").unwrap_err().0, Token::Ident(String::from("c")));
+        assert_eq!(get_definition("enum A(B #1);").unwrap_err().0, Token::Hash);
+
+        let json = definition.to_json();
+        assert_eq!(TypeDefinition::from_json(&json), Ok(definition));
+    }
+
+    #[test]
+    fn results() {
+        let results: Vec<_> = Parser::new(Lexer::new("tipo A(x B); ? tipo C(y: D);")).results().collect();
+
+        let error = ParseError { token: Token::Ident(String::from("B")), span: Span::new(9, 10) };
+        assert_eq!(results[0], Err(error));
+        assert_eq!(results[1].as_ref().unwrap_err().to_string(), "unknown character");
+        assert_eq!(results.len(), 2);
+
+        let definitions = Parser::new(Lexer::new("tipo A(x: B); tipo C(y: D);")).results();
+        let names: Result<Vec<_>, _> = definitions.map(|d| d.map(|d| d.name)).collect();
+        assert_eq!(names, Ok(vec!["A".into(), "C".into()]));
+    }
+
+    #[test]
+    fn json() {
+        let content = "/// Un punto.\n@obsoleto tipo Punto(x: Entero, @otra y: Entero);\ntipo ;";
+        let asts: Vec<Ast> = Parser::new(Lexer::new(content)).collect();
+
+        let json = asts.to_json();
+        assert_eq!(Vec::<Ast>::from_json(&json), Ok(asts));
+        assert_eq!(json.as_array().unwrap()[1].to_string(),
+            r#"{"Unexpected":["Semicolon",{"file":0,"start":69,"end":70}]}"#);
+
+        let error = Vec::<Ast>::from_json(&Json::parse(r#"[{"TypeDefinition":{"name":1}}]"#).unwrap());
+        assert_eq!(error, Err(String::from("[0]: `TypeDefinition`: `name`: expected a string")));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let content = "/// Un punto.\n@obsoleto tipo Punto(x: Entero #1, @unidad(\"m\") y: Real #2);\nenum E(A = 2);\ntipo ;";
+        let asts: Vec<Ast> = Parser::new(Lexer::new(content)).collect();
+
+        let json = serde_json::to_string(&asts).unwrap();
+        assert_eq!(json, asts.to_json().to_string());
+        assert_eq!(serde_json::from_str::<Vec<Ast>>(&json).unwrap(), asts);
     }
 }
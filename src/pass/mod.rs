@@ -0,0 +1,198 @@
+//! Pass module.
+//!
+//! Projects sometimes need checks or rewrites that only make sense for them,
+//! like forbidding a type name or adding a field to every type. A [`Pass`][0]
+//! is that: code from outside the compiler, registered in a
+//! [`PassManager`][1], that runs after the definitions are analyzed and
+//! before the code is generated. It can read the definitions and the
+//! [symbol table][2], change the definitions and report diagnostics.
+//!
+//! # Examples
+//!
+//!     use simcom::diagnostics::Diagnostic;
+//!     use simcom::driver::{Compilation, Options};
+//!     use simcom::pass::{Pass, PassContext, PassManager};
+//!
+//!     /// Nobody may define a type called `Cosa`.
+//!     struct NoCosa;
+//!
+//!     impl Pass for NoCosa {
+//!         fn run(&mut self, ctx: &mut PassContext) {
+//!             let spans: Vec<_> = ctx.definitions().iter()
+//!                 .filter(|d| d.name == "Cosa")
+//!                 .map(|d| d.name_span)
+//!                 .collect();
+//!             for span in spans {
+//!                 ctx.report(Diagnostic::error("`Cosa` is too vague", span));
+//!             }
+//!         }
+//!     }
+//!
+//!     let mut compilation = Compilation::new(Options::default());
+//!     compilation.add_file("a.sc", "tipo Cosa(x: Entero);");
+//!
+//!     let mut passes = PassManager::new();
+//!     passes.register(NoCosa);
+//!     let result = compilation.run_with(&mut passes);
+//!     assert_eq!(result.diagnostics[0].message, "`Cosa` is too vague");
+//!     assert!(result.code.is_none());
+//!
+//! [0]: trait.Pass.html
+//! [1]: struct.PassManager.html
+//! [2]: ../semantic/struct.Semantic.html
+
+use std::fmt;
+
+use super::diagnostics::Diagnostic;
+use super::parser::{Ast, TypeDefinition};
+use super::semantic::Semantic;
+
+/// A custom analysis or transformation of the definitions.
+pub trait Pass {
+    fn run(&mut self, ctx: &mut PassContext);
+}
+
+/// What a pass works on.
+pub struct PassContext<'a> {
+    definitions: &'a mut Vec<TypeDefinition<'static>>,
+    semantic: &'a Semantic,
+    diagnostics: &'a mut Vec<Diagnostic>,
+    /// Whether the pass asked to change the definitions.
+    changed: bool,
+}
+
+impl PassContext<'_> {
+    /// The definitions, in the order of the files.
+    pub fn definitions(&self) -> &[TypeDefinition<'static>] {
+        self.definitions
+    }
+
+    /// The definitions, to change them. The symbol table is built again
+    /// after the pass, so the next ones (and the code generator) see the
+    /// changes.
+    pub fn definitions_mut(&mut self) -> &mut Vec<TypeDefinition<'static>> {
+        self.changed = true;
+        self.definitions
+    }
+
+    /// The symbol table, as it was when the pass started.
+    pub fn semantic(&self) -> &Semantic {
+        self.semantic
+    }
+
+    /// Reports a problem. Errors stop the code from being generated, like
+    /// any other.
+    pub fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+/// The passes to run, in the order they were registered.
+#[derive(Default)]
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+}
+
+impl PassManager {
+    pub fn new() -> PassManager {
+        PassManager::default()
+    }
+
+    /// Adds a pass after the rest.
+    pub fn register<P: Pass + 'static>(&mut self, pass: P) -> &mut Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// Runs every pass. When one changes the definitions, `semantic` is
+    /// built again from them before the next one.
+    pub fn run(&mut self, definitions: &mut Vec<TypeDefinition<'static>>, semantic: &mut Semantic,
+               diagnostics: &mut Vec<Diagnostic>) {
+        for pass in &mut self.passes {
+            trace_span!(DEBUG, "pass");
+
+            let changed = {
+                let mut ctx = PassContext { definitions, semantic, diagnostics, changed: false };
+                pass.run(&mut ctx);
+                ctx.changed
+            };
+
+            if changed {
+                // There are only definitions, so this can't fail.
+                let ast = definitions.iter().cloned().map(Ast::TypeDefinition);
+                if let Ok(analyzed) = Semantic::analyze(ast) {
+                    *semantic = analyzed;
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for PassManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PassManager").field("passes", &self.passes.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use super::super::driver::{Compilation, Options};
+    use super::super::lexer::Span;
+    use super::super::parser::Parameter;
+    use super::{Pass, PassContext, PassManager};
+
+    /// Adds an `id` field to every type.
+    struct AddId;
+
+    impl Pass for AddId {
+        fn run(&mut self, ctx: &mut PassContext) {
+            for definition in ctx.definitions_mut() {
+                definition.parameters.push(Parameter {
+                    name: Cow::Borrowed("id"),
+                    typename: Cow::Borrowed("Id"),
+                    number: None,
+                    docs: Vec::new(),
+                    annotations: Vec::new(),
+                    name_span: Span::default(),
+                    type_span: Span::default(),
+                    span: Span::default(),
+                });
+            }
+        }
+    }
+
+    /// Runs after `AddId`.
+    struct Check;
+
+    impl Pass for Check {
+        fn run(&mut self, ctx: &mut PassContext) {
+            assert_eq!(ctx.semantic().order, vec!["Entero", "Id", "A"]);
+            assert!(ctx.definitions().iter().all(|d| d.parameters.len() == 2));
+        }
+    }
+
+    #[test]
+    fn transforms_are_seen_by_the_rest() {
+        let mut compilation = Compilation::new(Options::default());
+        compilation.add_file("a.sc", "tipo A(x: Entero);");
+
+        let mut passes = PassManager::new();
+        passes.register(AddId).register(Check);
+        assert_eq!(passes.len(), 2);
+
+        let result = compilation.run_with(&mut passes);
+        assert_eq!(result.semantic.unwrap().order, vec!["Entero", "Id", "A"]);
+        assert!(result.code.unwrap().contains("    Id id;\n"));
+        assert_eq!(result.definitions[0].parameters.len(), 2);
+    }
+}
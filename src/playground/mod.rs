@@ -0,0 +1,119 @@
+//! Playground module.
+//!
+//! An interactive playground, where students type a definition and see its
+//! tokens, its AST and the order of the types change as they go, only needs
+//! to pass a string in and get a string out. These functions do that, and
+//! return JSON that a web page can read directly. With the `wasm` feature,
+//! the [`wasm`][0] module exports them to JavaScript.
+//!
+//! [0]: wasm/index.html
+
+use super::driver::{compile, Options};
+use super::highlight::classify;
+use super::json::{Json, ToJson};
+use super::lexer::{Language, Lexer};
+use super::parser::{Ast, Parser};
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// Parses `source` and returns its AST, with the errors as diagnostics.
+///
+/// # Examples
+///
+///     use simcom::json::Json;
+///     use simcom::playground::compile_to_ast_json;
+///
+///     let json = Json::parse(&compile_to_ast_json("tipo A(x: Entero);")).unwrap();
+///     assert_eq!(json.pointer(&["ast"]).unwrap().as_array().unwrap().len(), 1);
+///     assert_eq!(json.pointer(&["diagnostics"]).unwrap().as_array().unwrap().len(), 0);
+pub fn compile_to_ast_json(source: &str) -> String {
+    let ast: Vec<Ast> = Parser::new(Lexer::new(source)).collect();
+    let diagnostics = ast.iter().filter_map(Ast::diagnostic).map(|d| d.to_json()).collect();
+
+    Json::object(vec![
+        ("ast", ast.to_json()),
+        ("diagnostics", Json::Array(diagnostics)),
+    ]).to_string()
+}
+
+/// Everything the playground shows: the tokens, the AST, the order of the
+/// types, which ones are in cycles and the diagnostics (lints included).
+/// Order and cycles are `null` when there are syntax errors.
+///
+/// # Examples
+///
+///     use simcom::json::Json;
+///     use simcom::playground::analyze;
+///
+///     let json = Json::parse(&analyze("tipo Linea(a: Punto); tipo Punto(x: Entero);")).unwrap();
+///     assert_eq!(json.get("order").unwrap().to_string(), r#"["Entero","Punto","Linea"]"#);
+///     assert_eq!(json.get("tokens").unwrap().as_array().unwrap().len(), 17);
+pub fn analyze(source: &str) -> String {
+    let tokens = Lexer::new(source).spanned()
+        .map(|(token, span)| Json::object(vec![
+            ("token", token.to_json()),
+            ("span", span.to_json()),
+        ]))
+        .collect();
+
+    let result = compile(source, &Options::default());
+    let ast: Vec<Ast> = result.definitions.iter().cloned().map(Ast::TypeDefinition).collect();
+    let (order, cycles) = match result.semantic {
+        Some(ref semantic) => {
+            let mut cycles: Vec<String> = semantic.cycles.iter().cloned().collect();
+            cycles.sort();
+            (semantic.order.to_json(), cycles.to_json())
+        },
+        None => (Json::Null, Json::Null),
+    };
+
+    Json::object(vec![
+        ("tokens", Json::Array(tokens)),
+        ("ast", ast.to_json()),
+        ("order", order),
+        ("cycles", cycles),
+        ("diagnostics", Json::Array(result.diagnostics.iter().map(|d| d.to_json()).collect())),
+    ]).to_string()
+}
+
+/// The class of every token, for the editor to color it: an array of
+/// objects with `class`, `start` and `end`, like
+/// `{"class":"type-name","start":5,"end":10}`.
+///
+/// # Examples
+///
+///     use simcom::json::Json;
+///     use simcom::playground::highlight;
+///
+///     let json = Json::parse(&highlight("tipo Punto(x: Entero);")).unwrap();
+///     assert_eq!(json.as_array().unwrap()[1].get("class"), Some(&Json::from("type-name")));
+pub fn highlight(source: &str) -> String {
+    classify(source, Language::default()).to_json().to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::json::Json;
+    use super::{analyze, compile_to_ast_json};
+
+    #[test]
+    fn syntax_errors() {
+        let json = Json::parse(&compile_to_ast_json("tipo A(x: Entero);;")).unwrap();
+        let diagnostics = json.get("diagnostics").and_then(Json::as_array).unwrap();
+        assert_eq!(diagnostics[0].get("message"), Some(&Json::from("unexpected `;`")));
+
+        let json = Json::parse(&analyze("tipo A(x: Entero);;")).unwrap();
+        assert_eq!(json.get("order"), Some(&Json::Null));
+        assert_eq!(json.get("ast").and_then(Json::as_array).map(|a| a.len()), Some(1));
+    }
+
+    #[test]
+    fn cycles_and_lints() {
+        let json = Json::parse(&analyze("tipo a(x: B); tipo B(y: a);")).unwrap();
+        assert_eq!(json.get("cycles").unwrap().to_string(), r#"["B","a"]"#);
+
+        let diagnostics = json.get("diagnostics").and_then(Json::as_array).unwrap();
+        assert_eq!(diagnostics[0].get("code"), Some(&Json::from("W0202")));
+    }
+}
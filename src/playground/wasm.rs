@@ -0,0 +1,31 @@
+//! The playground for JavaScript, with the `wasm` feature. Each function is
+//! the one of the same name in the [playground][0], exported with
+//! wasm-bindgen.
+//!
+//! [0]: ../index.html
+
+use wasm_bindgen::prelude::*;
+
+/// See [`playground::compile_to_ast_json`][0].
+///
+/// [0]: ../fn.compile_to_ast_json.html
+#[wasm_bindgen]
+pub fn compile_to_ast_json(source: &str) -> String {
+    super::compile_to_ast_json(source)
+}
+
+/// See [`playground::analyze`][0].
+///
+/// [0]: ../fn.analyze.html
+#[wasm_bindgen]
+pub fn analyze(source: &str) -> String {
+    super::analyze(source)
+}
+
+/// See [`playground::highlight`][0].
+///
+/// [0]: ../fn.highlight.html
+#[wasm_bindgen]
+pub fn highlight(source: &str) -> String {
+    super::highlight(source)
+}
@@ -0,0 +1,19 @@
+//! Prelude module.
+//!
+//! The types most programs using the library need, to import them all at
+//! once.
+//!
+//! # Examples
+//!
+//!     use simcom::prelude::*;
+//!
+//!     let asts: Vec<Ast> = Parser::new(Lexer::new("tipo Punto(x: Entero);")).collect();
+//!     let semantic = Semantic::analyze(asts).unwrap();
+//!     assert_eq!(semantic.order, vec!["Entero", "Punto"]);
+
+pub use super::lexer::{Lexer, Token};
+pub use super::parser::{Ast, Parser};
+#[cfg(feature = "std")]
+pub use super::semantic::Semantic;
+#[cfg(feature = "std")]
+pub use super::driver::{Driver, Options};
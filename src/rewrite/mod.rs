@@ -0,0 +1,203 @@
+//! Rewrite module.
+//!
+//! Renaming a type, fixing a diagnostic or formatting a file all end up as
+//! [`TextEdit`s][0]: spans of the original text and what replaces them.
+//! [`apply`][1] makes the new text out of them, leaving every byte the edits
+//! don't touch as it was, comments and spacing included.
+//!
+//! [0]: ../diagnostics/struct.TextEdit.html
+//! [1]: fn.apply.html
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::diagnostics::{Diagnostic, TextEdit};
+use super::lexer::Span;
+use super::source_map::{FileId, SourceMap};
+
+/// Why some edits can't be applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RewriteError {
+    /// Two edits change the same text.
+    Overlap(Span, Span),
+    /// The span is past the end of the text or splits a character.
+    OutOfBounds(Span),
+    /// The span is in a file that isn't in the source map.
+    UnknownFile(Span),
+}
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RewriteError::Overlap(a, b) => {
+                write!(f, "the edits at {}..{} and {}..{} overlap", a.start, a.end, b.start, b.end)
+            },
+            RewriteError::OutOfBounds(span) => write!(f, "the edit at {}..{} is out of the text", span.start, span.end),
+            RewriteError::UnknownFile(span) => write!(f, "the edit at {}..{} is in an unknown file", span.start, span.end),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for RewriteError {}
+
+/// Whether two edits change the same text. Insertions at the same place
+/// overlap too, since their order would be a guess.
+fn overlap(a: Span, b: Span) -> bool {
+    (a.start < b.end && b.start < a.end) || a.start == b.start
+}
+
+/// Applies `edits` to `source`. Their files are ignored, and their order
+/// doesn't matter.
+///
+/// # Examples
+///
+///     use simcom::diagnostics::TextEdit;
+///     use simcom::lexer::Span;
+///     use simcom::rewrite::apply;
+///
+///     let edits = [
+///         TextEdit { span: Span::new(21, 21), replacement: String::from(";") },
+///         TextEdit { span: Span::new(5, 10), replacement: String::from("Punto") },
+///     ];
+///     let source = "tipo punto(x: Entero)   /// Docs.";
+///     assert_eq!(apply(source, &edits).unwrap(), "tipo Punto(x: Entero);   /// Docs.");
+pub fn apply(source: &str, edits: &[TextEdit]) -> Result<String, RewriteError> {
+    let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+    sorted.sort_by_key(|edit| (edit.span.start, edit.span.end));
+
+    let mut out = String::with_capacity(source.len());
+    let mut copied = 0;
+    let mut last: Option<Span> = None;
+
+    for edit in sorted {
+        let span = edit.span;
+        if span.start > span.end || source.get(span.range()).is_none() {
+            return Err(RewriteError::OutOfBounds(span));
+        }
+        if let Some(last) = last.filter(|&last| overlap(last, span)) {
+            return Err(RewriteError::Overlap(last, span));
+        }
+
+        out.push_str(&source[copied..span.start]);
+        out.push_str(&edit.replacement);
+        copied = span.end;
+        last = Some(span);
+    }
+
+    out.push_str(&source[copied..]);
+    Ok(out)
+}
+
+/// Applies `edits` to the files of `sources` they are in. Returns the new
+/// text of the files that changed, in the order of the source map.
+pub fn apply_all(sources: &SourceMap, edits: &[TextEdit]) -> Result<Vec<(FileId, String)>, RewriteError> {
+    if let Some(edit) = edits.iter().find(|edit| sources.get(edit.span.file).is_none()) {
+        return Err(RewriteError::UnknownFile(edit.span));
+    }
+
+    let mut rewritten = Vec::new();
+    for file in sources.iter() {
+        let edits: Vec<TextEdit> = edits.iter()
+            .filter(|edit| edit.span.file == file.id)
+            .cloned()
+            .collect();
+        if !edits.is_empty() {
+            rewritten.push((file.id, apply(&file.content, &edits)?));
+        }
+    }
+
+    Ok(rewritten)
+}
+
+/// The edits of every machine applicable suggestion of `diagnostics`, ready
+/// to [`apply`][0]. A suggestion that overlaps one before it is left out
+/// whole, it can be applied in another run.
+///
+/// # Examples
+///
+///     use simcom::diagnostics::{Diagnostic, Suggestion};
+///     use simcom::lexer::Span;
+///     use simcom::rewrite::{apply, fixes};
+///
+///     let diagnostics = [
+///         Diagnostic::error("a", Span::new(0, 1)).with_suggestion(Suggestion::replace("fix a", Span::new(0, 1), "b")),
+///         Diagnostic::error("a", Span::new(0, 1)).with_suggestion(Suggestion::replace("fix a", Span::new(0, 1), "c")),
+///         Diagnostic::error("c", Span::new(2, 3)).with_suggestion(Suggestion::replace("maybe", Span::new(2, 3), "d").maybe_incorrect()),
+///     ];
+///     assert_eq!(apply("a c", &fixes(&diagnostics)).unwrap(), "b c");
+///
+/// [0]: fn.apply.html
+pub fn fixes(diagnostics: &[Diagnostic]) -> Vec<TextEdit> {
+    let mut edits: Vec<TextEdit> = Vec::new();
+
+    let suggestions = diagnostics.iter()
+        .flat_map(|diagnostic| &diagnostic.suggestions)
+        .filter(|suggestion| suggestion.is_machine_applicable());
+    for suggestion in suggestions {
+        let conflicts = suggestion.edits.iter().enumerate().any(|(i, new)| {
+            let others = edits.iter().chain(&suggestion.edits[..i]);
+            others.filter(|old| old.span.file == new.span.file).any(|old| overlap(old.span, new.span))
+        });
+        if !conflicts {
+            edits.extend(suggestion.edits.iter().cloned());
+        }
+    }
+
+    edits
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::diagnostics::TextEdit;
+    use super::super::lexer::Span;
+    use super::super::source_map::SourceMap;
+    use super::{apply, apply_all, RewriteError};
+
+    fn edit(start: usize, end: usize, replacement: &str) -> TextEdit {
+        TextEdit { span: Span::new(start, end), replacement: String::from(replacement) }
+    }
+
+    #[test]
+    fn untouched_bytes_stay() {
+        let source = "tipo A(x: B);\r\n\t/// Ñandú.\ntipo C(y: A);";
+        assert_eq!(apply(source, &[]).unwrap(), source);
+        assert_eq!(apply(source, &[edit(5, 6, "Alfa"), edit(39, 40, "Alfa")]).unwrap(),
+            "tipo Alfa(x: B);\r\n\t/// Ñandú.\ntipo C(y: Alfa);");
+        assert_eq!(apply(source, &[edit(0, source.len(), "")]).unwrap(), "");
+    }
+
+    #[test]
+    fn touching_edits() {
+        assert_eq!(apply("abc", &[edit(1, 2, "X"), edit(0, 1, "Y"), edit(2, 2, "Z")]).unwrap(), "YXZc");
+    }
+
+    #[test]
+    fn bad_edits() {
+        assert_eq!(apply("abc", &[edit(0, 2, ""), edit(1, 3, "")]),
+            Err(RewriteError::Overlap(Span::new(0, 2), Span::new(1, 3))));
+        assert_eq!(apply("abc", &[edit(1, 1, "x"), edit(1, 1, "y")]),
+            Err(RewriteError::Overlap(Span::new(1, 1), Span::new(1, 1))));
+        assert_eq!(apply("abc", &[edit(2, 4, "")]), Err(RewriteError::OutOfBounds(Span::new(2, 4))));
+        assert_eq!(apply("ñ", &[edit(1, 2, "")]), Err(RewriteError::OutOfBounds(Span::new(1, 2))));
+    }
+
+    #[test]
+    fn many_files() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a.sc", "tipo A(x: B);");
+        let b = sources.add("b.sc", "tipo B(y: B);");
+        sources.add("c.sc", "tipo C(z: A);");
+
+        let edits = [
+            TextEdit { span: Span::new(5, 6).in_file(b), replacement: String::from("Bote") },
+            TextEdit { span: Span::new(10, 11).in_file(b), replacement: String::from("Bote") },
+            TextEdit { span: Span::new(10, 11).in_file(a), replacement: String::from("Bote") },
+        ];
+        assert_eq!(apply_all(&sources, &edits).unwrap(), vec![
+            (a, String::from("tipo A(x: Bote);")),
+            (b, String::from("tipo Bote(y: Bote);")),
+        ]);
+    }
+}
@@ -4,39 +4,121 @@
 //! are in scope, type inference...) before tying to compile the program.
 //!
 //! The aim with this one, for simplicity, is to walk dependencies (including
-//! cyclic ones) to determine in what order they must be written.
+//! cyclic ones) to determine in what order they must be written. On the way,
+//! it resolves the names of the types of the fields: inside a module, a type
+//! that isn't qualified is the one of the same module, if there is one, and a
+//! type of the top level otherwise.
+//!
+//! It also checks that the types that `implementa` an interface have every
+//! field of the interface, with the same type, that the variants of every
+//! enumeration have different names and values, that fields with numbers
+//! don't share them, and that the [versions][0] of definitions and the
+//! [units][1] of fields make sense.
+//!
+//! [0]: ../versions/index.html
+//! [1]: ../units/index.html
 
+use std::borrow::Cow;
 use std::collections::hash_map::HashMap;
 use std::collections::hash_set::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+use std::vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize, Serializer};
 
-use super::lexer::Token;
-use super::parser::{Ast, Parser};
+use super::diagnostics::{codes, Diagnostic};
+use super::json::{FromJson, Json, ToJson};
+use super::lexer::{Span, Token};
+use super::parser::{Ast, Parameter, TypeDefinition, Variant};
+use super::{units, versions};
 
 /// Value returned in [`Semantic::analyze`][0] if any errors are found.
 ///
 /// In this analyzer, we only return error if we find any unexpected tokens.
-/// Here we store all of them.
+/// Here we store all of them. It can be used as a slice of tokens.
+///
+/// # Examples
+///
+///     use simcom::lexer::{Lexer, Token};
+///     use simcom::parser::Parser;
+///     use simcom::semantic::Semantic;
+///
+///     let error = Semantic::analyze(Parser::new(Lexer::new("tipo A(x: X);;"))).unwrap_err();
+///     assert_eq!(error[0], Token::Semicolon);
+///     assert_eq!(error.to_string(), "unexpected `;`");
 ///
 /// [0]: struct.Semantic.html#method.analyze
-pub type UnexpectedTokens = Vec<Token>;
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnexpectedTokens(pub Vec<Token>);
+
+impl Deref for UnexpectedTokens {
+    type Target = [Token];
+
+    fn deref(&self) -> &[Token] {
+        &self.0
+    }
+}
+
+impl fmt::Display for UnexpectedTokens {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unexpected ")?;
+        for (i, token) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", token)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for UnexpectedTokens {}
 
 /// The semantic analyzer in our language.
 ///
-/// Given an AST (from a [`Parser`][0]) determines the order in which all
-/// definitions must go.
+/// Given an AST (usually from a [`Parser`][0], or several of them chained when
+/// compiling many files) determines the order in which all definitions must go.
 ///
 /// [0]: ../parser/struct.Parser.html
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Semantic {
-    /// All the type definitions. Since we consumed the AST, we have to store
-    /// them somewhere.
-    pub definitions: HashMap<String, Vec<(String, String)>>,
+    /// All the type definitions, by their [qualified name][0]. Since we
+    /// consumed the AST, we have to store them somewhere. The types of their
+    /// fields are qualified too, once resolved.
+    ///
+    /// [0]: ../parser/struct.TypeDefinition.html#method.qualified_name
+    #[cfg_attr(feature = "serde", serde(serialize_with = "sorted_definitions"))]
+    pub definitions: HashMap<String, TypeDefinition<'static>>,
     /// The order in which to write the definitions.
     pub order: Vec<String>,
     /// If any cyclic dependency is found, all the types involved are stored
     /// here, so they can be handled accordingly.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "sorted_cycles"))]
     pub cycles: HashSet<String>,
 }
 
+/// Writes the definitions by name, like `ToJson` does.
+#[cfg(feature = "serde")]
+fn sorted_definitions<S: Serializer>(definitions: &HashMap<String, TypeDefinition<'static>>, serializer: S)
+    -> Result<S::Ok, S::Error>
+{
+    let mut names: Vec<&String> = definitions.keys().collect();
+    names.sort();
+    serializer.collect_map(names.into_iter().map(|name| (name, &definitions[name])))
+}
+
+/// Writes the cycles sorted, like `ToJson` does.
+#[cfg(feature = "serde")]
+fn sorted_cycles<S: Serializer>(cycles: &HashSet<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut cycles: Vec<&String> = cycles.iter().collect();
+    cycles.sort();
+    serializer.collect_seq(cycles)
+}
+
 impl Semantic {
     /// Builds the semantic analyzer and analyzes the AST.
     ///
@@ -73,47 +155,379 @@ impl Semantic {
     ///     panic!("Wrong if/else branch!");
     /// }
     /// ```
-    pub fn analyze(ast: Parser) -> Result<Self, UnexpectedTokens> {
+    pub fn analyze<'src, I: IntoIterator<Item = Ast<'src>>>(ast: I) -> Result<Self, UnexpectedTokens> {
         let mut definitions = HashMap::new();
         let mut errors = Vec::new();
 
         for definition in ast {
             match definition {
-                Ast::TypeDefinition(name, parameters) => {
-                    definitions.insert(name, build_parameters(parameters));
+                Ast::TypeDefinition(definition) => {
+                    definitions.insert(definition.qualified_name().into_owned(), definition.into_owned());
                 },
-                Ast::Unexpected(token) => errors.push(token),
-                _ => unreachable!(),
+                Ast::Unexpected(token, _) => errors.push(token),
             }
         }
+        resolve(&mut definitions);
 
         match errors.len() {
             0 => SemanticBuilder::build(definitions),
+            _ => Err(UnexpectedTokens(errors)),
+        }
+    }
+
+    /// Like [`analyze`][0], but the errors are [`Diagnostic`s][1], which know
+    /// where the tokens were. A type defined more than once in the same
+    /// module is an error too, pointing to its first definition, and so are
+    /// the [implementations][2] of interfaces that miss a field or have it
+    /// with another type, and enumerations with repeated variants or values.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::{Lexer, Span};
+    ///     use simcom::parser::Parser;
+    ///     use simcom::semantic::Semantic;
+    ///
+    ///     let errors = Semantic::check(Parser::new(Lexer::new("tipo A(x: X);;"))).unwrap_err();
+    ///     assert_eq!(errors[0].message, "unexpected `;`");
+    ///     assert_eq!(errors[0].span, Span::new(13, 14));
+    ///
+    ///     let errors = Semantic::check(Parser::new(Lexer::new("tipo A(x: X); tipo A(y: Y);"))).unwrap_err();
+    ///     assert_eq!(errors[0].message, "type `A` is defined more than once");
+    ///     assert_eq!(errors[0].span, Span::new(19, 20));
+    ///     assert_eq!(errors[0].labels[0].span, Span::new(5, 6));
+    ///
+    ///     let content = "tipo Id(x: Entero); modulo red; tipo Id(ip: Texto); tipo Nodo(id: Id);";
+    ///     let semantic = Semantic::check(Parser::new(Lexer::new(content))).unwrap();
+    ///     assert_eq!(semantic.definitions["red.Nodo"].parameters[0].typename, "red.Id");
+    ///
+    ///     let content = "interfaz Nombrado(nombre: Texto); tipo Gato(nombre: Entero) implementa Nombrado;";
+    ///     let errors = Semantic::check(Parser::new(Lexer::new(content))).unwrap_err();
+    ///     assert_eq!(errors[0].message, "field `nombre` of `Gato` is `Entero`, but `Nombrado` requires `Texto`");
+    ///
+    /// [0]: #method.analyze
+    /// [1]: ../diagnostics/struct.Diagnostic.html
+    /// [2]: ../parser/struct.TypeDefinition.html#structfield.implements
+    pub fn check<'src, I: IntoIterator<Item = Ast<'src>>>(ast: I) -> Result<Self, Vec<Diagnostic>> {
+        let mut definitions = Vec::new();
+        let mut errors = Vec::new();
+        // Where each name was defined first.
+        let mut first = HashMap::new();
+
+        for ast in ast {
+            if let Some(diagnostic) = ast.diagnostic() {
+                errors.push(diagnostic);
+                continue;
+            }
+
+            if let Ast::TypeDefinition(ref definition) = ast {
+                let name = definition.qualified_name();
+                match first.get(&name[..]) {
+                    Some(&previous) => errors.push(redefined(definition, previous)),
+                    None => {
+                        first.insert(name.into_owned(), definition.name_span);
+                    },
+                }
+            }
+            definitions.push(ast);
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let semantic = Semantic::analyze(definitions).map_err(|_| Vec::new())?;
+        let mut errors = implementations(&semantic);
+        errors.extend(variants(&semantic));
+        errors.extend(field_numbers(&semantic));
+        errors.extend(versions::check(&semantic));
+        errors.extend(units::check(&semantic));
+        match errors.len() {
+            0 => Ok(semantic),
             _ => Err(errors),
         }
     }
 }
 
+/// The error for the definition of a type that was already defined, where
+/// `previous` is the name of the first definition.
+fn redefined(definition: &TypeDefinition, previous: Span) -> Diagnostic {
+    let name = definition.qualified_name();
+    Diagnostic::error(format!("type `{}` is defined more than once", name), definition.name_span)
+        .with_code(codes::TYPE_REDEFINED)
+        .with_label(previous, format!("previous definition of `{}` is here", name))
+}
+
+/// The errors of the `implementa` clauses, by type name. Every interface
+/// must be defined, and the types implementing it must have all its fields
+/// with the same types. Interfaces aren't types, so they can't be the type
+/// of a field either.
+fn implementations(semantic: &Semantic) -> Vec<Diagnostic> {
+    let mut names: Vec<&String> = semantic.definitions.keys().collect();
+    names.sort();
+    let mut errors = Vec::new();
+
+    for name in names {
+        let definition = &semantic.definitions[name];
+
+        for parameter in &definition.parameters {
+            let interface = semantic.definitions.get(&parameter.typename[..]).filter(|d| d.is_interface());
+            if let Some(interface) = interface {
+                errors.push(Diagnostic::error(format!("interface `{}` can't be the type of field `{}`",
+                        parameter.typename, parameter.name), parameter.type_span)
+                    .with_code(codes::INTERFACE_AS_TYPE)
+                    .with_label(interface.name_span, format!("`{}` is defined here", parameter.typename)));
+            }
+        }
+
+        for implementation in &definition.implements {
+            let interface = match semantic.definitions.get(&implementation.name[..]) {
+                Some(interface) if interface.is_interface() => interface,
+                Some(other) => {
+                    errors.push(Diagnostic::error(format!("`{}` is a type, not an interface", implementation.name),
+                            implementation.span)
+                        .with_code(codes::NOT_AN_INTERFACE)
+                        .with_label(other.name_span, format!("`{}` is defined here", implementation.name)));
+                    continue;
+                },
+                None => {
+                    errors.push(Diagnostic::error(format!("interface `{}` isn't defined", implementation.name),
+                            implementation.span)
+                        .with_code(codes::NOT_AN_INTERFACE));
+                    continue;
+                },
+            };
+
+            for required in &interface.parameters {
+                match definition.parameters.iter().find(|p| p.name == required.name) {
+                    None => errors.push(Diagnostic::error(format!("type `{}` doesn't have the field `{}` of `{}`",
+                            name, required.name, implementation.name), implementation.span)
+                        .with_code(codes::MISSING_FIELD)
+                        .with_label(required.span, format!("`{}` is required here", required.name))),
+                    Some(field) if field.typename != required.typename => errors.push(Diagnostic::error(
+                            format!("field `{}` of `{}` is `{}`, but `{}` requires `{}`",
+                                field.name, name, field.typename, implementation.name, required.typename),
+                            field.type_span)
+                        .with_code(codes::FIELD_TYPE_MISMATCH)
+                        .with_label(required.type_span, format!("`{}` is required here", required.typename))),
+                    Some(_) => (),
+                }
+            }
+        }
+    }
+
+    errors
+}
+
+/// The errors of the variants of enumerations, by enumeration name: two
+/// variants with the same name or the same value, and values that C can't
+/// hold, since its enums are `int`s.
+fn variants(semantic: &Semantic) -> Vec<Diagnostic> {
+    let mut names: Vec<&String> = semantic.definitions.keys().collect();
+    names.sort();
+    let mut errors = Vec::new();
+
+    for name in names {
+        let definition = &semantic.definitions[name];
+        // The first variant with each name and with each value.
+        let mut first_names: HashMap<&str, &Variant> = HashMap::new();
+        let mut first_values: HashMap<i64, &Variant> = HashMap::new();
+
+        for (variant, value) in definition.variants.iter().zip(definition.values()) {
+            if let Some(&previous) = first_names.get(&variant.name[..]) {
+                errors.push(Diagnostic::error(format!("variant `{}` is defined more than once in `{}`",
+                        variant.name, name), variant.name_span)
+                    .with_code(codes::VARIANT_REDEFINED)
+                    .with_label(previous.name_span, format!("previous variant `{}` is here", variant.name)));
+                continue;
+            }
+            first_names.insert(&variant.name, variant);
+
+            if value < i64::from(i32::MIN) || value > i64::from(i32::MAX) {
+                errors.push(Diagnostic::error(format!("value {} of variant `{}` doesn't fit in 32 bits", value, variant.name),
+                        variant.span)
+                    .with_code(codes::VARIANT_VALUE_OUT_OF_RANGE));
+            }
+
+            match first_values.get(&value) {
+                Some(&previous) => {
+                    let mut error = Diagnostic::error(format!("variant `{}` of `{}` has the value {}, like `{}`",
+                            variant.name, name, value, previous.name), variant.span)
+                        .with_code(codes::VARIANT_VALUE_REPEATED)
+                        .with_label(previous.span, format!("`{}` has the value {}", previous.name, value));
+                    if variant.value.is_none() {
+                        error = error.with_note("a variant without a value has the next one after the variant before it");
+                    }
+                    errors.push(error);
+                },
+                None => {
+                    first_values.insert(value, variant);
+                },
+            }
+        }
+    }
+
+    errors
+}
+
+/// The largest field number, the one of Protobuf, so numbers can be used by
+/// any binary format.
+pub const MAX_FIELD_NUMBER: i64 = 536_870_911;
+
+/// The problems with the numbers of the fields: a definition numbers all of
+/// its fields or none, and each number is used once.
+fn field_numbers(semantic: &Semantic) -> Vec<Diagnostic> {
+    let mut names: Vec<&String> = semantic.definitions.keys().collect();
+    names.sort();
+    let mut errors = Vec::new();
+
+    for name in names {
+        let definition = &semantic.definitions[name];
+        let numbered = match definition.parameters.iter().find(|p| p.number.is_some()) {
+            Some(numbered) => numbered,
+            None => continue,
+        };
+        // The first field with each number.
+        let mut first: HashMap<i64, &Parameter> = HashMap::new();
+
+        for parameter in &definition.parameters {
+            let number = match parameter.number {
+                Some(number) => number,
+                None => {
+                    errors.push(Diagnostic::error(format!("field `{}` of `{}` has no number, but other fields do",
+                            parameter.name, name), parameter.span)
+                        .with_code(codes::FIELD_NUMBER_MISSING)
+                        .with_label(numbered.span, format!("`{}` has the number {}",
+                            numbered.name, numbered.number.unwrap_or_default())));
+                    continue;
+                },
+            };
+
+            if !(1..=MAX_FIELD_NUMBER).contains(&number) {
+                errors.push(Diagnostic::error(format!("number {} of field `{}` isn't between 1 and {}",
+                        number, parameter.name, MAX_FIELD_NUMBER), parameter.span)
+                    .with_code(codes::FIELD_NUMBER_OUT_OF_RANGE));
+            }
+
+            match first.get(&number) {
+                Some(&previous) => errors.push(Diagnostic::error(format!("field `{}` of `{}` has the number {}, like `{}`",
+                        parameter.name, name, number, previous.name), parameter.span)
+                    .with_code(codes::FIELD_NUMBER_REPEATED)
+                    .with_label(previous.span, format!("`{}` has the number {}", previous.name, number))),
+                None => {
+                    first.insert(number, parameter);
+                },
+            }
+        }
+    }
+
+    errors
+}
+
+/// Qualifies the types of the fields, and the interfaces, of the definitions
+/// in a module that refer to another definition of the same module.
+fn resolve(definitions: &mut HashMap<String, TypeDefinition<'static>>) {
+    let names: HashSet<String> = definitions.keys().cloned().collect();
+    let qualify = |module: &str, name: &mut Cow<'static, str>| {
+        let qualified = format!("{}.{}", module, name);
+        if !name.contains('.') && names.contains(&qualified) {
+            *name = Cow::Owned(qualified);
+        }
+    };
+
+    for definition in definitions.values_mut() {
+        let module = match definition.module {
+            Some(ref module) => module.to_string(),
+            None => continue,
+        };
+        for parameter in &mut definition.parameters {
+            qualify(&module, &mut parameter.typename);
+        }
+        for implementation in &mut definition.implements {
+            qualify(&module, &mut implementation.name);
+        }
+    }
+}
+
+/// The definitions are an object keyed by name. Keys and cycles are sorted,
+/// so the same input always gives the same output.
+impl ToJson for Semantic {
+    fn to_json(&self) -> Json {
+        let mut names: Vec<&String> = self.definitions.keys().collect();
+        names.sort();
+        let definitions = names.into_iter()
+            .map(|name| (name.clone(), self.definitions[name].to_json()))
+            .collect();
+
+        let mut cycles: Vec<String> = self.cycles.iter().cloned().collect();
+        cycles.sort();
+
+        Json::object(vec![
+            ("definitions", Json::Object(definitions)),
+            ("order", self.order.to_json()),
+            ("cycles", cycles.to_json()),
+        ])
+    }
+}
+
+impl FromJson for Semantic {
+    fn from_json(json: &Json) -> Result<Semantic, String> {
+        let definitions = match json.get("definitions") {
+            Some(Json::Object(pairs)) => pairs.iter()
+                .map(|(name, definition)| {
+                    TypeDefinition::from_json(definition)
+                        .map(|definition| (name.clone(), definition))
+                        .map_err(|e| format!("`definitions`: `{}`: {}", name, e))
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err(String::from("`definitions`: expected an object")),
+            None => return Err(String::from("missing `definitions`")),
+        };
+        let cycles: Vec<String> = json.field("cycles")?;
+
+        Ok(Semantic {
+            definitions,
+            order: json.field("order")?,
+            cycles: cycles.into_iter().collect(),
+        })
+    }
+}
+
 struct SemanticBuilder {
-    definitions: HashMap<String, Vec<(String, String)>>,
+    definitions: HashMap<String, TypeDefinition<'static>>,
 
     order: Vec<String>,
-    visited: HashSet<String>,
+    /// When each type was first visited, and the earliest one it can get
+    /// back to through the types that are still on the stack.
+    index: HashMap<String, usize>,
+    low: HashMap<String, usize>,
+    /// The types visited whose strongly connected component isn't complete
+    /// yet.
+    stack: Vec<String>,
+    on_stack: HashSet<String>,
     cycles: HashSet<String>,
 }
 
 impl SemanticBuilder {
-    fn build(definitions: HashMap<String, Vec<(String, String)>>) -> Result<Semantic, UnexpectedTokens> {
+    fn build(definitions: HashMap<String, TypeDefinition<'static>>) -> Result<Semantic, UnexpectedTokens> {
+        trace_span!(INFO, "semantic", definitions = definitions.len());
+
         let mut sb = Self {
             definitions,
             order: Vec::new(),
-            visited: HashSet::new(),
+            index: HashMap::new(),
+            low: HashMap::new(),
+            stack: Vec::new(),
+            on_stack: HashSet::new(),
             cycles: HashSet::new(),
         };
 
-        for node in sb.definitions.clone().keys() {
+        // Sorted, so the order doesn't depend on the one of the HashMap.
+        let mut nodes: Vec<String> = sb.definitions.keys().cloned().collect();
+        nodes.sort();
+        for node in &nodes {
             sb.visit(node);
         }
+        trace_event!(DEBUG, types = sb.order.len(), cycles = sb.cycles.len(), "analyzed");
 
         Ok(Semantic {
             definitions: sb.definitions,
@@ -122,38 +536,84 @@ impl SemanticBuilder {
         })
     }
 
-    fn visit(&mut self, node: &String) {
-        if self.order.contains(node) {
-            return;
-        } else if self.visited.contains(node) {
-            self.cycles = self.visited.clone();
+    /// Visits `root` and everything it depends on, writing each type after
+    /// its dependencies. The types of a cycle are its strongly connected
+    /// components (Tarjan's algorithm), with more than one type or a type
+    /// that contains itself. A loop instead of recursion, because a long
+    /// enough chain of types would overflow the stack.
+    fn visit(&mut self, root: &str) {
+        // The types from `root` to the one being visited, each with the
+        // dependencies that are left to visit.
+        let mut path = Vec::new();
+        self.enter(root, &mut path);
+
+        while let Some(&mut (ref node, ref mut dependencies)) = path.last_mut() {
+            let node = node.clone();
+            match dependencies.next() {
+                Some(dependency) => match self.index.get(&dependency) {
+                    None => self.enter(&dependency, &mut path),
+                    Some(&index) if self.on_stack.contains(&dependency) => self.lower(&node, index),
+                    Some(_) => (),
+                },
+                None => {
+                    path.pop();
+                    let low = self.low[&node];
+                    if let Some((parent, _)) = path.last() {
+                        self.lower(parent, low);
+                    }
+                    if low == self.index[&node] {
+                        self.close(&node);
+                    }
+                    self.order.push(node);
+                },
+            }
+        }
+    }
+
+    /// Starts visiting `node`, unless it was already visited.
+    fn enter(&mut self, node: &str, path: &mut Vec<(String, vec::IntoIter<String>)>) {
+        if self.index.contains_key(node) {
             return;
         }
 
-        self.visited.insert(node.clone());
+        let index = self.index.len();
+        self.index.insert(node.to_string(), index);
+        self.low.insert(node.to_string(), index);
+        self.stack.push(node.to_string());
+        self.on_stack.insert(node.to_string());
 
-        if let Some(d) = self.definitions.clone().get(node) {
-            for &(_, ref v) in d {
-                self.visit(v);
-            }
-        }
+        path.push((node.to_string(), self.dependencies(node).into_iter()));
+    }
 
-        self.order.push(node.clone());
-        self.visited.remove(node);
+    fn dependencies(&self, node: &str) -> Vec<String> {
+        match self.definitions.get(node) {
+            Some(d) => d.parameters.iter().map(|p| p.typename.to_string()).collect(),
+            None => Vec::new(),
+        }
     }
-}
 
-fn ast_to_parameter(ast: Ast) -> Option<(String, String)> {
-    match ast {
-        Ast::Parameter(name, typename) => Some((name, typename)),
-        _ => None,
+    fn lower(&mut self, node: &str, index: usize) {
+        if let Some(low) = self.low.get_mut(node) {
+            *low = (*low).min(index);
+        }
     }
-}
 
-fn build_parameters(ast: Vec<Ast>) -> Vec<(String, String)> {
-    ast.into_iter()
-        .filter_map(ast_to_parameter)
-        .collect()
+    /// Takes the strongly connected component of `root` off the stack.
+    fn close(&mut self, root: &str) {
+        let mut component = Vec::new();
+        while let Some(node) = self.stack.pop() {
+            self.on_stack.remove(&node);
+            let last = node == root;
+            component.push(node);
+            if last {
+                break;
+            }
+        }
+
+        if component.len() > 1 || self.dependencies(root).iter().any(|d| d == root) {
+            self.cycles.extend(component);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -166,6 +626,41 @@ mod test {
         Semantic::analyze(Parser::new(Lexer::new(content)))
     }
 
+    #[test]
+    fn unexpected_tokens() {
+        let error = UnexpectedTokens(vec![Token::Semicolon, Token::Ident(String::from("x"))]);
+        assert_eq!(error.len(), 2);
+        assert_eq!(error.to_string(), "unexpected `;`, `x`");
+    }
+
+    #[test]
+    fn json() {
+        let s = get_semantic("tipo A(x: B);\
+        tipo B(x: A);").unwrap();
+
+        let json = s.to_json();
+        assert_eq!(json.get("cycles").unwrap().to_string(), r#"["A","B"]"#);
+
+        let back = Semantic::from_json(&json).unwrap();
+        assert_eq!(back.definitions, s.definitions);
+        assert_eq!(back.order, s.order);
+        assert_eq!(back.cycles, s.cycles);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let s = get_semantic("tipo A(x: B); tipo B(x: A); tipo C(a: A); tipo D(c: C);").unwrap();
+
+        let json = serde_json::to_string(&s).unwrap();
+        assert_eq!(json, s.to_json().to_string());
+
+        let back: Semantic = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.definitions, s.definitions);
+        assert_eq!(back.order, s.order);
+        assert_eq!(back.cycles, s.cycles);
+    }
+
     #[test]
     fn order_ok() {
         let content = "tipo A(x: long);\
@@ -189,6 +684,27 @@ mod test {
         assert!(s.cycles.contains("B"));
     }
 
+    #[test]
+    fn cycles_are_strongly_connected_components() {
+        let sorted = |s: Semantic| {
+            let mut cycles: Vec<String> = s.cycles.into_iter().collect();
+            cycles.sort();
+            cycles
+        };
+
+        let s = get_semantic("tipo A(b: B); tipo B(a: A); tipo C(d: D); tipo D(c: C);").unwrap();
+        assert_eq!(sorted(s), ["A", "B", "C", "D"]);
+
+        // `X` uses the cycle, but it isn't part of it.
+        let s = get_semantic("tipo X(a: A); tipo A(b: B); tipo B(a: A);").unwrap();
+        assert_eq!(s.order.last().map(|name| &name[..]), Some("X"));
+        assert_eq!(sorted(s), ["A", "B"]);
+
+        // `C` gets back to `A` through `B`, that was already written.
+        let s = get_semantic("tipo A(b: B, c: C); tipo B(a: A); tipo C(b: B); tipo D(d: D); tipo E(e: Entero);").unwrap();
+        assert_eq!(sorted(s), ["A", "B", "C", "D"]);
+    }
+
     #[test]
     fn cycle_order_1() {
         let content = "tipo A(x: B);\
@@ -239,4 +755,113 @@ mod test {
         assert!(s.cycles.contains(&t_a));
         assert!(s.cycles.contains(&t_b));
     }
+
+    #[test]
+    fn modules() {
+        let content = "tipo Id(x: Entero); tipo Ruta(a: red.Nodo, b: geo.Punto);\
+        modulo red; tipo Id(ip: Texto); tipo Nodo(id: Id, ruta: Ruta);\
+        modulo geo; tipo Punto(id: Id, nodo: red.Id);";
+
+        let s = get_semantic(content).unwrap();
+        let mut names: Vec<&String> = s.definitions.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["Id", "Ruta", "geo.Punto", "red.Id", "red.Nodo"]);
+
+        let types = |name: &str| -> Vec<String> {
+            s.definitions[name].parameters.iter().map(|p| p.typename.to_string()).collect()
+        };
+        assert_eq!(types("red.Nodo"), vec!["red.Id", "Ruta"]);
+        assert_eq!(types("geo.Punto"), vec!["Id", "red.Id"]);
+        assert_eq!(types("Ruta"), vec!["red.Nodo", "geo.Punto"]);
+        assert!(s.cycles.contains("Ruta") && s.cycles.contains("red.Nodo"));
+
+        // The same name in two modules isn't a redefinition, but it is in the
+        // same one.
+        let content = "modulo a; tipo X(x: E); modulo b; tipo X(x: E); tipo X(y: E);";
+        let errors = Semantic::check(Parser::new(Lexer::new(content))).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "type `b.X` is defined more than once");
+        assert_eq!(errors[0].labels[0].span, Span::new(39, 40));
+    }
+
+    #[test]
+    fn interfaces() {
+        let check = |content: &str| -> Vec<(String, Span)> {
+            match Semantic::check(Parser::new(Lexer::new(content))) {
+                Ok(_) => Vec::new(),
+                Err(errors) => errors.into_iter().map(|e| (e.message, e.span)).collect(),
+            }
+        };
+
+        let content = "interfaz Nombrado(nombre: Texto); tipo Gato(edad: Entero, nombre: Texto) implementa Nombrado;";
+        assert_eq!(check(content), vec![]);
+
+        let content = "interfaz N(a: A, b: B);
+tipo T(b: C) implementa N, X, T;
+tipo U(n: N);";
+        assert_eq!(check(content), vec![
+            (String::from("type `T` doesn't have the field `a` of `N`"), Span::new(48, 49)),
+            (String::from("field `b` of `T` is `C`, but `N` requires `B`"), Span::new(34, 35)),
+            (String::from("interface `X` isn't defined"), Span::new(51, 52)),
+            (String::from("`T` is a type, not an interface"), Span::new(54, 55)),
+            (String::from("interface `N` can't be the type of field `n`"), Span::new(67, 68)),
+        ]);
+
+        // Inside a module, the interface and the types of its fields are
+        // resolved like any other type.
+        let content = "tipo Id(x: Entero); modulo red; interfaz Nodo(id: Id); tipo Id(ip: Texto);\
+        tipo Router(id: Id) implementa Nodo; modulo geo; tipo Punto(id: red.Id) implementa red.Nodo;";
+        assert_eq!(check(content), vec![]);
+    }
+
+    #[test]
+    fn enums() {
+        let check = |content: &str| -> Vec<(String, Span)> {
+            match Semantic::check(Parser::new(Lexer::new(content))) {
+                Ok(_) => Vec::new(),
+                Err(errors) => errors.into_iter().map(|e| (e.message, e.span)).collect(),
+            }
+        };
+
+        assert_eq!(check("enum Estado(Activo = 1, Inactivo = 2); tipo Usuario(estado: Estado);"), vec![]);
+
+        let content = "enum E(A = 1, B = 0, C, A, D = 3000000000);";
+        assert_eq!(check(content), vec![
+            (String::from("variant `C` of `E` has the value 1, like `A`"), Span::new(21, 22)),
+            (String::from("variant `A` is defined more than once in `E`"), Span::new(24, 25)),
+            (String::from("value 3000000000 of variant `D` doesn't fit in 32 bits"), Span::new(27, 41)),
+        ]);
+    }
+
+    #[test]
+    fn field_numbers() {
+        let check = |content: &str| -> Vec<(String, Span)> {
+            match Semantic::check(Parser::new(Lexer::new(content))) {
+                Ok(_) => Vec::new(),
+                Err(errors) => errors.into_iter().map(|e| (e.message, e.span)).collect(),
+            }
+        };
+
+        assert_eq!(check("tipo A(x: Entero #2, y: Entero #1); tipo B(x: Entero);"), vec![]);
+
+        let content = "tipo A(x: Entero #1, y: Entero, z: Real #1, w: Real #0);";
+        assert_eq!(check(content), vec![
+            (String::from("field `y` of `A` has no number, but other fields do"), Span::new(21, 30)),
+            (String::from("field `z` of `A` has the number 1, like `x`"), Span::new(32, 42)),
+            (String::from("number 0 of field `w` isn't between 1 and 536870911"), Span::new(44, 54)),
+        ]);
+    }
+
+    #[test]
+    fn long_chains() {
+        // Deeper than the stack would allow if visiting were recursive.
+        let content: String = (0..100_000).map(|i| format!("tipo T{}(x: T{});", i, i + 1)).collect();
+
+        let s = get_semantic(&content).unwrap();
+
+        assert_eq!(s.order.len(), 100_001);
+        assert_eq!(s.order[0], "T100000");
+        assert_eq!(s.order[100_000], "T0");
+        assert!(s.cycles.is_empty());
+    }
 }
@@ -0,0 +1,178 @@
+//! Source map module.
+//!
+//! When many files are compiled together, a [`Span`][0] alone doesn't say
+//! much: offset 10 is in every file. The [`SourceMap`][1] owns the text of all
+//! of them and gives each one a [`FileId`][2], which spans carry around. With
+//! both, any span can be turned into a file name, a line and a column.
+//!
+//! [0]: ../lexer/struct.Span.html
+//! [1]: struct.SourceMap.html
+//! [2]: struct.FileId.html
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use super::json::{FromJson, Json, ToJson};
+use super::lexer::Span;
+
+/// Identifies a file in a [`SourceMap`][0].
+///
+/// Text that doesn't come from a source map (like the one given to
+/// `Lexer::new`) belongs to the default one.
+///
+/// [0]: struct.SourceMap.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileId(usize);
+
+impl FileId {
+    /// The position of the file in its source map.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Written as its index.
+impl ToJson for FileId {
+    fn to_json(&self) -> Json {
+        Json::from(self.0)
+    }
+}
+
+impl FromJson for FileId {
+    fn from_json(json: &Json) -> Result<FileId, String> {
+        usize::from_json(json).map(FileId)
+    }
+}
+
+/// A file in a source map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    pub id: FileId,
+    /// How the file is called in messages.
+    pub name: String,
+    pub content: String,
+    /// Byte offset where every line starts, to find lines quickly.
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    fn new(id: FileId, name: String, content: String) -> SourceFile {
+        let line_starts = Some(0).into_iter()
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        SourceFile { id, name, content, line_starts }
+    }
+
+    /// The span of the whole file.
+    pub fn span(&self) -> Span {
+        Span::new(0, self.content.len()).in_file(self.id)
+    }
+
+    /// The line and column (both starting at 1) of a byte offset. Columns
+    /// count characters, not bytes.
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.content.len());
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let start = self.line_starts[line];
+        let column = self.content.get(start..offset)
+            .map(|text| text.chars().count())
+            .unwrap_or(offset - start);
+
+        (line + 1, column + 1)
+    }
+}
+
+/// Every file taking part in a compilation.
+///
+/// # Examples
+///
+///     use simcom::lexer::Span;
+///     use simcom::source_map::SourceMap;
+///
+///     let mut sources = SourceMap::new();
+///     let a = sources.add("a.sc", "tipo A(x: B);");
+///     let b = sources.add("b.sc", "tipo B(\n    y: Entero,\n);");
+///
+///     assert_eq!(sources.locate(Span::new(5, 6).in_file(a)), "a.sc:1:6");
+///     assert_eq!(sources.locate(Span::new(12, 13).in_file(b)), "b.sc:2:5");
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Adds a file, returning its id.
+    pub fn add<N: Into<String>, C: Into<String>>(&mut self, name: N, content: C) -> FileId {
+        let id = FileId(self.files.len());
+        self.files.push(SourceFile::new(id, name.into(), content.into()));
+        id
+    }
+
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SourceFile> {
+        self.files.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The file, line and column where `span` starts.
+    pub fn position(&self, span: Span) -> Option<(&SourceFile, usize, usize)> {
+        let file = self.get(span.file)?;
+        let (line, column) = file.line_column(span.start);
+        Some((file, line, column))
+    }
+
+    /// Where `span` is, as `file:line:column`.
+    pub fn locate(&self, span: Span) -> String {
+        match self.position(span) {
+            Some((file, line, column)) => format!("{}:{}:{}", file.name, line, column),
+            None => String::from("<unknown>"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::Span;
+    use super::{FileId, SourceMap};
+
+    #[test]
+    fn line_column() {
+        let mut sources = SourceMap::new();
+        let id = sources.add("a.sc", "tipo A(x: B);\ntipo ñ(y: B);\n");
+        let file = sources.get(id).unwrap();
+
+        assert_eq!(file.line_column(0), (1, 1));
+        assert_eq!(file.line_column(13), (1, 14));
+        assert_eq!(file.line_column(14), (2, 1));
+        assert_eq!(file.line_column(24), (2, 10));
+        assert_eq!(file.line_column(1000), (3, 1));
+    }
+
+    #[test]
+    fn unknown_files() {
+        let sources = SourceMap::new();
+        assert!(sources.is_empty());
+        assert_eq!(sources.locate(Span::new(0, 1).in_file(FileId(3))), "<unknown>");
+    }
+}
@@ -0,0 +1,275 @@
+//! Memory statistics module.
+//!
+//! Compiling a very big schema can take a lot of memory, and it isn't
+//! obvious which phase takes it. [`Compilation::stats`][0] compiles the files
+//! and counts what each phase produced and roughly how much heap it uses, as
+//! a [`Stats`][1].
+//!
+//! Sizes are estimates: what the values themselves ask for (the capacity of
+//! their vectors, strings and maps), not what the allocator really hands
+//! out, and only for memory on the heap. The size of the values themselves is
+//! included when they are inside a collection.
+//!
+//! [0]: ../driver/struct.Compilation.html#method.stats
+//! [1]: struct.Stats.html
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+
+use super::graph::{Edge, Graph, Node};
+use super::lexer::Token;
+use super::parser::{Annotation, Argument, Ast, Implementation, Parameter, TypeDefinition, Variant};
+use super::semantic::Semantic;
+
+/// Values that own memory on the heap.
+pub trait HeapSize {
+    /// Approximate number of bytes on the heap that belong to this value.
+    fn heap_size(&self) -> usize;
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+/// Borrowed text is counted where it is owned, not here.
+impl HeapSize for Cow<'_, str> {
+    fn heap_size(&self) -> usize {
+        match *self {
+            Cow::Borrowed(_) => 0,
+            Cow::Owned(ref text) => text.heap_size(),
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<T>() + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize, S> HeapSize for HashMap<K, V, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<(K, V)>()
+            + self.iter().map(|(k, v)| k.heap_size() + v.heap_size()).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize, S> HeapSize for HashSet<T, S> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * mem::size_of::<T>() + self.iter().map(HeapSize::heap_size).sum::<usize>()
+    }
+}
+
+impl HeapSize for Token {
+    fn heap_size(&self) -> usize {
+        match *self {
+            Token::Ident(ref text)
+            | Token::Number(ref text)
+            | Token::Text(ref text)
+            | Token::DocComment(ref text)
+            | Token::Whitespace(ref text) => text.heap_size(),
+            _ => 0,
+        }
+    }
+}
+
+impl HeapSize for Argument<'_> {
+    fn heap_size(&self) -> usize {
+        match *self {
+            Argument::Number(_) => 0,
+            Argument::Text(ref text) => text.heap_size(),
+        }
+    }
+}
+
+impl HeapSize for Annotation<'_> {
+    fn heap_size(&self) -> usize {
+        self.name.heap_size() + self.arguments.heap_size()
+    }
+}
+
+impl HeapSize for Implementation<'_> {
+    fn heap_size(&self) -> usize {
+        self.name.heap_size()
+    }
+}
+
+impl HeapSize for Parameter<'_> {
+    fn heap_size(&self) -> usize {
+        self.name.heap_size() + self.typename.heap_size() + self.docs.heap_size() + self.annotations.heap_size()
+    }
+}
+
+impl HeapSize for Variant<'_> {
+    fn heap_size(&self) -> usize {
+        self.name.heap_size() + self.docs.heap_size() + self.annotations.heap_size()
+    }
+}
+
+impl HeapSize for TypeDefinition<'_> {
+    fn heap_size(&self) -> usize {
+        self.name.heap_size()
+            + self.parameters.heap_size()
+            + self.implements.heap_size()
+            + self.variants.heap_size()
+            + self.docs.heap_size()
+            + self.annotations.heap_size()
+    }
+}
+
+impl HeapSize for Ast<'_> {
+    fn heap_size(&self) -> usize {
+        match *self {
+            Ast::TypeDefinition(ref definition) => definition.heap_size(),
+            Ast::Unexpected(ref token, _) => token.heap_size(),
+        }
+    }
+}
+
+impl HeapSize for Semantic {
+    fn heap_size(&self) -> usize {
+        self.definitions.heap_size() + self.order.heap_size() + self.cycles.heap_size()
+    }
+}
+
+impl HeapSize for Edge {
+    fn heap_size(&self) -> usize {
+        self.from.heap_size() + self.to.heap_size() + self.field.heap_size()
+    }
+}
+
+impl HeapSize for Node {
+    fn heap_size(&self) -> usize {
+        self.name.heap_size()
+    }
+}
+
+impl HeapSize for Graph {
+    fn heap_size(&self) -> usize {
+        self.nodes.heap_size() + self.edges.heap_size()
+    }
+}
+
+/// What a phase produced: how many things and how many bytes they take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PhaseStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+/// What each phase of a compilation produced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// The files, and the bytes of their text.
+    pub sources: PhaseStats,
+    /// Every token of every file.
+    pub tokens: PhaseStats,
+    /// AST nodes: definitions, parameters, annotations and doc comment lines.
+    /// Names borrow from the sources, so they take nothing.
+    pub ast: PhaseStats,
+    /// Entries in the symbol table, with the order and the cycles.
+    pub symbols: PhaseStats,
+    /// Edges of the dependency graph, with its nodes.
+    pub edges: PhaseStats,
+}
+
+impl Stats {
+    /// The bytes of every phase together.
+    pub fn total_bytes(&self) -> usize {
+        [self.sources, self.tokens, self.ast, self.symbols, self.edges].iter().map(|p| p.bytes).sum()
+    }
+}
+
+/// A table, one phase per line.
+///
+/// # Examples
+///
+///     use simcom::stats::{PhaseStats, Stats};
+///
+///     let stats = Stats { tokens: PhaseStats { count: 12, bytes: 3 * 1024 }, ..Stats::default() };
+///     assert!(stats.to_string().contains("tokens          12     3.0 KiB\n"));
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let phases = [
+            ("sources", self.sources),
+            ("tokens", self.tokens),
+            ("ast", self.ast),
+            ("symbols", self.symbols),
+            ("edges", self.edges),
+        ];
+
+        for &(name, phase) in &phases {
+            writeln!(f, "{:<8} {:>9} {:>11}", name, phase.count, Bytes(phase.bytes))?;
+        }
+        writeln!(f, "{:<8} {:>9} {:>11}", "total", "", Bytes(self.total_bytes()))
+    }
+}
+
+/// A number of bytes, in the biggest unit that keeps it above 1.
+struct Bytes(usize);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let units = ["B", "KiB", "MiB", "GiB"];
+        let mut size = self.0 as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit + 1 < units.len() {
+            size /= 1024.0;
+            unit += 1;
+        }
+
+        let text = match unit {
+            0 => format!("{} B", self.0),
+            _ => format!("{:.1} {}", size, units[unit]),
+        };
+        f.pad(&text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+    use std::mem;
+
+    use super::super::lexer::Token;
+    use super::super::parser::{Annotation, Parameter};
+    use super::{Bytes, HeapSize};
+
+    #[test]
+    fn sizes() {
+        let owned: Cow<str> = Cow::Owned(String::with_capacity(10));
+        assert_eq!(owned.heap_size(), 10);
+        assert_eq!(Cow::Borrowed("borrowed").heap_size(), 0);
+
+        let tokens = vec![Token::Ident(String::from("Punto")), Token::Colon];
+        assert_eq!(tokens.heap_size(), 2 * mem::size_of::<Token>() + 5);
+    }
+
+    #[test]
+    fn nested_values() {
+        let parameter = Parameter {
+            name: Cow::Owned(String::from("x")),
+            typename: Cow::Borrowed("Entero"),
+            number: None,
+            docs: vec![Cow::Borrowed("Horizontal.")],
+            annotations: vec![Annotation { name: Cow::Borrowed("obsoleto"), arguments: vec![], span: Default::default() }],
+            name_span: Default::default(),
+            type_span: Default::default(),
+            span: Default::default(),
+        };
+        let expected = 1 + mem::size_of::<Cow<str>>() + mem::size_of::<Annotation>();
+        assert_eq!(parameter.heap_size(), expected);
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(Bytes(10).to_string(), "10 B");
+        assert_eq!(Bytes(1536).to_string(), "1.5 KiB");
+        assert_eq!(Bytes(3 * 1024 * 1024).to_string(), "3.0 MiB");
+        assert_eq!(format!("{:>8}", Bytes(1)), "     1 B");
+    }
+}
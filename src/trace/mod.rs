@@ -0,0 +1,29 @@
+//! Tracing macros.
+//!
+//! With the `tracing` feature, the phases report what they do as spans and
+//! events of the [`tracing`][0] crate: one span for each phase, file and
+//! definition, and an event for each token and for each error. Embedders
+//! choose what to keep (and where to send it) with a subscriber.
+//!
+//! Without the feature these macros expand to nothing, so nothing of this
+//! ends in the compiled crate. The field values are not even evaluated.
+//!
+//! [0]: https://docs.rs/tracing
+
+/// Enters a span until the end of the current block, like
+/// `let _span = tracing::span!(...).entered()`. The level is the name of a
+/// `tracing::Level`, like `DEBUG`.
+macro_rules! trace_span {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = ::tracing::span!(::tracing::Level::$level, $($args)*).entered();
+    };
+}
+
+/// Records an event, like `tracing::event!`.
+macro_rules! trace_event {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        ::tracing::event!(::tracing::Level::$level, $($args)*);
+    };
+}
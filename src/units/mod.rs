@@ -0,0 +1,122 @@
+//! Units module.
+//!
+//! A numeric field, an `Entero` or a `Real`, can say what it measures in,
+//! with `@unidad("m")` before its name. The unit doesn't change the type, it
+//! goes into the generated code as a comment and into the documentation, and
+//! the `conflicting_units` [lint][0] looks for fields with the same name in
+//! different units.
+//!
+//! [0]: ../lint/enum.Lint.html#variant.ConflictingUnits
+
+use super::diagnostics::{codes, Diagnostic};
+use super::parser::{Annotation, Parameter};
+use super::semantic::Semantic;
+
+/// Names of the annotation with the unit of a field.
+pub const UNIT: &[&str] = &["unidad", "unit"];
+
+/// The built-in types that can have a unit.
+const NUMERIC: &[&str] = &["Entero", "Real"];
+
+/// The first `@unidad` of `annotations`, if there is one.
+fn annotation<'a, 'src>(annotations: &'a [Annotation<'src>]) -> Option<&'a Annotation<'src>> {
+    annotations.iter().find(|annotation| UNIT.contains(&&annotation.name[..]))
+}
+
+/// The unit of `parameter`, if it has one.
+///
+/// # Examples
+///
+///     use simcom::lexer::Lexer;
+///     use simcom::parser::{Ast, Parser};
+///     use simcom::units::unit;
+///
+///     match Parser::new(Lexer::new("tipo Viaje(@unidad(\"km\") distancia: Real, dias: Entero);")).next() {
+///         Some(Ast::TypeDefinition(definition)) => {
+///             assert_eq!(unit(&definition.parameters[0]), Some("km"));
+///             assert_eq!(unit(&definition.parameters[1]), None);
+///         },
+///         _ => panic!("expected a definition"),
+///     }
+pub fn unit<'a>(parameter: &'a Parameter) -> Option<&'a str> {
+    annotation(&parameter.annotations)
+        .and_then(Annotation::text)
+        .filter(|unit| !unit.is_empty())
+}
+
+/// The problems with the unit annotations of `semantic`: they go on numeric
+/// fields, and take a text that isn't empty.
+pub fn check(semantic: &Semantic) -> Vec<Diagnostic> {
+    let mut names: Vec<&String> = semantic.definitions.keys().collect();
+    names.sort();
+    let mut errors = Vec::new();
+
+    for name in names {
+        let definition = &semantic.definitions[name];
+        if let Some(annotation) = annotation(&definition.annotations) {
+            errors.push(Diagnostic::error(format!("`@{}` goes on fields, not on types", annotation.name), annotation.span)
+                .with_code(codes::UNIT_ARGUMENT));
+        }
+
+        for parameter in &definition.parameters {
+            // A type of the schema named like a built-in one isn't a number.
+            let numeric = NUMERIC.contains(&&parameter.typename[..])
+                && !semantic.definitions.contains_key(&parameter.typename[..]);
+            let units = parameter.annotations.iter()
+                .filter(|annotation| UNIT.contains(&&annotation.name[..]));
+            for annotation in units {
+                if annotation.text().map(str::is_empty).unwrap_or(true) {
+                    errors.push(Diagnostic::error(format!("`@{}` takes a unit, like `@{}(\"m\")`",
+                            annotation.name, annotation.name), annotation.span)
+                        .with_code(codes::UNIT_ARGUMENT));
+                } else if !numeric {
+                    errors.push(Diagnostic::error(format!("`@{}` goes on numeric fields, but `{}` is `{}`",
+                            annotation.name, parameter.name, parameter.typename), annotation.span)
+                        .with_code(codes::UNIT_ARGUMENT));
+                }
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::{Lexer, Span};
+    use super::super::parser::Parser;
+    use super::*;
+
+    #[test]
+    fn bad_annotations() {
+        let content = "@unidad(\"m\") tipo A(@unidad x: Real, @unit(\"\") y: Real, @unidad(2) z: Real, @unidad(\"s\") t: Real);";
+        let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+
+        let errors: Vec<(String, Span)> = check(&semantic).into_iter().map(|e| (e.message, e.span)).collect();
+        assert_eq!(errors, vec![
+            (String::from("`@unidad` goes on fields, not on types"), Span::new(0, 12)),
+            (String::from("`@unidad` takes a unit, like `@unidad(\"m\")`"), Span::new(20, 27)),
+            (String::from("`@unit` takes a unit, like `@unit(\"m\")`"), Span::new(37, 46)),
+            (String::from("`@unidad` takes a unit, like `@unidad(\"m\")`"), Span::new(56, 66)),
+        ]);
+    }
+
+    #[test]
+    fn units_are_for_numbers() {
+        let messages = |content| {
+            let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+            check(&semantic).into_iter().map(|e| e.message).collect::<Vec<String>>()
+        };
+
+        let content = "tipo A(@unidad(\"m\") nombre: Texto, @unidad(\"m\") x: Entero, @unit(\"s\") y: Real, \
+                       @unidad(\"m\") b: B); tipo B(x: Real);";
+        assert_eq!(messages(content), vec![
+            String::from("`@unidad` goes on numeric fields, but `nombre` is `Texto`"),
+            String::from("`@unidad` goes on numeric fields, but `b` is `B`"),
+        ]);
+
+        // `Real` is the one of the schema.
+        let content = "tipo Real(x: Entero); tipo C(@unidad(\"m\") r: Real);";
+        assert_eq!(messages(content), vec![String::from("`@unidad` goes on numeric fields, but `r` is `Real`")]);
+    }
+}
@@ -0,0 +1,263 @@
+//! Versions module.
+//!
+//! Definitions and fields can say in which versions of the schema they
+//! exist: `@desde(2)` from version 2 on, `@hasta(3)` up to version 3, both
+//! included. Without them, they are in every version.
+//!
+//! [`compatibility`][0] checks that a version can replace an older one
+//! without breaking the programs that use it: no type or field is removed,
+//! and no field changes its type, like `y` does in
+//! `tipo Punto(@hasta(2) y: Entero, @desde(3) y: Real);`. Every field is
+//! required, so adding one is fine.
+//!
+//! [0]: fn.compatibility.html
+
+use std::collections::HashMap;
+
+use super::diagnostics::{codes, Diagnostic};
+use super::parser::{Annotation, Parameter, TypeDefinition};
+use super::semantic::Semantic;
+
+/// Names of the annotation with the first version something is in.
+pub const SINCE: &[&str] = &["desde", "since"];
+/// Names of the annotation with the last version something is in.
+pub const UNTIL: &[&str] = &["hasta", "until"];
+
+/// The versions a definition or a field is in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Range {
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl Range {
+    /// The range of something with `annotations`. Only the first `@desde` and
+    /// `@hasta` count, and only if their argument is a version.
+    ///
+    /// # Examples
+    ///
+    ///     use simcom::lexer::Lexer;
+    ///     use simcom::parser::{Ast, Parser};
+    ///     use simcom::versions::Range;
+    ///
+    ///     match Parser::new(Lexer::new("@desde(2) @hasta(4) tipo A(x: B);")).next() {
+    ///         Some(Ast::TypeDefinition(definition)) => {
+    ///             let range = Range::of(&definition.annotations);
+    ///             assert_eq!(range, Range { since: Some(2), until: Some(4) });
+    ///             assert!(range.contains(3) && !range.contains(5));
+    ///         },
+    ///         _ => panic!("expected a definition"),
+    ///     }
+    pub fn of(annotations: &[Annotation]) -> Range {
+        let version = |names: &[&str]| {
+            annotations.iter()
+                .find(|annotation| names.contains(&&annotation.name[..]))
+                .and_then(Annotation::number)
+        };
+        Range { since: version(SINCE), until: version(UNTIL) }
+    }
+
+    pub fn contains(self, version: i64) -> bool {
+        self.since.map(|since| since <= version).unwrap_or(true)
+            && self.until.map(|until| version <= until).unwrap_or(true)
+    }
+
+    /// Whether some version is in both ranges.
+    pub fn overlaps(self, other: Range) -> bool {
+        let since = self.since.max(other.since);
+        let until = match (self.until, other.until) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        match (since, until) {
+            (Some(since), Some(until)) => since <= until,
+            _ => true,
+        }
+    }
+}
+
+/// The last version `semantic` mentions in a `@desde` or a `@hasta`, if it
+/// has any. That's the one the generated code is for.
+///
+/// # Examples
+///
+///     use simcom::lexer::Lexer;
+///     use simcom::parser::Parser;
+///     use simcom::semantic::Semantic;
+///     use simcom::versions::latest;
+///
+///     let content = "tipo Punto(x: Entero, @hasta(2) y: Entero, @desde(3) y: Real);";
+///     let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+///     assert_eq!(latest(&semantic), Some(3));
+pub fn latest(semantic: &Semantic) -> Option<i64> {
+    semantic.definitions.values()
+        .flat_map(|definition| {
+            let fields = definition.parameters.iter().map(|parameter| Range::of(&parameter.annotations));
+            Some(Range::of(&definition.annotations)).into_iter().chain(fields)
+        })
+        .flat_map(|range| range.since.into_iter().chain(range.until))
+        .max()
+}
+
+/// The problems with the version annotations of `semantic`: an argument that
+/// isn't a version, or a `@desde` after the `@hasta`.
+pub fn check(semantic: &Semantic) -> Vec<Diagnostic> {
+    let mut names: Vec<&String> = semantic.definitions.keys().collect();
+    names.sort();
+    let mut errors = Vec::new();
+
+    for name in names {
+        let definition = &semantic.definitions[name];
+        check_annotations(&definition.annotations, &mut errors);
+        for parameter in &definition.parameters {
+            check_annotations(&parameter.annotations, &mut errors);
+        }
+    }
+
+    errors
+}
+
+fn check_annotations(annotations: &[Annotation], errors: &mut Vec<Diagnostic>) {
+    let versions = annotations.iter()
+        .filter(|annotation| SINCE.contains(&&annotation.name[..]) || UNTIL.contains(&&annotation.name[..]));
+    for annotation in versions {
+        if annotation.number().map(|version| version < 0).unwrap_or(true) {
+            errors.push(Diagnostic::error(format!("`@{}` takes a version, like `@{}(2)`", annotation.name, annotation.name),
+                    annotation.span)
+                .with_code(codes::VERSION_ARGUMENT));
+        }
+    }
+
+    let first = |names: &[&str]| annotations.iter().find(|annotation| names.contains(&&annotation.name[..]));
+    if let (Some(since), Some(until)) = (first(SINCE), first(UNTIL)) {
+        if let (Some(first), Some(last)) = (since.number(), until.number()) {
+            if first > last {
+                errors.push(Diagnostic::error(format!("version {} is after version {}", first, last), since.span)
+                    .with_code(codes::EMPTY_VERSION_RANGE)
+                    .with_label(until.span, format!("the last version is {}", last)));
+            }
+        }
+    }
+}
+
+/// The fields of `definition` in `version`.
+fn fields<'a, 'src>(definition: &'a TypeDefinition<'src>, version: i64) -> Vec<&'a Parameter<'src>> {
+    definition.parameters.iter()
+        .filter(|parameter| Range::of(&parameter.annotations).contains(version))
+        .collect()
+}
+
+/// The changes from version `old` to version `new` of `semantic` that break
+/// the programs that use `old`: types and fields that are removed, and
+/// fields whose type changes. Types and fields are the same in both versions
+/// if they have the same name.
+///
+/// # Examples
+///
+///     use simcom::lexer::Lexer;
+///     use simcom::parser::Parser;
+///     use simcom::semantic::Semantic;
+///     use simcom::versions::compatibility;
+///
+///     let content = "tipo Punto(x: Entero, @hasta(2) y: Entero, @desde(3) y: Real);";
+///     let semantic = Semantic::analyze(Parser::new(Lexer::new(content))).unwrap();
+///
+///     assert!(compatibility(&semantic, 1, 2).is_empty());
+///     let errors = compatibility(&semantic, 2, 3);
+///     assert_eq!(errors[0].message, "field `y` of `Punto` is `Entero` in version 2 and `Real` in version 3");
+pub fn compatibility(semantic: &Semantic, old: i64, new: i64) -> Vec<Diagnostic> {
+    let mut names: Vec<&String> = semantic.definitions.keys().collect();
+    names.sort();
+    let mut errors = Vec::new();
+
+    for name in names {
+        let definition = &semantic.definitions[name];
+        let range = Range::of(&definition.annotations);
+        if !range.contains(old) {
+            continue;
+        }
+        if !range.contains(new) {
+            errors.push(Diagnostic::error(format!("type `{}` of version {} isn't in version {}", name, old, new),
+                    definition.name_span)
+                .with_code(codes::TYPE_REMOVED));
+            continue;
+        }
+
+        let mut after: HashMap<&str, Vec<&Parameter>> = HashMap::new();
+        for parameter in fields(definition, new) {
+            after.entry(&parameter.name).or_default().push(parameter);
+        }
+
+        for parameter in fields(definition, old) {
+            let same = after.get(&parameter.name[..]).map(|same| &same[..]).unwrap_or(&[]);
+            match same.first() {
+                None => errors.push(Diagnostic::error(format!("field `{}` of `{}` of version {} isn't in version {}",
+                        parameter.name, name, old, new), parameter.span)
+                    .with_code(codes::FIELD_REMOVED)),
+                Some(&retyped) if same.iter().all(|p| p.typename != parameter.typename) => {
+                    errors.push(Diagnostic::error(format!("field `{}` of `{}` is `{}` in version {} and `{}` in version {}",
+                            parameter.name, name, parameter.typename, old, retyped.typename, new), retyped.type_span)
+                        .with_code(codes::FIELD_RETYPED)
+                        .with_label(parameter.type_span, format!("`{}` in version {}", parameter.typename, old)));
+                },
+                Some(_) => (),
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::lexer::{Lexer, Span};
+    use super::super::parser::Parser;
+    use super::*;
+
+    fn get_semantic(content: &str) -> Semantic {
+        Semantic::analyze(Parser::new(Lexer::new(content))).unwrap()
+    }
+
+    fn messages(errors: Vec<Diagnostic>) -> Vec<(String, Span)> {
+        errors.into_iter().map(|e| (e.message, e.span)).collect()
+    }
+
+    #[test]
+    fn ranges() {
+        let range = |since, until| Range { since, until };
+        assert!(range(None, None).contains(-1));
+        assert!(range(Some(2), Some(2)).contains(2));
+        assert!(!range(Some(2), None).contains(1));
+        assert!(range(Some(2), None).overlaps(range(None, Some(2))));
+        assert!(!range(Some(3), None).overlaps(range(None, Some(2))));
+        assert!(!range(Some(1), Some(2)).overlaps(range(Some(4), Some(5))));
+        assert!(range(None, None).overlaps(range(Some(4), Some(5))));
+    }
+
+    #[test]
+    fn bad_annotations() {
+        let semantic = get_semantic("@desde tipo A(@hasta(-1) x: B, @since(3) @until(2) y: B, @desde(1, 2) z: B);");
+        assert_eq!(messages(check(&semantic)), vec![
+            (String::from("`@desde` takes a version, like `@desde(2)`"), Span::new(0, 6)),
+            (String::from("`@hasta` takes a version, like `@hasta(2)`"), Span::new(14, 24)),
+            (String::from("version 3 is after version 2"), Span::new(31, 40)),
+            (String::from("`@desde` takes a version, like `@desde(2)`"), Span::new(57, 69)),
+        ]);
+    }
+
+    #[test]
+    fn breaking_changes() {
+        let content = "@hasta(1) tipo Viejo(x: Entero);\n\
+                       @desde(2) tipo Nuevo(x: Entero);\n\
+                       tipo Punto(@hasta(2) x: Entero, @hasta(1) y: Entero, @desde(2) y: Entero, @desde(3) z: Real);";
+        let semantic = get_semantic(content);
+
+        assert_eq!(messages(compatibility(&semantic, 1, 2)), vec![
+            (String::from("type `Viejo` of version 1 isn't in version 2"), Span::new(15, 20)),
+        ]);
+        assert_eq!(messages(compatibility(&semantic, 2, 3)), vec![
+            (String::from("field `x` of `Punto` of version 2 isn't in version 3"), Span::new(77, 96)),
+        ]);
+        assert!(compatibility(&semantic, 3, 3).is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+//! What `simcom` says on the command line.
+
+#![cfg(feature = "std")]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs `simcom` with `args`, feeding it `input` through stdin.
+fn simcom(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_simcom"))
+        .args(args)
+        .arg("--no-config")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn semantic_errors() {
+    for command in &["check", "build", "lint"] {
+        let output = simcom(&[command, "--locale", "en"], "tipo A(x: B); tipo A(y: B);");
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.starts_with("\
+error[E0101]: <stdin>:1:20: type `A` is defined more than once
+    --> <stdin>:1:6: previous definition of `A` is here
+"), "simcom {}: {}", command, stderr);
+    }
+}
@@ -0,0 +1,111 @@
+//! Whatever the input, `simcom` reports errors and never crashes.
+
+#![cfg(feature = "std")]
+
+extern crate simcom;
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::{self, Command, Output, Stdio};
+
+use simcom::driver::{compile, Options};
+
+/// Runs `simcom` with `args`, feeding it `input` through stdin.
+fn simcom(args: &[&str], input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_simcom"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // It may exit before reading everything.
+    let _ = child.stdin.take().unwrap().write_all(input);
+    child.wait_with_output().unwrap()
+}
+
+/// Pseudo-random bytes, the same in every run.
+fn garbage(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    (0..len).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as u8
+    }).collect()
+}
+
+#[test]
+fn binary_garbage() {
+    let mut inputs = vec![
+        Vec::new(),
+        b"\xef\xbb\xbftipo A(x: B);".to_vec(),
+        b"tipo A(x: \xed\xa0\x80);".to_vec(),
+        b"tipo A(x: B);\0tipo B(y: A);".to_vec(),
+        b"/// \xc3\ntipo".to_vec(),
+    ];
+    inputs.extend((0..8).map(|seed| garbage(seed, 2000)));
+
+    for input in &inputs {
+        for args in &[&["check"][..], &["tokens"], &["lint"], &["graph"], &["fmt", "--stdout"], &[]] {
+            let output = simcom(args, input);
+            match output.status.code() {
+                Some(0) | Some(1) => (),
+                _ => panic!("simcom {:?} crashed on {:?}: {}", args, input,
+                            String::from_utf8_lossy(&output.stderr)),
+            }
+        }
+    }
+}
+
+#[test]
+fn nul_characters() {
+    let input = b"tipo A(x: Entero);\0tipo B(y: A);\n";
+    let output = simcom(&["check"], input);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error[E0002]: <stdin>:1:19: unknown character\n"));
+
+    let dir = env::temp_dir().join(format!("simcom-garbage-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("a.sc");
+    fs::write(&path, &input[..]).unwrap();
+    let output = simcom(&["fmt", path.to_str().unwrap()], b"");
+    let formatted = fs::read(&path).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(formatted, &input[..]);
+}
+
+#[test]
+fn invalid_utf8_says_where() {
+    let output = simcom(&["check"], b"tipo A(\n  x: \xff);");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "error: <stdin>: invalid UTF-8 at line 2, column 6\n");
+}
+
+#[test]
+fn exotic_inputs() {
+    let chain: String = (0..100_000).map(|i| format!("tipo T{}(x: T{});", i, i + 1)).collect();
+    let wide: String = (0..200_000).map(|i| format!("x{}: Entero, ", i)).collect();
+    let wide = format!("tipo Ancho({});", wide);
+    let commas = format!("tipo {}", ",".repeat(300_000));
+    let inputs = [
+        &chain[..],
+        &wide[..],
+        &commas[..],
+        "tipo \u{2028}A(x:\u{a0}B)",
+        "@@@@((((;;;///",
+        "tipo A(x: A);",
+        "tipo Ñandú(ñ: Ω, _: ẞ);",
+        "\u{feff}tipo A(x: B);",
+        "tipo A(\r\n/// Doc.\r\nx: B,\r\n);\r\n",
+    ];
+
+    for input in &inputs {
+        compile(input, &Options::default());
+    }
+}
@@ -1,6 +1,6 @@
 extern crate simcom;
 
-use simcom::lexer::{Lexer, Token};
+use simcom::lexer::{Language, Lexer, Span, Token, TokenStream};
 
 fn get_tokens(input: &str) -> Vec<Token> {
     Lexer::new(input).collect()
@@ -24,6 +24,43 @@ fn detect_keyword() {
     let tokens = get_tokens("tipo:: tipo)");
     assert_eq!(tokens,
                vec![Token::Type, Token::Colon, Token::Colon, Token::Type, Token::ParR]);
+
+    let tokens = get_tokens("interfaz implementa interfaces");
+    assert_eq!(tokens,
+               vec![Token::Interface, Token::Implements, Token::Ident(String::from("interfaces"))]);
+
+    let tokens = get_tokens("enum enums");
+    assert_eq!(tokens, vec![Token::Enum, Token::Ident(String::from("enums"))]);
+}
+
+#[test]
+fn numbers() {
+    let tokens = get_tokens("a = 12, b=-3 - 4x #");
+    assert_eq!(tokens,
+               vec![Token::Ident(String::from("a")),
+                    Token::Equals,
+                    Token::Number(String::from("12")),
+                    Token::Comma,
+                    Token::Ident(String::from("b")),
+                    Token::Equals,
+                    Token::Number(String::from("-3")),
+                    Token::Illegal,
+                    Token::Number(String::from("4")),
+                    Token::Ident(String::from("x")),
+                    Token::Hash]);
+}
+
+#[test]
+fn texts() {
+    let tokens = get_tokens(r#"("m", "a \"b\" \\") "x\y" "z"#);
+    assert_eq!(tokens,
+               vec![Token::ParL,
+                    Token::Text(String::from("m")),
+                    Token::Comma,
+                    Token::Text(String::from("a \"b\" \\")),
+                    Token::ParR,
+                    Token::Illegal,
+                    Token::Illegal]);
 }
 
 #[test]
@@ -36,6 +73,9 @@ fn detect_illegal() {
                     Token::Colon,
                     Token::Illegal,
                     Token::Type]);
+
+    let tokens = get_tokens("tipo\0)");
+    assert_eq!(tokens, vec![Token::Type, Token::Illegal, Token::ParR]);
 }
 
 #[test]
@@ -46,3 +86,85 @@ fn identifiers() {
                     Token::Ident(String::from("pal4abra")),
                     Token::Ident(String::from("castaña"))]);
 }
+
+#[test]
+fn qualified_names() {
+    let tokens = get_tokens("modulo geo.planos; geo.Punto geo. .x a.2");
+    assert_eq!(tokens,
+               vec![Token::Module,
+                    Token::Ident(String::from("geo.planos")),
+                    Token::Semicolon,
+                    Token::Ident(String::from("geo.Punto")),
+                    Token::Ident(String::from("geo")),
+                    Token::Illegal,
+                    Token::Illegal,
+                    Token::Ident(String::from("x")),
+                    Token::Ident(String::from("a")),
+                    Token::Illegal,
+                    Token::Number(String::from("2"))]);
+}
+
+#[test]
+fn english_keywords() {
+    let tokens: Vec<Token> = Lexer::with_language("type tipo interface implements", Language::English).collect();
+    assert_eq!(tokens,
+               vec![Token::Type, Token::Ident(String::from("tipo")), Token::Interface, Token::Implements]);
+}
+
+#[test]
+fn trivia() {
+    let tokens: Vec<Token> = Lexer::new(" tipo\t(").preserving_trivia().collect();
+    assert_eq!(tokens,
+               vec![Token::Whitespace(String::from(" ")),
+                    Token::Type,
+                    Token::Whitespace(String::from("\t")),
+                    Token::ParL]);
+}
+
+#[test]
+fn underscores() {
+    let tokens = get_tokens("mi_punto _x");
+    assert_eq!(tokens,
+               vec![Token::Ident(String::from("mi_punto")),
+                    Token::Ident(String::from("_x"))]);
+}
+
+#[test]
+fn doc_comments() {
+    let tokens = get_tokens("/// Un punto.\r\n///\ntipo // /");
+    assert_eq!(tokens,
+               vec![Token::DocComment(String::from("Un punto.")),
+                    Token::DocComment(String::new()),
+                    Token::Type,
+                    Token::Illegal,
+                    Token::Illegal]);
+}
+
+#[test]
+fn non_ascii() {
+    let tokens = get_tokens("año\u{a0}\u{3000} tamaño2ñ\n/// Línea.");
+    assert_eq!(tokens,
+               vec![Token::Ident(String::from("año")),
+                    Token::Ident(String::from("tamaño2ñ")),
+                    Token::DocComment(String::from("Línea."))]);
+
+    let tokens: Vec<Token> = Lexer::new(" \u{a0}\t¿").preserving_trivia().collect();
+    assert_eq!(tokens, vec![Token::Whitespace(String::from(" \u{a0}\t")), Token::Illegal]);
+}
+
+#[test]
+fn token_streams() {
+    let stream = TokenStream::new(Lexer::new("tipo  A;").preserving_trivia());
+    assert_eq!(stream.tokens().len(), 5);
+
+    let mut cursor = stream.cursor();
+    assert_eq!(cursor.next().unwrap(), &(Token::Type, Span::new(0, 4)));
+    let copy = cursor.clone();
+    assert_eq!(cursor.nth(1).unwrap().0, Token::Ident(String::from("A")));
+
+    // Cursors don't move each other.
+    assert_eq!(copy.peek().unwrap().0, Token::Whitespace(String::from("  ")));
+    assert_eq!(cursor.text(cursor.peek().unwrap().1), ";");
+    assert_eq!(cursor.last().unwrap().0, Token::EOF);
+    assert_eq!(stream.cursor().count(), 5);
+}
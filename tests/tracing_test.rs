@@ -0,0 +1,66 @@
+//! With the `tracing` feature, the phases report spans and events.
+
+#![cfg(all(feature = "tracing", feature = "std"))]
+
+extern crate simcom;
+extern crate tracing;
+
+use std::sync::{Arc, Mutex};
+
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use simcom::driver::{compile, Options};
+
+/// Keeps the names of the spans and the levels of the events.
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<String>>>,
+    events: Arc<Mutex<Vec<tracing::Level>>>,
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes) -> Id {
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(String::from(span.metadata().name()));
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _: &Id, _: &Record) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event) {
+        self.events.lock().unwrap().push(*event.metadata().level());
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+#[test]
+fn every_phase_is_traced() {
+    let recorder = Recorder::default();
+    let options = Options { jobs: 1, ..Options::default() };
+
+    tracing::subscriber::with_default(recorder.clone(), || {
+        compile("tipo Punto(x: Entero, y: Entero); tipo Linea(a: Punto, b: Punto);", &options)
+    });
+
+    let spans = recorder.spans.lock().unwrap();
+    for name in &["compilation", "parse", "file", "definition", "semantic", "lint", "codegen"] {
+        assert!(spans.iter().any(|span| span == name), "no `{}` span in {:?}", name, spans);
+    }
+    // The parser looks for a third definition before finding the end of the
+    // file, and the code generator writes two.
+    assert_eq!(spans.iter().filter(|span| *span == "definition").count(), 3 + 2);
+
+    let events = recorder.events.lock().unwrap();
+    let tokens = events.iter().filter(|level| **level == tracing::Level::TRACE).count();
+    assert_eq!(tokens, 12 + 12 + 1);
+}